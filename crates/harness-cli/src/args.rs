@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+pub(crate) const USAGE: &str = "Usage: harness [OPTIONS] [DIR]
+
+Runs the YAML and TOML test definitions in DIR, the current directory by
+default. A harness.toml in DIR holds settings shared by all tests.
+
+Options:
+  --filter TEXT     Only run tests whose name contains TEXT
+  --list            List the tests and their steps instead of running them
+  --report PATH     Write a JUnit XML report of all tests to PATH
+  --jobs N          Run up to N tests at the same time, 1 by default
+  --profile NAME    Apply the profile NAME of harness.toml, defaults to
+                    $HARNESS_PROFILE
+  -h, --help        Print this help
+
+Exits with 0 if all tests passed, 1 if a test failed and 2 if the tests
+could not be run.";
+
+/// Command line of the runner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Args {
+    pub(crate) dir: PathBuf,
+    pub(crate) filter: Option<String>,
+    pub(crate) list: bool,
+    pub(crate) report: Option<PathBuf>,
+    pub(crate) jobs: usize,
+    pub(crate) profile: Option<String>,
+    pub(crate) help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("."),
+            filter: None,
+            list: false,
+            report: None,
+            jobs: 1,
+            profile: None,
+            help: false,
+        }
+    }
+}
+
+impl Args {
+    /// Parses the arguments following the program name, both `--flag value`
+    /// and `--flag=value` are accepted
+    pub(crate) fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut dir = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .map(ToString::to_string)
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("Missing value for {}", flag))
+            };
+            match flag.as_str() {
+                "--filter" => parsed.filter = Some(value()?),
+                "--report" => parsed.report = Some(PathBuf::from(value()?)),
+                "--profile" => parsed.profile = Some(value()?),
+                "--jobs" => {
+                    let jobs = value()?;
+                    parsed.jobs = jobs
+                        .parse()
+                        .ok()
+                        .filter(|jobs| *jobs > 0)
+                        .ok_or_else(|| format!("Invalid value '{}' for --jobs", jobs))?;
+                }
+                "--list" => parsed.list = true,
+                "-h" | "--help" => parsed.help = true,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option {}", flag)),
+                _ if dir.is_some() => return Err(format!("Unexpected argument {}", arg)),
+                _ => dir = Some(PathBuf::from(arg)),
+            }
+        }
+        if let Some(dir) = dir {
+            parsed.dir = dir;
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse(&[
+                "--filter",
+                "checkout",
+                "tests/e2e",
+                "--jobs=4",
+                "--report=junit.xml"
+            ]),
+            Ok(Args {
+                dir: PathBuf::from("tests/e2e"),
+                filter: Some("checkout".to_string()),
+                report: Some(PathBuf::from("junit.xml")),
+                jobs: 4,
+                ..Args::default()
+            })
+        );
+        assert_eq!(parse(&["--list"]).map(|args| args.list), Ok(true));
+        assert_eq!(
+            parse(&["--jobs", "0"]),
+            Err("Invalid value '0' for --jobs".to_string())
+        );
+        assert_eq!(
+            parse(&["--filter"]),
+            Err("Missing value for --filter".to_string())
+        );
+        assert_eq!(parse(&["a", "b"]), Err("Unexpected argument b".to_string()));
+    }
+}
@@ -0,0 +1,188 @@
+//! Test runner executing the YAML and TOML test definitions of a directory,
+//! see [`args::USAGE`]
+
+mod args;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+use harness::{config, HarnessSettings, SuiteConfig, TestHarness, TestSuite, PROFILE_VAR};
+
+use crate::args::{Args, USAGE};
+
+/// File in the test directory with the settings shared by all tests
+const SUITE_CONFIG: &str = "harness.toml";
+
+/// Test definitions in `dir` and its subdirectories, in path order
+fn discover(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut definitions = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.file_name().is_some_and(|name| name != SUITE_CONFIG)
+                && path.extension().is_some_and(|extension| {
+                    ["yaml", "yml", "toml"].contains(&&*extension.to_string_lossy())
+                })
+            {
+                definitions.push(path);
+            }
+        }
+    }
+    definitions.sort();
+    Ok(definitions)
+}
+
+/// Loads the definition at `path` with the suite settings applied
+fn load(path: &Path, settings: &HarnessSettings) -> Result<TestHarness, String> {
+    let mut harness = config::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    harness.apply_settings(settings);
+    Ok(harness)
+}
+
+fn run(args: &Args) -> Result<bool, String> {
+    let suite_config = args.dir.join(SUITE_CONFIG);
+    let settings = if suite_config.exists() {
+        let profile = args.profile.clone().or_else(|| {
+            env::var(PROFILE_VAR)
+                .ok()
+                .filter(|profile| !profile.is_empty())
+        });
+        SuiteConfig::load(&suite_config)
+            .and_then(|config| config.settings(profile.as_deref()))
+            .map_err(|e| format!("{}: {}", suite_config.display(), e))?
+    } else {
+        HarnessSettings::default()
+    };
+    let paths =
+        discover(&args.dir).map_err(|e| format!("Failed to read {}: {}", args.dir.display(), e))?;
+
+    // Load every definition up front, so mistakes are reported before any
+    // test ran
+    let mut suite = TestSuite::new("harness");
+    suite.jobs = args.jobs;
+    for path in paths {
+        let harness = load(&path, &settings)?;
+        if args
+            .filter
+            .as_ref()
+            .is_some_and(|filter| !harness.test_name.contains(filter.as_str()))
+        {
+            continue;
+        }
+        if args.list {
+            println!("{} ({})", harness.test_name, path.display());
+            for planned in &harness.steps {
+                println!("  {}", planned.step.name());
+            }
+        }
+        suite.add(harness);
+    }
+    if args.list {
+        return Ok(true);
+    }
+    if suite.is_empty() {
+        return Err(format!("No tests found in {}", args.dir.display()));
+    }
+
+    let report = suite.run();
+    println!("{}", report);
+    if let Some(path) = &args.report {
+        fs::write(path, report.to_junit_xml())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(report.passed())
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) if args.help => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_definitions() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        for name in [
+            "b.yaml",
+            "a.toml",
+            "harness.toml",
+            "notes.md",
+            "nested/c.yml",
+        ] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+
+        let found = discover(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|path| path.strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            ["a.toml", "b.yaml", "nested/c.yml"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn test_run_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("harness.toml"), "failure_log_lines = 0\n").unwrap();
+        fs::write(
+            dir.path().join("pass.yaml"),
+            "name: Passing\nsteps:\n  - shell: \"true\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("fail.yaml"),
+            "name: Failing\nsteps:\n  - shell: \"false\"\n",
+        )
+        .unwrap();
+        let report = dir.path().join("junit.xml");
+        let args = Args {
+            dir: dir.path().to_path_buf(),
+            report: Some(report.clone()),
+            jobs: 2,
+            ..Args::default()
+        };
+
+        assert_eq!(run(&args), Ok(false));
+        let xml = fs::read_to_string(report).unwrap();
+        assert!(
+            xml.contains(r#"<testsuites name="harness" tests="2" failures="1""#),
+            "{}",
+            xml
+        );
+        let filtered = Args {
+            filter: Some("Pass".to_string()),
+            report: None,
+            ..args
+        };
+        assert_eq!(run(&filtered), Ok(true));
+    }
+}
@@ -0,0 +1,100 @@
+//! Procedural macros of the `harness` crate, use them through its `macros`
+//! feature
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, FnArg, ItemFn, LitStr, Type};
+
+/// Turns an async fn setting up a harness into a `#[test]` executing it.
+/// The fn receives either `&mut TestHarness` or a `TestHarnessBuilder` it
+/// returns again, and may return a `Result` whose error fails the test:
+///
+/// ```ignore
+/// #[harness_test(root_dir = "tests/e2e")]
+/// async fn checkout(harness: &mut TestHarness) {
+///     harness.add_service(Box::new(api()));
+///     harness.add_step(start("Api"));
+/// }
+/// ```
+///
+/// The harness is named after the fn unless `name` is given, its root
+/// directory is `root_dir` relative to the package, the package itself by
+/// default. See `TestHarness::run_test` for how it is executed
+#[proc_macro_attribute]
+pub fn harness_test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name = None;
+    let mut root_dir = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?);
+        } else if meta.path.is_ident("root_dir") {
+            root_dir = Some(meta.value()?.parse::<LitStr>()?);
+        } else {
+            return Err(meta.error("expected `name` or `root_dir`"));
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with parser);
+    let function = parse_macro_input!(item as ItemFn);
+    expand(function, name, root_dir)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(
+    function: ItemFn,
+    name: Option<LitStr>,
+    root_dir: Option<LitStr>,
+) -> syn::Result<TokenStream2> {
+    let signature = &function.sig;
+    if signature.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            signature.fn_token,
+            "#[harness_test] expects an async fn",
+        ));
+    }
+    let input = match signature.inputs.iter().collect::<Vec<_>>()[..] {
+        [FnArg::Typed(input)] => input,
+        _ =>
+            return Err(syn::Error::new_spanned(
+                &signature.inputs,
+                "#[harness_test] expects a single harness or builder argument",
+            )),
+    };
+
+    let ident = &signature.ident;
+    let name = name.unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+    let root_dir = match root_dir {
+        Some(root_dir) => quote!(concat!(env!("CARGO_MANIFEST_DIR"), "/", #root_dir)),
+        None => quote!(env!("CARGO_MANIFEST_DIR")),
+    };
+    // Attributes such as #[ignore] apply to the test
+    let attrs = &function.attrs;
+    let vis = &function.vis;
+    let inner = ItemFn {
+        attrs: Vec::new(),
+        vis: syn::Visibility::Inherited,
+        ..function.clone()
+    };
+    let run = if let Type::Reference(_) = *input.ty {
+        quote! {
+            ::harness::TestHarness::new(#name, #root_dir)
+                .run_test(|harness| ::std::boxed::Box::pin(#ident(harness)));
+        }
+    } else {
+        quote! {
+            ::harness::TestHarness::builder(#name)
+                .root_dir(#root_dir)
+                .run_test(#ident);
+        }
+    };
+    Ok(quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #ident() {
+            #inner
+            #run
+        }
+    })
+}
@@ -0,0 +1,103 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing::{info, warn};
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that bundles files and directories produced during the test into a
+/// gzipped tarball, e.g. for upload as CI artifacts. Paths are relative to the
+/// root directory of the test, missing ones are skipped with a warning
+#[derive(Debug, Clone)]
+pub struct ArchiveArtifacts {
+    /// Name the step is reported under
+    pub name: String,
+    pub paths: Vec<PathBuf>,
+    /// Path of the `.tar.gz` archive to write
+    pub output: PathBuf,
+}
+
+impl ServiceStepExecutor for ArchiveArtifacts {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let output = env.resolve(&self.output);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                StepError::io(
+                    format!("Failed to create directory {}", parent.display()),
+                    e,
+                )
+            })?;
+        }
+        let file = File::create(&output).map_err(|e| {
+            StepError::io(format!("Failed to create archive {}", output.display()), e)
+        })?;
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+        for path in &self.paths {
+            let source = env.resolve(path);
+            let appended = if source.is_dir() {
+                builder.append_dir_all(path, &source)
+            } else if source.exists() {
+                builder.append_path_with_name(&source, path)
+            } else {
+                warn!("Skipping missing artifact {}", source.display());
+                continue;
+            };
+            appended
+                .map_err(|e| StepError::io(format!("Failed to archive {}", source.display()), e))?;
+        }
+
+        builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .map_err(|e| {
+                StepError::io(format!("Failed to write archive {}", output.display()), e)
+            })?;
+        info!("Archived artifacts into {}", output.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::read::GzDecoder;
+
+    use super::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_archive_artifacts_contains_all_files() {
+        let root = tempfile::tempdir().expect("Failed to create temp dir");
+        fs::create_dir(root.path().join("logs")).unwrap();
+        fs::write(root.path().join("logs/service.log"), "started\n").unwrap();
+        fs::write(root.path().join("result.json"), "{}").unwrap();
+
+        let step = ArchiveArtifacts {
+            name: "Archive_Artifacts".to_string(),
+            paths: vec![
+                PathBuf::from("logs"),
+                PathBuf::from("result.json"),
+                PathBuf::from("missing.txt"),
+            ],
+            output: PathBuf::from("bundle/artifacts.tar.gz"),
+        };
+        let mut harness = TestHarness::new("ArchiveTester", root.path().to_str().unwrap());
+        step.execute(&mut harness.step_env())
+            .expect("Failed to archive artifacts");
+
+        let archive = root.path().join("bundle/artifacts.tar.gz");
+        assert!(archive.exists());
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(archive).unwrap()));
+        let entries = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().into_owned())
+            .collect::<Vec<_>>();
+        assert!(entries.contains(&PathBuf::from("logs/service.log")));
+        assert!(entries.contains(&PathBuf::from("result.json")));
+    }
+}
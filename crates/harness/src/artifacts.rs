@@ -0,0 +1,220 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+use tracing::{info, warn};
+
+use crate::{OutputStream, RunReport, StepStatus, TestHarness};
+
+/// Directory of a run that steps and services write files to for upload by
+/// CI, `{root_dir}/artifacts/{test_name}/{timestamp}` with the timestamp in
+/// milliseconds since the Unix epoch. Clones share the same directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactSink {
+    dir: PathBuf,
+}
+
+impl ArtifactSink {
+    /// The artifact directory of a run of `test_name` starting now
+    pub fn new(root_dir: impl AsRef<Path>, test_name: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        Self {
+            dir: root_dir
+                .as_ref()
+                .join("artifacts")
+                .join(test_name)
+                .join(timestamp.to_string()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    /// Writes `contents` to the file `name` in the directory, creating it
+    /// and the directories on the way
+    pub fn write(&self, name: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<PathBuf> {
+        let path = self.dir.join(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Copies the file at `source` to `name` in the directory
+    pub fn copy(&self, name: impl AsRef<Path>, source: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let path = self.dir.join(name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::copy(source, &path)?;
+        Ok(path)
+    }
+}
+
+/// Human readable summary of a run, as written to `summary.txt`
+fn summary(report: &RunReport) -> String {
+    let mut summary = String::new();
+    let result = if report.passed() { "passed" } else { "failed" };
+    let _ = writeln!(summary, "Test {}: {}", report.test_name, result);
+    let _ = writeln!(summary, "\nSteps:");
+    for (idx, step) in report.steps.iter().enumerate() {
+        let status = match &step.status {
+            StepStatus::Passed => "passed".to_string(),
+            StepStatus::Failed(e) => format!("FAILED: {}", e),
+            StepStatus::Warning(e) => format!("warning: {}", e),
+            StepStatus::Skipped(reason) => format!("skipped: {}", reason),
+        };
+        let _ = writeln!(summary, "  {}. {}: {}", idx + 1, step.name, status);
+    }
+    let problems = report
+        .unexpected_exits
+        .iter()
+        .map(ToString::to_string)
+        .chain(report.liveness_failures.iter().map(ToString::to_string))
+        .chain(report.cleanup_failures.iter().cloned())
+        .collect::<Vec<_>>();
+    if !problems.is_empty() {
+        let _ = writeln!(summary, "\nProblems:");
+        for problem in problems {
+            let _ = writeln!(summary, "  {}", problem);
+        }
+    }
+    if !report.command_lines.is_empty() {
+        let _ = writeln!(summary, "\nCommand lines:");
+        for (service, command_line) in &report.command_lines {
+            let _ = writeln!(summary, "  {}: {}", service, command_line);
+        }
+    }
+    summary
+}
+
+impl TestHarness {
+    /// Writes the output of every service, the values of the context and a
+    /// summary of the run into [`TestHarness::artifacts`]. Failures to write
+    /// are logged, they do not change the outcome of the run
+    pub(crate) fn collect_failure_bundle(&self, report: &RunReport) {
+        let result = (|| -> io::Result<()> {
+            for service in &self.services {
+                let mut log = String::new();
+                for line in service.output() {
+                    let stream = match line.stream {
+                        OutputStream::Stdout => "out",
+                        OutputStream::Stderr => "err",
+                    };
+                    let _ = writeln!(log, "[{}] {}", stream, line.line);
+                }
+                if !log.is_empty() {
+                    self.artifacts
+                        .write(format!("logs/{}.log", service.name()), log)?;
+                }
+                for path in service
+                    .log_files()
+                    .into_iter()
+                    .flat_map(|files| [files.stdout, files.stderr])
+                {
+                    if let Some(file_name) = path.file_name().filter(|_| path.exists()) {
+                        self.artifacts
+                            .copy(Path::new("logs").join(file_name), &path)?;
+                    }
+                }
+            }
+            let context =
+                serde_json::to_string_pretty(&self.context.to_json()).map_err(io::Error::other)?;
+            self.artifacts.write("context.json", context)?;
+            self.artifacts.write("summary.txt", summary(report))?;
+            self.artifacts.write("report.html", report.to_html())?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => info!(
+                "Collected failure artifacts in {}",
+                self.artifacts.dir().display()
+            ),
+            Err(e) => warn!(
+                "Failed to collect failure artifacts in {}: {}",
+                self.artifacts.dir().display(),
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        AsyncFnStep, FailurePolicy, ServiceStepExecutor, StepEnv, StepError, SubProcessService,
+        SubProcessServiceStarter, TestHarness, TestStep,
+    };
+
+    #[derive(Debug)]
+    struct WriteArtifact;
+
+    impl ServiceStepExecutor for WriteArtifact {
+        fn name(&self) -> &str { "WriteArtifact" }
+
+        fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+            env.artifacts
+                .write("responses/order.json", "{}")
+                .map_err(|e| StepError::io("Failed to write artifact", e))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failure_bundle() {
+        let root = tempfile::tempdir().unwrap();
+        let mut harness = TestHarness::new("ArtifactTester", root.path().to_str().unwrap());
+        harness.add_service(Box::new(SubProcessService::new("Echo", "sh", vec![
+            "-c".to_string(),
+            "echo ready; exec sleep 30".to_string(),
+        ])));
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Start_Echo".to_string(),
+            description: "Starts the echo service".to_string(),
+            service_name: "Echo".to_string(),
+            wait_after: Some(std::time::Duration::from_millis(200)),
+        })));
+        harness.add_step(TestStep::Service(Box::new(WriteArtifact)));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Create_Order".to_string(),
+            description: "Fails to create an order".to_string(),
+            futurefn: Box::new(|context| {
+                context.insert("order_id", json!(42));
+                Box::new(async { Err("status 500".to_string()) })
+            }),
+        })));
+        harness.failure_policy = FailurePolicy::Continue;
+        let artifacts = harness.artifacts.clone();
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(!report.passed());
+        let dir = artifacts.dir();
+        assert!(dir.starts_with(root.path().join("artifacts/ArtifactTester")));
+        assert!(dir.join("responses/order.json").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("logs/Echo.log")).unwrap(),
+            "[out] ready\n"
+        );
+        let context = std::fs::read_to_string(dir.join("context.json")).unwrap();
+        assert!(context.contains("\"order_id\": 42"), "{}", context);
+        let summary = std::fs::read_to_string(dir.join("summary.txt")).unwrap();
+        assert!(
+            summary.starts_with("Test ArtifactTester: failed\n"),
+            "{}",
+            summary
+        );
+        assert!(
+            summary.contains("  3. Create_Order: FAILED: status 500\n"),
+            "{}",
+            summary
+        );
+        let html = std::fs::read_to_string(dir.join("report.html")).unwrap();
+        assert!(html.contains("<pre>  | ready</pre>"), "{}", html);
+    }
+}
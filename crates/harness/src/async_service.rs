@@ -0,0 +1,180 @@
+use std::fmt::{self, Debug};
+
+use crate::runtime::HarnessRuntime;
+use crate::{Service, ServiceError, TestHarness};
+
+/// A service that is natively async, e.g. a client pool or an in-process
+/// server. Its methods are driven on the runtime of the harness, which is
+/// kept for the whole run, so tasks spawned in [`AsyncService::start`] keep
+/// running until the service is stopped
+#[allow(async_fn_in_trait)]
+pub trait AsyncService: Debug + Send {
+    /// Name the service can be looked up by from steps
+    fn name(&self) -> &str;
+    async fn start(&mut self) -> Result<(), ServiceError>;
+    async fn is_running(&self) -> bool;
+    async fn stop(&mut self) -> Result<(), ServiceError>;
+    /// Whether the service is ready to be used, see [`Service::is_healthy`]
+    async fn is_healthy(&mut self) -> bool { self.is_running().await }
+}
+
+impl TestHarness {
+    /// Adds an [`AsyncService`], which steps use like any other [`Service`]
+    pub fn add_async_service(&mut self, service: impl AsyncService + 'static) {
+        self.add_service(Box::new(AsyncServiceAdapter {
+            service,
+            runtime: self.runtime.clone(),
+        }));
+    }
+}
+
+/// Exposes an [`AsyncService`] as a [`Service`] by blocking on the runtime
+/// of the harness
+struct AsyncServiceAdapter<S> {
+    service: S,
+    runtime: HarnessRuntime,
+}
+
+impl<S: AsyncService> AsyncServiceAdapter<S> {
+    fn fail(&self, error: impl ToString) -> ServiceError {
+        ServiceError::failed(self.service.name(), error.to_string())
+    }
+}
+
+impl<S: Debug> Debug for AsyncServiceAdapter<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { self.service.fmt(f) }
+}
+
+impl<S: AsyncService> Service for AsyncServiceAdapter<S> {
+    fn name(&self) -> &str { self.service.name() }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        self.runtime
+            .block_on(self.service.start())
+            .map_err(|e| self.fail(e))?
+    }
+
+    fn is_running(&self) -> bool {
+        self.runtime
+            .block_on(self.service.is_running())
+            .unwrap_or(false)
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        self.runtime
+            .block_on(self.service.stop())
+            .map_err(|e| self.fail(e))?
+    }
+
+    fn is_healthy(&mut self) -> bool {
+        self.runtime
+            .block_on(self.service.is_healthy())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::Value;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::task::JoinHandle;
+
+    use super::*;
+    use crate::{
+        AsyncFnStep, SubProcessService, SubProcessServiceStarter, SubProcessServiceStopper,
+        TestStep,
+    };
+
+    /// Serves a greeting from a task spawned on the harness runtime
+    #[derive(Debug)]
+    struct GreetingServer {
+        port: u16,
+        server: Option<JoinHandle<()>>,
+    }
+
+    impl AsyncService for GreetingServer {
+        fn name(&self) -> &str { "Greeter" }
+
+        async fn start(&mut self) -> Result<(), ServiceError> {
+            let listener = TcpListener::bind(("127.0.0.1", self.port))
+                .await
+                .map_err(|source| ServiceError::Start {
+                    service: self.name().to_string(),
+                    source,
+                })?;
+            self.server = Some(tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let _ = stream.write_all(b"hello").await;
+                }
+            }));
+            Ok(())
+        }
+
+        async fn is_running(&self) -> bool {
+            self.server
+                .as_ref()
+                .is_some_and(|server| !server.is_finished())
+        }
+
+        async fn stop(&mut self) -> Result<(), ServiceError> {
+            if let Some(server) = self.server.take() {
+                server.abort();
+                let _ = server.await;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mixes_async_and_subprocess_services() {
+        let mut harness = TestHarness::new("AsyncServiceTester", ".");
+        let port = harness.ports.tcp("greeter").unwrap();
+        harness.add_async_service(GreetingServer { port, server: None });
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        for service_name in ["Greeter", "Sleeper"] {
+            harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+                name: format!("Start_{}", service_name),
+                description: format!("Starts {}", service_name),
+                service_name: service_name.to_string(),
+                wait_after: None,
+            })));
+        }
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Greet".to_string(),
+            description: "Reads the greeting".to_string(),
+            futurefn: Box::new(move |_| {
+                Box::new(async move {
+                    let mut stream = TcpStream::connect(("127.0.0.1", port))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    let mut greeting = String::new();
+                    tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut greeting)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    Ok(Value::String(greeting))
+                })
+            }),
+        })));
+        for service_name in ["Sleeper", "Greeter"] {
+            harness.add_step(TestStep::Service(Box::new(SubProcessServiceStopper {
+                name: format!("Stop_{}", service_name),
+                description: format!("Stops {}", service_name),
+                service_name: service_name.to_string(),
+                wait_after: Some(Duration::from_millis(10)),
+            })));
+        }
+
+        let context = harness.context.clone();
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(
+            context.get("Greet"),
+            Some(Value::String("hello".to_string()))
+        );
+    }
+}
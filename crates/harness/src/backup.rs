@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::PathBuf;
+
+use tracing::info;
+
+use crate::{ServiceAction, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that snapshots the state of a service through
+/// [`crate::Service::backup`] before destructive steps, so it can later be
+/// brought back with [`Restore`]. `output` is relative to the root directory of
+/// the test
+#[derive(Debug, Clone)]
+pub struct Backup {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    pub output: PathBuf,
+}
+
+impl ServiceStepExecutor for Backup {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let output = env.resolve(&self.output);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                StepError::io(
+                    format!("Failed to create directory {}", parent.display()),
+                    e,
+                )
+            })?;
+        }
+        env.service(&self.service_name)?.backup(&output)?;
+        info!(
+            "Backed up service '{}' to {}",
+            self.service_name,
+            output.display()
+        );
+        Ok(())
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+/// A step that restores the state of a service from a backup taken by
+/// [`Backup`]. `input` is relative to the root directory of the test
+#[derive(Debug, Clone)]
+pub struct Restore {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    pub input: PathBuf,
+}
+
+impl ServiceStepExecutor for Restore {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let input = env.resolve(&self.input);
+        if !input.exists() {
+            return Err(StepError::Assertion(format!(
+                "Backup {} does not exist",
+                input.display()
+            )));
+        }
+        env.service(&self.service_name)?.restore(&input)?;
+        info!(
+            "Restored service '{}' from {}",
+            self.service_name,
+            input.display()
+        );
+        Ok(())
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{Service, ServiceError, TestHarness};
+
+    #[derive(Debug)]
+    struct InMemoryStore {
+        value: Arc<Mutex<String>>,
+    }
+
+    impl Service for InMemoryStore {
+        fn name(&self) -> &str { "Store" }
+
+        fn start(&mut self) -> Result<(), ServiceError> { Ok(()) }
+
+        fn is_running(&self) -> bool { true }
+
+        fn stop(&mut self) -> Result<(), ServiceError> { Ok(()) }
+
+        fn backup(&mut self, output: &Path) -> Result<(), ServiceError> {
+            fs::write(output, self.value.lock().unwrap().as_bytes())
+                .map_err(|e| ServiceError::failed(self.name(), e.to_string()))
+        }
+
+        fn restore(&mut self, input: &Path) -> Result<(), ServiceError> {
+            *self.value.lock().unwrap() = fs::read_to_string(input)
+                .map_err(|e| ServiceError::failed(self.name(), e.to_string()))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_backup_and_restore_in_memory_value() {
+        let root = tempfile::tempdir().unwrap();
+        let value = Arc::new(Mutex::new("precious".to_string()));
+        let mut harness = TestHarness::new("BackupTester", root.path().to_str().unwrap());
+        harness.add_service(Box::new(InMemoryStore {
+            value: value.clone(),
+        }));
+
+        Backup {
+            name: "Backup_Store".to_string(),
+            service_name: "Store".to_string(),
+            output: PathBuf::from("backups/store.bak"),
+        }
+        .execute(&mut harness.step_env())
+        .expect("Failed to back up");
+        assert!(root.path().join("backups/store.bak").exists());
+
+        // The destructive part of the test
+        value.lock().unwrap().clear();
+
+        Restore {
+            name: "Restore_Store".to_string(),
+            service_name: "Store".to_string(),
+            input: PathBuf::from("backups/store.bak"),
+        }
+        .execute(&mut harness.step_env())
+        .expect("Failed to restore");
+        assert_eq!(*value.lock().unwrap(), "precious");
+    }
+}
@@ -0,0 +1,125 @@
+use std::future::Future;
+
+use serde_json::Value;
+
+use crate::{
+    AsyncFnStep, AsyncService, Context, FailurePolicy, Service, ServiceStepExecutor, StepOptions,
+    TestHarness, TestStep,
+};
+
+/// Builds a [`TestHarness`] as a chain of calls, see [`TestHarness::builder`]
+pub struct TestHarnessBuilder {
+    pub(crate) harness: TestHarness,
+}
+
+impl TestHarness {
+    /// Starts building a harness rooted at the current directory
+    pub fn builder(test_name: &str) -> TestHarnessBuilder {
+        TestHarnessBuilder {
+            harness: TestHarness::new(test_name, "."),
+        }
+    }
+}
+
+impl TestHarnessBuilder {
+    pub fn root_dir(mut self, root_dir: &str) -> Self {
+        self.harness.root_dir = root_dir.to_string();
+        self
+    }
+
+    pub fn service(mut self, service: impl Service + 'static) -> Self {
+        self.harness.add_service(Box::new(service));
+        self
+    }
+
+    pub fn async_service(mut self, service: impl AsyncService + 'static) -> Self {
+        self.harness.add_async_service(service);
+        self
+    }
+
+    /// Declares that `service` depends on `depends_on`, see
+    /// [`TestHarness::add_dependency`]
+    pub fn dependency(mut self, service: &str, depends_on: &str) -> Self {
+        self.harness.add_dependency(service, depends_on);
+        self
+    }
+
+    pub fn step(self, step: impl ServiceStepExecutor + 'static) -> Self {
+        self.step_with(step, StepOptions::default())
+    }
+
+    pub fn step_with(
+        mut self,
+        step: impl ServiceStepExecutor + 'static,
+        options: StepOptions,
+    ) -> Self {
+        self.harness
+            .add_step_with(TestStep::Service(Box::new(step)), options);
+        self
+    }
+
+    /// Adds an [`AsyncFnStep`] running the future returned by `futurefn`
+    pub fn async_step<F, Fut>(mut self, name: &str, description: &str, mut futurefn: F) -> Self
+    where
+        F: FnMut(&Context) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Value, String>> + 'static, {
+        self.harness
+            .add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+                name: name.to_string(),
+                description: description.to_string(),
+                futurefn: Box::new(move |context| Box::new(futurefn(context))),
+            })));
+        self
+    }
+
+    pub fn failure_policy(mut self, failure_policy: FailurePolicy) -> Self {
+        self.harness.failure_policy = failure_policy;
+        self
+    }
+
+    pub fn build(self) -> TestHarness { self.harness }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AssertContextValue, SubProcessService, SubProcessServiceStarter, SubProcessServiceStopper,
+    };
+
+    #[test]
+    fn test_builder_pipeline() {
+        let report = TestHarness::builder("BuilderTester")
+            .service(SubProcessService::new("Sleeper", "sleep", vec![
+                "30".to_string()
+            ]))
+            .step(SubProcessServiceStarter {
+                name: "Sleeper".to_string(),
+                description: "Starts the sleeper".to_string(),
+                service_name: "Sleeper".to_string(),
+                wait_after: None,
+            })
+            .async_step("Greet", "Produces a greeting", |_| async {
+                Ok(Value::from("hello"))
+            })
+            .step(AssertContextValue {
+                name: "Check_Greeting".to_string(),
+                key: "Greet".to_string(),
+                pointer: String::new(),
+                expected: Value::from("hello"),
+            })
+            .step(SubProcessServiceStopper {
+                name: "Stop_Sleeper".to_string(),
+                description: "Stops the sleeper".to_string(),
+                service_name: "Sleeper".to_string(),
+                wait_after: None,
+            })
+            .failure_policy(FailurePolicy::Abort)
+            .build()
+            .execute()
+            .expect("Failed to execute test steps");
+
+        assert!(report.passed());
+        assert_eq!(report.steps.len(), 4);
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::Instant;
+
+use tracing::{info, warn};
+
+use crate::panic::PanicHookGuard;
+use crate::{RunReport, StepOptions, StepReport, StepStatus, TestHarness, TestStep};
+
+impl TestHarness {
+    /// Attaches a `cleanup` to the step named `step_name`, e.g. deleting the
+    /// tenant the step created. Once the step was attempted, whether it
+    /// passed, failed or panicked, the cleanup runs when the test ends:
+    /// after the main steps and before the teardown steps, or when the
+    /// harness is dropped while unwinding from a panic. Cleanups run in
+    /// reverse order of their steps, and their failures fail the run without
+    /// stopping the other cleanups
+    pub fn on_cleanup(&mut self, step_name: &str, cleanup: TestStep) {
+        self.cleanups.push((step_name.to_string(), cleanup));
+    }
+
+    /// Arms the cleanups attached to the step about to be attempted
+    pub(crate) fn arm_cleanups(&mut self, step_name: &str) {
+        let (armed, pending) = std::mem::take(&mut self.cleanups)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(name, _)| name == step_name);
+        self.cleanups = pending;
+        self.armed_cleanups
+            .extend(armed.into_iter().map(|(_, cleanup)| cleanup));
+    }
+
+    /// Executes the armed cleanups, latest first, recording them like
+    /// teardown steps
+    pub(crate) fn run_cleanups(
+        &mut self,
+        report: &mut RunReport,
+        panic_hook: Option<&PanicHookGuard>,
+    ) {
+        while let Some(mut cleanup) = self.armed_cleanups.pop() {
+            let name = cleanup.name().to_string();
+            info!("Executing cleanup step: {}", name);
+            let started = Instant::now();
+            let result = self.attempt_step(&mut cleanup, &StepOptions::default(), panic_hook);
+            let status = match result {
+                Ok(()) => self.passed_or_skipped(),
+                Err(e) => {
+                    warn!("Cleanup step '{}' failed: {}", name, e);
+                    StepStatus::Failed(e.to_string())
+                }
+            };
+            report.steps.push(StepReport {
+                started: Some(started),
+                duration: started.elapsed(),
+                ..StepReport::new(&cleanup, status)
+            });
+        }
+    }
+
+    /// Executes the cleanups still armed when the harness is dropped without
+    /// finishing its run, logging failures
+    pub(crate) fn run_remaining_cleanups(&mut self) {
+        while let Some(mut cleanup) = self.armed_cleanups.pop() {
+            info!("Executing cleanup step: {}", cleanup.name());
+            if let Err(e) = self.attempt_step(&mut cleanup, &StepOptions::default(), None) {
+                warn!("Cleanup step '{}' failed: {}", cleanup.name(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{AsyncFnStep, FailurePolicy, HarnessError};
+
+    fn record(name: &str, events: &Arc<Mutex<Vec<String>>>, fails: bool) -> TestStep {
+        let events = events.clone();
+        let event = name.to_string();
+        TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: name.to_string(),
+            description: format!("Records {}", name),
+            futurefn: Box::new(move |_| {
+                events.lock().unwrap().push(event.clone());
+                Box::new(async move {
+                    if fails {
+                        Err("boom".to_string())
+                    } else {
+                        Ok(Value::Null)
+                    }
+                })
+            }),
+        }))
+    }
+
+    #[test]
+    fn test_cleanups_of_attempted_steps_run() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("CleanupTester", ".");
+        harness.failure_policy = FailurePolicy::Abort;
+        harness.add_step(record("upload", &events, false));
+        harness.add_step(record("create_tenant", &events, true));
+        harness.add_step(record("skipped", &events, false));
+        harness.add_teardown(record("teardown", &events, false));
+        harness.on_cleanup("upload", record("delete_upload", &events, true));
+        harness.on_cleanup("create_tenant", record("delete_tenant", &events, false));
+        harness.on_cleanup("skipped", record("never", &events, false));
+
+        let error = harness.execute().unwrap_err();
+        assert_eq!(error.to_string(), "Step 2 'create_tenant' failed: boom");
+        assert_eq!(*events.lock().unwrap(), [
+            "upload",
+            "create_tenant",
+            "delete_tenant",
+            "delete_upload",
+            "teardown"
+        ]);
+    }
+
+    #[test]
+    fn test_failed_cleanup_fails_run() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("CleanupTester", ".");
+        harness.add_step(record("upload", &events, false));
+        harness.on_cleanup("upload", record("delete_upload", &events, true));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(!report.passed());
+        let statuses = report
+            .steps
+            .iter()
+            .map(|step| (step.name.as_str(), &step.status))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, [
+            ("upload", &StepStatus::Passed),
+            ("delete_upload", &StepStatus::Failed("boom".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn test_armed_cleanups_run_on_drop() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("CleanupTester", ".");
+        harness.on_cleanup("upload", record("delete_upload", &events, false));
+        let mut step = record("upload", &events, false);
+        harness
+            .attempt_step(&mut step, &StepOptions::default(), None)
+            .expect("Failed to execute step");
+        drop(harness);
+        assert_eq!(*events.lock().unwrap(), ["upload", "delete_upload"]);
+    }
+
+    #[test]
+    fn test_cleanup_of_unknown_step_is_invalid() {
+        let mut harness = TestHarness::new("CleanupTester", ".");
+        harness.on_cleanup("missing", record("cleanup", &Arc::default(), false));
+        let Err(HarnessError::InvalidPlan(problems)) = harness.validate() else {
+            panic!("Expected an invalid plan");
+        };
+        assert_eq!(problems, [
+            "Cleanup 'cleanup' refers to unknown step 'missing'"
+        ]);
+    }
+}
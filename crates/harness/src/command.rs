@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use regex::Regex;
+use serde_json::Value;
+use tracing::info;
+
+use crate::{PollPolicy, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step running a one-shot command to completion and checking its exit
+/// code and output, e.g. to seed a database or run migrations between steps.
+/// Arguments and environment values may contain `{{port:name}}`,
+/// `${ENV_VAR}` and `{{context.key}}` placeholders, see
+/// [`crate::PortAllocator::substitute`]
+#[derive(Debug, Clone)]
+pub struct ShellCommandStep {
+    /// Name the step is reported under
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    /// Working directory relative to the root directory of the test, the
+    /// root directory itself if unset
+    pub cwd: Option<PathBuf>,
+    /// Exit code the command must have, any exit code passes if unset
+    pub expect_exit_code: Option<i32>,
+    /// Patterns that must all match somewhere in stdout
+    pub stdout_patterns: Vec<Regex>,
+    /// Patterns that must all match somewhere in stderr
+    pub stderr_patterns: Vec<Regex>,
+    /// The command is killed and the step fails if it runs longer
+    pub timeout: Duration,
+    /// Stores stdout in the [`crate::Context`] under this key
+    pub context_key: Option<String>,
+}
+
+impl ShellCommandStep {
+    /// Runs `command` with `args`, expecting it to exit with 0
+    pub fn new(name: &str, command: &str, args: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            args,
+            env: HashMap::new(),
+            cwd: None,
+            expect_exit_code: Some(0),
+            stdout_patterns: Vec::new(),
+            stderr_patterns: Vec::new(),
+            timeout: Duration::from_secs(60),
+            context_key: None,
+        }
+    }
+
+    /// Runs `script` with `sh -c`, expecting it to exit with 0
+    pub fn sh(name: &str, script: &str) -> Self {
+        Self::new(name, "sh", vec!["-c".to_string(), script.to_string()])
+    }
+}
+
+/// Reads a pipe of `child` to the end on a background thread
+fn read_to_end(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+/// Waits for `child` to exit, killing it after `timeout`
+fn wait_or_kill(child: &mut Child, timeout: Duration) -> Result<Option<i32>, String> {
+    let exited = PollPolicy {
+        interval: Duration::from_millis(10),
+        timeout,
+        ..Default::default()
+    }
+    .poll(|| child.try_wait().ok().flatten().ok_or(()));
+    match exited {
+        Ok(status) => Ok(status.code()),
+        Err(()) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(format!("still running after {:?}", timeout))
+        }
+    }
+}
+
+impl ServiceStepExecutor for ShellCommandStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| env.substitute_ports(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut cmd = Command::new(&self.command);
+        cmd.args(&args)
+            .current_dir(env.resolve(self.cwd.clone().unwrap_or_default()))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        for (key, value) in &self.env {
+            cmd.env(key, env.substitute_ports(value)?);
+        }
+
+        let description = format!("'{} {}'", self.command, args.join(" "));
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| StepError::io(format!("Failed to run {}", description), e))?;
+        let stdout = read_to_end(child.stdout.take());
+        let stderr = read_to_end(child.stderr.take());
+        let exit_code = wait_or_kill(&mut child, self.timeout)
+            .map_err(|e| StepError::Failed(format!("{} {}", description, e)))?;
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+        info!("{} exited with {:?}", description, exit_code);
+
+        if let Some(expected) = self
+            .expect_exit_code
+            .filter(|expected| exit_code != Some(*expected))
+        {
+            return Err(StepError::Assertion(format!(
+                "{} exited with {}, expected {}\nstdout:\n{}\nstderr:\n{}",
+                description,
+                exit_code.map_or("a signal".to_string(), |code| code.to_string()),
+                expected,
+                stdout,
+                stderr
+            )));
+        }
+        for (stream, output, patterns) in [
+            ("stdout", &stdout, &self.stdout_patterns),
+            ("stderr", &stderr, &self.stderr_patterns),
+        ] {
+            if let Some(pattern) = patterns.iter().find(|pattern| !pattern.is_match(output)) {
+                return Err(StepError::Assertion(format!(
+                    "{} of {} does not match '{}':\n{}",
+                    stream, description, pattern, output
+                )));
+            }
+        }
+        if let Some(key) = &self.context_key {
+            env.context.insert(key, Value::String(stdout));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_command_output_and_exit_code() {
+        let mut harness = TestHarness::new("ShellCommandTester", ".");
+        let mut step = ShellCommandStep::sh("Seed", "echo seeded $ROWS rows; echo done >&2");
+        step.env.insert("ROWS".to_string(), "42".to_string());
+        step.stdout_patterns = vec![Regex::new(r"seeded \d+ rows").unwrap()];
+        step.stderr_patterns = vec![Regex::new("done").unwrap()];
+        step.context_key = Some("seeded".to_string());
+        step.execute(&mut harness.step_env())
+            .expect("Command should pass");
+        assert_eq!(
+            harness.context.get("seeded"),
+            Some(Value::String("seeded 42 rows\n".to_string()))
+        );
+
+        step.stdout_patterns = vec![Regex::new("migrated").unwrap()];
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("does not match 'migrated'"), "{}", error);
+    }
+
+    #[test]
+    fn test_command_exit_code_and_timeout() {
+        let mut harness = TestHarness::new("ShellCommandTester", ".");
+        let error = ShellCommandStep::sh("Fail", "echo broken >&2; exit 3")
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("exited with 3, expected 0"), "{}", error);
+        assert!(error.ends_with("stderr:\nbroken\n"), "{}", error);
+
+        let mut step = ShellCommandStep::sh("Hang", "exec sleep 30");
+        step.timeout = Duration::from_millis(100);
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("still running after 100ms"), "{}", error);
+    }
+}
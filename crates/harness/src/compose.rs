@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tracing::info;
+
+use crate::container::unique_resource_name;
+use crate::{
+    container_runtime_from_env, shell_quote, ContainerRuntime, OutputLine, PollPolicy,
+    ReadinessProbe, Service, ServiceError, TestHarness,
+};
+
+/// A group of services defined in a Docker Compose file, brought up with
+/// `compose up` on start and torn down with `compose down` on stop. The
+/// compose services listed in `services` are registered as
+/// [`ComposeMember`]s next to the group by [`TestHarness::add_compose`], so
+/// steps can await them and read their logs by name
+#[derive(Debug, Clone)]
+pub struct ComposeService {
+    pub name: String,
+    pub compose_file: PathBuf,
+    /// Compose project the containers are created in, unique per test
+    /// process by default so parallel runs do not share containers
+    pub project: String,
+    /// Compose services exposed as members of the group
+    pub services: Vec<String>,
+    pub runtime: Arc<dyn ContainerRuntime>,
+    running: bool,
+    last_command_line: Option<String>,
+}
+
+impl ComposeService {
+    /// Runs the project with the runtime selected by
+    /// [`container_runtime_from_env`]
+    pub fn new(name: &str, compose_file: impl AsRef<Path>) -> Self {
+        Self::with_runtime(name, compose_file, container_runtime_from_env())
+    }
+
+    pub fn with_runtime(
+        name: &str,
+        compose_file: impl AsRef<Path>,
+        runtime: Arc<dyn ContainerRuntime>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            compose_file: compose_file.as_ref().to_path_buf(),
+            project: unique_resource_name(name),
+            services: Vec::new(),
+            runtime,
+            running: false,
+            last_command_line: None,
+        }
+    }
+
+    /// Member for the compose service `service` of this group
+    pub fn member(&self, service: &str) -> ComposeMember {
+        ComposeMember {
+            service: service.to_string(),
+            project: self.project(),
+            readiness: None,
+            readiness_policy: PollPolicy::default(),
+        }
+    }
+
+    fn project(&self) -> Project {
+        Project {
+            runtime: self.runtime.clone(),
+            args: vec![
+                "compose".to_string(),
+                "--project-name".to_string(),
+                self.project.clone(),
+                "--file".to_string(),
+                self.compose_file.display().to_string(),
+            ],
+        }
+    }
+}
+
+/// Runs compose commands against a project
+#[derive(Debug, Clone)]
+struct Project {
+    runtime: Arc<dyn ContainerRuntime>,
+    /// Arguments of the CLI selecting the project
+    args: Vec<String>,
+}
+
+impl Project {
+    fn with_command<'a>(&'a self, command: &[&'a str]) -> Vec<&'a str> {
+        self.args
+            .iter()
+            .map(String::as_str)
+            .chain(command.iter().copied())
+            .collect()
+    }
+
+    fn run(&self, service_name: &str, command: &[&str]) -> Result<String, ServiceError> {
+        self.runtime.run(service_name, &self.with_command(command))
+    }
+
+    fn logs(&self, command: &[&str]) -> Vec<OutputLine> {
+        self.runtime.logs(&self.with_command(command))
+    }
+}
+
+impl Service for ComposeService {
+    fn name(&self) -> &str { &self.name }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        if self.running {
+            return Err(ServiceError::AlreadyRunning(self.name.clone()));
+        }
+        let project = self.project();
+        let command = ["up", "--detach"];
+        let command_line = std::iter::once(self.runtime.program())
+            .chain(project.with_command(&command))
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        info!("Starting compose group '{}': {}", self.name, command_line);
+        self.last_command_line = Some(command_line);
+        project.run(&self.name, &command)?;
+        self.running = true;
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+            && self
+                .project()
+                .run(&self.name, &["ps", "--quiet", "--status", "running"])
+                .is_ok_and(|ids| !ids.is_empty())
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        if self.running {
+            self.project()
+                .run(&self.name, &["down", "--volumes", "--remove-orphans"])?;
+            self.running = false;
+        }
+        Ok(())
+    }
+
+    fn required_tools(&self) -> Vec<String> { vec![self.runtime.program().to_string()] }
+
+    fn last_command_line(&self) -> Option<String> { self.last_command_line.clone() }
+
+    fn output(&self) -> Vec<OutputLine> { self.project().logs(&["logs", "--no-color"]) }
+}
+
+/// A single service of a [`ComposeService`], registered under the name of
+/// the compose service. Starting and stopping it only affects its container
+#[derive(Debug, Clone)]
+pub struct ComposeMember {
+    pub service: String,
+    project: Project,
+    /// Checked by [`Service::is_healthy`], so starting the member waits
+    /// until it passes according to `readiness_policy`
+    pub readiness: Option<ReadinessProbe>,
+    pub readiness_policy: PollPolicy,
+}
+
+impl Service for ComposeMember {
+    fn name(&self) -> &str { &self.service }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        self.project
+            .run(&self.service, &["up", "--detach", &self.service])
+            .map(drop)
+    }
+
+    fn is_running(&self) -> bool {
+        self.project
+            .run(&self.service, &[
+                "ps",
+                "--quiet",
+                "--status",
+                "running",
+                &self.service,
+            ])
+            .is_ok_and(|ids| !ids.is_empty())
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        self.project
+            .run(&self.service, &["stop", &self.service])
+            .map(drop)
+    }
+
+    fn is_healthy(&mut self) -> bool {
+        let output = || self.output();
+        self.is_running()
+            && self
+                .readiness
+                .as_ref()
+                .is_none_or(|probe| probe.check(output).is_ok())
+    }
+
+    fn readiness_policy(&self) -> Option<PollPolicy> {
+        self.readiness.as_ref().map(|_| self.readiness_policy)
+    }
+
+    fn required_tools(&self) -> Vec<String> { vec![self.project.runtime.program().to_string()] }
+
+    fn output(&self) -> Vec<OutputLine> {
+        self.project
+            .logs(&["logs", "--no-color", "--no-log-prefix", &self.service])
+    }
+}
+
+impl TestHarness {
+    /// Adds the compose group and a [`ComposeMember`] for each of its
+    /// services, which depend on the group, so
+    /// [`crate::StartAllServices`] brings the group up first. Use
+    /// [`TestHarness::add_service`] with [`ComposeService::member`] instead
+    /// to configure the readiness of a member
+    pub fn add_compose(&mut self, compose: ComposeService) {
+        for service in &compose.services {
+            self.add_service(Box::new(compose.member(service)));
+            self.add_dependency(service, &compose.name);
+        }
+        self.add_service(Box::new(compose));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_members_depend_on_group() {
+        let mut compose = ComposeService::new("Backend Stack", "tests/compose.yaml");
+        compose.services = vec!["db".to_string(), "cache".to_string()];
+        let project = format!("harness-backend-stack-{}", std::process::id());
+        assert_eq!(compose.project, project);
+
+        let mut harness = TestHarness::new("ComposeTester", ".");
+        harness.add_compose(compose);
+        let names = harness
+            .services
+            .iter()
+            .map(|service| service.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["db", "cache", "Backend Stack"]);
+        assert_eq!(harness.dependencies["db"], ["Backend Stack"]);
+        assert_eq!(harness.services[0].required_tools(), ["docker"]);
+    }
+}
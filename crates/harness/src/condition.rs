@@ -0,0 +1,106 @@
+use std::env;
+use std::fmt::{self, Debug, Display};
+
+use serde_json::Value;
+
+use crate::Context;
+
+/// A custom check of a [`Condition`]
+pub type ConditionFn = Box<dyn Fn(&Context) -> bool + Send>;
+
+/// What a [`crate::TestStep::Conditional`] branches on, evaluated when the
+/// step is reached so it sees values stored by earlier steps
+pub enum Condition {
+    /// A value is stored under the key in the [`Context`]
+    ContextSet(String),
+    /// The value under the key in the [`Context`] equals the given value
+    ContextEquals(String, Value),
+    /// The environment variable is set
+    EnvSet(String),
+    /// The environment variable is set to the given value
+    EnvEquals(String, String),
+    /// The check returns true, described by the string in reports
+    Custom(String, ConditionFn),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn holds(&self, context: &Context) -> bool {
+        match self {
+            Self::ContextSet(key) => context.get(key).is_some(),
+            Self::ContextEquals(key, expected) => context.get(key).as_ref() == Some(expected),
+            Self::EnvSet(var) => env::var_os(var).is_some(),
+            Self::EnvEquals(var, expected) => env::var(var).is_ok_and(|value| value == *expected),
+            Self::Custom(_, check) => check(context),
+            Self::Not(condition) => !condition.holds(context),
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ContextSet(key) => write!(f, "context value '{}' is set", key),
+            Self::ContextEquals(key, expected) =>
+                write!(f, "context value '{}' equals {}", key, expected),
+            Self::EnvSet(var) => write!(f, "${} is set", var),
+            Self::EnvEquals(var, expected) => write!(f, "${} equals '{}'", var, expected),
+            Self::Custom(description, _) => write!(f, "{}", description),
+            Self::Not(condition) => write!(f, "not ({})", condition),
+        }
+    }
+}
+
+impl Debug for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "Condition({})", self) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssertContextValue, StepStatus, TestHarness, TestStep};
+
+    fn assert_tls(name: &str, expected: &str) -> Box<TestStep> {
+        Box::new(TestStep::Service(Box::new(AssertContextValue {
+            name: name.to_string(),
+            key: "tls".to_string(),
+            pointer: String::new(),
+            expected: Value::from(expected),
+        })))
+    }
+
+    #[test]
+    fn test_conditional_branches() {
+        let mut harness = TestHarness::new("ConditionalTester", ".");
+        harness.context.insert("tls", Value::from("off"));
+        harness.add_step(TestStep::Conditional {
+            condition: Condition::ContextSet("cert".to_string()),
+            then: assert_tls("Check_Tls_With_Cert", "on"),
+            otherwise: Some(assert_tls("Check_Tls_Without_Cert", "off")),
+        });
+        harness.add_step(TestStep::Conditional {
+            condition: Condition::EnvSet("HARNESS_CONDITIONAL_TEST_UNSET".to_string()),
+            then: assert_tls("Check_Tls_From_Env", "on"),
+            otherwise: None,
+        });
+        harness.add_step(TestStep::Conditional {
+            condition: Condition::Not(Box::new(Condition::ContextEquals(
+                "tls".to_string(),
+                Value::from("on"),
+            ))),
+            then: assert_tls("Check_Tls_Off", "off"),
+            otherwise: None,
+        });
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(report.steps[0].status, StepStatus::Passed);
+        assert_eq!(
+            report.steps[1].status,
+            StepStatus::Skipped(
+                "condition '$HARNESS_CONDITIONAL_TEST_UNSET is set' does not hold".to_string()
+            )
+        );
+        assert_eq!(report.steps[2].status, StepStatus::Passed);
+    }
+}
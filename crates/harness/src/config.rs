@@ -0,0 +1,601 @@
+//! Test definitions written in YAML, or the same structure in TOML, instead
+//! of Rust, so scenarios can be added without touching code. A definition
+//! names the test, its services and its steps:
+//!
+//! ```yaml
+//! name: Checkout
+//! root_dir: ..              # relative to the file, its directory if unset
+//! exclusive: [port 8080]    # see TestHarness::require_exclusive
+//! services:
+//!   - name: Api
+//!     command: python3
+//!     args: [-m, http.server, "8080"]
+//!     env: { PYTHONUNBUFFERED: "1" }
+//!     cwd: www              # relative to root_dir
+//!     readiness:
+//!       http: http://localhost:8080/
+//!       timeout: 10
+//! steps:
+//!   - start: Api
+//!   - name: Fetch_Index
+//!     http: { url: "http://localhost:8080/", status: 200 }
+//!   - shell: test -f www/index.html
+//!   - wait: 0.5
+//!   - stop: Api
+//! ```
+//!
+//! Services take a `readiness` probe of `tcp: PORT`, `http: URL` (or `{ url,
+//! status }`), `command: [PROGRAM, ARGS...]` or `log: PATTERN`, with optional
+//! `timeout` and `interval` in seconds. Each step is one of:
+//!
+//! - `start: SERVICE` and `stop: SERVICE`
+//! - `http: { url, method, status, headers, body, json, timeout, save_as }`,
+//!   where `json` is the expected response body and `save_as` stores the body
+//!   in the [`crate::Context`]
+//! - `shell: SCRIPT` or `shell: { script | command and args, env, cwd,
+//!   exit_code, timeout, save_as }`
+//! - `wait: SECONDS` or `wait: { service, timeout }`, the latter waiting for
+//!   the service to become healthy
+//!
+//! Steps take an optional `name` to report them under. Durations are in
+//! seconds, unknown keys are rejected to catch typos. Commands, arguments,
+//! environment values and URLs may contain `${ENV_VAR}`, `{{context.key}}`
+//! and `{{port:name}}` placeholders, substituted when the service starts or
+//! the step runs, see [`crate::PortAllocator::substitute`]
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use regex::Regex;
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+
+use crate::{
+    HttpRequestStep, PollPolicy, ReadinessProbe, ServiceStepExecutor, ShellCommandStep, StepEnv,
+    StepError, SubProcessService, SubProcessServiceStarter, SubProcessServiceStopper, TestHarness,
+    TestStep,
+};
+
+/// Error of loading a test definition or a [`crate::SuiteConfig`]
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Invalid TOML: {0}")]
+    Toml(#[from] toml::de::Error),
+    /// The file does not describe a test or settings, `at` is the path of
+    /// the offending value such as `steps[2].http.url`
+    #[error("Invalid configuration at {at}: {message}")]
+    Invalid { at: String, message: String },
+}
+
+/// Reads the test definition at `path`, as TOML if it has a `.toml`
+/// extension and as YAML otherwise, resolving `root_dir` against the
+/// directory of the file
+pub fn load(path: impl AsRef<Path>) -> Result<TestHarness, ConfigError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "toml")
+    {
+        let table = text.parse::<toml::Table>()?;
+        return from_value(&serde_yaml::to_value(table)?, dir);
+    }
+    parse(&text, dir)
+}
+
+/// Builds a harness from the test definition in `yaml`, resolving `root_dir`
+/// against `dir`
+pub fn parse(yaml: &str, dir: &Path) -> Result<TestHarness, ConfigError> {
+    from_value(&serde_yaml::from_str::<Value>(yaml)?, dir)
+}
+
+fn from_value(value: &Value, dir: &Path) -> Result<TestHarness, ConfigError> {
+    let definition = Node {
+        value,
+        at: "the top level".to_string(),
+    };
+    definition.allow_keys(&["name", "root_dir", "exclusive", "services", "steps"])?;
+    let root_dir = match definition.get("root_dir") {
+        Some(root_dir) => dir.join(root_dir.str()?),
+        None => dir.to_path_buf(),
+    };
+    let mut harness = TestHarness::new(
+        definition.required("name")?.str()?,
+        &root_dir.to_string_lossy(),
+    );
+    if let Some(exclusive) = definition.get("exclusive") {
+        harness.exclusive_resources = exclusive.texts()?;
+    }
+    for service in definition.list("services")? {
+        harness.add_service(Box::new(parse_service(&service, &root_dir)?));
+    }
+    for step in definition.list("steps")? {
+        harness.add_step(parse_step(&step)?);
+    }
+    Ok(harness)
+}
+
+/// A value in the definition with its path for error messages
+struct Node<'a> {
+    value: &'a Value,
+    at: String,
+}
+
+impl<'a> Node<'a> {
+    fn invalid(&self, message: impl Into<String>) -> ConfigError {
+        ConfigError::Invalid {
+            at: self.at.clone(),
+            message: message.into(),
+        }
+    }
+
+    fn mapping(&self) -> Result<&'a Mapping, ConfigError> {
+        self.value
+            .as_mapping()
+            .ok_or_else(|| self.invalid("expected a mapping"))
+    }
+
+    fn get(&self, key: &str) -> Option<Self> {
+        let value = self.value.get(key)?;
+        let at = match self.at.as_str() {
+            "the top level" => key.to_string(),
+            at => format!("{}.{}", at, key),
+        };
+        Some(Self { value, at })
+    }
+
+    fn required(&self, key: &str) -> Result<Self, ConfigError> {
+        self.get(key)
+            .ok_or_else(|| self.invalid(format!("missing '{}'", key)))
+    }
+
+    /// Fails if the mapping has keys other than `keys`
+    fn allow_keys(&self, keys: &[&str]) -> Result<(), ConfigError> {
+        for key in self.mapping()?.keys() {
+            let key = key.as_str().unwrap_or_default();
+            if !keys.contains(&key) {
+                return Err(self.invalid(format!(
+                    "unknown key '{}', expected one of: {}",
+                    key,
+                    keys.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The elements of the sequence under `key`, none if it is unset
+    fn list(&self, key: &str) -> Result<Vec<Self>, ConfigError> {
+        let Some(node) = self.get(key) else {
+            return Ok(Vec::new());
+        };
+        let items = node
+            .value
+            .as_sequence()
+            .ok_or_else(|| node.invalid("expected a list"))?;
+        Ok(items
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| Self {
+                value,
+                at: format!("{}[{}]", node.at, idx),
+            })
+            .collect())
+    }
+
+    fn str(&self) -> Result<&'a str, ConfigError> {
+        self.value
+            .as_str()
+            .ok_or_else(|| self.invalid("expected a string"))
+    }
+
+    /// A scalar as text, so numbers can be passed as arguments unquoted
+    fn text(&self) -> Result<String, ConfigError> {
+        match self.value {
+            Value::String(text) => Ok(text.clone()),
+            Value::Number(number) => Ok(number.to_string()),
+            Value::Bool(flag) => Ok(flag.to_string()),
+            _ => Err(self.invalid("expected a string")),
+        }
+    }
+
+    fn texts(&self) -> Result<Vec<String>, ConfigError> {
+        let items = self
+            .value
+            .as_sequence()
+            .ok_or_else(|| self.invalid("expected a list"))?;
+        items
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| {
+                Node {
+                    value,
+                    at: format!("{}[{}]", self.at, idx),
+                }
+                .text()
+            })
+            .collect()
+    }
+
+    fn text_map(&self) -> Result<HashMap<String, String>, ConfigError> {
+        self.mapping()?
+            .keys()
+            .map(|key| {
+                let key = key
+                    .as_str()
+                    .ok_or_else(|| self.invalid("expected string keys"))?;
+                Ok((key.to_string(), self.required(key)?.text()?))
+            })
+            .collect()
+    }
+
+    fn u16(&self) -> Result<u16, ConfigError> {
+        self.value
+            .as_u64()
+            .and_then(|number| u16::try_from(number).ok())
+            .ok_or_else(|| self.invalid("expected a number from 0 to 65535"))
+    }
+
+    fn i32(&self) -> Result<i32, ConfigError> {
+        self.value
+            .as_i64()
+            .and_then(|number| i32::try_from(number).ok())
+            .ok_or_else(|| self.invalid("expected an exit code"))
+    }
+
+    fn secs(&self) -> Result<Duration, ConfigError> {
+        self.value
+            .as_f64()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or_else(|| self.invalid("expected seconds"))
+    }
+}
+
+fn parse_service(node: &Node<'_>, root_dir: &Path) -> Result<SubProcessService, ConfigError> {
+    node.allow_keys(&["name", "command", "args", "env", "cwd", "readiness"])?;
+    let mut service = SubProcessService::new(
+        node.required("name")?.str()?,
+        node.required("command")?.str()?,
+        node.get("args")
+            .map_or(Ok(Vec::new()), |args| args.texts())?,
+    );
+    if let Some(env) = node.get("env") {
+        service.env = env.text_map()?;
+    }
+    if let Some(cwd) = node.get("cwd") {
+        service.cwd = Some(root_dir.join(cwd.str()?));
+    }
+    if let Some(readiness) = node.get("readiness") {
+        readiness.allow_keys(&["tcp", "http", "command", "log", "timeout", "interval"])?;
+        let probes = ["tcp", "http", "command", "log"]
+            .into_iter()
+            .filter_map(|kind| Some((kind, readiness.get(kind)?)))
+            .collect::<Vec<_>>();
+        let [(kind, probe)] = probes.as_slice() else {
+            return Err(readiness.invalid("expected one of tcp, http, command or log"));
+        };
+        service.readiness = Some(match *kind {
+            "tcp" => ReadinessProbe::TcpPort(probe.u16()?),
+            "http" if probe.value.is_mapping() => {
+                probe.allow_keys(&["url", "status"])?;
+                ReadinessProbe::HttpGet {
+                    url: probe.required("url")?.str()?.to_string(),
+                    expect_status: probe.get("status").map_or(Ok(200), |status| status.u16())?,
+                }
+            }
+            "http" => ReadinessProbe::HttpGet {
+                url: probe.str()?.to_string(),
+                expect_status: 200,
+            },
+            "command" => {
+                let mut args = match probe.value {
+                    Value::String(script) =>
+                        vec!["sh".to_string(), "-c".to_string(), script.clone()],
+                    _ => probe.texts()?,
+                };
+                if args.is_empty() {
+                    return Err(probe.invalid("expected a command"));
+                }
+                ReadinessProbe::Command {
+                    program: args.remove(0),
+                    args,
+                }
+            }
+            _ => ReadinessProbe::LogPattern(
+                Regex::new(probe.str()?).map_err(|e| probe.invalid(e.to_string()))?,
+            ),
+        });
+        if let Some(timeout) = readiness.get("timeout") {
+            service.readiness_policy.timeout = timeout.secs()?;
+        }
+        if let Some(interval) = readiness.get("interval") {
+            service.readiness_policy.interval = interval.secs()?;
+        }
+    }
+    Ok(service)
+}
+
+fn parse_step(node: &Node<'_>) -> Result<TestStep, ConfigError> {
+    let kinds = ["start", "stop", "http", "shell", "wait"];
+    let found = kinds
+        .into_iter()
+        .filter_map(|kind| Some((kind, node.get(kind)?)))
+        .collect::<Vec<_>>();
+    let [(kind, step)] = found.as_slice() else {
+        return Err(node.invalid("expected one of start, stop, http, shell or wait"));
+    };
+    node.allow_keys(&["name", kind])?;
+    let name = node.get("name").map(|name| name.str()).transpose()?;
+    let executor: Box<dyn ServiceStepExecutor> = match *kind {
+        "start" => {
+            let service_name = step.str()?;
+            Box::new(SubProcessServiceStarter {
+                name: name.map_or_else(|| format!("Start_{}", service_name), str::to_string),
+                description: format!("Starts {}", service_name),
+                service_name: service_name.to_string(),
+                wait_after: None,
+            })
+        }
+        "stop" => {
+            let service_name = step.str()?;
+            Box::new(SubProcessServiceStopper {
+                name: name.map_or_else(|| format!("Stop_{}", service_name), str::to_string),
+                description: format!("Stops {}", service_name),
+                service_name: service_name.to_string(),
+                wait_after: None,
+            })
+        }
+        "http" => Box::new(parse_http(step, name)?),
+        "shell" => Box::new(parse_shell(step, name)?),
+        _ => Box::new(parse_wait(step, name)?),
+    };
+    Ok(TestStep::Service(executor))
+}
+
+fn parse_http(node: &Node<'_>, name: Option<&str>) -> Result<HttpRequestStep, ConfigError> {
+    node.allow_keys(&[
+        "url", "method", "status", "headers", "body", "json", "timeout", "save_as",
+    ])?;
+    let url = node.required("url")?.str()?;
+    let method = node
+        .get("method")
+        .map_or(Ok("GET"), |method| method.str())?;
+    let mut step = HttpRequestStep::get(name.unwrap_or(&format!("{} {}", method, url)), url);
+    step.method = method.to_string();
+    if let Some(status) = node.get("status") {
+        step.expect_status = Some(status.u16()?);
+    }
+    if let Some(headers) = node.get("headers") {
+        step.headers = headers.text_map()?.into_iter().collect();
+        step.headers.sort();
+    }
+    if let Some(body) = node.get("body") {
+        step.body = Some(body.text()?);
+    }
+    if let Some(json) = node.get("json") {
+        step.expect_json =
+            Some(serde_json::to_value(json.value).map_err(|e| json.invalid(e.to_string()))?);
+    }
+    if let Some(timeout) = node.get("timeout") {
+        step.timeout = timeout.secs()?;
+    }
+    if let Some(key) = node.get("save_as") {
+        step.context_key = Some(key.str()?.to_string());
+    }
+    Ok(step)
+}
+
+fn parse_shell(node: &Node<'_>, name: Option<&str>) -> Result<ShellCommandStep, ConfigError> {
+    if let Value::String(script) = node.value {
+        return Ok(ShellCommandStep::sh(name.unwrap_or(script), script));
+    }
+    node.allow_keys(&[
+        "script",
+        "command",
+        "args",
+        "env",
+        "cwd",
+        "exit_code",
+        "timeout",
+        "save_as",
+    ])?;
+    let mut step = match (node.get("script"), node.get("command")) {
+        (Some(script), None) => {
+            let script = script.str()?;
+            ShellCommandStep::sh(name.unwrap_or(script), script)
+        }
+        (None, Some(command)) => {
+            let command = command.str()?;
+            let args = node
+                .get("args")
+                .map_or(Ok(Vec::new()), |args| args.texts())?;
+            let default_name = [command.to_string()]
+                .into_iter()
+                .chain(args.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(" ");
+            ShellCommandStep::new(name.unwrap_or(&default_name), command, args)
+        }
+        _ => return Err(node.invalid("expected either 'script' or 'command'")),
+    };
+    if let Some(env) = node.get("env") {
+        step.env = env.text_map()?;
+    }
+    if let Some(cwd) = node.get("cwd") {
+        step.cwd = Some(PathBuf::from(cwd.str()?));
+    }
+    if let Some(exit_code) = node.get("exit_code") {
+        step.expect_exit_code = Some(exit_code.i32()?);
+    }
+    if let Some(timeout) = node.get("timeout") {
+        step.timeout = timeout.secs()?;
+    }
+    if let Some(key) = node.get("save_as") {
+        step.context_key = Some(key.str()?.to_string());
+    }
+    Ok(step)
+}
+
+fn parse_wait(node: &Node<'_>, name: Option<&str>) -> Result<WaitStep, ConfigError> {
+    if !node.value.is_mapping() {
+        let duration = node.secs()?;
+        return Ok(WaitStep {
+            name: name.map_or_else(
+                || format!("Wait_{}s", duration.as_secs_f64()),
+                str::to_string,
+            ),
+            until: Wait::Elapsed(duration),
+        });
+    }
+    node.allow_keys(&["service", "timeout"])?;
+    let service_name = node.required("service")?.str()?;
+    let policy = PollPolicy {
+        timeout: node
+            .get("timeout")
+            .map_or(Ok(PollPolicy::default().timeout), |timeout| timeout.secs())?,
+        ..Default::default()
+    };
+    Ok(WaitStep {
+        name: name.map_or_else(|| format!("Wait_For_{}", service_name), str::to_string),
+        until: Wait::Healthy(service_name.to_string(), policy),
+    })
+}
+
+/// What a [`WaitStep`] waits for
+#[derive(Debug, Clone)]
+enum Wait {
+    Elapsed(Duration),
+    /// The service passes its health check, polled according to the policy
+    Healthy(String, PollPolicy),
+}
+
+/// A `wait` step of a test definition
+#[derive(Debug, Clone)]
+struct WaitStep {
+    name: String,
+    until: Wait,
+}
+
+impl ServiceStepExecutor for WaitStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        match &self.until {
+            Wait::Elapsed(duration) => std::thread::sleep(*duration),
+            Wait::Healthy(service_name, policy) => {
+                env.wait_until_healthy(service_name, policy)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StepStatus;
+
+    #[test]
+    fn test_run_yaml_definition() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("index.html"), "<h1>Hello</h1>").unwrap();
+        let yaml = r#"
+name: YamlTester
+services:
+  - name: Web
+    command: python3
+    args: [-m, http.server, "{{port:web}}", --bind, 127.0.0.1]
+    cwd: .
+    readiness:
+      http: "http://127.0.0.1:{{port:web}}/"
+      timeout: 10
+steps:
+  - start: Web
+  - name: Fetch_Index
+    http: { url: "http://127.0.0.1:{{port:web}}/index.html", save_as: index }
+  - shell:
+      command: test
+      args: [-f, index.html]
+  - wait: { service: Web, timeout: 1 }
+  - stop: Web
+"#;
+        let config = dir.path().join("checkout.yaml");
+        fs::write(&config, yaml).unwrap();
+
+        let harness = load(&config).expect("Failed to load definition");
+        assert_eq!(harness.root_dir, dir.path().to_string_lossy());
+        let context = harness.context.clone();
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        let names = report
+            .steps
+            .iter()
+            .map(|step| (step.name.as_str(), &step.status))
+            .collect::<Vec<_>>();
+        assert_eq!(names, [
+            ("Start_Web", &StepStatus::Passed),
+            ("Fetch_Index", &StepStatus::Passed),
+            ("test -f index.html", &StepStatus::Passed),
+            ("Wait_For_Web", &StepStatus::Passed),
+            ("Stop_Web", &StepStatus::Passed),
+        ]);
+        assert_eq!(
+            context.get("index"),
+            Some(serde_json::Value::from("<h1>Hello</h1>"))
+        );
+    }
+
+    #[test]
+    fn test_load_toml_definition() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("smoke.toml");
+        let toml =
+            "name = \"TomlTester\"\n\n[[steps]]\nshell = \"true\"\n\n[[steps]]\nwait = 0.01\n";
+        fs::write(&path, toml).unwrap();
+
+        let harness = load(&path).expect("Failed to load definition");
+        assert_eq!(harness.test_name, "TomlTester");
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(report.steps.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_located() {
+        let error = |yaml| {
+            parse(yaml, Path::new("."))
+                .err()
+                .expect("Definition is valid")
+                .to_string()
+        };
+        assert_eq!(
+            error("name: T\nsteps:\n  - start: Api\n  - http: { method: POST }\n"),
+            "Invalid configuration at steps[1].http: missing 'url'"
+        );
+        assert_eq!(
+            error("name: T\nsteps:\n  - strat: Api\n"),
+            "Invalid configuration at steps[0]: expected one of start, stop, http, shell or wait"
+        );
+        assert_eq!(
+            error("name: T\nservices:\n  - name: Api\n    command: api\n    readiness: { tcp: 99999 }\n"),
+            "Invalid configuration at services[0].readiness.tcp: expected a number from 0 to 65535"
+        );
+    }
+}
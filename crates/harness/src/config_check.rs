@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// Format of a configuration file checked by [`ValidateConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+/// A step that checks a configuration file consumed by a service is well
+/// formed before the service is started, failing with the location of the
+/// first parse error. `path` is relative to the root directory of the test
+#[derive(Debug, Clone)]
+pub struct ValidateConfig {
+    /// Name the step is reported under
+    pub name: String,
+    pub path: PathBuf,
+    pub format: ConfigFormat,
+}
+
+impl ServiceStepExecutor for ValidateConfig {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let path = env.resolve(&self.path);
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| StepError::io(format!("Failed to read config {}", path.display()), e))?;
+
+        let (format, location, message) = match self.format {
+            ConfigFormat::Json => match serde_json::from_str::<serde_json::Value>(&contents) {
+                Ok(_) => return Ok(()),
+                Err(e) => ("JSON", Some((e.line(), e.column())), e.to_string()),
+            },
+            ConfigFormat::Yaml => match serde_yaml::from_str::<serde_yaml::Value>(&contents) {
+                Ok(_) => return Ok(()),
+                Err(e) => (
+                    "YAML",
+                    e.location()
+                        .map(|location| (location.line(), location.column())),
+                    e.to_string(),
+                ),
+            },
+            ConfigFormat::Toml => match contents.parse::<toml::Table>() {
+                Ok(_) => return Ok(()),
+                Err(e) => (
+                    "TOML",
+                    e.span().map(|span| line_and_column(&contents, span.start)),
+                    e.message().to_string(),
+                ),
+            },
+        };
+        Err(StepError::Assertion(match location {
+            Some((line, column)) => format!(
+                "Invalid {} in {} at line {}, column {}: {}",
+                format,
+                path.display(),
+                line,
+                column,
+                message
+            ),
+            None => format!("Invalid {} in {}: {}", format, path.display(), message),
+        }))
+    }
+}
+
+/// One-based line and column of a byte offset
+fn line_and_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |idx| idx + 1) + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_validate_config_reports_yaml_error_line() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("good.yaml"),
+            "server:\n  port: 8080\n  hosts: [a, b]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.path().join("bad.yaml"),
+            "server:\n  port: 8080\n  hosts: [a, b\nlogging: debug\n",
+        )
+        .unwrap();
+        let mut harness = TestHarness::new("ConfigTester", root.path().to_str().unwrap());
+
+        ValidateConfig {
+            name: "Validate_Good_Config".to_string(),
+            path: PathBuf::from("good.yaml"),
+            format: ConfigFormat::Yaml,
+        }
+        .execute(&mut harness.step_env())
+        .expect("Valid config was rejected");
+
+        let error = ValidateConfig {
+            name: "Validate_Bad_Config".to_string(),
+            path: PathBuf::from("bad.yaml"),
+            format: ConfigFormat::Yaml,
+        }
+        .execute(&mut harness.step_env())
+        .unwrap_err()
+        .to_string();
+        assert!(error.starts_with("Invalid YAML"), "{}", error);
+        assert!(error.contains("at line 4"), "{}", error);
+    }
+
+    #[test]
+    fn test_line_and_column() {
+        assert_eq!(line_and_column("a = 1\nb = \n", 10), (2, 5));
+        assert_eq!(line_and_column("x", 0), (1, 1));
+    }
+}
@@ -0,0 +1,129 @@
+use std::env;
+use std::fmt::Debug;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Instant;
+
+use tracing::warn;
+
+use crate::{OutputLine, OutputStream, ServiceError};
+
+/// CLI that [`crate::DockerService`] and [`crate::ComposeService`] manage
+/// containers with. Docker and Podman accept the same commands, so
+/// implementations only differ in the executable
+pub trait ContainerRuntime: Debug + Send + Sync {
+    /// Executable of the CLI, e.g. `docker`
+    fn program(&self) -> &str;
+
+    /// Runs the CLI with `args` on behalf of `service_name`, returning its
+    /// trimmed stdout
+    fn run(&self, service_name: &str, args: &[&str]) -> Result<String, ServiceError> {
+        let output = Command::new(self.program())
+            .args(args)
+            .output()
+            .map_err(|e| {
+                ServiceError::failed(
+                    service_name,
+                    format!("Failed to run {}: {}", self.program(), e),
+                )
+            })?;
+        if !output.status.success() {
+            return Err(ServiceError::failed(
+                service_name,
+                format!(
+                    "{} {} exited with {}: {}",
+                    self.program(),
+                    args.first().unwrap_or(&""),
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Output of a command printing logs, such as `logs`. All lines are
+    /// stamped with the time they were read
+    fn logs(&self, args: &[&str]) -> Vec<OutputLine> {
+        let Ok(output) = Command::new(self.program()).args(args).output() else {
+            return Vec::new();
+        };
+        let at = Instant::now();
+        let lines = |bytes: &[u8], stream| {
+            String::from_utf8_lossy(bytes)
+                .lines()
+                .map(|line| OutputLine {
+                    stream,
+                    line: line.to_string(),
+                    at,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut output_lines = lines(&output.stdout, OutputStream::Stdout);
+        output_lines.extend(lines(&output.stderr, OutputStream::Stderr));
+        output_lines
+    }
+}
+
+/// The `docker` CLI
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Docker;
+
+impl ContainerRuntime for Docker {
+    fn program(&self) -> &str { "docker" }
+}
+
+/// The `podman` CLI, which also runs rootless on CI runners without a Docker
+/// daemon
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Podman;
+
+impl ContainerRuntime for Podman {
+    fn program(&self) -> &str { "podman" }
+}
+
+/// Runtime selected by `HARNESS_CONTAINER_RUNTIME`, `docker` or `podman`,
+/// defaulting to Docker
+pub fn container_runtime_from_env() -> Arc<dyn ContainerRuntime> {
+    let name = env::var("HARNESS_CONTAINER_RUNTIME").ok();
+    runtime_named(name.as_deref())
+}
+
+/// Name for a resource such as a compose project derived from `name`, unique
+/// per test process so parallel runs do not share resources, using only
+/// lowercase letters, digits and dashes
+pub(crate) fn unique_resource_name(name: &str) -> String {
+    format!("harness-{}-{}", name, std::process::id())
+        .to_lowercase()
+        .replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "-")
+}
+
+fn runtime_named(name: Option<&str>) -> Arc<dyn ContainerRuntime> {
+    match name.map(str::trim) {
+        Some("podman") => Arc::new(Podman),
+        None | Some("" | "docker") => Arc::new(Docker),
+        Some(other) => {
+            warn!(
+                "Unknown container runtime '{}' in HARNESS_CONTAINER_RUNTIME, using docker",
+                other
+            );
+            Arc::new(Docker)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DockerService, Service};
+
+    #[test]
+    fn test_runtime_selection() {
+        assert_eq!(runtime_named(None).program(), "docker");
+        assert_eq!(runtime_named(Some("podman")).program(), "podman");
+        assert_eq!(runtime_named(Some("rkt")).program(), "docker");
+
+        let service = DockerService::with_runtime("Cache", "redis:7", Arc::new(Podman));
+        assert_eq!(service.required_tools(), ["podman"]);
+    }
+}
@@ -0,0 +1,204 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use serde_json::Value;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// Values shared between the steps of a test, keyed by name. Async steps
+/// store their output under their own name. Clones share the same store.
+/// Besides JSON values, it holds one value of each Rust type, e.g. a
+/// connection pool or a newtype around an auth token, see
+/// [`Context::insert_typed`]
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    values: Arc<RwLock<HashMap<String, Value>>>,
+    typed: Arc<RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>,
+}
+
+impl Context {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.values
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(key)
+            .cloned()
+    }
+
+    /// Looks up a value nested in the value under `key` by a JSON pointer
+    /// such as `/user/id`
+    pub fn pointer(&self, key: &str, pointer: &str) -> Option<Value> {
+        self.get(key)
+            .and_then(|value| value.pointer(pointer).cloned())
+    }
+
+    pub fn insert(&self, key: &str, value: Value) {
+        self.values
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(key.to_string(), value);
+    }
+
+    /// All values stored by name, as a JSON object
+    pub fn to_json(&self) -> Value {
+        let values = self
+            .values
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        Value::Object(
+            values
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        )
+    }
+
+    /// Stores `value` as the value of its type, replacing the previous one
+    pub fn insert_typed<T: Any + Send + Sync>(&self, value: T) {
+        self.typed
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// The value of type `T` stored by [`Context::insert_typed`]
+    pub fn typed<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let value = self
+            .typed
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&TypeId::of::<T>())
+            .cloned()?;
+        value.downcast().ok()
+    }
+}
+
+/// A step asserting that the value found at a JSON pointer within a context
+/// value equals `expected`
+#[derive(Debug, Clone)]
+pub struct AssertContextValue {
+    /// Name the step is reported under
+    pub name: String,
+    pub key: String,
+    /// JSON pointer into the value, an empty pointer refers to the whole value
+    pub pointer: String,
+    pub expected: Value,
+}
+
+impl ServiceStepExecutor for AssertContextValue {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let value = env.context.get(&self.key).ok_or_else(|| {
+            StepError::Assertion(format!("No context value stored under '{}'", self.key))
+        })?;
+        let actual = value.pointer(&self.pointer).ok_or_else(|| {
+            StepError::Assertion(format!(
+                "Context value '{}' has nothing at '{}': {}",
+                self.key, self.pointer, value
+            ))
+        })?;
+        if *actual != self.expected {
+            return Err(StepError::Assertion(format!(
+                "Context value '{}' at '{}' is {}, expected {}",
+                self.key, self.pointer, actual, self.expected
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{AsyncFnStep, StepStatus, TestHarness, TestStep};
+
+    #[test]
+    fn test_async_step_output_is_readable_by_later_step() {
+        let mut harness = TestHarness::new("ContextTester", ".");
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Create_User".to_string(),
+            description: "Produces the created user".to_string(),
+            futurefn: Box::new(|_| Box::new(async { Ok(json!({ "user": { "id": 42 } })) })),
+        })));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Read_User".to_string(),
+            description: "Consumes the created user".to_string(),
+            futurefn: Box::new(|context| {
+                let id = context.pointer("Create_User", "/user/id");
+                Box::new(async move { id.ok_or_else(|| "missing user id".to_string()) })
+            }),
+        })));
+        harness.add_step(TestStep::Service(Box::new(AssertContextValue {
+            name: "Check_User_Id".to_string(),
+            key: "Create_User".to_string(),
+            pointer: "/user/id".to_string(),
+            expected: json!(42),
+        })));
+        harness.add_step(TestStep::Service(Box::new(AssertContextValue {
+            name: "Check_Wrong_User_Id".to_string(),
+            key: "Create_User".to_string(),
+            pointer: "/user/id".to_string(),
+            expected: json!(7),
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert_eq!(report.steps[0].status, StepStatus::Passed);
+        assert_eq!(report.steps[1].status, StepStatus::Passed);
+        assert_eq!(report.steps[2].status, StepStatus::Passed);
+        assert_eq!(
+            report.steps[3].status,
+            StepStatus::Failed(
+                "Context value 'Create_User' at '/user/id' is 42, expected 7".to_string()
+            )
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct AuthToken(String);
+
+    #[derive(Debug)]
+    struct StoreToken;
+
+    impl ServiceStepExecutor for StoreToken {
+        fn name(&self) -> &str { "StoreToken" }
+
+        fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+            env.context.insert_typed(AuthToken("secret".to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_typed_values_are_shared_between_steps() {
+        let mut harness = TestHarness::new("ContextTester", ".");
+        harness.add_step(TestStep::Service(Box::new(StoreToken)));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Use_Token".to_string(),
+            description: "Reads the stored token".to_string(),
+            futurefn: Box::new(|context| {
+                let token = context.typed::<AuthToken>();
+                Box::new(async move {
+                    let token = token.ok_or_else(|| "missing token".to_string())?;
+                    Ok(json!(format!("Bearer {}", token.0)))
+                })
+            }),
+        })));
+        let context = harness.context.clone();
+        assert!(context.typed::<AuthToken>().is_none());
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(context.get("Use_Token"), Some(json!("Bearer secret")));
+        assert_eq!(
+            context.typed::<AuthToken>().as_deref(),
+            Some(&AuthToken("secret".to_string()))
+        );
+        assert!(context.typed::<String>().is_none());
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt::{self, Debug};
+
+use tracing::{info, warn};
+
+use crate::{PollPolicy, ServiceAction, ServiceError, ServiceStepExecutor, StepEnv, StepError};
+
+/// Hook run by [`BlueGreenDeployment`] to act on the deployment
+pub type DeploymentHook = Box<dyn Fn(&mut StepEnv<'_>) -> Result<(), String> + Send>;
+
+/// A step that verifies a blue/green deployment: it starts `green` next to
+/// the running `blue`, waits until green is healthy, switches traffic over,
+/// verifies the switch and finally stops blue. If green never becomes
+/// healthy, or switching or verifying fails, traffic is switched back with
+/// `rollback` and green is stopped, leaving blue serving
+pub struct BlueGreenDeployment {
+    /// Name the step is reported under
+    pub name: String,
+    pub blue: String,
+    pub green: String,
+    /// How long green gets to become healthy
+    pub health_policy: PollPolicy,
+    /// Moves traffic from blue to green, e.g. by rewriting a router config
+    /// or signalling the router
+    pub switch: DeploymentHook,
+    /// Checks that traffic actually reaches green
+    pub verify: DeploymentHook,
+    /// Moves traffic back to blue
+    pub rollback: DeploymentHook,
+}
+
+impl Debug for BlueGreenDeployment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlueGreenDeployment")
+            .field("name", &self.name)
+            .field("blue", &self.blue)
+            .field("green", &self.green)
+            .finish()
+    }
+}
+
+impl BlueGreenDeployment {
+    /// Undoes a partially applied deployment and returns the original error
+    fn roll_back(&self, env: &mut StepEnv<'_>, switched: bool, error: StepError) -> StepError {
+        warn!(
+            "Blue/green deployment failed, rolling back to '{}': {}",
+            self.blue, error
+        );
+        if switched {
+            if let Err(e) = (self.rollback)(env) {
+                return StepError::Failed(format!(
+                    "{}; rolling back traffic to '{}' failed: {}",
+                    error, self.blue, e
+                ));
+            }
+        }
+        if let Err(e) = env.service(&self.green).and_then(|green| green.stop()) {
+            return StepError::Failed(format!(
+                "{}; stopping '{}' failed: {}",
+                error, self.green, e
+            ));
+        }
+        error
+    }
+}
+
+impl ServiceStepExecutor for BlueGreenDeployment {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        if !env.service(&self.blue)?.is_running() {
+            return Err(ServiceError::NotRunning(self.blue.clone()).into());
+        }
+
+        info!("Blue/green: starting green service '{}'", self.green);
+        let green_idx = env.service_index(&self.green)?;
+        env.start_service(green_idx)?;
+
+        info!("Blue/green: waiting for '{}' to become healthy", self.green);
+        if let Err(error) = env.wait_until_healthy(&self.green, &self.health_policy) {
+            return Err(self.roll_back(env, false, error));
+        }
+
+        info!("Blue/green: switching traffic to '{}'", self.green);
+        if let Err(e) = (self.switch)(env) {
+            let error = StepError::Failed(format!(
+                "Switching traffic to '{}' failed: {}",
+                self.green, e
+            ));
+            return Err(self.roll_back(env, true, error));
+        }
+
+        info!("Blue/green: verifying traffic reaches '{}'", self.green);
+        if let Err(e) = (self.verify)(env) {
+            let error = StepError::Assertion(format!(
+                "Verifying the switch to '{}' failed: {}",
+                self.green, e
+            ));
+            return Err(self.roll_back(env, true, error));
+        }
+
+        info!("Blue/green: stopping blue service '{}'", self.blue);
+        env.service(&self.blue)?.stop()?;
+        Ok(())
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![
+            ServiceAction::Start(self.green.clone()),
+            ServiceAction::Stop(self.blue.clone()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{Service, TestHarness};
+
+    #[derive(Debug)]
+    struct MockService {
+        name: &'static str,
+        running: Arc<AtomicBool>,
+    }
+
+    impl Service for MockService {
+        fn name(&self) -> &str { self.name }
+
+        fn start(&mut self) -> Result<(), ServiceError> {
+            self.running.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn is_running(&self) -> bool { self.running.load(Ordering::SeqCst) }
+
+        fn stop(&mut self) -> Result<(), ServiceError> {
+            self.running.store(false, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    struct Deployment {
+        harness: TestHarness,
+        blue: Arc<AtomicBool>,
+        green: Arc<AtomicBool>,
+        /// Backends the router was switched to, in order
+        switches: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    fn deployment() -> Deployment {
+        let blue = Arc::new(AtomicBool::new(true));
+        let green = Arc::new(AtomicBool::new(false));
+        let mut harness = TestHarness::new("BlueGreenTester", ".");
+        harness.add_service(Box::new(MockService {
+            name: "router",
+            running: Arc::new(AtomicBool::new(true)),
+        }));
+        harness.add_service(Box::new(MockService {
+            name: "blue",
+            running: blue.clone(),
+        }));
+        harness.add_service(Box::new(MockService {
+            name: "green",
+            running: green.clone(),
+        }));
+        Deployment {
+            harness,
+            blue,
+            green,
+            switches: Arc::default(),
+        }
+    }
+
+    fn step(switches: &Arc<Mutex<Vec<&'static str>>>, verify_ok: bool) -> BlueGreenDeployment {
+        let (switch_route, verify_route, rollback_route) =
+            (switches.clone(), switches.clone(), switches.clone());
+        BlueGreenDeployment {
+            name: "Deploy_Green".to_string(),
+            blue: "blue".to_string(),
+            green: "green".to_string(),
+            health_policy: PollPolicy {
+                interval: Duration::from_millis(10),
+                timeout: Duration::from_secs(1),
+                ..Default::default()
+            },
+            switch: Box::new(move |_| {
+                switch_route.lock().unwrap().push("green");
+                Ok(())
+            }),
+            verify: Box::new(move |_| match verify_route.lock().unwrap().last() {
+                Some(&"green") if verify_ok => Ok(()),
+                _ => Err("traffic does not reach green".to_string()),
+            }),
+            rollback: Box::new(move |_| {
+                rollback_route.lock().unwrap().push("blue");
+                Ok(())
+            }),
+        }
+    }
+
+    #[test]
+    fn test_blue_green_switches_and_stops_blue() {
+        let mut deployment = deployment();
+        step(&deployment.switches, true)
+            .execute(&mut deployment.harness.step_env())
+            .expect("Deployment failed");
+
+        assert_eq!(*deployment.switches.lock().unwrap(), ["green"]);
+        let ready = &deployment.harness.ready_log;
+        assert_eq!(
+            ready
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["green"]
+        );
+        assert!(deployment.green.load(Ordering::SeqCst));
+        assert!(!deployment.blue.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_blue_green_rolls_back_on_failed_verification() {
+        let mut deployment = deployment();
+        let error = step(&deployment.switches, false)
+            .execute(&mut deployment.harness.step_env())
+            .unwrap_err();
+
+        assert!(matches!(error, StepError::Assertion(_)), "{:?}", error);
+        assert!(
+            error.to_string().starts_with("Verifying the switch"),
+            "{}",
+            error
+        );
+        assert_eq!(*deployment.switches.lock().unwrap(), ["green", "blue"]);
+        assert!(!deployment.green.load(Ordering::SeqCst));
+        assert!(deployment.blue.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tracing::info;
+
+use crate::{
+    container_runtime_from_env, shell_quote, ContainerNetwork, ContainerRuntime, OutputLine,
+    PollPolicy, PortAllocator, ReadinessProbe, Service, ServiceError,
+};
+
+/// A service running a container with the CLI of a [`ContainerRuntime`].
+/// The container is created on start and removed on stop, and it counts as
+/// healthy once all mapped host ports accept connections
+pub struct DockerService {
+    pub name: String,
+    pub image: String,
+    /// Arguments passed to the entrypoint of the image
+    pub args: Vec<String>,
+    /// Host ports mapped to container ports
+    pub ports: Vec<(u16, u16)>,
+    pub env: HashMap<String, String>,
+    /// Host paths mounted at container paths
+    pub volumes: Vec<(PathBuf, String)>,
+    /// How [`crate::SubProcessServiceStarter`] waits for the mapped ports
+    pub readiness_policy: PollPolicy,
+    pub runtime: Arc<dyn ContainerRuntime>,
+    /// Names of the networks the container is attached to, see
+    /// [`DockerService::join`]
+    pub networks: Vec<String>,
+    /// Name the container is reachable by on its networks, derived from the
+    /// name of the service by default
+    pub network_alias: String,
+    container_id: Option<String>,
+    last_command_line: Option<String>,
+}
+
+impl DockerService {
+    /// Runs the container with the runtime selected by
+    /// [`container_runtime_from_env`]
+    pub fn new(name: &str, image: &str) -> Self {
+        Self::with_runtime(name, image, container_runtime_from_env())
+    }
+
+    pub fn with_runtime(name: &str, image: &str, runtime: Arc<dyn ContainerRuntime>) -> Self {
+        Self {
+            name: name.to_string(),
+            image: image.to_string(),
+            args: Vec::new(),
+            ports: Vec::new(),
+            env: HashMap::new(),
+            volumes: Vec::new(),
+            readiness_policy: PollPolicy::default(),
+            runtime,
+            networks: Vec::new(),
+            network_alias: name
+                .to_lowercase()
+                .replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "-"),
+            container_id: None,
+            last_command_line: None,
+        }
+    }
+
+    /// Attaches the container to `network` when it starts
+    pub fn join(&mut self, network: &ContainerNetwork) {
+        self.networks.push(network.network_name.clone());
+    }
+
+    /// Id of the running container
+    pub fn container_id(&self) -> Option<&str> { self.container_id.as_deref() }
+
+    /// Arguments of `docker run` creating the container
+    fn run_args(&self) -> Vec<String> {
+        let mut args = vec!["run".to_string(), "--detach".to_string()];
+        // Further networks are connected once the container exists
+        if let Some(network) = self.networks.first() {
+            args.extend([
+                "--network".to_string(),
+                network.clone(),
+                "--network-alias".to_string(),
+                self.network_alias.clone(),
+            ]);
+        }
+        for (host, container) in &self.ports {
+            args.extend(["--publish".to_string(), format!("{}:{}", host, container)]);
+        }
+        let mut env = self.env.iter().collect::<Vec<_>>();
+        env.sort();
+        for (key, value) in env {
+            args.extend(["--env".to_string(), format!("{}={}", key, value)]);
+        }
+        for (host, container) in &self.volumes {
+            args.extend([
+                "--volume".to_string(),
+                format!("{}:{}", host.display(), container),
+            ]);
+        }
+        args.push(self.image.clone());
+        args.extend(self.args.iter().cloned());
+        args
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, ServiceError> {
+        self.runtime.run(&self.name, args)
+    }
+}
+
+impl Debug for DockerService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DockerService")
+            .field("name", &self.name)
+            .field("image", &self.image)
+            .field("runtime", &self.runtime)
+            .field("container_id", &self.container_id)
+            .finish()
+    }
+}
+
+impl Service for DockerService {
+    fn name(&self) -> &str { &self.name }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        if self.container_id.is_some() {
+            return Err(ServiceError::AlreadyRunning(self.name.clone()));
+        }
+        let args = self.run_args();
+        let command_line = std::iter::once(self.runtime.program())
+            .chain(args.iter().map(String::as_str))
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        info!("Starting container '{}': {}", self.name, command_line);
+        self.last_command_line = Some(command_line);
+
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        let id = self.run(&args)?;
+        for network in self.networks.iter().skip(1) {
+            let connected = self.run(&[
+                "network",
+                "connect",
+                "--alias",
+                &self.network_alias,
+                network,
+                &id,
+            ]);
+            if let Err(e) = connected {
+                let _ = self.run(&["rm", "--force", &id]);
+                return Err(e);
+            }
+        }
+        self.container_id = Some(id);
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.container_id.as_deref().is_some_and(|id| {
+            self.run(&["inspect", "--format", "{{.State.Running}}", id])
+                .is_ok_and(|running| running == "true")
+        })
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        if let Some(id) = &self.container_id {
+            self.run(&["rm", "--force", id])?;
+            self.container_id = None;
+        }
+        Ok(())
+    }
+
+    fn is_healthy(&mut self) -> bool {
+        self.is_running()
+            && self
+                .ports
+                .iter()
+                .all(|(host, _)| ReadinessProbe::TcpPort(*host).check(Vec::new).is_ok())
+    }
+
+    fn readiness_policy(&self) -> Option<PollPolicy> {
+        (!self.ports.is_empty()).then_some(self.readiness_policy)
+    }
+
+    fn resolve_ports(&mut self, ports: &PortAllocator) -> Result<(), ServiceError> {
+        ports.substitute_in(
+            &self.name,
+            self.args.iter_mut().chain(self.env.values_mut()),
+        )
+    }
+
+    fn required_tools(&self) -> Vec<String> { vec![self.runtime.program().to_string()] }
+
+    fn last_command_line(&self) -> Option<String> { self.last_command_line.clone() }
+
+    fn output(&self) -> Vec<OutputLine> {
+        self.container_id
+            .as_deref()
+            .map_or_else(Vec::new, |id| self.runtime.logs(&["logs", id]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Docker;
+
+    #[test]
+    fn test_run_args() {
+        let mut service = DockerService::new("Postgres", "postgres:16");
+        service.ports.push((15432, 5432));
+        service
+            .env
+            .insert("POSTGRES_PASSWORD".to_string(), "secret".to_string());
+        service.volumes.push((
+            PathBuf::from("/tmp/data"),
+            "/var/lib/postgresql/data".to_string(),
+        ));
+        service.args = vec!["-c".to_string(), "fsync=off".to_string()];
+
+        assert_eq!(service.run_args(), [
+            "run",
+            "--detach",
+            "--publish",
+            "15432:5432",
+            "--env",
+            "POSTGRES_PASSWORD=secret",
+            "--volume",
+            "/tmp/data:/var/lib/postgresql/data",
+            "postgres:16",
+            "-c",
+            "fsync=off",
+        ]);
+        assert_eq!(service.readiness_policy(), Some(PollPolicy::default()));
+        assert_eq!(service.required_tools(), ["docker"]);
+    }
+
+    #[test]
+    fn test_join_network_under_alias() {
+        let network = ContainerNetwork::with_runtime("Backend", Arc::new(Docker));
+        let mut service = DockerService::with_runtime("Orders DB", "postgres:16", Arc::new(Docker));
+        service.join(&network);
+        assert_eq!(service.network_alias, "orders-db");
+        assert_eq!(service.run_args(), [
+            "run",
+            "--detach",
+            "--network",
+            &network.network_name,
+            "--network-alias",
+            "orders-db",
+            "postgres:16",
+        ]);
+    }
+}
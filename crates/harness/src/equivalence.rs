@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Write};
+
+use tracing::info;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// Rewrites a response body before it is compared
+pub type Normalizer = Box<dyn Fn(&str) -> String + Send>;
+
+/// A step that requests two endpoints and fails unless they respond the
+/// same, e.g. to shadow an old service with its replacement during a
+/// migration. Bodies and headers are compared, and statuses if
+/// `compare_status` is set
+pub struct AssertEquivalent {
+    /// Name the step is reported under
+    pub name: String,
+    /// May contain `{{port:name}}` placeholders, see
+    /// [`crate::PortAllocator`]
+    pub url_a: String,
+    pub url_b: String,
+    /// Headers left out of the comparison, matched case-insensitively, e.g.
+    /// `date`
+    pub ignore_headers: Vec<String>,
+    pub compare_status: bool,
+    /// Applied to both bodies before comparing, e.g. to blank out generated
+    /// ids
+    pub normalize: Option<Normalizer>,
+}
+
+impl Debug for AssertEquivalent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssertEquivalent")
+            .field("name", &self.name)
+            .field("url_a", &self.url_a)
+            .field("url_b", &self.url_b)
+            .field("ignore_headers", &self.ignore_headers)
+            .field("compare_status", &self.compare_status)
+            .finish()
+    }
+}
+
+struct Response {
+    status: u16,
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+impl AssertEquivalent {
+    async fn fetch(&self, url: &str) -> Result<Response, String> {
+        let response = reqwest::get(url)
+            .await
+            .map_err(|e| format!("Failed to request {}: {}", url, e))?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| {
+                !self
+                    .ignore_headers
+                    .iter()
+                    .any(|ignored| ignored.eq_ignore_ascii_case(name.as_str()))
+            })
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read the response of {}: {}", url, e))?;
+        let body = match &self.normalize {
+            Some(normalize) => normalize(&body),
+            None => body,
+        };
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+impl ServiceStepExecutor for AssertEquivalent {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let url_a = env.substitute_ports(&self.url_a)?;
+        let url_b = env.substitute_ports(&self.url_b)?;
+        let (a, b) =
+            env.block_on(async { tokio::try_join!(self.fetch(&url_a), self.fetch(&url_b)) })??;
+
+        let mut mismatches = Vec::new();
+        if self.compare_status && a.status != b.status {
+            mismatches.push(format!("status {} != {}", a.status, b.status));
+        }
+        if a.headers != b.headers {
+            mismatches.push(format!(
+                "headers differ:\n{}",
+                diff(&render_headers(&a.headers), &render_headers(&b.headers))
+            ));
+        }
+        if a.body != b.body {
+            mismatches.push(format!("bodies differ:\n{}", diff(&a.body, &b.body)));
+        }
+        if !mismatches.is_empty() {
+            return Err(StepError::Assertion(format!(
+                "Responses differ (- {}, + {}), {}",
+                url_a,
+                url_b,
+                mismatches.join(", ")
+            )));
+        }
+        info!("{} and {} respond the same", url_a, url_b);
+        Ok(())
+    }
+}
+
+fn render_headers(headers: &BTreeMap<String, String>) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Line diff of `a` and `b`, lines only in `a` are prefixed with `-` and
+/// lines only in `b` with `+`
+fn diff(a: &str, b: &str) -> String {
+    let a = a.lines().collect::<Vec<_>>();
+    let b = b.lines().collect::<Vec<_>>();
+    // Lengths of the longest common subsequences of the suffixes
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            let _ = writeln!(out, "  {}", a[i]);
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            let _ = writeln!(out, "- {}", a[i]);
+            i += 1;
+        } else {
+            let _ = writeln!(out, "+ {}", b[j]);
+            j += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::TestHarness;
+
+    /// Serves `body` to every request, returning the URL of the endpoint
+    fn serve(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        url
+    }
+
+    fn step(url_a: String, url_b: String) -> AssertEquivalent {
+        AssertEquivalent {
+            name: "Compare_Services".to_string(),
+            url_a,
+            url_b,
+            ignore_headers: vec!["Date".to_string()],
+            compare_status: true,
+            normalize: None,
+        }
+    }
+
+    #[test]
+    fn test_equivalent_endpoints() {
+        let mut harness = TestHarness::new("EquivalenceTester", ".");
+        let body = "{\"id\": 1}\n{\"id\": 2}\n";
+        step(serve(body), serve(body))
+            .execute(&mut harness.step_env())
+            .expect("Identical endpoints should be equivalent");
+    }
+
+    #[test]
+    fn test_differing_endpoints_report_diff() {
+        let mut harness = TestHarness::new("EquivalenceTester", ".");
+        let error = step(serve("a\nb\nc\n"), serve("a\nB\nc\n"))
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("bodies differ"), "{}", error);
+        assert!(error.contains("  a\n- b\n+ B\n  c\n"), "{}", error);
+    }
+}
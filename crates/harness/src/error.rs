@@ -0,0 +1,165 @@
+use std::any::Any;
+use std::io;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::MissingTool;
+
+/// Error of a [`crate::Service`]
+#[derive(Debug, Error)]
+pub enum ServiceError {
+    #[error("Service '{0}' not found")]
+    NotFound(String),
+    #[error("Service '{0}' is already running")]
+    AlreadyRunning(String),
+    #[error("Service '{0}' is not running")]
+    NotRunning(String),
+    #[error("Failed to start service '{service}': {source}")]
+    Start {
+        service: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Failed to stop service '{service}': {source}")]
+    Stop {
+        service: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Service '{service}' does not support {operation}")]
+    Unsupported {
+        service: String,
+        operation: &'static str,
+    },
+    #[error("Service '{service}' failed: {message}")]
+    Failed { service: String, message: String },
+}
+
+impl ServiceError {
+    /// A failure of `service` described by `message`
+    pub fn failed(service: &str, message: impl Into<String>) -> Self {
+        Self::Failed {
+            service: service.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Error of a [`crate::ServiceStepExecutor`]
+#[derive(Debug, Error)]
+pub enum StepError {
+    #[error(transparent)]
+    Service(#[from] ServiceError),
+    /// A condition checked by the step does not hold
+    #[error("{0}")]
+    Assertion(String),
+    /// A condition the step waited for did not occur in time
+    #[error("Timed out after {after:?}: {what}")]
+    Timeout { what: String, after: Duration },
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+    /// The async work of the step was cancelled by a signal, see
+    /// [`crate::TestHarness::install_signal_handlers`]
+    #[error("Interrupted by {0}")]
+    Interrupted(&'static str),
+    /// The step panicked, with the panic message
+    #[error("Step panicked: {0}")]
+    Panicked(String),
+    /// Any other failure, e.g. returned by an async step
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl StepError {
+    pub(crate) fn io(context: impl Into<String>, source: io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn panicked(payload: &(dyn Any + Send)) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        Self::Panicked(message)
+    }
+}
+
+impl From<String> for StepError {
+    fn from(message: String) -> Self { Self::Failed(message) }
+}
+
+/// Error of a [`crate::TestHarness`] run
+#[derive(Debug, Error)]
+pub enum HarnessError {
+    #[error("Missing required tools: {}", join(.0, ", "))]
+    MissingTools(Vec<MissingTool>),
+    #[error("Invalid test plan: {}", join(.0, "; "))]
+    InvalidPlan(Vec<String>),
+    #[error("Step '{0}' not found")]
+    UnknownStep(String),
+    /// The run was stopped by a signal, see
+    /// [`crate::TestHarness::install_signal_handlers`]
+    #[error("Run interrupted by {signal}")]
+    Interrupted { signal: &'static str },
+    /// A fixture could not be set up, see [`crate::Fixture::setup`]
+    #[error("Fixture '{name}' failed: {source}")]
+    FixtureFailed {
+        name: String,
+        #[source]
+        source: StepError,
+    },
+    /// A required step failed under [`crate::FailurePolicy::Abort`]
+    #[error("Step {index} '{name}' failed: {source}")]
+    StepFailed {
+        /// Position of the step in the plan, starting at 1
+        index: usize,
+        name: String,
+        #[source]
+        source: StepError,
+    },
+}
+
+fn join(items: &[impl ToString], separator: &str) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_step_failure_keeps_its_sources() {
+        let error = HarnessError::StepFailed {
+            index: 2,
+            name: "Start_Db".to_string(),
+            source: StepError::from(ServiceError::Start {
+                service: "Db".to_string(),
+                source: io::Error::new(io::ErrorKind::NotFound, "no such file"),
+            }),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "Step 2 'Start_Db' failed: Failed to start service 'Db': no such file"
+        );
+        let step_error = error.source().expect("Step error is the source");
+        assert!(step_error.is::<StepError>());
+        let io_error = step_error.source().expect("IO error is the source");
+        assert!(io_error.is::<io::Error>());
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::{PollPolicy, ServiceAction, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that waits for a one-shot job service to exit on its own and
+/// checks its exit code, any code passes if `expected_code` is unset. A job
+/// still running after `timeout` fails the step and is stopped if
+/// `kill_on_timeout` is set
+#[derive(Debug, Clone)]
+pub struct WaitForExit {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    pub expected_code: Option<i32>,
+    pub timeout: Duration,
+    pub kill_on_timeout: bool,
+}
+
+impl ServiceStepExecutor for WaitForExit {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let service = env.service(&self.service_name)?;
+        let policy = PollPolicy {
+            interval: Duration::from_millis(10),
+            timeout: self.timeout,
+            ..Default::default()
+        };
+        let status = match policy.poll(|| service.exit_status().ok_or(())) {
+            Ok(status) => status,
+            Err(()) => {
+                let mut what = format!("Service '{}' still running", self.service_name);
+                if self.kill_on_timeout {
+                    match service.stop() {
+                        Ok(()) => what.push_str(", stopped it"),
+                        Err(e) => what.push_str(&format!(", failed to stop it: {}", e)),
+                    }
+                }
+                return Err(StepError::Timeout {
+                    what,
+                    after: self.timeout,
+                });
+            }
+        };
+
+        match (self.expected_code, status.code()) {
+            (Some(expected), Some(code)) if code != expected => Err(StepError::Assertion(format!(
+                "Service '{}' exited with code {}, expected {}",
+                self.service_name, code, expected
+            ))),
+            (Some(expected), None) => Err(StepError::Assertion(format!(
+                "Service '{}' exited with {}, expected code {}",
+                self.service_name, status, expected
+            ))),
+            _ => {
+                info!("Service '{}' exited with {}", self.service_name, status);
+                Ok(())
+            }
+        }
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SubProcessService, TestHarness};
+
+    fn wait_for_job(script: &str, expected_code: i32, timeout: Duration) -> Result<(), StepError> {
+        let mut harness = TestHarness::new("ExitCodeTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Job", "sh", vec![
+            "-c".to_string(),
+            script.to_string(),
+        ])));
+        harness
+            .step_env()
+            .start_service(0)
+            .expect("Failed to start job");
+        WaitForExit {
+            name: "Wait_For_Job".to_string(),
+            service_name: "Job".to_string(),
+            expected_code: Some(expected_code),
+            timeout,
+            kill_on_timeout: true,
+        }
+        .execute(&mut harness.step_env())
+    }
+
+    #[test]
+    fn test_expected_exit_code_passes() {
+        wait_for_job("exit 3", 3, Duration::from_secs(5)).expect("Job exited with 3");
+    }
+
+    #[test]
+    fn test_unexpected_exit_code_fails() {
+        let error = wait_for_job("exit 3", 0, Duration::from_secs(5)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Service 'Job' exited with code 3, expected 0"
+        );
+    }
+
+    #[test]
+    fn test_running_job_is_stopped_on_timeout() {
+        let error = wait_for_job("sleep 30", 0, Duration::from_millis(100)).unwrap_err();
+        assert!(matches!(error, StepError::Timeout { .. }), "{:?}", error);
+        assert!(error.to_string().ends_with("stopped it"), "{}", error);
+    }
+}
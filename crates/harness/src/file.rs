@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::PathBuf;
+
+use regex::Regex;
+use tracing::info;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// A step checking a file written by a service, e.g. an export or a log.
+/// `path` is relative to the root directory of the test
+#[derive(Debug, Clone)]
+pub struct FileAssertionStep {
+    /// Name the step is reported under
+    pub name: String,
+    pub path: PathBuf,
+    /// Whether the file must exist, the other checks only apply if it does
+    pub exists: bool,
+    /// Size bounds in bytes, inclusive
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Pattern that must match somewhere in the contents
+    pub content: Option<Regex>,
+}
+
+impl FileAssertionStep {
+    /// Expects the file to exist
+    pub fn exists(name: &str, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.to_string(),
+            path: path.into(),
+            exists: true,
+            min_size: None,
+            max_size: None,
+            content: None,
+        }
+    }
+
+    /// Expects the file not to exist
+    pub fn absent(name: &str, path: impl Into<PathBuf>) -> Self {
+        Self {
+            exists: false,
+            ..Self::exists(name, path)
+        }
+    }
+}
+
+impl ServiceStepExecutor for FileAssertionStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let path = env.resolve(&self.path);
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if self.exists {
+                    return Err(StepError::Assertion(format!(
+                        "{} does not exist",
+                        path.display()
+                    )));
+                }
+                info!("{} does not exist", path.display());
+                return Ok(());
+            }
+            Err(e) =>
+                return Err(StepError::io(
+                    format!("Failed to stat {}", path.display()),
+                    e,
+                )),
+        };
+        if !self.exists {
+            return Err(StepError::Assertion(format!(
+                "{} exists, expected it to be absent",
+                path.display()
+            )));
+        }
+
+        let size = metadata.len();
+        if self.min_size.is_some_and(|min| size < min)
+            || self.max_size.is_some_and(|max| size > max)
+        {
+            return Err(StepError::Assertion(format!(
+                "{} has {} bytes, expected between {} and {}",
+                path.display(),
+                size,
+                self.min_size.unwrap_or(0),
+                self.max_size
+                    .map_or("any".to_string(), |max| max.to_string())
+            )));
+        }
+        if let Some(pattern) = &self.content {
+            let contents = fs::read(&path)
+                .map_err(|e| StepError::io(format!("Failed to read {}", path.display()), e))?;
+            if !pattern.is_match(&String::from_utf8_lossy(&contents)) {
+                return Err(StepError::Assertion(format!(
+                    "{} does not match '{}'",
+                    path.display(),
+                    pattern
+                )));
+            }
+        }
+        info!("{} exists with {} bytes", path.display(), size);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_file_assertions() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("export")).unwrap();
+        fs::write(root.path().join("export/rows.csv"), "id,name\n1,alice\n").unwrap();
+        let mut harness = TestHarness::new("FileTester", root.path().to_str().unwrap());
+        let mut check = |step: FileAssertionStep| {
+            step.execute(&mut harness.step_env())
+                .map_err(|e| e.to_string())
+        };
+
+        let mut step = FileAssertionStep::exists("Check_Export", "export/rows.csv");
+        step.min_size = Some(10);
+        step.max_size = Some(100);
+        step.content = Some(Regex::new(r"(?m)^1,alice$").unwrap());
+        check(step.clone()).expect("File matches");
+        check(FileAssertionStep::absent(
+            "Check_No_Temp",
+            "export/rows.csv.tmp",
+        ))
+        .expect("File is absent");
+
+        step.max_size = Some(10);
+        let error = check(step.clone()).unwrap_err();
+        assert!(
+            error.ends_with("has 16 bytes, expected between 10 and 10"),
+            "{}",
+            error
+        );
+        step.max_size = None;
+        step.content = Some(Regex::new("bob").unwrap());
+        let error = check(step).unwrap_err();
+        assert!(
+            error.ends_with("rows.csv does not match 'bob'"),
+            "{}",
+            error
+        );
+        let error = check(FileAssertionStep::exists(
+            "Check_Missing",
+            "export/missing.csv",
+        ))
+        .unwrap_err();
+        assert!(error.ends_with("missing.csv does not exist"), "{}", error);
+        let error =
+            check(FileAssertionStep::absent("Check_Absent", "export/rows.csv")).unwrap_err();
+        assert!(
+            error.ends_with("exists, expected it to be absent"),
+            "{}",
+            error
+        );
+    }
+}
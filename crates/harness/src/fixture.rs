@@ -0,0 +1,203 @@
+use std::fmt::Debug;
+use std::time::Instant;
+
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+use crate::{HarnessError, RunReport, StepEnv, StepError, StepReport, StepStatus, TestHarness};
+
+/// A reusable prerequisite of tests, e.g. TLS certificates or a seeded
+/// database. Fixtures added with [`TestHarness::add_fixture`] are set up in
+/// order before the setup steps, the value each produces is stored in the
+/// [`crate::Context`] under its name, where steps and `{{context.name}}`
+/// placeholders pick it up. They are torn down in reverse order once the
+/// services were stopped at the end of the run
+pub trait Fixture: Debug + Send {
+    /// Name the value of the fixture is stored under
+    fn name(&self) -> &str;
+    /// Prepares the prerequisite, returning the value exported to the steps,
+    /// e.g. a path, URL or key. A failure fails the run like a failed setup
+    /// step
+    fn setup(&mut self, env: &mut StepEnv<'_>) -> Result<Value, StepError>;
+    /// Removes what [`Fixture::setup`] created, also after the run `failed`
+    /// or panicked. Failures are logged
+    fn teardown(&mut self, _failed: bool) -> Result<(), StepError> { Ok(()) }
+}
+
+/// A fixture of a harness, with whether it was set up
+#[derive(Debug)]
+pub(crate) struct FixtureSlot {
+    pub(crate) fixture: Box<dyn Fixture>,
+    pub(crate) set_up: bool,
+}
+
+impl TestHarness {
+    pub fn add_fixture(&mut self, fixture: Box<dyn Fixture>) {
+        self.fixtures.push(FixtureSlot {
+            fixture,
+            set_up: false,
+        });
+    }
+
+    /// Sets up the fixtures until one fails, recording each like a setup
+    /// step and returning the failure
+    pub(crate) fn set_up_fixtures(&mut self, report: &mut RunReport) -> Result<(), HarnessError> {
+        let mut fixtures = std::mem::take(&mut self.fixtures);
+        let mut failure = None;
+        for slot in fixtures.iter_mut().filter(|slot| !slot.set_up) {
+            let name = slot.fixture.name().to_string();
+            let mut step_report = StepReport {
+                name: format!("Fixture_{}", name),
+                description: format!("Sets up fixture '{}'", name),
+                status: StepStatus::Skipped("setup failed".to_string()),
+                started: None,
+                duration: Default::default(),
+            };
+            if failure.is_none() {
+                info!("Setting up fixture '{}'", name);
+                let started = Instant::now();
+                let result = slot.fixture.setup(&mut self.step_env());
+                step_report.started = Some(started);
+                step_report.duration = started.elapsed();
+                step_report.status = match result {
+                    Ok(value) => {
+                        slot.set_up = true;
+                        self.context.insert(&name, value);
+                        StepStatus::Passed
+                    }
+                    Err(e) => {
+                        error!("Fixture '{}' failed: {}", name, e);
+                        let status = StepStatus::Failed(e.to_string());
+                        failure = Some(HarnessError::FixtureFailed { name, source: e });
+                        status
+                    }
+                };
+            }
+            report.steps.push(step_report);
+        }
+        self.fixtures = fixtures;
+        failure.map_or(Ok(()), Err)
+    }
+
+    /// Tears down the fixtures that were set up, in reverse order
+    pub(crate) fn tear_down_fixtures(&mut self, failed: bool) {
+        while let Some(mut slot) = self.fixtures.pop() {
+            if !slot.set_up {
+                continue;
+            }
+            if let Err(e) = slot.fixture.teardown(failed) {
+                warn!(
+                    "Failed to tear down fixture '{}': {}",
+                    slot.fixture.name(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{FailurePolicy, ShellCommandStep, TestStep};
+
+    /// Records its setup and teardown in `events`
+    #[derive(Debug)]
+    struct Recorded {
+        name: String,
+        value: Result<Value, String>,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Fixture for Recorded {
+        fn name(&self) -> &str { &self.name }
+
+        fn setup(&mut self, _env: &mut StepEnv<'_>) -> Result<Value, StepError> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("setup {}", self.name));
+            self.value.clone().map_err(StepError::Failed)
+        }
+
+        fn teardown(&mut self, failed: bool) -> Result<(), StepError> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("teardown {} failed={}", self.name, failed));
+            Ok(())
+        }
+    }
+
+    fn recorded(
+        name: &str,
+        value: Result<Value, String>,
+        events: &Arc<Mutex<Vec<String>>>,
+    ) -> Box<Recorded> {
+        Box::new(Recorded {
+            name: name.to_string(),
+            value,
+            events: events.clone(),
+        })
+    }
+
+    #[test]
+    fn test_fixture_values_reach_steps() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("FixtureTester", ".");
+        harness.add_fixture(recorded("certs", Ok(Value::from("/tmp/certs")), &events));
+        harness.add_fixture(recorded("tenant", Ok(Value::from(7)), &events));
+        let mut step = ShellCommandStep::sh("Check", "test \"$CERTS/$TENANT\" = /tmp/certs/7");
+        step.env
+            .insert("CERTS".to_string(), "{{context.certs}}".to_string());
+        step.env
+            .insert("TENANT".to_string(), "{{context.tenant}}".to_string());
+        harness.add_step(TestStep::Service(Box::new(step)));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(report.steps[0].name, "Fixture_certs");
+        assert_eq!(*events.lock().unwrap(), [
+            "setup certs",
+            "setup tenant",
+            "teardown tenant failed=false",
+            "teardown certs failed=false",
+        ]);
+    }
+
+    #[test]
+    fn test_failed_fixture_skips_steps() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("FixtureTester", ".");
+        harness.failure_policy = FailurePolicy::Continue;
+        harness.add_fixture(recorded("db", Ok(Value::Null), &events));
+        harness.add_fixture(recorded("seed", Err("no rows".to_string()), &events));
+        harness.add_fixture(recorded("cache", Ok(Value::Null), &events));
+        harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+            "Check", "true",
+        ))));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        let statuses = report
+            .steps
+            .iter()
+            .map(|step| (step.name.as_str(), &step.status))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, [
+            ("Fixture_db", &StepStatus::Passed),
+            ("Fixture_seed", &StepStatus::Failed("no rows".to_string())),
+            (
+                "Fixture_cache",
+                &StepStatus::Skipped("setup failed".to_string())
+            ),
+            ("Check", &StepStatus::Skipped("setup failed".to_string())),
+        ]);
+        assert_eq!(*events.lock().unwrap(), [
+            "setup db",
+            "setup seed",
+            "teardown db failed=true"
+        ]);
+    }
+}
@@ -0,0 +1,107 @@
+use tracing::info;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that fails if the test process itself, i.e. the harness and
+/// everything running in it, holds more threads or resident memory than
+/// allowed, e.g. to catch per-step leaks in large plans. Only available on
+/// Linux, where both are read from `/proc`
+#[derive(Debug, Clone)]
+pub struct AssertHarnessFootprint {
+    /// Name the step is reported under
+    pub name: String,
+    pub max_threads: Option<usize>,
+    /// Maximum resident set size in bytes
+    pub max_rss: Option<u64>,
+}
+
+/// Thread count and resident set size in bytes of the current process
+pub(crate) fn footprint() -> Result<(usize, u64), String> {
+    let status = std::fs::read_to_string("/proc/self/status")
+        .map_err(|e| format!("Failed to read /proc/self/status: {}", e))?;
+    let field = |name: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|value| value.split_whitespace().next())
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or_else(|| format!("No {} in /proc/self/status", name))
+    };
+    Ok((field("Threads:")? as usize, field("VmRSS:")? * 1024))
+}
+
+impl ServiceStepExecutor for AssertHarnessFootprint {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, _env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let (threads, rss) = footprint()?;
+        let mut exceeded = Vec::new();
+        if let Some(max_threads) = self.max_threads.filter(|max| threads > *max) {
+            exceeded.push(format!("{} threads (max {})", threads, max_threads));
+        }
+        if let Some(max_rss) = self.max_rss.filter(|max| rss > *max) {
+            exceeded.push(format!("{} bytes resident (max {})", rss, max_rss));
+        }
+        if !exceeded.is_empty() {
+            return Err(StepError::Assertion(format!(
+                "Harness footprint exceeded: {}",
+                exceeded.join(", ")
+            )));
+        }
+        info!(
+            "Harness footprint: {} threads, {} bytes resident",
+            threads, rss
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{AsyncFnStep, TestHarness, TestStep};
+
+    #[test]
+    fn test_thread_count_bounded_over_many_async_steps() {
+        let (threads_before, _) = footprint().expect("Failed to sample footprint");
+        let mut harness = TestHarness::new("FootprintTester", ".");
+        for idx in 0..50 {
+            harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+                name: format!("Step_{}", idx),
+                description: "Spawns a task onto the runtime".to_string(),
+                futurefn: Box::new(|_| {
+                    Box::new(async {
+                        tokio::spawn(async {}).await.map_err(|e| e.to_string())?;
+                        Ok(Value::Null)
+                    })
+                }),
+            })));
+        }
+        // Leaves room for tests running in parallel, but not a runtime per step
+        harness.add_step(TestStep::Service(Box::new(AssertHarnessFootprint {
+            name: "Check_Footprint".to_string(),
+            max_threads: Some(threads_before + 40),
+            max_rss: None,
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps.last());
+    }
+
+    #[test]
+    fn test_footprint_over_limit_fails() {
+        let mut harness = TestHarness::new("FootprintTester", ".");
+        let error = AssertHarnessFootprint {
+            name: "Check_Footprint".to_string(),
+            max_threads: Some(0),
+            max_rss: Some(1),
+        }
+        .execute(&mut harness.step_env())
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("threads (max 0)"), "{}", error);
+        assert!(error.contains("bytes resident (max 1)"), "{}", error);
+    }
+}
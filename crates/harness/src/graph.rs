@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use tracing::info;
+
+use crate::{PollPolicy, ServiceAction, ServiceStepExecutor, StepEnv, StepError, TestHarness};
+
+impl TestHarness {
+    /// Declares that `service` depends on `depends_on`
+    pub fn add_dependency(&mut self, service: &str, depends_on: &str) {
+        self.dependencies
+            .entry(service.to_string())
+            .or_default()
+            .push(depends_on.to_string());
+    }
+
+    /// Renders the execution plan as a Graphviz DOT diagram. Services are
+    /// nodes with edges to the services they depend on, steps form an
+    /// ordered chain, and dashed edges annotate which service a step acts on
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph {} {{", quote(&self.test_name));
+        let _ = writeln!(dot, "    subgraph cluster_services {{");
+        let _ = writeln!(dot, "        label=\"services\";");
+        for service in &self.services {
+            let _ = writeln!(
+                dot,
+                "        {} [label={}, shape=box];",
+                service_node(service.name()),
+                quote(service.name())
+            );
+        }
+        for service in &self.services {
+            for dependency in self.dependencies.get(service.name()).into_iter().flatten() {
+                let _ = writeln!(
+                    dot,
+                    "        {} -> {} [label=\"depends on\"];",
+                    service_node(service.name()),
+                    service_node(dependency)
+                );
+            }
+        }
+        let _ = writeln!(dot, "    }}");
+
+        let _ = writeln!(dot, "    subgraph cluster_steps {{");
+        let _ = writeln!(dot, "        label=\"steps\";");
+        for (idx, planned) in self.steps.iter().enumerate() {
+            let _ = writeln!(
+                dot,
+                "        {} [label={}];",
+                step_node(idx),
+                quote(&format!("{}. {}", idx + 1, planned.step.name()))
+            );
+            if idx > 0 {
+                let _ = writeln!(dot, "        {} -> {};", step_node(idx - 1), step_node(idx));
+            }
+        }
+        let _ = writeln!(dot, "    }}");
+
+        for (idx, planned) in self.steps.iter().enumerate() {
+            let actions = planned
+                .step
+                .executors()
+                .into_iter()
+                .flat_map(|step_executor| step_executor.service_actions());
+            for action in actions {
+                let (label, services) = match &action {
+                    ServiceAction::Start(service) => ("starts", vec![service.as_str()]),
+                    ServiceAction::StartAll =>
+                        ("starts", self.services.iter().map(|s| s.name()).collect()),
+                    ServiceAction::Stop(service) => ("stops", vec![service.as_str()]),
+                    ServiceAction::Use(service) => ("uses", vec![service.as_str()]),
+                };
+                for service in services {
+                    let _ = writeln!(
+                        dot,
+                        "    {} -> {} [style=dashed, label=\"{}\"];",
+                        step_node(idx),
+                        service_node(service),
+                        label
+                    );
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A step that starts every service that is not running yet, in dependency
+/// order. A service is only started once all its dependencies report
+/// [`crate::Service::is_healthy`], polled according to `health_policy`
+#[derive(Debug, Clone)]
+pub struct StartAllServices {
+    /// Name the step is reported under
+    pub name: String,
+    pub health_policy: PollPolicy,
+}
+
+impl ServiceStepExecutor for StartAllServices {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let names = env
+            .services
+            .iter()
+            .map(|service| service.name().to_string())
+            .collect::<Vec<_>>();
+        let dependencies = env.dependencies;
+        for idx in startup_order(&names, dependencies)? {
+            if env.services[idx].is_running() {
+                continue;
+            }
+            for dependency in dependencies.get(&names[idx]).into_iter().flatten() {
+                env.service(dependency)?;
+                env.wait_until_healthy(dependency, &self.health_policy)
+                    .map_err(|_| StepError::Timeout {
+                        what: format!(
+                            "Service '{}' blocked by the health gate of its dependency '{}'",
+                            names[idx], dependency
+                        ),
+                        after: self.health_policy.timeout,
+                    })?;
+            }
+            env.start_service(idx)?;
+            info!("Started service '{}'", names[idx]);
+        }
+        Ok(())
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> { vec![ServiceAction::StartAll] }
+}
+
+/// A step that starts a service only if its dependency is healthy right now,
+/// otherwise the step is skipped and the service is left stopped. Unlike
+/// [`StartAllServices`] it neither waits for the dependency nor fails
+#[derive(Debug, Clone)]
+pub struct StartIfHealthy {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    pub depends_on: String,
+}
+
+impl ServiceStepExecutor for StartIfHealthy {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        if !env.service(&self.depends_on)?.is_healthy() {
+            env.skip(&format!(
+                "dependency '{}' of service '{}' is not healthy",
+                self.depends_on, self.service_name
+            ));
+            return Ok(());
+        }
+        let idx = env.service_index(&self.service_name)?;
+        env.start_service(idx)?;
+        info!(
+            "Started service '{}', its dependency '{}' is healthy",
+            self.service_name, self.depends_on
+        );
+        Ok(())
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![
+            ServiceAction::Use(self.depends_on.clone()),
+            ServiceAction::Start(self.service_name.clone()),
+        ]
+    }
+}
+
+/// Orders services so every service comes after its dependencies, otherwise
+/// keeping the order they were added in
+pub(crate) fn startup_order(
+    names: &[String],
+    dependencies: &HashMap<String, Vec<String>>,
+) -> Result<Vec<usize>, String> {
+    dependency_order(names, dependencies, "service")
+}
+
+/// Orders `names` so every entry comes after the entries it depends on,
+/// otherwise keeping their order. Dependencies refer to all entries with that
+/// name, `kind` names the entries in errors
+pub(crate) fn dependency_order(
+    names: &[String],
+    dependencies: &HashMap<String, Vec<String>>,
+    kind: &str,
+) -> Result<Vec<usize>, String> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Mark {
+        Unvisited,
+        Visiting,
+        Done,
+    }
+
+    struct Visit<'a> {
+        names: &'a [String],
+        dependencies: &'a HashMap<String, Vec<String>>,
+        kind: &'a str,
+        marks: Vec<Mark>,
+        path: Vec<usize>,
+        order: Vec<usize>,
+    }
+
+    impl Visit<'_> {
+        fn visit(&mut self, idx: usize) -> Result<(), String> {
+            match self.marks[idx] {
+                Mark::Done => return Ok(()),
+                Mark::Visiting => {
+                    let start = self.path.iter().position(|&on_path| on_path == idx);
+                    let cycle = self.path[start.unwrap_or_default()..]
+                        .iter()
+                        .chain([&idx])
+                        .map(|&idx| format!("'{}'", self.names[idx]))
+                        .collect::<Vec<_>>();
+                    return Err(format!(
+                        "Dependency cycle between {}s {}",
+                        self.kind,
+                        cycle.join(" -> ")
+                    ));
+                }
+                Mark::Unvisited => self.marks[idx] = Mark::Visiting,
+            }
+            self.path.push(idx);
+            let name = &self.names[idx];
+            for dependency in self.dependencies.get(name).into_iter().flatten() {
+                let dependency_idxs = (0..self.names.len())
+                    .filter(|&other| self.names[other] == *dependency)
+                    .collect::<Vec<_>>();
+                if dependency_idxs.is_empty() {
+                    return Err(format!(
+                        "'{}' depends on unknown {} '{}'",
+                        name, self.kind, dependency
+                    ));
+                }
+                for dependency_idx in dependency_idxs {
+                    self.visit(dependency_idx)?;
+                }
+            }
+            self.path.pop();
+            self.marks[idx] = Mark::Done;
+            self.order.push(idx);
+            Ok(())
+        }
+    }
+
+    let mut visit = Visit {
+        names,
+        dependencies,
+        kind,
+        marks: vec![Mark::Unvisited; names.len()],
+        path: Vec::new(),
+        order: Vec::with_capacity(names.len()),
+    };
+    for idx in 0..names.len() {
+        visit.visit(idx)?;
+    }
+    Ok(visit.order)
+}
+
+fn quote(id: &str) -> String { format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\"")) }
+
+fn service_node(name: &str) -> String { quote(&format!("service:{}", name)) }
+
+fn step_node(idx: usize) -> String { quote(&format!("step:{}", idx + 1)) }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        Condition, Service, ServiceError, StepStatus, SubProcessService, SubProcessServiceStarter,
+        TestStep,
+    };
+
+    fn service(name: &str) -> Box<SubProcessService> {
+        Box::new(SubProcessService::new(name, "true", Vec::new()))
+    }
+
+    fn starter(name: &str) -> TestStep {
+        TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: name.to_string(),
+            description: format!("Starts {}", name),
+            service_name: name.to_string(),
+            wait_after: None,
+        }))
+    }
+
+    #[derive(Debug)]
+    struct GatedService {
+        name: &'static str,
+        running: bool,
+        healthy_after_checks: usize,
+        health_checks: usize,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl GatedService {
+        fn boxed(
+            name: &'static str,
+            healthy_after_checks: usize,
+            events: &Arc<Mutex<Vec<String>>>,
+        ) -> Box<Self> {
+            Box::new(Self {
+                name,
+                running: false,
+                healthy_after_checks,
+                health_checks: 0,
+                events: events.clone(),
+            })
+        }
+    }
+
+    impl Service for GatedService {
+        fn name(&self) -> &str { self.name }
+
+        fn start(&mut self) -> Result<(), ServiceError> {
+            self.running = true;
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{} started", self.name));
+            Ok(())
+        }
+
+        fn is_running(&self) -> bool { self.running }
+
+        fn stop(&mut self) -> Result<(), ServiceError> {
+            self.running = false;
+            Ok(())
+        }
+
+        fn is_healthy(&mut self) -> bool {
+            self.health_checks += 1;
+            let healthy = self.running && self.health_checks >= self.healthy_after_checks;
+            if healthy {
+                self.events
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} healthy", self.name));
+            }
+            healthy
+        }
+    }
+
+    fn gated_harness(db_healthy_after_checks: usize) -> (TestHarness, Arc<Mutex<Vec<String>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("HealthGateTester", ".");
+        // Added in reverse so the order has to come from the dependency
+        harness.add_service(GatedService::boxed("app", 1, &events));
+        harness.add_service(GatedService::boxed("db", db_healthy_after_checks, &events));
+        harness.add_dependency("app", "db");
+        (harness, events)
+    }
+
+    #[test]
+    fn test_start_all_waits_for_dependency_health() {
+        let (mut harness, events) = gated_harness(3);
+        let step = StartAllServices {
+            name: "Start_All".to_string(),
+            health_policy: PollPolicy {
+                interval: Duration::from_millis(10),
+                timeout: Duration::from_secs(5),
+                ..Default::default()
+            },
+        };
+        step.execute(&mut harness.step_env())
+            .expect("Failed to start services");
+        assert_eq!(*events.lock().unwrap(), vec![
+            "db started",
+            "db healthy",
+            "app started"
+        ]);
+    }
+
+    #[test]
+    fn test_start_all_reports_blocking_health_gate() {
+        let (mut harness, events) = gated_harness(usize::MAX);
+        let step = StartAllServices {
+            name: "Start_All".to_string(),
+            health_policy: PollPolicy {
+                interval: Duration::from_millis(10),
+                timeout: Duration::from_millis(50),
+                ..Default::default()
+            },
+        };
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("dependency 'db'"), "{}", error);
+        assert_eq!(*events.lock().unwrap(), vec!["db started"]);
+    }
+
+    #[test]
+    fn test_start_if_healthy_skips_when_dependency_unhealthy() {
+        let (mut harness, events) = gated_harness(usize::MAX);
+        harness.add_step(TestStep::Service(Box::new(StartIfHealthy {
+            name: "Start_App".to_string(),
+            service_name: "app".to_string(),
+            depends_on: "db".to_string(),
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed());
+        assert_eq!(
+            report.steps[0].status,
+            StepStatus::Skipped("dependency 'db' of service 'app' is not healthy".to_string())
+        );
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_startup_order_respects_dependencies() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("StartupOrderTester", ".");
+        harness.add_service(GatedService::boxed("web", 1, &events));
+        harness.add_service(GatedService::boxed("app", 1, &events));
+        harness.add_service(GatedService::boxed("db", 1, &events));
+        harness.add_dependency("web", "app");
+        harness.add_dependency("app", "db");
+        harness.add_step(TestStep::Service(Box::new(StartAllServices {
+            name: "Start_All".to_string(),
+            health_policy: PollPolicy::default(),
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        let startup_order = report.startup_order();
+        let names = startup_order
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["db", "app", "web"]);
+        assert!(startup_order.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+
+    #[test]
+    fn test_to_dot_contains_services_dependencies_and_steps() {
+        let mut harness = TestHarness::new("DotTester", ".");
+        harness.add_service(service("db"));
+        harness.add_service(service("app"));
+        harness.add_dependency("app", "db");
+        harness.add_step(starter("db"));
+        harness.add_step(starter("app"));
+        harness.add_service(service("cache"));
+        harness.add_step(TestStep::Conditional {
+            condition: Condition::EnvSet("HARNESS_DOT_TEST_CACHE".to_string()),
+            then: Box::new(starter("cache")),
+            otherwise: None,
+        });
+
+        let dot = harness.to_dot();
+        assert!(dot.starts_with("digraph \"DotTester\" {"));
+        assert!(dot.contains("\"service:db\" [label=\"db\", shape=box];"));
+        assert!(dot.contains("\"service:app\" [label=\"app\", shape=box];"));
+        assert!(dot.contains("\"service:app\" -> \"service:db\" [label=\"depends on\"];"));
+        assert!(dot.contains("\"step:1\" [label=\"1. db\"];"));
+        assert!(dot.contains("\"step:1\" -> \"step:2\";"));
+        assert!(dot.contains("\"step:2\" -> \"service:app\" [style=dashed, label=\"starts\"];"));
+        assert!(dot.contains("\"step:3\" -> \"service:cache\" [style=dashed, label=\"starts\"];"));
+    }
+}
@@ -0,0 +1,355 @@
+use std::time::Duration;
+
+use bytes::{Buf, Bytes};
+use tokio::net::TcpStream;
+use tracing::info;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// Status of a `grpc.health.v1` check meaning the service is serving
+const SERVING: u64 = 1;
+
+/// Response to a unary gRPC call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcResponse {
+    /// gRPC status code, 0 for OK
+    pub code: u32,
+    pub message: String,
+    /// Encoded response message, empty if the call failed
+    pub body: Vec<u8>,
+}
+
+/// Calls `method`, e.g. `/grpc.health.v1.Health/Check`, on the server at
+/// `host:port` over plaintext HTTP/2 with an encoded protobuf `request`
+async fn unary_call(address: &str, method: &str, request: &[u8]) -> Result<GrpcResponse, String> {
+    let stream = TcpStream::connect(address)
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+    let (client, connection) = h2::client::handshake(stream)
+        .await
+        .map_err(|e| format!("HTTP/2 handshake with {} failed: {}", address, e))?;
+    tokio::spawn(connection);
+
+    let mut client = client
+        .ready()
+        .await
+        .map_err(|e| format!("Connection to {} failed: {}", address, e))?;
+    let request_head = http::Request::post(format!("http://{}{}", address, method))
+        .header("content-type", "application/grpc")
+        .header("te", "trailers")
+        .body(())
+        .map_err(|e| format!("Invalid gRPC method '{}': {}", method, e))?;
+    let (response, mut send) = client
+        .send_request(request_head, false)
+        .map_err(|e| format!("Failed to call {}: {}", method, e))?;
+    let mut frame = Vec::with_capacity(request.len() + 5);
+    frame.push(0);
+    frame.extend_from_slice(&(request.len() as u32).to_be_bytes());
+    frame.extend_from_slice(request);
+    send.send_data(Bytes::from(frame), true)
+        .map_err(|e| format!("Failed to call {}: {}", method, e))?;
+
+    let (head, mut body) = response
+        .await
+        .map_err(|e| format!("{} failed: {}", method, e))?
+        .into_parts();
+    let mut received = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk =
+            chunk.map_err(|e| format!("Failed to read the response of {}: {}", method, e))?;
+        let _ = body.flow_control().release_capacity(chunk.len());
+        received.extend_from_slice(&chunk);
+    }
+    let trailers = body
+        .trailers()
+        .await
+        .map_err(|e| format!("Failed to read the response of {}: {}", method, e))?;
+
+    // A failing call may only send headers
+    let status = |name| {
+        trailers
+            .as_ref()
+            .and_then(|trailers| trailers.get(name))
+            .or_else(|| head.headers.get(name))
+            .and_then(|value| value.to_str().ok())
+    };
+    let code = status("grpc-status")
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("{} responded without a gRPC status", method))?;
+    let message = status("grpc-message").unwrap_or_default().to_string();
+    let mut received = received.as_slice();
+    let body = if received.len() >= 5 {
+        received.advance(1);
+        let len = received.get_u32() as usize;
+        received[..len.min(received.len())].to_vec()
+    } else {
+        Vec::new()
+    };
+    Ok(GrpcResponse {
+        code,
+        message,
+        body,
+    })
+}
+
+/// Reads a protobuf varint from the start of `buf`
+fn read_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// A readiness check calling the standard `grpc.health.v1.Health/Check`,
+/// passing once the server reports `SERVING` for `service`
+#[derive(Debug, Clone)]
+pub struct GrpcHealthProbe {
+    /// `host:port` of the server
+    pub address: String,
+    /// Service to check, the overall health of the server if empty
+    pub service: String,
+    pub timeout: Duration,
+}
+
+impl GrpcHealthProbe {
+    /// Checks the overall health of the server at `address`
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            service: String::new(),
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Encoded `HealthCheckRequest`
+    fn request(&self) -> Vec<u8> {
+        if self.service.is_empty() {
+            return Vec::new();
+        }
+        let mut request = vec![0x0a];
+        let mut len = self.service.len();
+        while len >= 0x80 {
+            request.push((len & 0x7f) as u8 | 0x80);
+            len >>= 7;
+        }
+        request.push(len as u8);
+        request.extend_from_slice(self.service.as_bytes());
+        request
+    }
+
+    /// Serving status in an encoded `HealthCheckResponse`
+    fn serving_status(mut body: &[u8]) -> u64 {
+        let mut status = 0;
+        while let Some(tag) = read_varint(&mut body) {
+            match tag {
+                0x08 => status = read_varint(&mut body).unwrap_or(0),
+                _ => break,
+            }
+        }
+        status
+    }
+
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to create runtime: {}", e))?;
+        let request = self.request();
+        let call = unary_call(&self.address, "/grpc.health.v1.Health/Check", &request);
+        let response = runtime
+            .block_on(async { tokio::time::timeout(self.timeout, call).await })
+            .map_err(|_| format!("Health check of {} timed out", self.address))??;
+        if response.code != 0 {
+            return Err(format!(
+                "Health check of {} failed with status {}: {}",
+                self.address, response.code, response.message
+            ));
+        }
+        match Self::serving_status(&response.body) {
+            SERVING => Ok(()),
+            status => Err(format!(
+                "{} reports status {} for '{}'",
+                self.address, status, self.service
+            )),
+        }
+    }
+}
+
+/// A step making a unary gRPC call and checking the status and response,
+/// e.g. against services that only speak HTTP/2. Messages are passed
+/// encoded, e.g. with `prost::Message::encode_to_vec`. The address may
+/// contain `{{port:name}}` placeholders, see [`crate::PortAllocator`]
+#[derive(Debug, Clone)]
+pub struct GrpcCallStep {
+    /// Name the step is reported under
+    pub name: String,
+    /// `host:port` of the server
+    pub address: String,
+    /// Full method path, e.g. `/orders.v1.Orders/Get`
+    pub method: String,
+    /// Encoded request message
+    pub request: Vec<u8>,
+    /// gRPC status code the call must end with
+    pub expect_code: u32,
+    /// Encoded message the response must equal, any response passes if unset
+    pub expect_response: Option<Vec<u8>>,
+    pub timeout: Duration,
+}
+
+impl GrpcCallStep {
+    /// A call expecting status OK
+    pub fn new(name: &str, address: &str, method: &str, request: Vec<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            address: address.to_string(),
+            method: method.to_string(),
+            request,
+            expect_code: 0,
+            expect_response: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ServiceStepExecutor for GrpcCallStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let address = env.substitute_ports(&self.address)?;
+        let call = unary_call(&address, &self.method, &self.request);
+        let response = env
+            .block_on(async { tokio::time::timeout(self.timeout, call).await })?
+            .map_err(|_| StepError::Timeout {
+                what: format!("{} on {}", self.method, address),
+                after: self.timeout,
+            })??;
+        info!(
+            "{} on {} ended with status {}",
+            self.method, address, response.code
+        );
+
+        if response.code != self.expect_code {
+            return Err(StepError::Assertion(format!(
+                "{} on {} ended with status {}, expected {}: {}",
+                self.method, address, response.code, self.expect_code, response.message
+            )));
+        }
+        if let Some(expected) = self
+            .expect_response
+            .as_ref()
+            .filter(|expected| **expected != response.body)
+        {
+            return Err(StepError::Assertion(format!(
+                "{} on {} responded with {:?}, expected {:?}",
+                self.method, address, response.body, expected
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener as StdTcpListener;
+
+    use http::HeaderMap;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{PortAllocator, TestHarness};
+
+    /// Serves gRPC on `listener`, echoing requests to `/echo.Echo/Say`,
+    /// reporting `SERVING` for health checks of `orders` and failing anything
+    /// else
+    fn serve_grpc(listener: StdTcpListener) {
+        listener.set_nonblocking(true).unwrap();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async move {
+                let listener = TcpListener::from_std(listener).unwrap();
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(async move {
+                        let mut connection = h2::server::handshake(stream).await.unwrap();
+                        while let Some(Ok((request, mut respond))) = connection.accept().await {
+                            let method = request.uri().path().to_string();
+                            let mut body = request.into_body();
+                            let mut frame = Vec::new();
+                            while let Some(Ok(chunk)) = body.data().await {
+                                frame.extend_from_slice(&chunk);
+                            }
+                            let (code, reply) = match method.as_str() {
+                                "/echo.Echo/Say" => (0, frame),
+                                "/grpc.health.v1.Health/Check"
+                                    if frame[5..] == *b"\x0a\x06orders" =>
+                                    (0, vec![0, 0, 0, 0, 2, 0x08, 0x01]),
+                                _ => (12, Vec::new()),
+                            };
+                            let head = http::Response::builder()
+                                .header("content-type", "application/grpc")
+                                .body(())
+                                .unwrap();
+                            let mut send = respond.send_response(head, false).unwrap();
+                            send.send_data(Bytes::from(reply), false).unwrap();
+                            let mut trailers = HeaderMap::new();
+                            trailers.insert("grpc-status", code.into());
+                            send.send_trailers(trailers).unwrap();
+                        }
+                    });
+                }
+            });
+        });
+    }
+
+    fn start_server(harness: &TestHarness) -> String {
+        let port = harness.ports.tcp("Grpc").unwrap();
+        serve_grpc(StdTcpListener::bind(("127.0.0.1", port)).unwrap());
+        format!("127.0.0.1:{}", port)
+    }
+
+    #[test]
+    fn test_health_probe() {
+        let harness = TestHarness::new("GrpcTester", ".");
+        let address = start_server(&harness);
+        let mut probe = GrpcHealthProbe::new(&address);
+        probe.service = "orders".to_string();
+        probe.check().expect("Service is serving");
+
+        probe.service = "payments".to_string();
+        let error = probe.check().unwrap_err();
+        assert!(error.ends_with("failed with status 12: "), "{}", error);
+        let closed = format!("127.0.0.1:{}", PortAllocator::new().tcp("Closed").unwrap());
+        assert!(GrpcHealthProbe::new(&closed).check().is_err());
+    }
+
+    #[test]
+    fn test_call_step() {
+        let mut harness = TestHarness::new("GrpcTester", ".");
+        start_server(&harness);
+        let mut step = GrpcCallStep::new(
+            "Say hello",
+            "127.0.0.1:{{port:Grpc}}",
+            "/echo.Echo/Say",
+            b"\x0a\x05hello".to_vec(),
+        );
+        step.expect_response = Some(b"\x0a\x05hello".to_vec());
+        step.execute(&mut harness.step_env())
+            .expect("Echo should respond");
+
+        step.method = "/echo.Echo/Shout".to_string();
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("ended with status 12, expected 0"),
+            "{}",
+            error
+        );
+    }
+}
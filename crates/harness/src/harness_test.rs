@@ -0,0 +1,108 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::{HarnessOptions, RunReport, TestHarness, TestHarnessBuilder};
+
+/// Future of the fn setting up a harness in [`TestHarness::run_test`],
+/// borrowing the harness
+pub type SetupFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// What the fn setting up a harness in [`TestHarness::run_test`] returns,
+/// nothing or a `Result` whose error fails the test
+pub trait TestOutcome {
+    /// Why the setup failed, if it did
+    fn into_result(self) -> Result<(), String>;
+}
+
+impl TestOutcome for () {
+    fn into_result(self) -> Result<(), String> { Ok(()) }
+}
+
+impl<E: Debug> TestOutcome for Result<(), E> {
+    fn into_result(self) -> Result<(), String> { self.map_err(|e| format!("{:?}", e)) }
+}
+
+impl TestHarness {
+    /// Runs the harness as the body of a `#[test]`, as generated by
+    /// `#[harness_test]`: `setup` is driven on the runtime of the harness,
+    /// which async steps and services keep using, then the harness is
+    /// executed with the [`HarnessOptions`] of the test binary applied.
+    /// Panics with the failures of the run unless it passed, after the
+    /// services were stopped and the requested reports written
+    pub fn run_test<T: TestOutcome>(
+        mut self,
+        setup: impl for<'a> FnOnce(&'a mut TestHarness) -> SetupFuture<'a, T>,
+    ) -> RunReport {
+        let runtime = self.runtime.clone();
+        let outcome = runtime
+            .block_on(setup(&mut self))
+            .unwrap_or_else(|e| panic!("{}", e));
+        if let Err(e) = outcome.into_result() {
+            panic!("Setting up test {} failed: {}", self.test_name, e);
+        }
+        self.finish_test()
+    }
+
+    fn finish_test(mut self) -> RunReport {
+        self.apply_options(HarnessOptions::from_args().unwrap_or_else(|e| panic!("{}", e)));
+        let test_name = self.test_name.clone();
+        let report = self
+            .execute()
+            .unwrap_or_else(|e| panic!("Test {} could not run: {}", test_name, e));
+        assert!(
+            report.passed(),
+            "Test {} failed:\n{}",
+            test_name,
+            report.failures().join("\n")
+        );
+        report
+    }
+}
+
+impl TestHarnessBuilder {
+    /// Like [`TestHarness::run_test`], for a `setup` taking and returning the
+    /// builder
+    pub fn run_test<Fut>(self, setup: impl FnOnce(Self) -> Fut) -> RunReport
+    where
+        Fut: Future<Output = Self>, {
+        let runtime = self.harness.runtime.clone();
+        let builder = runtime
+            .block_on(setup(self))
+            .unwrap_or_else(|e| panic!("{}", e));
+        builder.build().finish_test()
+    }
+}
+
+#[cfg(all(test, feature = "macros"))]
+mod tests {
+    use crate::{
+        harness_test, ShellCommandStep, StepError, TestHarness, TestHarnessBuilder, TestStep,
+    };
+
+    #[harness_test(root_dir = "src")]
+    async fn test_harness_passed_by_reference(harness: &mut TestHarness) -> Result<(), StepError> {
+        // Setup runs on the runtime of the harness
+        tokio::task::yield_now().await;
+        assert!(harness.root_dir.ends_with("/src"));
+        harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+            "Check_Root",
+            "test -f harness_test.rs",
+        ))));
+        Ok(())
+    }
+
+    #[harness_test(name = "BuilderTester")]
+    async fn test_harness_builder(builder: TestHarnessBuilder) -> TestHarnessBuilder {
+        builder.step(ShellCommandStep::sh("Check", "true"))
+    }
+
+    #[harness_test]
+    #[should_panic(expected = "Test test_failing_harness failed:\nStep 'Fail' failed")]
+    async fn test_failing_harness(harness: &mut TestHarness) {
+        harness.failure_log_lines = 0;
+        harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+            "Fail", "false",
+        ))));
+    }
+}
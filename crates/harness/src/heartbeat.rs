@@ -0,0 +1,74 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+/// Logs that a step is still running every `interval` until dropped, so CI
+/// does not consider a long step stuck for lack of output
+pub(crate) struct Heartbeat {
+    stop: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    pub(crate) fn start(step_name: &str, interval: Duration) -> Self {
+        let (stop, stopped) = mpsc::channel();
+        let step_name = step_name.to_string();
+        let started = Instant::now();
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                info!(
+                    "Step '{}' still running, elapsed {:.1}s",
+                    step_name,
+                    started.elapsed().as_secs_f32()
+                );
+            }
+        });
+        Self {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{test_log, AsyncFnStep, TestHarness, TestStep};
+
+    #[test]
+    fn test_heartbeat_logged_during_long_step() {
+        test_log::init();
+        let mut harness = TestHarness::new("HeartbeatTester", ".");
+        harness.heartbeat = Some(Duration::from_millis(50));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Long_Running_Step".to_string(),
+            description: "Takes a while".to_string(),
+            futurefn: Box::new(|_| {
+                Box::new(async {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    Ok(Value::Null)
+                })
+            }),
+        })));
+
+        harness.execute().expect("Failed to execute test steps");
+        let heartbeats = test_log::captured()
+            .into_iter()
+            .filter(|line| line.contains("Step 'Long_Running_Step' still running"))
+            .count();
+        assert!(heartbeats >= 1, "No heartbeat was logged");
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::junit::{escape, escape_attribute};
+use crate::{RunReport, StepStatus};
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+h1 .passed { color: #2a7d2a; } h1 .failed { color: #c0392b; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+td, th { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+.timeline .row { display: flex; align-items: center; margin: 2px 0; }
+.timeline .label { width: 16em; flex: none; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+.timeline .track { position: relative; flex: auto; height: 1.2em; background: #f4f4f4; }
+.timeline .bar { position: absolute; top: 0; height: 100%; min-width: 2px; }
+.passed { background: #4caf50; } .failed { background: #e74c3c; }
+.warning { background: #f39c12; } .skipped { background: #bbb; }
+.starting { background: #9ecae1; } .running { background: #3182bd; }
+pre { background: #f4f4f4; padding: 0.6em; overflow-x: auto; }
+";
+
+/// CSS class of a step status, matching the JSON report
+fn status_class(status: &StepStatus) -> &'static str {
+    match status {
+        StepStatus::Passed => "passed",
+        StepStatus::Failed(_) => "failed",
+        StepStatus::Warning(_) => "warning",
+        StepStatus::Skipped(_) => "skipped",
+    }
+}
+
+impl RunReport {
+    /// The report as a single HTML page without external resources, for
+    /// humans triaging a failed run: a timeline of the steps and the
+    /// lifecycles of the services, the failures with their causes and the
+    /// last output of the services
+    pub fn to_html(&self) -> String {
+        let result = if self.passed() { "passed" } else { "failed" };
+        let end = self
+            .steps
+            .iter()
+            .filter_map(|step| Some(step.started? + step.duration))
+            .chain(self.startup_log.iter().map(|(_, at)| *at))
+            .chain(self.ready_log.iter().map(|(_, at)| *at))
+            .map(|at| at.saturating_duration_since(self.started))
+            .fold(self.duration, Duration::max)
+            .max(Duration::from_millis(1));
+        let offset = |at: Instant| at.saturating_duration_since(self.started);
+        let percent = |offset: Duration| 100.0 * offset.as_secs_f64() / end.as_secs_f64();
+        let bar = |class: &str, from: Duration, to: Duration, title: &str| {
+            format!(
+                r#"<div class="bar {}" style="left: {:.2}%; width: {:.2}%" title="{}"></div>"#,
+                class,
+                percent(from),
+                percent(to.saturating_sub(from)),
+                escape_attribute(title)
+            )
+        };
+
+        let mut html = String::new();
+        let _ = writeln!(html, "<!DOCTYPE html>");
+        let _ = writeln!(html, r#"<html lang="en"><head><meta charset="utf-8">"#);
+        let _ = writeln!(
+            html,
+            "<title>{}: {}</title>",
+            escape(&self.test_name),
+            result
+        );
+        let _ = writeln!(html, "<style>\n{}</style></head><body>", STYLE);
+        let _ = writeln!(
+            html,
+            r#"<h1>{} <span class="{}">{}</span></h1>"#,
+            escape(&self.test_name),
+            result,
+            result
+        );
+        let _ = writeln!(
+            html,
+            "<p>{} steps, {} services, took {:.3}s</p>",
+            self.steps.len(),
+            self.services.len(),
+            self.duration.as_secs_f64()
+        );
+
+        let _ = writeln!(html, "<h2>Timeline</h2>\n<div class=\"timeline\">");
+        for service in &self.services {
+            let Some((_, started)) = self
+                .startup_log
+                .iter()
+                .rev()
+                .find(|(name, _)| name == service)
+            else {
+                continue;
+            };
+            let started = offset(*started);
+            let ready = self
+                .ready_log
+                .iter()
+                .rev()
+                .find(|(name, _)| name == service)
+                .map(|(_, at)| offset(*at).max(started));
+            let bars = match ready {
+                Some(ready) => [
+                    bar(
+                        "starting",
+                        started,
+                        ready,
+                        &format!("starting {:.3}s", (ready - started).as_secs_f64()),
+                    ),
+                    bar("running", ready, end, "ready"),
+                ]
+                .concat(),
+                None => bar("running", started, end, "started"),
+            };
+            let _ = writeln!(
+                html,
+                r#"<div class="row"><span class="label">service {}</span><div class="track">{}</div></div>"#,
+                escape(service),
+                bars
+            );
+        }
+        for step in &self.steps {
+            let Some(started) = step.started else {
+                continue;
+            };
+            let started = offset(started);
+            let title = format!(
+                "{}: {} in {:.3}s",
+                step.name,
+                status_class(&step.status),
+                step.duration.as_secs_f64()
+            );
+            let _ = writeln!(
+                html,
+                r#"<div class="row"><span class="label" title="{}">{}</span><div class="track">{}</div></div>"#,
+                escape_attribute(&step.description),
+                escape(&step.name),
+                bar(
+                    status_class(&step.status),
+                    started,
+                    started + step.duration,
+                    &title
+                )
+            );
+        }
+        let _ = writeln!(html, "</div>");
+
+        let _ = writeln!(
+            html,
+            "<h2>Steps</h2>\n<table><tr><th>#</th><th>Step</th><th>Status</th><th>Duration</th><th>Details</th></tr>"
+        );
+        for (idx, step) in self.steps.iter().enumerate() {
+            let details = match &step.status {
+                StepStatus::Passed => String::new(),
+                StepStatus::Failed(message)
+                | StepStatus::Warning(message)
+                | StepStatus::Skipped(message) => format!("<pre>{}</pre>", escape(message)),
+            };
+            let _ = writeln!(
+                html,
+                r#"<tr><td>{}</td><td title="{}">{}</td><td class="{}">{}</td><td>{:.3}s</td><td>{}</td></tr>"#,
+                idx + 1,
+                escape_attribute(&step.description),
+                escape(&step.name),
+                status_class(&step.status),
+                status_class(&step.status),
+                step.duration.as_secs_f64(),
+                details
+            );
+        }
+        let _ = writeln!(html, "</table>");
+
+        let failures = self.failures();
+        if !failures.is_empty() {
+            let _ = writeln!(html, "<h2>Failures</h2>\n<ul>");
+            for failure in failures {
+                let _ = writeln!(html, "<li><pre>{}</pre></li>", escape(&failure));
+            }
+            let _ = writeln!(html, "</ul>");
+        }
+
+        if !self.services.is_empty() {
+            let _ = writeln!(
+                html,
+                "<h2>Services</h2>\n<table><tr><th>Service</th><th>Command line</th><th>Log files</th></tr>"
+            );
+            for service in &self.services {
+                let command_line = self
+                    .command_lines
+                    .iter()
+                    .find(|(name, _)| name == service)
+                    .map_or("", |(_, command_line)| command_line.as_str());
+                let log_files = self
+                    .log_files
+                    .iter()
+                    .find(|(name, _)| name == service)
+                    .map(|(_, files)| {
+                        format!(
+                            "{}<br>{}",
+                            escape(&files.stdout.to_string_lossy()),
+                            escape(&files.stderr.to_string_lossy())
+                        )
+                    })
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td><code>{}</code></td><td>{}</td></tr>",
+                    escape(service),
+                    escape(command_line),
+                    log_files
+                );
+            }
+            let _ = writeln!(html, "</table>");
+        }
+
+        if !self.log_excerpts.is_empty() {
+            let _ = writeln!(html, "<h2>Service output</h2>");
+            for (service, excerpt) in &self.log_excerpts {
+                let _ = writeln!(
+                    html,
+                    "<details open><summary>{}</summary><pre>{}</pre></details>",
+                    escape(service),
+                    escape(excerpt)
+                );
+            }
+        }
+        let _ = writeln!(html, "</body></html>");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StepReport;
+
+    #[test]
+    fn test_html_report() {
+        let mut report = RunReport::new("Checkout <eu>");
+        report.duration = Duration::from_secs(2);
+        report.services = vec!["Api".to_string()];
+        report.startup_log = vec![("Api".to_string(), report.started)];
+        report.ready_log = vec![(
+            "Api".to_string(),
+            report.started + Duration::from_millis(500),
+        )];
+        report.steps = vec![StepReport {
+            name: "Create_Order".to_string(),
+            description: "Creates an order".to_string(),
+            status: StepStatus::Failed("status 500 & \"no stock\"".to_string()),
+            started: Some(report.started + Duration::from_secs(1)),
+            duration: Duration::from_millis(500),
+        }];
+        report.log_excerpts = vec![("Api".to_string(), "  ! panic: <nil>".to_string())];
+
+        let html = report.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>\n"), "{}", html);
+        assert!(html.contains("<h1>Checkout &lt;eu&gt; <span class=\"failed\">failed</span></h1>"));
+        assert!(
+            html.contains(r#"<div class="bar starting" style="left: 0.00%; width: 25.00%""#),
+            "{}",
+            html
+        );
+        assert!(html.contains(r#"<div class="bar running" style="left: 25.00%; width: 75.00%""#));
+        assert!(html.contains(r#"<div class="bar failed" style="left: 50.00%; width: 25.00%""#));
+        assert!(html.contains(
+            "<li><pre>Step &apos;Create_Order&apos; failed: status 500 &amp; &quot;no stock&quot;</pre></li>"
+        ));
+        assert!(html.contains("<pre>  ! panic: &lt;nil&gt;</pre>"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use reqwest::Method;
+use serde_json::Value;
+use tracing::info;
+
+use crate::{JsonAssertion, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step sending an HTTP request and checking the response, e.g. to probe
+/// an API without writing an async step. The URL, headers and body may
+/// contain `{{port:name}}` placeholders, see [`crate::PortAllocator`]
+#[derive(Debug, Clone)]
+pub struct HttpRequestStep {
+    /// Name the step is reported under
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// Status the response must have, any status passes if unset
+    pub expect_status: Option<u16>,
+    /// JSON the response body must match. Objects in the body may have more
+    /// fields than the expected ones, everything else must be equal
+    pub expect_json: Option<Value>,
+    /// Assertions the response body must satisfy as JSON
+    pub assertions: Vec<JsonAssertion>,
+    pub timeout: Duration,
+    /// Stores the response body in the [`crate::Context`] under this key,
+    /// parsed as JSON if possible
+    pub context_key: Option<String>,
+}
+
+impl HttpRequestStep {
+    /// A GET request expecting status 200
+    pub fn get(name: &str, url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+            expect_status: Some(200),
+            expect_json: None,
+            assertions: Vec::new(),
+            timeout: Duration::from_secs(30),
+            context_key: None,
+        }
+    }
+}
+
+/// Whether `actual` matches `expected`, where objects in `actual` may have
+/// additional fields
+fn json_matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual| json_matches(value, actual))
+        }),
+        (Value::Array(expected), Value::Array(actual)) =>
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(expected, actual)| json_matches(expected, actual)),
+        _ => expected == actual,
+    }
+}
+
+impl ServiceStepExecutor for HttpRequestStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let method = Method::from_bytes(self.method.as_bytes())
+            .map_err(|_| StepError::Failed(format!("Invalid HTTP method '{}'", self.method)))?;
+        let url = env.substitute_ports(&self.url)?;
+        let mut request = reqwest::Client::new()
+            .request(method, &url)
+            .timeout(self.timeout);
+        for (name, value) in &self.headers {
+            request = request.header(name, env.substitute_ports(value)?);
+        }
+        if let Some(body) = &self.body {
+            request = request.body(env.substitute_ports(body)?);
+        }
+
+        let description = format!("{} {}", self.method, url);
+        let (status, body) = env.block_on(async {
+            let response = request
+                .send()
+                .await
+                .map_err(|e| format!("{} failed: {}", description, e))?;
+            let status = response.status().as_u16();
+            let body = response
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read the response of {}: {}", description, e))?;
+            Ok::<_, String>((status, body))
+        })??;
+        info!("{} responded with {}", description, status);
+
+        if let Some(expected) = self.expect_status.filter(|expected| *expected != status) {
+            return Err(StepError::Assertion(format!(
+                "{} responded with {}, expected {}: {}",
+                description, status, expected, body
+            )));
+        }
+        let json = serde_json::from_str::<Value>(&body).ok();
+        if let Some(expected) = &self.expect_json {
+            let matched = json
+                .as_ref()
+                .is_some_and(|actual| json_matches(expected, actual));
+            if !matched {
+                return Err(StepError::Assertion(format!(
+                    "{} responded with {}, expected JSON matching {}",
+                    description, body, expected
+                )));
+            }
+        }
+        if !self.assertions.is_empty() {
+            let json = json.as_ref().ok_or_else(|| {
+                StepError::Assertion(format!(
+                    "{} responded with {}, expected JSON",
+                    description, body
+                ))
+            })?;
+            for assertion in &self.assertions {
+                assertion.check(json)?;
+            }
+        }
+        if let Some(key) = &self.context_key {
+            env.context.insert(key, json.unwrap_or(Value::String(body)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{Matcher, TestHarness};
+
+    /// Responds to every request on `port` with its method and body as JSON
+    fn serve_echo(port: u16) {
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0; 4096];
+                let len = stream.read(&mut request).unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..len]).into_owned();
+                let method = request.split_whitespace().next().unwrap_or_default();
+                let body = request.split("\r\n\r\n").nth(1).unwrap_or_default();
+                let response = json!({"method": method, "body": body, "ok": true}).to_string();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 201 Created\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_request_with_placeholder_and_json_match() {
+        let mut harness = TestHarness::new("HttpTester", ".");
+        serve_echo(harness.ports.tcp("echo").expect("Failed to reserve port"));
+        let mut step = HttpRequestStep::get("Create", "http://127.0.0.1:{{port:echo}}/items");
+        step.method = "POST".to_string();
+        step.body = Some("{\"id\": 1}".to_string());
+        step.expect_status = Some(201);
+        step.expect_json = Some(json!({"method": "POST", "ok": true}));
+        step.context_key = Some("created".to_string());
+        step.execute(&mut harness.step_env())
+            .expect("Request should match");
+        assert_eq!(
+            harness.context.pointer("created", "/body"),
+            Some(json!("{\"id\": 1}"))
+        );
+
+        step.expect_json = Some(json!({"method": "GET"}));
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("expected JSON matching"), "{}", error);
+    }
+
+    #[test]
+    fn test_json_assertions_on_body() {
+        let mut harness = TestHarness::new("HttpTester", ".");
+        serve_echo(harness.ports.tcp("echo").expect("Failed to reserve port"));
+        let mut step = HttpRequestStep::get("Get", "http://127.0.0.1:{{port:echo}}/");
+        step.expect_status = None;
+        step.assertions = vec![
+            JsonAssertion::new("$.method", Matcher::Equals(json!("GET"))),
+            JsonAssertion::new("$.ok", Matcher::Equals(json!(true))),
+        ];
+        step.execute(&mut harness.step_env())
+            .expect("Assertions should hold");
+
+        step.assertions = vec![JsonAssertion::new(
+            "$.method",
+            Matcher::Equals(json!("POST")),
+        )];
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.ends_with(
+                "Assertion on $.method failed\n  expected: equal to \"POST\"\n  actual:   \"GET\""
+            ),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_unexpected_status_fails() {
+        let mut harness = TestHarness::new("HttpTester", ".");
+        serve_echo(harness.ports.tcp("echo").expect("Failed to reserve port"));
+        let step = HttpRequestStep::get("Get", "http://127.0.0.1:{{port:echo}}/");
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("responded with 201, expected 200"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt::{self, Debug};
+use std::future::Future;
+
+use serde_json::Value;
+use tracing::info;
+
+use crate::{Context, ServiceStepExecutor, StepEnv, StepError};
+
+/// An async operation that can be run repeatedly
+pub type AsyncOperation =
+    Box<dyn Fn(&Context) -> Box<dyn Future<Output = Result<Value, String>>> + Send>;
+
+/// A step that runs an operation twice and fails unless `compare` considers
+/// the two results equivalent, e.g. to verify that retrying a request does
+/// not change its outcome
+pub struct AssertIdempotent {
+    /// Name the step is reported under
+    pub name: String,
+    pub operation: AsyncOperation,
+    /// Whether the results of the first and second run are equivalent
+    pub compare: fn(&Value, &Value) -> bool,
+}
+
+impl Debug for AssertIdempotent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssertIdempotent")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ServiceStepExecutor for AssertIdempotent {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let first = env
+            .block_on(Box::into_pin((self.operation)(env.context)))?
+            .map_err(|e| format!("First run failed: {}", e))?;
+        let second = env
+            .block_on(Box::into_pin((self.operation)(env.context)))?
+            .map_err(|e| format!("Second run failed: {}", e))?;
+
+        if !(self.compare)(&first, &second) {
+            return Err(StepError::Assertion(format!(
+                "Operation is not idempotent, first run returned {}, second run returned {}",
+                first, second
+            )));
+        }
+        info!("Operation is idempotent, both runs returned {}", second);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_stable_operation_is_idempotent() {
+        let mut harness = TestHarness::new("IdempotencyTester", ".");
+        let step = AssertIdempotent {
+            name: "Check_Idempotency".to_string(),
+            operation: Box::new(|_| Box::new(async { Ok(Value::from("created")) })),
+            compare: |first, second| first == second,
+        };
+        step.execute(&mut harness.step_env())
+            .expect("Stable operation should be idempotent");
+    }
+
+    #[test]
+    fn test_timestamp_operation_is_not_idempotent() {
+        let mut harness = TestHarness::new("IdempotencyTester", ".");
+        let step = AssertIdempotent {
+            name: "Check_Idempotency".to_string(),
+            operation: Box::new(|_| {
+                Box::new(async {
+                    tokio::time::sleep(Duration::from_millis(2)).await;
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                    Ok(Value::from(now.as_nanos().to_string()))
+                })
+            }),
+            compare: |first, second| first == second,
+        };
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("not idempotent"), "{}", error);
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::Notify;
+#[cfg(unix)]
+use tracing::{info, warn};
+
+use crate::TestHarness;
+
+/// Records the signals a run was interrupted by and cancels the async work
+/// in progress when one arrives
+#[derive(Debug, Default)]
+pub(crate) struct Interrupt {
+    /// Number of signals received and the last of them
+    received: Mutex<(u64, Option<&'static str>)>,
+    notify: Notify,
+}
+
+impl Interrupt {
+    /// Name of the signal the run was interrupted by, if any
+    pub(crate) fn signal(&self) -> Option<&'static str> { self.received().1 }
+
+    /// Number of signals received so far
+    pub(crate) fn count(&self) -> u64 { self.received().0 }
+
+    pub(crate) fn raise(&self, signal: &'static str) {
+        {
+            let mut received = self
+                .received
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            *received = (received.0 + 1, Some(signal));
+        }
+        self.notify.notify_waiters();
+    }
+
+    /// Resolves with the last signal once more than `count` signals were
+    /// received, so work started after a signal is only cancelled by another
+    pub(crate) async fn raised_after(&self, count: u64) -> &'static str {
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            // Registers for notification before checking, so a signal
+            // arriving in between is not missed
+            notified.as_mut().enable();
+            if let (received, Some(signal)) = self.received() {
+                if received > count {
+                    return signal;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    fn received(&self) -> (u64, Option<&'static str>) {
+        *self
+            .received
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Raises SIGINT and SIGTERM received by the process on this interrupt
+    /// until the returned guard is dropped
+    #[cfg(unix)]
+    pub(crate) fn listen(self: &Arc<Self>) -> Option<SignalGuard> {
+        let relay = match SignalRelay::global() {
+            Ok(relay) => relay,
+            Err(e) => {
+                warn!("Not handling signals: {}", e);
+                return None;
+            }
+        };
+        relay.lock().push(self.clone());
+        Some(SignalGuard {
+            interrupt: self.clone(),
+        })
+    }
+}
+
+/// Relays SIGINT and SIGTERM to the interrupts of the runs in progress. The
+/// signal streams are registered once per process on a runtime of its own,
+/// as the runtimes of harnesses may be dropped or shared between them
+#[cfg(unix)]
+#[derive(Debug, Default)]
+struct SignalRelay {
+    interrupts: Mutex<Vec<Arc<Interrupt>>>,
+}
+
+#[cfg(unix)]
+impl SignalRelay {
+    fn global() -> Result<&'static Self, &'static str> {
+        static RELAY: OnceLock<Result<SignalRelay, String>> = OnceLock::new();
+        RELAY
+            .get_or_init(Self::spawn)
+            .as_ref()
+            .map_err(String::as_str)
+    }
+
+    fn spawn() -> Result<Self, String> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to create signal runtime: {}", e))?;
+        let streams = {
+            let _guard = runtime.enter();
+            signal(SignalKind::interrupt())
+                .and_then(|sigint| signal(SignalKind::terminate()).map(|sigterm| (sigint, sigterm)))
+        };
+        let (mut sigint, mut sigterm) =
+            streams.map_err(|e| format!("Failed to register signals: {}", e))?;
+        std::thread::Builder::new()
+            .name("harness-signals".to_string())
+            .spawn(move || {
+                runtime.block_on(async {
+                    loop {
+                        let (signal, exit_code) = tokio::select! {
+                            _ = sigint.recv() => ("SIGINT", 130),
+                            _ = sigterm.recv() => ("SIGTERM", 143),
+                        };
+                        Self::global()
+                            .expect("Signal relay is running")
+                            .relay(signal, exit_code);
+                    }
+                });
+            })
+            .map_err(|e| format!("Failed to spawn signal thread: {}", e))?;
+        Ok(Self::default())
+    }
+
+    /// Raises `signal` on the runs in progress, or terminates the process
+    /// with the exit code of a shell as the default disposition would have if
+    /// there are none
+    fn relay(&self, signal: &'static str, exit_code: i32) {
+        let interrupts = self.lock();
+        if interrupts.is_empty() {
+            warn!("Received {} outside of a run, exiting", signal);
+            std::process::exit(exit_code);
+        }
+        info!("Received {}, stopping the run", signal);
+        for interrupt in interrupts.iter() {
+            interrupt.raise(signal);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Arc<Interrupt>>> {
+        self.interrupts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Stops relaying signals to an interrupt when dropped, see
+/// [`Interrupt::listen`]
+#[cfg(unix)]
+#[derive(Debug)]
+pub(crate) struct SignalGuard {
+    interrupt: Arc<Interrupt>,
+}
+
+#[cfg(unix)]
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        if let Ok(relay) = SignalRelay::global() {
+            relay
+                .lock()
+                .retain(|interrupt| !Arc::ptr_eq(interrupt, &self.interrupt));
+        }
+    }
+}
+
+impl TestHarness {
+    /// Handles SIGINT and SIGTERM while the harness runs: the async work of
+    /// the current step is cancelled, the remaining steps are skipped, all
+    /// services are stopped in reverse order and teardown runs, after which
+    /// [`TestHarness::execute`] returns [`crate::HarnessError::Interrupted`].
+    /// Steps that block without awaiting are not cancelled, the run stops
+    /// once they return. Outside of a run, the signals terminate the process
+    /// as they would without handlers
+    #[cfg(unix)]
+    pub fn install_signal_handlers(&mut self) { self.handle_signals = true; }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        AsyncFnStep, HarnessError, ServiceStepExecutor, StepEnv, StepError, SubProcessService,
+        SubProcessServiceStarter, TestStep,
+    };
+
+    #[derive(Debug)]
+    struct RecordRunning(Arc<AtomicBool>);
+
+    impl ServiceStepExecutor for RecordRunning {
+        fn name(&self) -> &str { "Record_Running" }
+
+        fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+            let running = env.service("Sleeper")?.is_running();
+            self.0.store(running, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_sigterm_cancels_step_and_stops_services() {
+        let mut harness = TestHarness::new("SignalTester", ".");
+        harness.install_signal_handlers();
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Start_Sleeper".to_string(),
+            description: "Starts the sleeper".to_string(),
+            service_name: "Sleeper".to_string(),
+            wait_after: None,
+        })));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Hang".to_string(),
+            description: "Signals the harness, then waits for a long time".to_string(),
+            futurefn: Box::new(|_| {
+                Box::new(async {
+                    Command::new("kill")
+                        .args(["-s", "TERM", &std::process::id().to_string()])
+                        .status()
+                        .map_err(|e| e.to_string())?;
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    Ok(Value::Null)
+                })
+            }),
+        })));
+        let running_in_teardown = Arc::new(AtomicBool::new(true));
+        harness.add_teardown(TestStep::Service(Box::new(RecordRunning(
+            running_in_teardown.clone(),
+        ))));
+
+        let started = Instant::now();
+        let error = harness.execute().unwrap_err();
+        assert!(
+            matches!(error, HarnessError::Interrupted { signal: "SIGTERM" }),
+            "{:?}",
+            error
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(!running_in_teardown.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_signal_handlers_are_released_after_run() {
+        for _ in 0..2 {
+            let mut harness = TestHarness::new("SignalTester", ".");
+            harness.install_signal_handlers();
+            let interrupt = harness.interrupt.clone();
+            let report = harness.execute().expect("Failed to execute test steps");
+            assert!(report.passed());
+            // Only this test holds the interrupt once the relay let go of it
+            assert_eq!(Arc::strong_count(&interrupt), 1);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use std::fmt::{self, Display};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// One step of a parsed JSONPath
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+    /// `..key`, the key at any depth
+    Descendant(String),
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>, String> {
+    let invalid = |reason: &str| format!("Invalid JSONPath '{}': {}", path, reason);
+    let mut rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| invalid("must start with $"))?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        let key_end = |s: &str| s.find(['.', '[']).unwrap_or(s.len());
+        if let Some(after) = rest.strip_prefix("..") {
+            let end = key_end(after);
+            if end == 0 {
+                return Err(invalid("expected a key after .."));
+            }
+            segments.push(Segment::Descendant(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = key_end(after);
+            segments.push(match &after[..end] {
+                "" => return Err(invalid("expected a key after .")),
+                "*" => Segment::Wildcard,
+                key => Segment::Key(key.to_string()),
+            });
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(|| invalid("unclosed ["))?;
+            let inner = after[..end].trim();
+            segments.push(if inner == "*" {
+                Segment::Wildcard
+            } else if let Some(key) = inner
+                .strip_prefix('\'')
+                .and_then(|key| key.strip_suffix('\''))
+                .or_else(|| {
+                    inner
+                        .strip_prefix('"')
+                        .and_then(|key| key.strip_suffix('"'))
+                })
+            {
+                Segment::Key(key.to_string())
+            } else {
+                Segment::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| invalid(&format!("invalid index '{}'", inner)))?,
+                )
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(invalid(&format!("unexpected '{}'", rest)));
+        }
+    }
+    Ok(segments)
+}
+
+/// Every value nested in `value` under `key` at any depth
+fn descendants<'a>(value: &'a Value, key: &str, found: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(nested) = map.get(key) {
+                found.push(nested);
+            }
+            map.values()
+                .for_each(|nested| descendants(nested, key, found));
+        }
+        Value::Array(values) => values
+            .iter()
+            .for_each(|nested| descendants(nested, key, found)),
+        _ => {}
+    }
+}
+
+/// Values in `value` selected by a JSONPath such as `$.orders[*].id`,
+/// supporting child keys, quoted keys in brackets, array indices counting
+/// from the end if negative, wildcards and `..key` for keys at any depth
+pub fn json_path<'a>(value: &'a Value, path: &str) -> Result<Vec<&'a Value>, String> {
+    let mut selected = vec![value];
+    for segment in parse_path(path)? {
+        let mut next = Vec::new();
+        for value in selected {
+            match (&segment, value) {
+                (Segment::Key(key), Value::Object(map)) => next.extend(map.get(key)),
+                (Segment::Index(idx), Value::Array(values)) => {
+                    let idx = if *idx < 0 {
+                        values.len().checked_sub(idx.unsigned_abs() as usize)
+                    } else {
+                        Some(*idx as usize)
+                    };
+                    next.extend(idx.and_then(|idx| values.get(idx)));
+                }
+                (Segment::Wildcard, Value::Array(values)) => next.extend(values),
+                (Segment::Wildcard, Value::Object(map)) => next.extend(map.values()),
+                (Segment::Descendant(key), value) => descendants(value, key, &mut next),
+                _ => {}
+            }
+        }
+        selected = next;
+    }
+    Ok(selected)
+}
+
+/// A check on a JSON value, see [`JsonAssertion`]
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    Equals(Value),
+    /// Strings containing the string, arrays containing an equal element or
+    /// objects containing the fields of the object
+    Contains(Value),
+    /// Strings matching the pattern
+    Matches(Regex),
+    GreaterThan(f64),
+    AtLeast(f64),
+    LessThan(f64),
+    AtMost(f64),
+}
+
+impl Matcher {
+    fn matches(&self, actual: &Value) -> bool {
+        let number = actual.as_f64();
+        match self {
+            Self::Equals(expected) => expected == actual,
+            Self::Contains(expected) => match (actual, expected) {
+                (Value::String(actual), Value::String(expected)) => actual.contains(expected),
+                (Value::Array(actual), expected) => actual.contains(expected),
+                (Value::Object(actual), Value::Object(expected)) => expected
+                    .iter()
+                    .all(|(key, value)| actual.get(key) == Some(value)),
+                _ => false,
+            },
+            Self::Matches(pattern) => actual.as_str().is_some_and(|s| pattern.is_match(s)),
+            Self::GreaterThan(bound) => number.is_some_and(|n| n > *bound),
+            Self::AtLeast(bound) => number.is_some_and(|n| n >= *bound),
+            Self::LessThan(bound) => number.is_some_and(|n| n < *bound),
+            Self::AtMost(bound) => number.is_some_and(|n| n <= *bound),
+        }
+    }
+}
+
+impl Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Equals(expected) => write!(f, "equal to {}", expected),
+            Self::Contains(expected) => write!(f, "containing {}", expected),
+            Self::Matches(pattern) => write!(f, "matching '{}'", pattern),
+            Self::GreaterThan(bound) => write!(f, "greater than {}", bound),
+            Self::AtLeast(bound) => write!(f, "at least {}", bound),
+            Self::LessThan(bound) => write!(f, "less than {}", bound),
+            Self::AtMost(bound) => write!(f, "at most {}", bound),
+        }
+    }
+}
+
+/// Differences between two JSON values as one line per differing path
+fn json_diff(path: &str, expected: &Value, actual: &Value, lines: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                let nested = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual) => json_diff(&nested, value, actual, lines),
+                    None => lines.push(format!("  - {}: {}", nested, value)),
+                }
+            }
+            for (key, value) in actual
+                .iter()
+                .filter(|(key, _)| !expected.contains_key(*key))
+            {
+                lines.push(format!("  + {}.{}: {}", path, key, value));
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for idx in 0..expected.len().max(actual.len()) {
+                let nested = format!("{}[{}]", path, idx);
+                match (expected.get(idx), actual.get(idx)) {
+                    (Some(expected), Some(actual)) => json_diff(&nested, expected, actual, lines),
+                    (Some(expected), None) => lines.push(format!("  - {}: {}", nested, expected)),
+                    (None, Some(actual)) => lines.push(format!("  + {}: {}", nested, actual)),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if expected != actual => {
+            lines.push(format!("  - {}: {}", path, expected));
+            lines.push(format!("  + {}: {}", path, actual));
+        }
+        _ => {}
+    }
+}
+
+/// Why a [`JsonAssertion`] did not hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionFailure {
+    pub path: String,
+    pub expected: String,
+    /// Values selected by the path, empty if it selected nothing
+    pub actual: Vec<Value>,
+    /// Differing paths for [`Matcher::Equals`], prefixed `-` for expected
+    /// and `+` for actual values
+    pub diff: Vec<String>,
+}
+
+impl Display for AssertionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Assertion on {} failed\n  expected: {}",
+            self.path, self.expected
+        )?;
+        match self.actual.as_slice() {
+            [] => write!(f, "\n  actual:   nothing at {}", self.path)?,
+            [actual] => write!(f, "\n  actual:   {}", actual)?,
+            actual => {
+                let actual = actual.iter().map(Value::to_string).collect::<Vec<_>>();
+                write!(f, "\n  actual:   [{}]", actual.join(", "))?;
+            }
+        }
+        if !self.diff.is_empty() {
+            write!(f, "\n  diff:\n{}", self.diff.join("\n"))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AssertionFailure {}
+
+impl From<AssertionFailure> for StepError {
+    fn from(failure: AssertionFailure) -> Self { Self::Assertion(failure.to_string()) }
+}
+
+/// Lets async steps, which fail with a string, use `?` on assertions
+impl From<AssertionFailure> for String {
+    fn from(failure: AssertionFailure) -> Self { failure.to_string() }
+}
+
+/// An assertion that the values selected by a JSONPath all satisfy a
+/// [`Matcher`]. Fails if the path selects nothing
+#[derive(Debug, Clone)]
+pub struct JsonAssertion {
+    pub path: String,
+    pub matcher: Matcher,
+}
+
+impl JsonAssertion {
+    pub fn new(path: &str, matcher: Matcher) -> Self {
+        Self {
+            path: path.to_string(),
+            matcher,
+        }
+    }
+
+    pub fn check(&self, value: &Value) -> Result<(), AssertionFailure> {
+        let failure = |actual: Vec<Value>, diff| AssertionFailure {
+            path: self.path.clone(),
+            expected: self.matcher.to_string(),
+            actual,
+            diff,
+        };
+        let selected = json_path(value, &self.path)
+            .map_err(|e| failure(Vec::new(), vec![format!("  {}", e)]))?;
+        if !selected.is_empty() && selected.iter().all(|value| self.matcher.matches(value)) {
+            return Ok(());
+        }
+        let mut diff = Vec::new();
+        if let (Matcher::Equals(expected), [actual]) = (&self.matcher, selected.as_slice()) {
+            if expected.is_object() || expected.is_array() {
+                json_diff(&self.path, expected, actual, &mut diff);
+            }
+        }
+        Err(failure(selected.into_iter().cloned().collect(), diff))
+    }
+}
+
+/// A step checking a value in the [`crate::Context`], e.g. the output an
+/// async step stored under its name
+#[derive(Debug, Clone)]
+pub struct AssertJson {
+    /// Name the step is reported under
+    pub name: String,
+    pub key: String,
+    pub assertions: Vec<JsonAssertion>,
+}
+
+impl ServiceStepExecutor for AssertJson {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let value = env.context.get(&self.key).ok_or_else(|| {
+            StepError::Assertion(format!("No context value stored under '{}'", self.key))
+        })?;
+        for assertion in &self.assertions {
+            assertion.check(&value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn order() -> Value {
+        json!({
+            "id": "ord-1",
+            "status": "paid",
+            "total": 42.5,
+            "items": [
+                { "sku": "apple", "qty": 2 },
+                { "sku": "pear", "qty": 1, "tags": ["fresh"] }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_json_path() {
+        let order = order();
+        let select = |path| json_path(&order, path).unwrap();
+        assert_eq!(select("$.status"), [&json!("paid")]);
+        assert_eq!(select("$.items[*].sku"), [&json!("apple"), &json!("pear")]);
+        assert_eq!(select("$.items[-1]['sku']"), [&json!("pear")]);
+        assert_eq!(select("$..qty"), [&json!(2), &json!(1)]);
+        assert_eq!(select("$.items[5]"), Vec::<&Value>::new());
+        assert_eq!(select("$"), [&order]);
+        assert!(json_path(&order, "items").is_err());
+        assert!(json_path(&order, "$.items[x]").is_err());
+    }
+
+    #[test]
+    fn test_matchers() {
+        let order = order();
+        let check = |path, matcher| JsonAssertion::new(path, matcher).check(&order);
+        check("$.status", Matcher::Equals(json!("paid"))).unwrap();
+        check("$.id", Matcher::Matches(Regex::new(r"^ord-\d+$").unwrap())).unwrap();
+        check("$.items[1].tags", Matcher::Contains(json!("fresh"))).unwrap();
+        check("$.items[0]", Matcher::Contains(json!({ "sku": "apple" }))).unwrap();
+        check("$.items[*].qty", Matcher::AtLeast(1.0)).unwrap();
+        check("$.total", Matcher::LessThan(50.0)).unwrap();
+
+        let failure = check("$.total", Matcher::GreaterThan(50.0)).unwrap_err();
+        assert_eq!(
+            failure.to_string(),
+            "Assertion on $.total failed\n  expected: greater than 50\n  actual:   42.5"
+        );
+        let failure = check("$.refund", Matcher::Equals(json!(0))).unwrap_err();
+        assert!(failure
+            .to_string()
+            .ends_with("actual:   nothing at $.refund"));
+    }
+
+    #[test]
+    fn test_equals_failure_shows_diff() {
+        let failure = JsonAssertion::new(
+            "$.items[0]",
+            Matcher::Equals(json!({ "sku": "apple", "qty": 3, "price": 1 })),
+        )
+        .check(&order())
+        .unwrap_err();
+        // Keys are compared in sorted order
+        assert_eq!(failure.diff, [
+            "  - $.items[0].price: 1",
+            "  - $.items[0].qty: 3",
+            "  + $.items[0].qty: 2",
+        ]);
+        assert!(
+            failure.to_string().ends_with(
+                "diff:\n  - $.items[0].price: 1\n  - $.items[0].qty: 3\n  + $.items[0].qty: 2"
+            ),
+            "{}",
+            failure
+        );
+    }
+}
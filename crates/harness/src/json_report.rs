@@ -0,0 +1,150 @@
+use std::time::{Instant, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::{RunReport, StepStatus};
+
+impl RunReport {
+    /// The report as JSON for post-processing, e.g. by dashboards. Times are
+    /// in seconds, those of services relative to the start of the run:
+    ///
+    /// - `test_name`, `passed`, `started_at` (since the Unix epoch) and
+    ///   `duration`
+    /// - `services`: `name`, `command_line`, `started_after`, `ready_after` and
+    ///   `log_files` with `stdout` and `stderr`, unset values are null
+    /// - `steps`: `name`, `description`, `status` (`passed`, `failed`,
+    ///   `warning` or `skipped`), `message` and `duration`
+    /// - `failures`: causes of the failure of the run as text
+    /// - `auto_included`: steps executed as prerequisites
+    pub fn to_json(&self) -> Value {
+        let offset = |log: &[(String, Instant)], name: &str| {
+            log.iter()
+                .rev()
+                .find(|(service, _)| service == name)
+                .map(|(_, at)| at.saturating_duration_since(self.started).as_secs_f64())
+        };
+        let services = self
+            .services
+            .iter()
+            .map(|name| {
+                let command_line = self
+                    .command_lines
+                    .iter()
+                    .find(|(service, _)| service == name)
+                    .map(|(_, command_line)| command_line);
+                let log_files = self
+                    .log_files
+                    .iter()
+                    .find(|(service, _)| service == name)
+                    .map(|(_, files)| json!({ "stdout": files.stdout, "stderr": files.stderr }));
+                json!({
+                    "name": name,
+                    "command_line": command_line,
+                    "started_after": offset(&self.startup_log, name),
+                    "ready_after": offset(&self.ready_log, name),
+                    "log_files": log_files,
+                })
+            })
+            .collect::<Vec<_>>();
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| {
+                let (status, message) = match &step.status {
+                    StepStatus::Passed => ("passed", None),
+                    StepStatus::Failed(message) => ("failed", Some(message)),
+                    StepStatus::Warning(message) => ("warning", Some(message)),
+                    StepStatus::Skipped(reason) => ("skipped", Some(reason)),
+                };
+                json!({
+                    "name": step.name,
+                    "description": step.description,
+                    "status": status,
+                    "message": message,
+                    "duration": step.duration.as_secs_f64(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let started_at = self
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        json!({
+            "test_name": self.test_name,
+            "passed": self.passed(),
+            "started_at": started_at.as_secs_f64(),
+            "duration": self.duration.as_secs_f64(),
+            "services": services,
+            "steps": steps,
+            "failures": self.failures(),
+            "auto_included": self.auto_included,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{LogFiles, StepReport};
+
+    #[test]
+    fn test_json_report() {
+        let mut report = RunReport::new("Checkout");
+        report.services = vec!["Api".to_string(), "Worker".to_string()];
+        report.startup_log = vec![(
+            "Api".to_string(),
+            report.started + Duration::from_millis(500),
+        )];
+        report.command_lines = vec![("Api".to_string(), "api --port 8080".to_string())];
+        report.log_files = vec![("Api".to_string(), LogFiles::new("/tmp/checkout", "Api"))];
+        report.steps = vec![StepReport {
+            name: "Create_Order".to_string(),
+            description: "Creates an order".to_string(),
+            status: StepStatus::Failed("status 500".to_string()),
+            started: None,
+            duration: Duration::from_millis(250),
+        }];
+
+        let json = report.to_json();
+        assert_eq!(json["passed"], json!(false));
+        assert_eq!(
+            json["services"],
+            json!([
+                {
+                    "name": "Api",
+                    "command_line": "api --port 8080",
+                    "started_after": 0.5,
+                    "ready_after": null,
+                    "log_files": {
+                        "stdout": "/tmp/checkout/logs/Api.out.log",
+                        "stderr": "/tmp/checkout/logs/Api.err.log",
+                    },
+                },
+                {
+                    "name": "Worker",
+                    "command_line": null,
+                    "started_after": null,
+                    "ready_after": null,
+                    "log_files": null,
+                },
+            ])
+        );
+        assert_eq!(
+            json["steps"],
+            json!([{
+                "name": "Create_Order",
+                "description": "Creates an order",
+                "status": "failed",
+                "message": "status 500",
+                "duration": 0.25,
+            }])
+        );
+        assert_eq!(
+            json["failures"],
+            json!(["Step 'Create_Order' failed: status 500"])
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::Write;
+
+use crate::{RunReport, StepStatus};
+
+/// Escapes text for use in XML text
+pub(crate) fn escape(text: &str) -> String { escape_attribute(text).replace("&#10;", "\n") }
+
+/// Escapes text for use in XML attribute values
+pub(crate) fn escape_attribute(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            // Other control characters are not allowed in XML 1.0
+            c if c.is_control() && c != '\t' && c != '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl RunReport {
+    /// Renders the report as JUnit XML with a test suite named after the
+    /// test and a test case per step, as shown by the test summaries of CI
+    /// systems. Failed steps are failures, skipped steps are skipped and
+    /// warnings of optional steps are kept in the output of their test case
+    pub fn to_junit_xml(&self) -> String {
+        Self::combined_junit_xml(&self.test_name, std::slice::from_ref(self))
+    }
+
+    /// Renders the reports of several tests as one JUnit XML document named
+    /// `name`, with a test suite per test as in [`RunReport::to_junit_xml`]
+    pub fn combined_junit_xml(name: &str, reports: &[Self]) -> String {
+        let (mut tests, mut failures, mut skipped, mut time) = (0, 0, 0, 0.0);
+        let mut suites = String::new();
+        for report in reports {
+            let counts = report.write_junit_testsuite(&mut suites);
+            tests += counts.0;
+            failures += counts.1;
+            skipped += counts.2;
+            time += counts.3;
+        }
+
+        let mut xml = String::new();
+        let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            xml,
+            r#"<testsuites name="{}" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+            escape_attribute(name),
+            tests,
+            failures,
+            skipped,
+            time
+        );
+        xml.push_str(&suites);
+        let _ = writeln!(xml, "</testsuites>");
+        xml
+    }
+
+    /// Appends the test suite of the report to `xml`, returning its number
+    /// of tests, failures and skipped tests and its time
+    fn write_junit_testsuite(&self, xml: &mut String) -> (usize, usize, usize, f64) {
+        let count = |matches: fn(&StepStatus) -> bool| {
+            self.steps
+                .iter()
+                .filter(|step| matches(&step.status))
+                .count()
+        };
+        let failures = count(|status| matches!(status, StepStatus::Failed(_)));
+        let skipped = count(|status| matches!(status, StepStatus::Skipped(_)));
+        let time = self
+            .steps
+            .iter()
+            .map(|step| step.duration.as_secs_f64())
+            .sum::<f64>();
+        let suite = escape_attribute(&self.test_name);
+
+        let _ = writeln!(
+            xml,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" skipped="{}" time="{:.3}">"#,
+            suite,
+            self.steps.len(),
+            failures,
+            skipped,
+            time
+        );
+        for step in &self.steps {
+            let _ = write!(
+                xml,
+                r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+                escape_attribute(&step.name),
+                suite,
+                step.duration.as_secs_f64()
+            );
+            if step.description.is_empty() && step.status == StepStatus::Passed {
+                let _ = writeln!(xml, "/>");
+                continue;
+            }
+            let _ = writeln!(xml, ">");
+            if !step.description.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    r#"      <properties><property name="description" value="{}"/></properties>"#,
+                    escape_attribute(&step.description)
+                );
+            }
+            match &step.status {
+                StepStatus::Passed => {}
+                StepStatus::Failed(message) => {
+                    let _ = writeln!(
+                        xml,
+                        r#"      <failure message="{}">{}</failure>"#,
+                        escape_attribute(message),
+                        escape(message)
+                    );
+                }
+                StepStatus::Skipped(reason) => {
+                    let _ = writeln!(
+                        xml,
+                        r#"      <skipped message="{}"/>"#,
+                        escape_attribute(reason)
+                    );
+                }
+                StepStatus::Warning(message) => {
+                    let _ = writeln!(
+                        xml,
+                        "      <system-out>Optional step failed: {}</system-out>",
+                        escape(message)
+                    );
+                }
+            }
+            let _ = writeln!(xml, "    </testcase>");
+        }
+        let _ = writeln!(xml, "  </testsuite>");
+        (self.steps.len(), failures, skipped, time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{RunReport, StepReport, StepStatus};
+
+    fn step(name: &str, status: StepStatus, millis: u64) -> StepReport {
+        StepReport {
+            name: name.to_string(),
+            description: String::new(),
+            status,
+            started: None,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        let mut report = RunReport::new("Checkout");
+        report.steps = vec![
+            StepReport {
+                description: "Starts the <api>".to_string(),
+                ..step("Start_Api", StepStatus::Passed, 1200)
+            },
+            step(
+                "Create_Order",
+                StepStatus::Failed("expected \"paid\"\ngot & more".to_string()),
+                300,
+            ),
+            step("Refund", StepStatus::Skipped("run aborted".to_string()), 0),
+        ];
+
+        assert_eq!(
+            report.to_junit_xml(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="Checkout" tests="3" failures="1" skipped="1" time="1.500">
+  <testsuite name="Checkout" tests="3" failures="1" errors="0" skipped="1" time="1.500">
+    <testcase name="Start_Api" classname="Checkout" time="1.200">
+      <properties><property name="description" value="Starts the &lt;api&gt;"/></properties>
+    </testcase>
+    <testcase name="Create_Order" classname="Checkout" time="0.300">
+      <failure message="expected &quot;paid&quot;&#10;got &amp; more">expected &quot;paid&quot;
+got &amp; more</failure>
+    </testcase>
+    <testcase name="Refund" classname="Checkout" time="0.000">
+      <skipped message="run aborted"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
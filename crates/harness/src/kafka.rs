@@ -0,0 +1,538 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use regex::Regex;
+use serde_json::Value;
+use tracing::info;
+
+use crate::{
+    container_runtime_from_env, ContainerRuntime, Context, DockerService, OutputLine, PollPolicy,
+    PortAllocator, Service, ServiceError, ServiceStepExecutor, StepEnv, StepError, TestHarness,
+};
+
+/// Port the broker listens on for clients inside its container
+const INTERNAL_PORT: u16 = 9092;
+/// Port the broker listens on for clients on the host
+const EXTERNAL_PORT: u16 = 19092;
+
+/// Which broker a [`KafkaService`] runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KafkaFlavor {
+    /// Redpanda in development mode, which starts in a few seconds
+    Redpanda,
+    /// Apache Kafka in KRaft mode, using the layout of the `apache/kafka`
+    /// image
+    Kafka,
+}
+
+impl KafkaFlavor {
+    fn default_image(self) -> &'static str {
+        match self {
+            Self::Redpanda => "redpandadata/redpanda:v24.1.7",
+            Self::Kafka => "apache/kafka:3.7.0",
+        }
+    }
+
+    /// Command checking inside the container whether the broker serves
+    /// requests
+    fn ready_command(self) -> Vec<String> {
+        match self {
+            Self::Redpanda => vec!["rpk".to_string(), "cluster".to_string(), "info".to_string()],
+            Self::Kafka => vec![
+                "/opt/kafka/bin/kafka-broker-api-versions.sh".to_string(),
+                format!("--bootstrap-server=localhost:{}", INTERNAL_PORT),
+            ],
+        }
+    }
+
+    /// Command producing its arguments after the topic as messages
+    fn produce_command(self, topic: &str, messages: &[String]) -> Vec<String> {
+        let producer = match self {
+            Self::Redpanda => "rpk topic produce \"$topic\"".to_string(),
+            Self::Kafka => format!(
+                "/opt/kafka/bin/kafka-console-producer.sh --bootstrap-server=localhost:{} \
+                 --topic=\"$topic\"",
+                INTERNAL_PORT
+            ),
+        };
+        let script = format!("topic=$1; shift; printf '%s\\n' \"$@\" | {}", producer);
+        ["sh", "-c", &script, "sh", topic]
+            .into_iter()
+            .map(String::from)
+            .chain(messages.iter().cloned())
+            .collect()
+    }
+
+    /// Command printing the values of the first `count` messages of `topic`,
+    /// one per line, giving up after `timeout`
+    fn consume_command(self, topic: &str, count: usize, timeout: Duration) -> Vec<String> {
+        match self {
+            Self::Redpanda => vec![
+                "timeout".to_string(),
+                timeout.as_secs().max(1).to_string(),
+                "rpk".to_string(),
+                "topic".to_string(),
+                "consume".to_string(),
+                topic.to_string(),
+                "--offset=start".to_string(),
+                format!("--num={}", count),
+                "--format=%v\\n".to_string(),
+            ],
+            Self::Kafka => vec![
+                "/opt/kafka/bin/kafka-console-consumer.sh".to_string(),
+                format!("--bootstrap-server=localhost:{}", INTERNAL_PORT),
+                format!("--topic={}", topic),
+                "--from-beginning".to_string(),
+                format!("--max-messages={}", count),
+                format!("--timeout-ms={}", timeout.as_millis()),
+            ],
+        }
+    }
+}
+
+/// The broker of a [`KafkaService`] as used by [`KafkaProduceStep`] and
+/// [`KafkaConsumeAssertStep`], which run the CLI shipped with the broker in
+/// its container
+#[derive(Debug, Clone)]
+pub struct KafkaBroker {
+    service_name: String,
+    flavor: KafkaFlavor,
+    runtime: Arc<dyn ContainerRuntime>,
+    container_id: Arc<Mutex<Option<String>>>,
+}
+
+impl KafkaBroker {
+    /// Runs `command` in the container of the broker, returning its stdout
+    fn exec(&self, command: &[String]) -> Result<String, ServiceError> {
+        let container_id = self
+            .container_id
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| ServiceError::NotRunning(self.service_name.clone()))?;
+        let args = ["exec", &container_id]
+            .into_iter()
+            .chain(command.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        self.runtime.run(&self.service_name, &args)
+    }
+}
+
+/// A single-node Kafka compatible broker in a container, counting as healthy
+/// once it serves requests. Clients on the host reach it at
+/// `127.0.0.1:{{port:name}}`, see [`crate::PortAllocator`], and once
+/// started this address is stored in the [`Context`] under `context_key`,
+/// see [`TestHarness::add_kafka`]
+#[derive(Debug)]
+pub struct KafkaService {
+    pub name: String,
+    pub flavor: KafkaFlavor,
+    pub image: String,
+    /// Host port clients connect to, reserved from the [`PortAllocator`] of
+    /// the harness if unset
+    pub port: Option<u16>,
+    /// How [`crate::SubProcessServiceStarter`] waits for the broker
+    pub readiness_policy: PollPolicy,
+    pub context_key: String,
+    /// Context the bootstrap address is stored in
+    pub context: Option<Context>,
+    broker: KafkaBroker,
+    container: Option<DockerService>,
+}
+
+impl KafkaService {
+    /// A broker run with the runtime selected by
+    /// [`container_runtime_from_env`]
+    pub fn new(name: &str, flavor: KafkaFlavor) -> Self {
+        Self::with_runtime(name, flavor, container_runtime_from_env())
+    }
+
+    pub fn with_runtime(
+        name: &str,
+        flavor: KafkaFlavor,
+        runtime: Arc<dyn ContainerRuntime>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            flavor,
+            image: flavor.default_image().to_string(),
+            port: None,
+            readiness_policy: PollPolicy {
+                interval: Duration::from_millis(500),
+                timeout: Duration::from_secs(90),
+                ..Default::default()
+            },
+            context_key: "kafka_bootstrap_servers".to_string(),
+            context: None,
+            broker: KafkaBroker {
+                service_name: name.to_string(),
+                flavor,
+                runtime,
+                container_id: Arc::new(Mutex::new(None)),
+            },
+            container: None,
+        }
+    }
+
+    /// Handle for steps producing to and consuming from the broker
+    pub fn broker(&self) -> KafkaBroker {
+        KafkaBroker {
+            flavor: self.flavor,
+            ..self.broker.clone()
+        }
+    }
+
+    /// Bootstrap address for clients on the host, once the port is known
+    pub fn bootstrap_servers(&self) -> Option<String> {
+        self.port.map(|port| format!("127.0.0.1:{}", port))
+    }
+
+    /// Container of the broker advertising `port` on the host
+    fn container(&self, port: u16) -> DockerService {
+        let mut container =
+            DockerService::with_runtime(&self.name, &self.image, self.broker.runtime.clone());
+        container.ports = vec![(port, EXTERNAL_PORT)];
+        match self.flavor {
+            KafkaFlavor::Redpanda => {
+                container.args = vec![
+                    "redpanda".to_string(),
+                    "start".to_string(),
+                    "--mode=dev-container".to_string(),
+                    "--smp=1".to_string(),
+                    format!(
+                        "--kafka-addr=internal://0.0.0.0:{},external://0.0.0.0:{}",
+                        INTERNAL_PORT, EXTERNAL_PORT
+                    ),
+                    format!(
+                        "--advertise-kafka-addr=internal://localhost:{},external://127.0.0.1:{}",
+                        INTERNAL_PORT, port
+                    ),
+                ];
+            }
+            KafkaFlavor::Kafka => {
+                let env = [
+                    ("KAFKA_NODE_ID", "1".to_string()),
+                    ("KAFKA_PROCESS_ROLES", "broker,controller".to_string()),
+                    (
+                        "KAFKA_LISTENERS",
+                        format!(
+                            "INTERNAL://:{},EXTERNAL://:{},CONTROLLER://:9093",
+                            INTERNAL_PORT, EXTERNAL_PORT
+                        ),
+                    ),
+                    (
+                        "KAFKA_ADVERTISED_LISTENERS",
+                        format!(
+                            "INTERNAL://localhost:{},EXTERNAL://127.0.0.1:{}",
+                            INTERNAL_PORT, port
+                        ),
+                    ),
+                    (
+                        "KAFKA_LISTENER_SECURITY_PROTOCOL_MAP",
+                        "INTERNAL:PLAINTEXT,EXTERNAL:PLAINTEXT,CONTROLLER:PLAINTEXT".to_string(),
+                    ),
+                    ("KAFKA_INTER_BROKER_LISTENER_NAME", "INTERNAL".to_string()),
+                    ("KAFKA_CONTROLLER_LISTENER_NAMES", "CONTROLLER".to_string()),
+                    (
+                        "KAFKA_CONTROLLER_QUORUM_VOTERS",
+                        "1@localhost:9093".to_string(),
+                    ),
+                    ("KAFKA_OFFSETS_TOPIC_REPLICATION_FACTOR", "1".to_string()),
+                    (
+                        "KAFKA_TRANSACTION_STATE_LOG_REPLICATION_FACTOR",
+                        "1".to_string(),
+                    ),
+                    ("KAFKA_TRANSACTION_STATE_LOG_MIN_ISR", "1".to_string()),
+                ];
+                container
+                    .env
+                    .extend(env.map(|(key, value)| (key.to_string(), value)));
+            }
+        }
+        container
+    }
+}
+
+impl Service for KafkaService {
+    fn name(&self) -> &str { &self.name }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        if self.container.is_some() {
+            return Err(ServiceError::AlreadyRunning(self.name.clone()));
+        }
+        let port = self.port.ok_or_else(|| {
+            ServiceError::failed(
+                &self.name,
+                "No port set, add the service to a harness to reserve one",
+            )
+        })?;
+        let mut container = self.container(port);
+        container.start()?;
+        *self.broker.container_id.lock().unwrap() = container.container_id().map(String::from);
+        self.container = Some(container);
+        if let (Some(context), Some(servers)) = (&self.context, self.bootstrap_servers()) {
+            context.insert(&self.context_key, Value::String(servers));
+        }
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.container
+            .as_ref()
+            .is_some_and(|container| container.is_running())
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        if let Some(container) = &mut self.container {
+            container.stop()?;
+            self.container = None;
+            *self.broker.container_id.lock().unwrap() = None;
+        }
+        Ok(())
+    }
+
+    fn is_healthy(&mut self) -> bool {
+        self.is_running() && self.broker.exec(&self.flavor.ready_command()).is_ok()
+    }
+
+    fn resolve_ports(&mut self, ports: &PortAllocator) -> Result<(), ServiceError> {
+        if self.port.is_none() {
+            let port = ports
+                .tcp(&self.name)
+                .map_err(|e| ServiceError::failed(&self.name, e.to_string()))?;
+            self.port = Some(port);
+        }
+        Ok(())
+    }
+
+    fn readiness_policy(&self) -> Option<PollPolicy> { Some(self.readiness_policy) }
+
+    fn required_tools(&self) -> Vec<String> { vec![self.broker.runtime.program().to_string()] }
+
+    fn last_command_line(&self) -> Option<String> {
+        self.container
+            .as_ref()
+            .and_then(|container| container.last_command_line())
+    }
+
+    fn output(&self) -> Vec<OutputLine> {
+        self.container
+            .as_ref()
+            .map(|container| container.output())
+            .unwrap_or_default()
+    }
+}
+
+impl TestHarness {
+    /// Adds the broker, storing its bootstrap address in the context of the
+    /// harness once it started
+    pub fn add_kafka(&mut self, mut kafka: KafkaService) {
+        kafka.context = Some(self.context.clone());
+        self.add_service(Box::new(kafka));
+    }
+}
+
+/// A step producing messages to a topic, which is created if it does not
+/// exist
+#[derive(Debug, Clone)]
+pub struct KafkaProduceStep {
+    /// Name the step is reported under
+    pub name: String,
+    pub broker: KafkaBroker,
+    pub topic: String,
+    /// Values of the messages, produced in order
+    pub messages: Vec<String>,
+}
+
+impl ServiceStepExecutor for KafkaProduceStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, _env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let command = self
+            .broker
+            .flavor
+            .produce_command(&self.topic, &self.messages);
+        self.broker.exec(&command)?;
+        info!(
+            "Produced {} message(s) to '{}' on '{}'",
+            self.messages.len(),
+            self.topic,
+            self.broker.service_name
+        );
+        Ok(())
+    }
+}
+
+/// A step reading a topic from its start and checking its first messages
+/// match `expected` in order, failing if fewer arrive within `timeout`
+#[derive(Debug, Clone)]
+pub struct KafkaConsumeAssertStep {
+    /// Name the step is reported under
+    pub name: String,
+    pub broker: KafkaBroker,
+    pub topic: String,
+    /// Patterns the values of the first messages must match
+    pub expected: Vec<Regex>,
+    pub timeout: Duration,
+    /// Stores the consumed values in the [`Context`] under this key
+    pub context_key: Option<String>,
+}
+
+impl ServiceStepExecutor for KafkaConsumeAssertStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let command =
+            self.broker
+                .flavor
+                .consume_command(&self.topic, self.expected.len(), self.timeout);
+        // Both CLIs fail if they give up waiting before reading all messages
+        let (output, error) = match self.broker.exec(&command) {
+            Ok(output) => (output, None),
+            Err(e) => (String::new(), Some(e)),
+        };
+        let messages = output.lines().collect::<Vec<_>>();
+        info!(
+            "Consumed {} message(s) from '{}' on '{}'",
+            messages.len(),
+            self.topic,
+            self.broker.service_name
+        );
+
+        for (idx, pattern) in self.expected.iter().enumerate() {
+            match messages.get(idx) {
+                Some(message) if pattern.is_match(message) => {}
+                Some(message) => {
+                    return Err(StepError::Assertion(format!(
+                        "Message {} of '{}' is '{}', expected it to match '{}'",
+                        idx, self.topic, message, pattern
+                    )));
+                }
+                None => {
+                    let mut what = format!(
+                        "{} of {} message(s) on '{}'",
+                        messages.len(),
+                        self.expected.len(),
+                        self.topic
+                    );
+                    if let Some(e) = &error {
+                        what.push_str(&format!(": {}", e));
+                    }
+                    return Err(StepError::Timeout {
+                        what,
+                        after: self.timeout,
+                    });
+                }
+            }
+        }
+        if let Some(key) = &self.context_key {
+            env.context
+                .insert(key, messages.into_iter().map(Value::from).collect());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the commands run with it and answers `exec` with `reply`
+    #[derive(Debug)]
+    struct FakeRuntime {
+        commands: Mutex<Vec<Vec<String>>>,
+        reply: String,
+    }
+
+    impl ContainerRuntime for FakeRuntime {
+        fn program(&self) -> &str { "fake" }
+
+        fn run(&self, _service_name: &str, args: &[&str]) -> Result<String, ServiceError> {
+            self.commands
+                .lock()
+                .unwrap()
+                .push(args.iter().map(|arg| arg.to_string()).collect());
+            Ok(match args.first() {
+                Some(&"run") => "abc123".to_string(),
+                _ => self.reply.clone(),
+            })
+        }
+    }
+
+    fn start_broker(reply: &str) -> (Arc<FakeRuntime>, KafkaService, TestHarness) {
+        let runtime = Arc::new(FakeRuntime {
+            commands: Mutex::new(Vec::new()),
+            reply: reply.to_string(),
+        });
+        let mut kafka =
+            KafkaService::with_runtime("Events", KafkaFlavor::Redpanda, runtime.clone());
+        kafka.port = Some(19092);
+        kafka.start().expect("Failed to start broker");
+        (runtime, kafka, TestHarness::new("KafkaTester", "."))
+    }
+
+    #[test]
+    fn test_produce_runs_cli_in_container() {
+        let (runtime, kafka, mut harness) = start_broker("");
+        KafkaProduceStep {
+            name: "Produce_Orders".to_string(),
+            broker: kafka.broker(),
+            topic: "orders".to_string(),
+            messages: vec!["created 1".to_string(), "paid 1".to_string()],
+        }
+        .execute(&mut harness.step_env())
+        .expect("Failed to produce");
+
+        let commands = runtime.commands.lock().unwrap();
+        assert!(commands[0].contains(
+            &"--advertise-kafka-addr=internal://localhost:9092,external://127.0.0.1:19092"
+                .to_string()
+        ));
+        assert_eq!(commands[1], [
+            "exec",
+            "abc123",
+            "sh",
+            "-c",
+            "topic=$1; shift; printf '%s\\n' \"$@\" | rpk topic produce \"$topic\"",
+            "sh",
+            "orders",
+            "created 1",
+            "paid 1",
+        ]);
+    }
+
+    #[test]
+    fn test_consume_asserts_messages_in_order() {
+        let (_, kafka, mut harness) = start_broker("created 1\npaid 1");
+        let consume = |expected: &[&str]| KafkaConsumeAssertStep {
+            name: "Consume_Orders".to_string(),
+            broker: kafka.broker(),
+            topic: "orders".to_string(),
+            expected: expected.iter().map(|re| Regex::new(re).unwrap()).collect(),
+            timeout: Duration::from_secs(5),
+            context_key: Some("orders".to_string()),
+        };
+
+        consume(&["^created", "^paid"])
+            .execute(&mut harness.step_env())
+            .expect("Messages should match");
+        assert_eq!(
+            harness.context.get("orders"),
+            Some(serde_json::json!(["created 1", "paid 1"]))
+        );
+        let error = consume(&["^paid"])
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.ends_with("is 'created 1', expected it to match '^paid'"),
+            "{}",
+            error
+        );
+        let error = consume(&["^created", "^paid", "^shipped"])
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("2 of 3 message(s) on 'orders'"), "{}", error);
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use serde_json::Value;
+use tracing::info;
+
+use crate::container::unique_resource_name;
+use crate::{shell_quote, Context, Service, ServiceError, TestHarness};
+
+/// A throwaway Kubernetes cluster created with `kind` on start and deleted
+/// on stop, e.g. to test operators and controllers end to end. Once started,
+/// the path of its kubeconfig is stored in the [`Context`] under
+/// `context_key`, see [`TestHarness::add_kind_cluster`]
+#[derive(Debug, Clone)]
+pub struct KindClusterService {
+    pub name: String,
+    /// Name of the kind cluster, unique per test process by default
+    pub cluster_name: String,
+    /// Where kind writes the kubeconfig of the cluster
+    pub kubeconfig: PathBuf,
+    /// Node image, the default of the installed kind if unset
+    pub node_image: Option<String>,
+    /// kind cluster configuration file
+    pub config: Option<PathBuf>,
+    /// How long creating the cluster waits for the control plane
+    pub wait: Duration,
+    pub context_key: String,
+    /// Context the kubeconfig path is stored in
+    pub context: Option<Context>,
+    running: bool,
+    last_command_line: Option<String>,
+}
+
+impl KindClusterService {
+    /// A cluster writing its kubeconfig to `{root_dir}/kind/{name}.kubeconfig`
+    pub fn new(name: &str, root_dir: impl AsRef<Path>) -> Self {
+        Self {
+            name: name.to_string(),
+            cluster_name: unique_resource_name(name),
+            kubeconfig: root_dir
+                .as_ref()
+                .join("kind")
+                .join(format!("{}.kubeconfig", name)),
+            node_image: None,
+            config: None,
+            wait: Duration::from_secs(120),
+            context_key: "kubeconfig".to_string(),
+            context: None,
+            running: false,
+            last_command_line: None,
+        }
+    }
+
+    /// Arguments of `kind` creating the cluster
+    fn create_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "create".to_string(),
+            "cluster".to_string(),
+            "--name".to_string(),
+            self.cluster_name.clone(),
+            "--kubeconfig".to_string(),
+            self.kubeconfig.display().to_string(),
+            "--wait".to_string(),
+            format!("{}s", self.wait.as_secs()),
+        ];
+        if let Some(node_image) = &self.node_image {
+            args.extend(["--image".to_string(), node_image.clone()]);
+        }
+        if let Some(config) = &self.config {
+            args.extend(["--config".to_string(), config.display().to_string()]);
+        }
+        args
+    }
+
+    fn kind(&self, args: &[&str]) -> Result<String, ServiceError> {
+        let output = Command::new("kind")
+            .args(args)
+            .output()
+            .map_err(|e| ServiceError::failed(&self.name, format!("Failed to run kind: {}", e)))?;
+        if !output.status.success() {
+            return Err(ServiceError::failed(
+                &self.name,
+                format!(
+                    "kind {} exited with {}: {}",
+                    args.join(" "),
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl Service for KindClusterService {
+    fn name(&self) -> &str { &self.name }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        if self.running {
+            return Err(ServiceError::AlreadyRunning(self.name.clone()));
+        }
+        let args = self.create_args();
+        let command_line = std::iter::once("kind")
+            .chain(args.iter().map(String::as_str))
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        info!("Creating kind cluster '{}': {}", self.name, command_line);
+        self.last_command_line = Some(command_line);
+        if let Some(dir) = self.kubeconfig.parent() {
+            std::fs::create_dir_all(dir).map_err(|source| ServiceError::Start {
+                service: self.name.clone(),
+                source,
+            })?;
+        }
+
+        self.kind(&args.iter().map(String::as_str).collect::<Vec<_>>())?;
+        self.running = true;
+        if let Some(context) = &self.context {
+            context.insert(
+                &self.context_key,
+                Value::String(self.kubeconfig.display().to_string()),
+            );
+        }
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+            && self
+                .kind(&["get", "clusters"])
+                .is_ok_and(|clusters| clusters.lines().any(|name| name == self.cluster_name))
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        if self.running {
+            self.kind(&["delete", "cluster", "--name", &self.cluster_name])?;
+            self.running = false;
+        }
+        Ok(())
+    }
+
+    fn required_tools(&self) -> Vec<String> { vec!["kind".to_string()] }
+
+    fn last_command_line(&self) -> Option<String> { self.last_command_line.clone() }
+}
+
+impl TestHarness {
+    /// Adds the cluster, storing its kubeconfig path in the context of the
+    /// harness once it started
+    pub fn add_kind_cluster(&mut self, mut cluster: KindClusterService) {
+        cluster.context = Some(self.context.clone());
+        self.add_service(Box::new(cluster));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_args() {
+        let mut cluster = KindClusterService::new("Operator", "/tmp/run");
+        cluster.node_image = Some("kindest/node:v1.30.0".to_string());
+        let cluster_name = format!("harness-operator-{}", std::process::id());
+        assert_eq!(cluster.create_args(), [
+            "create",
+            "cluster",
+            "--name",
+            &cluster_name,
+            "--kubeconfig",
+            "/tmp/run/kind/Operator.kubeconfig",
+            "--wait",
+            "120s",
+            "--image",
+            "kindest/node:v1.30.0",
+        ]);
+
+        let mut harness = TestHarness::new("KindTester", "/tmp/run");
+        harness.add_kind_cluster(cluster);
+        assert_eq!(harness.services[0].required_tools(), ["kind"]);
+    }
+}
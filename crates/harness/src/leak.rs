@@ -0,0 +1,166 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+use tracing::info;
+
+use crate::{ServiceAction, ServiceStepExecutor, StepEnv, StepError, TestStep};
+
+/// A step that runs an operation and fails if the open file descriptors of a
+/// service grew by more than `tolerance` across it, e.g. to verify a service
+/// closes connections after a burst of requests
+#[derive(Debug)]
+pub struct AssertNoFdLeak {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    /// How many more descriptors the service may hold after the operation
+    pub tolerance: usize,
+    operation: RefCell<TestStep>,
+}
+
+impl AssertNoFdLeak {
+    pub fn new(name: &str, service_name: &str, operation: TestStep) -> Self {
+        Self {
+            name: name.to_string(),
+            service_name: service_name.to_string(),
+            tolerance: 0,
+            operation: RefCell::new(operation),
+        }
+    }
+}
+
+/// Number of file descriptors the process holds open
+fn open_fds(pid: u32) -> Result<usize, String> {
+    let fd_dir = PathBuf::from(format!("/proc/{}/fd", pid));
+    std::fs::read_dir(&fd_dir)
+        .map(Iterator::count)
+        .map_err(|e| format!("Failed to read {}: {}", fd_dir.display(), e))
+}
+
+impl ServiceStepExecutor for AssertNoFdLeak {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let mut operation = self.operation.borrow_mut();
+        let pid = env
+            .service(&self.service_name)?
+            .pid()
+            .ok_or_else(|| format!("Service '{}' has no running process", self.service_name))?;
+
+        let before = open_fds(pid)?;
+        let operation_name = operation.name().to_string();
+        env.execute_step(&mut operation)
+            .map_err(|e| format!("Operation '{}' failed: {}", operation_name, e))?;
+        let after = open_fds(pid)?;
+
+        info!(
+            "Service '{}' held {} open file descriptors before '{}' and {} after",
+            self.service_name, before, operation_name, after
+        );
+        if after > before + self.tolerance {
+            return Err(StepError::Assertion(format!(
+                "Service '{}' leaked file descriptors during '{}': {} open before, {} after \
+                 (tolerance {})",
+                self.service_name, operation_name, before, after, self.tolerance
+            )));
+        }
+        Ok(())
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::process::Command;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{PollPolicy, SubProcessService, TestHarness};
+
+    /// Makes the service open a file on SIGUSR1 and waits until it did so
+    #[derive(Debug)]
+    struct OpenFile {
+        ack: PathBuf,
+    }
+
+    impl ServiceStepExecutor for OpenFile {
+        fn name(&self) -> &str { "OpenFile" }
+
+        fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+            let pid = env.service("Fd_Service")?.pid().unwrap();
+            let status = Command::new("kill")
+                .args(["-s", "USR1", &pid.to_string()])
+                .status()
+                .map_err(|e| e.to_string())?;
+            assert!(status.success());
+            Ok(wait_for(&self.ack)?)
+        }
+    }
+
+    fn wait_for(path: &Path) -> Result<(), String> {
+        PollPolicy {
+            interval: Duration::from_millis(20),
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        }
+        .poll(|| {
+            path.exists()
+                .then_some(())
+                .ok_or_else(|| format!("{} not written", path.display()))
+        })
+    }
+
+    /// Runs `OpenFile` against a service that keeps the file open if `leak`
+    fn run_operation(leak: bool) -> Result<(), StepError> {
+        let dir = tempfile::tempdir().unwrap();
+        let ready = dir.path().join("ready");
+        let ack = dir.path().join("ack");
+        let script = format!(
+            "import signal, sys, time\nleaked = []\ndef handle(*_):\n    f = open(sys.argv[1], \
+             'w')\n    leaked.append(f) if {} else f.close()\n    open(sys.argv[3], 'w').close()\n\
+             signal.signal(signal.SIGUSR1, handle)\nopen(sys.argv[2], 'w').close()\nwhile True: \
+             time.sleep(1)",
+            if leak { "True" } else { "False" }
+        );
+        let mut harness = TestHarness::new("FdLeakTester", ".");
+        harness.add_service(Box::new(SubProcessService::new(
+            "Fd_Service",
+            "python3",
+            vec![
+                "-c".to_string(),
+                script,
+                dir.path().join("data").display().to_string(),
+                ready.display().to_string(),
+                ack.display().to_string(),
+            ],
+        )));
+        harness.services[0]
+            .start()
+            .expect("Failed to start service");
+        wait_for(&ready).expect("Service did not get ready");
+
+        let step = AssertNoFdLeak::new(
+            "Check_Fds",
+            "Fd_Service",
+            TestStep::Service(Box::new(OpenFile { ack })),
+        );
+        let result = step.execute(&mut harness.step_env());
+        harness.services[0].stop().expect("Failed to stop service");
+        result
+    }
+
+    #[test]
+    fn test_no_fd_leak_when_file_is_closed() {
+        run_operation(false).expect("Closing the file should not leak");
+    }
+
+    #[test]
+    fn test_fd_leak_when_file_stays_open() {
+        let error = run_operation(true).unwrap_err().to_string();
+        assert!(error.contains("leaked file descriptors"), "{}", error);
+    }
+}
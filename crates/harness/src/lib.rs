@@ -1,24 +1,317 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fmt::Debug;
 use std::future::Future;
-use std::process::{Child, Command};
-use std::time::Duration;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
-use log::{error, info};
+use serde_json::Value;
+use tokio::runtime::Handle;
+use tracing::{debug, error, info, info_span, warn};
+
+// Lets the paths generated by `#[harness_test]` resolve within the crate
+extern crate self as harness;
+
+use crate::fixture::FixtureSlot;
+use crate::heartbeat::Heartbeat;
+use crate::interrupt::Interrupt;
+use crate::liveness::LivenessMonitor;
+use crate::monitor::CrashWatcher;
+use crate::panic::PanicHookGuard;
+use crate::preflight::find_executable;
+use crate::runtime::HarnessRuntime;
+
+mod archive;
+mod artifacts;
+mod async_service;
+mod backup;
+mod builder;
+mod cleanup;
+mod command;
+mod compose;
+mod condition;
+pub mod config;
+mod config_check;
+mod container;
+mod context;
+mod deploy;
+mod docker;
+mod equivalence;
+mod error;
+mod exit;
+mod file;
+mod fixture;
+#[cfg(target_os = "linux")]
+mod footprint;
+mod graph;
+mod grpc;
+mod harness_test;
+mod heartbeat;
+mod html;
+mod http;
+mod idempotent;
+mod interrupt;
+mod json_assert;
+mod json_report;
+mod junit;
+mod kafka;
+mod kind;
+#[cfg(target_os = "linux")]
+mod leak;
+#[cfg(feature = "libtest")]
+mod libtest;
+mod liveness;
+mod log_level;
+mod log_match;
+mod log_tail;
+mod macros;
+mod monitor;
+mod network;
+mod options;
+mod output;
+mod panic;
+mod parallel;
+mod phase;
+mod ports;
+mod postgres;
+mod preflight;
+mod probe;
+#[cfg(all(feature = "profiling", target_os = "linux"))]
+mod profile;
+mod progress;
+mod readiness;
+mod redis;
+mod report;
+mod reporter;
+mod rerun;
+mod resources;
+mod retry;
+mod runtime;
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+mod sandbox;
+mod select;
+mod service_template;
+mod settings;
+mod snapshot;
+mod stability;
+mod startup;
+mod stdin;
+mod stop;
+mod suite;
+mod tags;
+mod tap;
+mod tcp;
+mod tempdir;
+mod template;
+#[cfg(test)]
+mod test_log;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+mod timings;
+mod validate;
+mod websocket;
+
+pub use archive::ArchiveArtifacts;
+pub use artifacts::ArtifactSink;
+pub use async_service::AsyncService;
+pub use backup::{Backup, Restore};
+pub use builder::TestHarnessBuilder;
+pub use command::ShellCommandStep;
+pub use compose::{ComposeMember, ComposeService};
+pub use condition::{Condition, ConditionFn};
+pub use config_check::{ConfigFormat, ValidateConfig};
+pub use container::{container_runtime_from_env, ContainerRuntime, Docker, Podman};
+pub use context::{AssertContextValue, Context};
+pub use deploy::{BlueGreenDeployment, DeploymentHook};
+pub use docker::DockerService;
+pub use equivalence::{AssertEquivalent, Normalizer};
+pub use error::{HarnessError, ServiceError, StepError};
+pub use exit::WaitForExit;
+pub use file::FileAssertionStep;
+pub use fixture::Fixture;
+#[cfg(target_os = "linux")]
+pub use footprint::AssertHarnessFootprint;
+pub use graph::{StartAllServices, StartIfHealthy};
+pub use grpc::{GrpcCallStep, GrpcHealthProbe, GrpcResponse};
+#[cfg(feature = "macros")]
+pub use harness_macros::harness_test;
+pub use harness_test::{SetupFuture, TestOutcome};
+pub use http::HttpRequestStep;
+pub use idempotent::{AssertIdempotent, AsyncOperation};
+pub use json_assert::{json_path, AssertJson, AssertionFailure, JsonAssertion, Matcher};
+pub use kafka::{KafkaBroker, KafkaConsumeAssertStep, KafkaFlavor, KafkaProduceStep, KafkaService};
+pub use kind::KindClusterService;
+#[cfg(target_os = "linux")]
+pub use leak::AssertNoFdLeak;
+pub use liveness::{LivenessCheck, LivenessFailure};
+pub use log_level::AssertNoLogLevel;
+pub use log_match::LogMatchStep;
+pub use network::ContainerNetwork;
+pub use options::HarnessOptions;
+pub use output::{LogFiles, OutputLine, OutputStream};
+pub use ports::PortAllocator;
+pub use postgres::{PostgresBackend, PostgresService};
+pub use preflight::MissingTool;
+pub use probe::{Assertion, EventuallyAssert, PollPolicy, Predicate, WaitUntilStep};
+#[cfg(all(feature = "profiling", target_os = "linux"))]
+pub use profile::{CaptureProfile, Profiler};
+pub use readiness::ReadinessProbe;
+pub use redis::{RedisBackend, RedisCommandStep, RedisReply, RedisService};
+pub use report::{RunReport, StepReport, StepStatus, UnexpectedExit};
+pub use reporter::ReportFormat;
+pub use rerun::{RetriedRun, RetryOutcome};
+pub use resources::WaitForCpuIdle;
+pub use retry::RetryPolicy;
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+pub use sandbox::Sandbox;
+pub use service_template::ServiceTemplate;
+pub use settings::{HarnessSettings, SuiteConfig, PROFILE_VAR};
+pub use snapshot::{SnapshotSource, SnapshotStep, UPDATE_SNAPSHOTS_VAR};
+pub use stability::{StabilityReport, StepStability};
+pub use startup::AssertStartupTime;
+pub use stdin::StdinSource;
+pub use stop::StopMode;
+pub use suite::{RunOnceRegistry, SuiteReport, TestSuite};
+pub use tags::TagFilter;
+pub use tcp::TcpProbeStep;
+pub use tempdir::TempDirFixture;
+pub use template::{AsyncFnTemplate, SharedAsyncFn, StepTemplate};
+pub use timings::Timings;
+pub use websocket::WebSocketStep;
 
 /// A single step of a test
 #[derive(Debug)]
 pub enum TestStep {
     /// A step that executes over services, such as starting or stopping a
     /// service
-    Service(Box<dyn ServiceStepExecutor<StepError = String>>),
+    Service(Box<dyn ServiceStepExecutor>),
     /// A step that executes an async function
     AsyncFn(Box<AsyncFnStep>),
+    /// Steps executed concurrently, e.g. probes of many endpoints after
+    /// startup. Async steps run together on the runtime of the run, other
+    /// steps on threads of their own without access to the services. All
+    /// steps run to completion and the group fails if any of them failed
+    Parallel(Vec<TestStep>),
+    /// Executes `then` if the condition holds when the step is reached,
+    /// otherwise `otherwise` or, if unset, marks the step skipped. Reported
+    /// under the name of `then`
+    Conditional {
+        condition: Condition,
+        then: Box<TestStep>,
+        otherwise: Option<Box<TestStep>>,
+    },
+}
+
+impl TestStep {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Service(step_executor) => step_executor.name(),
+            Self::AsyncFn(async_step) => &async_step.name,
+            Self::Parallel(_) => "Parallel",
+            Self::Conditional { then, .. } => then.name(),
+        }
+    }
+
+    /// What the step does, as shown in reports
+    pub fn description(&self) -> &str {
+        match self {
+            Self::Service(step_executor) => step_executor.description(),
+            Self::AsyncFn(async_step) => &async_step.description,
+            Self::Parallel(_) => "",
+            Self::Conditional { then, .. } => then.description(),
+        }
+    }
+
+    /// The service steps of this step, including those in parallel groups
+    pub(crate) fn executors(&self) -> Vec<&dyn ServiceStepExecutor> {
+        match self {
+            Self::Service(step_executor) => vec![&**step_executor],
+            Self::AsyncFn(_) => Vec::new(),
+            Self::Parallel(steps) => steps.iter().flat_map(Self::executors).collect(),
+            Self::Conditional {
+                then, otherwise, ..
+            } => then
+                .executors()
+                .into_iter()
+                .chain(otherwise.iter().flat_map(|otherwise| otherwise.executors()))
+                .collect(),
+        }
+    }
+}
+
+/// Options controlling how the harness treats a step
+#[derive(Debug, Clone)]
+pub struct StepOptions {
+    /// Whether a failure of the step fails the test. A failing optional step
+    /// is recorded as a warning in the [`RunReport`] and does not tear down
+    /// services
+    pub required: bool,
+    /// Steps sharing a key run only once across all harnesses sharing a
+    /// [`RunOnceRegistry`], e.g. for expensive seeding of warm-started
+    /// services
+    pub run_once_key: Option<String>,
+    /// Names of steps this step relies on, included automatically when the
+    /// step is selected by [`TestHarness::run_only`]
+    pub prerequisites: Vec<String>,
+    /// Names of steps that must pass before this step. Steps are reordered to
+    /// run after the steps they depend on and skipped if one of them did not
+    /// pass. Like prerequisites, they are included by
+    /// [`TestHarness::run_only`]
+    pub depends_on: Vec<String>,
+    /// Labels such as `slow` or `network` to select steps by, see
+    /// [`TestHarness::execute_filtered`]
+    pub tags: Vec<String>,
+    /// How often the step is attempted before it is marked failed
+    pub retry: RetryPolicy,
+}
+
+impl Default for StepOptions {
+    fn default() -> Self {
+        Self {
+            required: true,
+            run_once_key: None,
+            prerequisites: Vec::new(),
+            depends_on: Vec::new(),
+            tags: Vec::new(),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// What the harness does after a required step failed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Stop services, skip the remaining steps and return the error from
+    /// [`TestHarness::execute`] once teardown ran
+    Abort,
+    /// Keep executing the remaining steps with services left as they are
+    Continue,
+    /// Stop services, then keep executing the remaining steps. The failure
+    /// is recorded in the [`RunReport`]
+    #[default]
+    ContinueWithCleanup,
+}
+
+/// A step together with the options it was added with
+#[derive(Debug)]
+pub struct PlannedStep {
+    pub step: TestStep,
+    pub options: StepOptions,
 }
 
+/// Deferred body of an [`AsyncFnStep`], the value it resolves to is stored in
+/// the [`Context`] under the name of the step. It is called again for every
+/// retry of the step
+pub type AsyncStepFn =
+    Box<dyn FnMut(&Context) -> Box<dyn Future<Output = Result<Value, String>>> + Send>;
+
 pub struct AsyncFnStep {
     pub name: String,
     pub description: String,
-    pub futurefn: Box<dyn FnOnce() -> Box<dyn Future<Output = Result<(), String>>>>,
+    pub futurefn: AsyncStepFn,
 }
 
 impl Debug for AsyncFnStep {
@@ -35,8 +328,83 @@ impl Debug for AsyncFnStep {
 pub struct TestHarness {
     pub test_name: String,
     pub root_dir: String,
-    pub services: Vec<Box<dyn Service<ServiceError = String>>>,
-    pub steps: Vec<TestStep>,
+    pub services: Vec<Box<dyn Service>>,
+    pub steps: Vec<PlannedStep>,
+    /// Services whose unexpected exits are recorded as failures, see
+    /// [`TestHarness::monitor_crashes`]
+    pub crash_monitored: Vec<String>,
+    /// Names of the services each service depends on, see
+    /// [`TestHarness::add_dependency`]
+    pub dependencies: HashMap<String, Vec<String>>,
+    /// Groups of services that must not run at the same time, see
+    /// [`TestHarness::add_exclusive_group`]
+    pub exclusive_groups: Vec<Vec<String>>,
+    /// Resources the test cannot share with other tests, see
+    /// [`TestHarness::require_exclusive`]
+    pub exclusive_resources: Vec<String>,
+    /// How many times stopping a service during cleanup is retried before
+    /// it is reported as a cleanup failure
+    pub stop_retries: u32,
+    pub stop_retry_delay: Duration,
+    /// Keys of run-once steps that already ran, share it between harnesses to
+    /// skip repeated setup
+    pub run_once_registry: RunOnceRegistry,
+    /// Values shared between steps
+    pub context: Context,
+    /// Directory steps and services write files to, which also receives the
+    /// service logs, the context and a summary when the run fails
+    pub artifacts: ArtifactSink,
+    /// Ports reserved for the services, substituted for `{{port:name}}`
+    /// placeholders in their configuration and in step URLs
+    pub ports: PortAllocator,
+    /// Interval at which a still running step is logged, disabled if unset
+    pub heartbeat: Option<Duration>,
+    /// Show the running step with its elapsed time and the status of the
+    /// services when stdout is a terminal, logs are unaffected
+    pub progress: bool,
+    /// What happens after a required step failed
+    pub failure_policy: FailurePolicy,
+    /// Leave services running after a required step failed, to inspect them
+    pub keep_alive: bool,
+    /// Log the current step and the services when a step panics
+    pub panic_hook: bool,
+    /// How many of the last output lines of each service are logged when a
+    /// required step fails, disabled if 0
+    pub failure_log_lines: usize,
+    /// Services started during the run, in order
+    startup_log: Vec<(String, Instant)>,
+    /// Services that were awaited to become healthy, with when they did
+    ready_log: Vec<(String, Instant)>,
+    /// Why the current step skipped itself, see [`StepEnv::skip`]
+    skip_reason: Option<String>,
+    /// Runtime async steps and services are driven on, shared by all of them
+    runtime: HarnessRuntime,
+    /// Names of the steps to execute with their prerequisites, all steps if
+    /// unset
+    filter: Option<Vec<String>>,
+    /// Signal the run was interrupted by, see
+    /// [`TestHarness::install_signal_handlers`]
+    interrupt: Arc<Interrupt>,
+    handle_signals: bool,
+    /// Steps executed before the main steps, see [`TestHarness::add_setup`]
+    setup: Vec<PlannedStep>,
+    /// Steps executed after the main steps, see [`TestHarness::add_teardown`]
+    teardown: Vec<PlannedStep>,
+    /// Services checked in the background, see
+    /// [`TestHarness::monitor_liveness`]
+    liveness_checks: Vec<(String, LivenessCheck)>,
+    liveness_monitors: Vec<LivenessMonitor>,
+    /// Watches crash-monitored services during the run, see
+    /// [`TestHarness::monitor_crashes`]
+    crash_watcher: Option<CrashWatcher>,
+    /// Torn down once the run finished, see [`TestHarness::add_fixture`]
+    fixtures: Vec<FixtureSlot>,
+    /// Cleanups of steps not attempted yet, see [`TestHarness::on_cleanup`]
+    cleanups: Vec<(String, TestStep)>,
+    /// Cleanups of attempted steps, run when the test ends
+    armed_cleanups: Vec<TestStep>,
+    /// Report files written after the run, see [`TestHarness::write_report`]
+    reports: Vec<(ReportFormat, PathBuf)>,
 }
 
 impl TestHarness {
@@ -46,61 +414,604 @@ impl TestHarness {
             root_dir: root_dir.to_string(),
             services: Vec::new(),
             steps: Vec::new(),
+            crash_monitored: Vec::new(),
+            dependencies: HashMap::new(),
+            exclusive_groups: Vec::new(),
+            exclusive_resources: Vec::new(),
+            stop_retries: 0,
+            stop_retry_delay: Duration::from_millis(500),
+            run_once_registry: RunOnceRegistry::default(),
+            context: Context::new(),
+            artifacts: ArtifactSink::new(root_dir, test_name),
+            ports: PortAllocator::new(),
+            heartbeat: None,
+            progress: false,
+            failure_policy: FailurePolicy::default(),
+            keep_alive: false,
+            panic_hook: false,
+            failure_log_lines: 20,
+            startup_log: Vec::new(),
+            ready_log: Vec::new(),
+            skip_reason: None,
+            runtime: HarnessRuntime::default(),
+            filter: None,
+            interrupt: Arc::default(),
+            handle_signals: false,
+            setup: Vec::new(),
+            teardown: Vec::new(),
+            liveness_checks: Vec::new(),
+            liveness_monitors: Vec::new(),
+            crash_watcher: None,
+            fixtures: Vec::new(),
+            cleanups: Vec::new(),
+            armed_cleanups: Vec::new(),
+            reports: Vec::new(),
         }
     }
 
-    pub fn add_service(&mut self, service: Box<dyn Service<ServiceError = String>>) {
-        self.services.push(service);
+    /// Adds a service, which steps refer to by its [`Service::name`]. Names
+    /// have to be unique, which is checked by [`TestHarness::validate`]
+    pub fn add_service(&mut self, service: Box<dyn Service>) { self.services.push(service); }
+
+    /// Finds a service by its [`Service::name`]
+    pub fn service(&self, name: &str) -> Option<&dyn Service> {
+        self.services
+            .iter()
+            .find(|service| service.name() == name)
+            .map(|service| &**service)
     }
 
-    pub fn add_step(&mut self, step: TestStep) { self.steps.push(step); }
+    /// Finds a service by its [`Service::name`] for modification
+    pub fn service_mut(&mut self, name: &str) -> Option<&mut Box<dyn Service>> {
+        self.services
+            .iter_mut()
+            .find(|service| service.name() == name)
+    }
 
-    pub fn execute(mut self) -> Result<(), String> {
+    pub fn add_step(&mut self, step: TestStep) { self.add_step_with(step, StepOptions::default()); }
+
+    pub fn add_step_with(&mut self, step: TestStep, options: StepOptions) {
+        self.steps.push(PlannedStep { step, options });
+    }
+
+    pub fn execute(self) -> Result<RunReport, HarnessError> { self.run(None) }
+
+    /// Executes the test like [`TestHarness::execute`], but drives all async
+    /// steps and services on the given runtime instead of creating a runtime
+    /// of its own.
+    /// The handle should belong to a multi-threaded runtime, and this must
+    /// not be called from within an async context
+    pub fn execute_on(self, handle: &Handle) -> Result<RunReport, HarnessError> {
+        self.run(Some(handle))
+    }
+
+    fn run(mut self, handle: Option<&Handle>) -> Result<RunReport, HarnessError> {
+        let _span = info_span!("test", name = %self.test_name).entered();
         info!(
             "Executing test: {} with rootdir: {}",
             self.test_name, self.root_dir
         );
+        let missing_tools = self.preflight_check();
+        if !missing_tools.is_empty() {
+            return Err(HarnessError::MissingTools(missing_tools));
+        }
+        self.validate()?;
+        self.order_steps()?;
+        let panic_hook = self
+            .panic_hook
+            .then(|| PanicHookGuard::install(&self.test_name));
+        if let Some(handle) = handle {
+            self.runtime.provide(handle.clone());
+        }
+        #[cfg(unix)]
+        let _signals = self
+            .handle_signals
+            .then(|| self.interrupt.listen())
+            .flatten();
+        let mut report = RunReport::new(&self.test_name);
+        let selection = match &self.filter {
+            Some(step_names) => {
+                let (selection, auto_included) = self.select(step_names)?;
+                report.auto_included = auto_included;
+                Some(selection)
+            }
+            None => None,
+        };
+        self.start_liveness_monitors();
+        self.start_crash_watcher();
+        let setup = self
+            .set_up_fixtures(&mut report)
+            .and_then(|()| self.run_setup(&mut report, panic_hook.as_ref()));
+        let setup_failed = setup.is_err();
+        let mut aborted = setup
+            .err()
+            .filter(|_| self.failure_policy == FailurePolicy::Abort);
+        self.check_interrupt(&mut report, &mut aborted);
         let total_steps = self.steps.len();
-        for (idx, step) in self.steps.into_iter().enumerate() {
+        let steps = std::mem::take(&mut self.steps);
+        let mut passed = HashSet::new();
+        for (idx, PlannedStep { mut step, options }) in steps.into_iter().enumerate() {
+            let name = step.name().to_string();
+            if setup_failed || aborted.is_some() {
+                let reason = if setup_failed {
+                    "setup failed"
+                } else {
+                    "run aborted"
+                };
+                info!("Skipping step {}/{}: {}", idx + 1, total_steps, reason);
+                report.steps.push(StepReport::new(
+                    &step,
+                    StepStatus::Skipped(reason.to_string()),
+                ));
+                continue;
+            }
+            if selection
+                .as_ref()
+                .is_some_and(|selection| !selection.contains(&name))
+            {
+                info!("Skipping step {}/{}: not selected", idx + 1, total_steps);
+                let status = StepStatus::Skipped("not selected".to_string());
+                report.steps.push(StepReport::new(&step, status));
+                continue;
+            }
+            if let Some(dependency) = options
+                .depends_on
+                .iter()
+                .find(|dependency| !passed.contains(*dependency))
+            {
+                let reason = format!("dependency '{}' did not pass", dependency);
+                info!("Skipping step {}/{}: {}", idx + 1, total_steps, reason);
+                report
+                    .steps
+                    .push(StepReport::new(&step, StepStatus::Skipped(reason)));
+                continue;
+            }
+            if let Some(failure) = report.liveness_failures.first() {
+                warn!(
+                    "Failing step {}/{} without executing it: {}",
+                    idx + 1,
+                    total_steps,
+                    failure
+                );
+                let status = StepStatus::Failed(failure.to_string());
+                report.steps.push(StepReport::new(&step, status));
+                continue;
+            }
+            if let Some(key) = &options.run_once_key {
+                if !self.run_once_registry.claim(key) {
+                    info!(
+                        "Skipping step {}/{}: run-once key '{}' already ran",
+                        idx + 1,
+                        total_steps,
+                        key
+                    );
+                    let status = StepStatus::Skipped(format!("run-once key '{}' already ran", key));
+                    report.steps.push(StepReport::new(&step, status));
+                    continue;
+                }
+            }
             info!("Executing step {}/{}:\n   {:?}", idx + 1, total_steps, step);
-            let result = match step {
-                TestStep::Service(step_executor) =>
-                    step_executor.execute(self.services.as_mut_slice()),
-                TestStep::AsyncFn(async_step) => tokio::runtime::Runtime::new()
-                    .map_err(|e| format!("Failed to create runtime: {}", e))?
-                    .block_on(Box::into_pin((async_step.futurefn)())),
+            let progress = self.show_progress(&format!("step {}/{}", idx + 1, total_steps), &step);
+            let started = Instant::now();
+            let result = self.attempt_step(&mut step, &options, panic_hook.as_ref());
+            drop(progress);
+            let duration = started.elapsed();
+            let skip_reason = self.skip_reason.take();
+            if let Some(key) = &options.run_once_key {
+                match &result {
+                    Ok(()) => self.run_once_registry.complete(key),
+                    Err(_) => self.run_once_registry.release(key),
+                }
+            }
+            let status = match result {
+                Err(e) if !options.required => {
+                    warn!("Optional step failed: {}", e);
+                    StepStatus::Warning(e.to_string())
+                }
+                Err(e) => {
+                    error!("Step execution failed: {}", e);
+                    self.clean_up_after_failure(&mut report);
+                    let status = StepStatus::Failed(e.to_string());
+                    if self.failure_policy == FailurePolicy::Abort {
+                        aborted = Some(HarnessError::StepFailed {
+                            index: idx + 1,
+                            name: name.clone(),
+                            source: e,
+                        });
+                    }
+                    status
+                }
+                Ok(()) if skip_reason.is_some() => {
+                    let reason = skip_reason.unwrap_or_default();
+                    info!("Step {}/{} skipped: {}", idx + 1, total_steps, reason);
+                    StepStatus::Skipped(reason)
+                }
+                Ok(()) => {
+                    info!("Step executed successfully: {}/{}", idx + 1, total_steps);
+                    passed.insert(name.clone());
+                    StepStatus::Passed
+                }
             };
-            if let Err(e) = result {
-                error!("Step execution failed: {}", e);
-                for service in self.services.iter_mut().rev() {
-                    if service.is_running() {
-                        match service.stop() {
-                            Ok(_) => info!("Service {:?} stopped successfully", service),
-                            Err(e) => error!("Failed to stop service {:?}: {}", service, e),
-                        }
+            report.steps.push(StepReport {
+                started: Some(started),
+                duration,
+                ..StepReport::new(&step, status)
+            });
+            self.check_crashes(&mut report);
+            self.check_liveness(&mut report);
+            self.check_interrupt(&mut report, &mut aborted);
+        }
+        // Teardown stops services, which is not a liveness failure
+        self.liveness_monitors.clear();
+        self.crash_watcher = None;
+        self.run_cleanups(&mut report, panic_hook.as_ref());
+        self.run_teardown(&mut report, panic_hook.as_ref());
+        // Cleanups of teardown steps
+        self.run_cleanups(&mut report, panic_hook.as_ref());
+        report.command_lines = self
+            .services
+            .iter()
+            .filter_map(|service| Some((service.name().to_string(), service.last_command_line()?)))
+            .collect();
+        report.startup_log = std::mem::take(&mut self.startup_log);
+        report.ready_log = std::mem::take(&mut self.ready_log);
+        report.services = self
+            .services
+            .iter()
+            .map(|service| service.name().to_string())
+            .collect();
+        report.log_files = self
+            .services
+            .iter()
+            .filter_map(|service| Some((service.name().to_string(), service.log_files()?)))
+            .collect();
+        report.log_excerpts = self.service_log_tails();
+        report.duration = report.started.elapsed();
+        info!("Timings of test {}:\n{}", self.test_name, report.timings());
+        self.write_reports(&report);
+        let failed = aborted.is_some() || !report.passed();
+        if failed {
+            self.collect_failure_bundle(&report);
+        }
+        // Services may still use the fixtures
+        if !self.keep_alive {
+            self.stop_remaining_services();
+        }
+        self.tear_down_fixtures(failed);
+        if let Some(error) = aborted {
+            return Err(error);
+        }
+        for unexpected_exit in &report.unexpected_exits {
+            error!("{}", unexpected_exit);
+        }
+        info!("Test execution completed for {}", self.test_name);
+        Ok(report)
+    }
+
+    /// Executes a step according to its options, retrying it if it fails
+    pub(crate) fn attempt_step(
+        &mut self,
+        step: &mut TestStep,
+        options: &StepOptions,
+        panic_hook: Option<&PanicHookGuard>,
+    ) -> Result<(), StepError> {
+        let name = step.name().to_string();
+        let _span = info_span!("step", name = %name).entered();
+        self.arm_cleanups(&name);
+        if let Some(panic_hook) = panic_hook {
+            panic_hook.enter_step(&name, &self.services);
+        }
+        let _heartbeat = self
+            .heartbeat
+            .map(|interval| Heartbeat::start(&name, interval));
+        let mut attempt = 1;
+        loop {
+            let result = catch_unwind(AssertUnwindSafe(|| self.step_env().execute_step(step)))
+                .unwrap_or_else(|payload| Err(StepError::panicked(&*payload)));
+            match result {
+                Err(e) if attempt < options.retry.max_attempts => {
+                    let delay = options.retry.delay(attempt);
+                    warn!(
+                        "Step '{}' failed on attempt {}/{}, retrying in {:?}: {}",
+                        name, attempt, options.retry.max_attempts, delay, e
+                    );
+                    self.skip_reason = None;
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Logs the output of the services and applies the [`FailurePolicy`] to
+    /// them after a required step failed
+    pub(crate) fn clean_up_after_failure(&mut self, report: &mut RunReport) {
+        self.dump_service_logs();
+        if self.failure_policy == FailurePolicy::Continue {
+            info!("Continuing with services left as they are");
+        } else if self.keep_alive {
+            warn!("Keeping services running for inspection");
+        } else {
+            self.stop_services(report);
+        }
+    }
+
+    /// Stops all services once the run was interrupted by a signal, which
+    /// aborts the run
+    fn check_interrupt(&mut self, report: &mut RunReport, aborted: &mut Option<HarnessError>) {
+        let Some(signal) = self.interrupt.signal() else {
+            return;
+        };
+        if !matches!(aborted, Some(HarnessError::Interrupted { .. })) {
+            warn!("Run interrupted by {}, stopping services", signal);
+            self.stop_services(report);
+            *aborted = Some(HarnessError::Interrupted { signal });
+        }
+    }
+
+    /// Stops all running services in reverse order without retrying
+    fn stop_remaining_services(&mut self) {
+        for service in self.services.iter_mut().rev() {
+            if service.is_running() {
+                let _span =
+                    info_span!("service", name = %service.name(), operation = "stop").entered();
+                if let Err(e) = service.stop() {
+                    error!("Failed to stop service {:?}: {}", service, e);
+                }
+            }
+        }
+    }
+
+    /// Environment handed to step executors
+    pub(crate) fn step_env(&mut self) -> StepEnv<'_> {
+        StepEnv {
+            root_dir: Path::new(&self.root_dir),
+            services: self.services.as_mut_slice(),
+            dependencies: &self.dependencies,
+            context: &self.context,
+            artifacts: &self.artifacts,
+            ports: &self.ports,
+            startup_log: &mut self.startup_log,
+            ready_log: &mut self.ready_log,
+            skip_reason: &mut self.skip_reason,
+            runtime: &self.runtime,
+            interrupt: &self.interrupt,
+        }
+    }
+
+    /// Stops all running services in reverse order, retrying each according
+    /// to [`TestHarness::stop_retries`]
+    fn stop_services(&mut self, report: &mut RunReport) {
+        for service in self.services.iter_mut().rev() {
+            if !service.is_running() {
+                continue;
+            }
+            let _span = info_span!("service", name = %service.name(), operation = "stop").entered();
+            let mut attempt = 0;
+            loop {
+                match service.stop() {
+                    Ok(_) => {
+                        info!("Service {:?} stopped successfully", service);
+                        break;
+                    }
+                    Err(e) if attempt < self.stop_retries => {
+                        attempt += 1;
+                        warn!(
+                            "Failed to stop service {:?}, retrying ({}/{}): {}",
+                            service, attempt, self.stop_retries, e
+                        );
+                        std::thread::sleep(self.stop_retry_delay);
+                    }
+                    Err(e) => {
+                        error!("Failed to stop service {:?}: {}", service, e);
+                        report.cleanup_failures.push(e.to_string());
+                        break;
                     }
                 }
-            } else {
-                info!("Step executed successfully: {}/{}", idx + 1, total_steps);
             }
         }
-        info!("Test execution completed for {}", self.test_name);
+    }
+}
+
+impl Drop for TestHarness {
+    /// Runs the armed cleanups and stops services that are still running,
+    /// e.g. because the harness was dropped while unwinding from a panic,
+    /// unless they are kept alive for inspection
+    fn drop(&mut self) {
+        self.run_remaining_cleanups();
+        if !self.keep_alive {
+            self.stop_remaining_services();
+        }
+        self.tear_down_fixtures(std::thread::panicking());
+    }
+}
+
+/// What a step gets to work with while it executes
+#[derive(Debug)]
+pub struct StepEnv<'a> {
+    /// Root directory of the test, relative paths used by steps are resolved
+    /// against it
+    pub root_dir: &'a Path,
+    pub services: &'a mut [Box<dyn Service>],
+    /// Names of the services each service depends on
+    pub dependencies: &'a HashMap<String, Vec<String>>,
+    /// Values shared between steps
+    pub context: &'a Context,
+    /// Directory of the run for files worth keeping, e.g. responses
+    pub artifacts: &'a ArtifactSink,
+    pub ports: &'a PortAllocator,
+    startup_log: &'a mut Vec<(String, Instant)>,
+    ready_log: &'a mut Vec<(String, Instant)>,
+    skip_reason: &'a mut Option<String>,
+    runtime: &'a HarnessRuntime,
+    interrupt: &'a Interrupt,
+}
+
+impl StepEnv<'_> {
+    /// Resolves a path relative to the root directory of the test
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf { self.root_dir.join(path) }
+
+    /// Finds a service by its [`Service::name`]
+    pub fn service(&mut self, name: &str) -> Result<&mut Box<dyn Service>, ServiceError> {
+        let idx = self.service_index(name)?;
+        Ok(&mut self.services[idx])
+    }
+
+    /// Position of a service in [`TestHarness::services`], looked up by its
+    /// [`Service::name`]
+    pub fn service_index(&self, name: &str) -> Result<usize, ServiceError> {
+        self.services
+            .iter()
+            .position(|service| service.name() == name)
+            .ok_or_else(|| ServiceError::NotFound(name.to_string()))
+    }
+
+    /// Starts the service at `idx`, recording it in the startup order of the
+    /// run
+    pub fn start_service(&mut self, idx: usize) -> Result<(), ServiceError> {
+        let service = &mut self.services[idx];
+        let _span = info_span!("service", name = %service.name(), operation = "start").entered();
+        service.resolve_ports(&self.ports.with_context(self.context))?;
+        service.start()?;
+        self.startup_log
+            .push((service.name().to_string(), Instant::now()));
         Ok(())
     }
+
+    /// Replaces the placeholders in `text`, e.g. a URL, such as
+    /// `{{port:name}}` with the ports reserved for the services and
+    /// `{{context.key}}` with values of the context, see
+    /// [`PortAllocator::substitute`]
+    pub fn substitute_ports(&self, text: &str) -> Result<String, StepError> {
+        self.ports
+            .with_context(self.context)
+            .substitute(text)
+            .map_err(|e| {
+                StepError::io(
+                    format!("Failed to substitute the placeholders of '{}'", text),
+                    e,
+                )
+            })
+    }
+
+    /// Marks the step as skipped for the given reason, reported as
+    /// [`StepStatus::Skipped`] if the step then succeeds
+    pub fn skip(&mut self, reason: &str) { *self.skip_reason = Some(reason.to_string()); }
+
+    /// When the service was last started by the harness
+    pub fn started_at(&self, name: &str) -> Option<Instant> {
+        self.startup_log
+            .iter()
+            .rev()
+            .find(|(service, _)| service == name)
+            .map(|(_, started_at)| *started_at)
+    }
+
+    /// When the service became healthy after it was last started, if that
+    /// was awaited with [`StepEnv::wait_until_healthy`]
+    pub fn ready_at(&self, name: &str) -> Option<Instant> {
+        let started_at = self.started_at(name)?;
+        self.ready_log
+            .iter()
+            .rev()
+            .find(|(service, ready_at)| service == name && *ready_at >= started_at)
+            .map(|(_, ready_at)| *ready_at)
+    }
+
+    /// Polls [`Service::is_healthy`] according to `policy` and records when
+    /// the service became healthy
+    pub fn wait_until_healthy(
+        &mut self,
+        name: &str,
+        policy: &PollPolicy,
+    ) -> Result<Instant, StepError> {
+        let _span = info_span!("service", name = %name, operation = "wait_until_healthy").entered();
+        let service = self.service(name)?;
+        let ready_at = policy
+            .poll(|| service.is_healthy().then(Instant::now).ok_or(()))
+            .map_err(|_| StepError::Timeout {
+                what: format!("Service '{}' not healthy", name),
+                after: policy.timeout,
+            })?;
+        self.ready_log.push((name.to_string(), ready_at));
+        Ok(ready_at)
+    }
+
+    /// Executes a step, e.g. the operation wrapped by another step. The
+    /// output of async steps is stored in the context under their name
+    pub fn execute_step(&mut self, step: &mut TestStep) -> Result<(), StepError> {
+        match step {
+            TestStep::Service(step_executor) => step_executor.execute(self),
+            TestStep::AsyncFn(async_step) => {
+                let future = Box::into_pin((async_step.futurefn)(self.context));
+                let value = self.block_on(future)??;
+                self.context.insert(&async_step.name, value);
+                Ok(())
+            }
+            TestStep::Parallel(steps) => self.execute_parallel(steps),
+            TestStep::Conditional {
+                condition,
+                then,
+                otherwise,
+            } =>
+                if condition.holds(self.context) {
+                    self.execute_step(then)
+                } else if let Some(otherwise) = otherwise {
+                    self.execute_step(otherwise)
+                } else {
+                    self.skip(&format!("condition '{}' does not hold", condition));
+                    Ok(())
+                },
+        }
+    }
+
+    /// Drives a future to completion on the runtime of the run, which is
+    /// shared by all steps, so tasks spawned by one step keep running in the
+    /// next
+    pub fn block_on<F: Future>(&self, future: F) -> Result<F::Output, StepError> {
+        let interrupt = self.interrupt;
+        let received = interrupt.count();
+        self.runtime.block_on(async {
+            tokio::select! {
+                output = future => Ok(output),
+                signal = interrupt.raised_after(received) => Err(StepError::Interrupted(signal)),
+            }
+        })?
+    }
+}
+
+pub trait ServiceStepExecutor: Debug + Send {
+    /// Name the step is reported under
+    fn name(&self) -> &str;
+    /// What the step does, as shown in reports
+    fn description(&self) -> &str { "" }
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError>;
+    /// External executables the step relies on, checked by
+    /// [`TestHarness::preflight_check`]
+    fn required_tools(&self) -> Vec<String> { Vec::new() }
+    /// Services the step acts on, used to describe the execution plan
+    fn service_actions(&self) -> Vec<ServiceAction> { Vec::new() }
 }
 
-pub trait ServiceStepExecutor: Debug {
-    type StepError;
-    fn execute(
-        &self,
-        services: &mut [Box<dyn Service<ServiceError = String>>],
-    ) -> Result<(), Self::StepError>;
+/// How a step acts on a service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceAction {
+    Start(String),
+    /// The step starts every service of the harness
+    StartAll,
+    Stop(String),
+    /// The step interacts with the service without changing its state
+    Use(String),
 }
 
+#[derive(Clone)]
 pub struct SubProcessServiceStarter {
+    /// Name the step is reported under
     pub name: String,
     pub description: String,
-    pub service_idx: usize,
+    /// Name of the service to start
+    pub service_name: String,
     pub wait_after: Option<Duration>,
 }
 
@@ -109,37 +1020,43 @@ impl Debug for SubProcessServiceStarter {
         f.debug_struct("SubProcessServiceStarter")
             .field("name", &self.name)
             .field("description", &self.description)
+            .field("service_name", &self.service_name)
             .finish()
     }
 }
 
 impl ServiceStepExecutor for SubProcessServiceStarter {
-    type StepError = String;
-
-    fn execute(
-        &self,
-        services: &mut [Box<dyn Service<ServiceError = String>>],
-    ) -> Result<(), Self::StepError> {
-        // Implementation of the step execution logic
-        assert!(services.len() == 1, "Expected exactly one service");
-        let service = &mut services[self.service_idx];
-        if service.is_running() {
-            return Err(format!("Service '{}' is already running", self.name));
+    fn name(&self) -> &str { &self.name }
+
+    fn description(&self) -> &str { &self.description }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let idx = env.service_index(&self.service_name)?;
+        if env.services[idx].is_running() {
+            return Err(ServiceError::AlreadyRunning(self.service_name.clone()).into());
+        }
+        env.start_service(idx)?;
+        if let Some(policy) = env.services[idx].readiness_policy() {
+            env.wait_until_healthy(&self.service_name, &policy)?;
         }
-        service
-            .start()
-            .map_err(|e| format!("Failed to start service '{}': {}", self.name, e))?;
         if let Some(wait_duration) = self.wait_after {
             std::thread::sleep(wait_duration);
         }
         Ok(())
     }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Start(self.service_name.clone())]
+    }
 }
 
+#[derive(Clone)]
 pub struct SubProcessServiceStopper {
+    /// Name the step is reported under
     pub name: String,
     pub description: String,
-    pub service_idx: usize,
+    /// Name of the service to stop
+    pub service_name: String,
     pub wait_after: Option<Duration>,
 }
 
@@ -148,45 +1065,202 @@ impl Debug for SubProcessServiceStopper {
         f.debug_struct("SubProcessServiceStopper")
             .field("name", &self.name)
             .field("description", &self.description)
+            .field("service_name", &self.service_name)
             .finish()
     }
 }
 
 impl ServiceStepExecutor for SubProcessServiceStopper {
-    type StepError = String;
-
-    fn execute(
-        &self,
-        services: &mut [Box<dyn Service<ServiceError = String>>],
-    ) -> Result<(), Self::StepError> {
-        // Implementation of the step execution logic
-        assert!(services.len() == 1, "Expected exactly one service");
-        let service = &mut services[0];
+    fn name(&self) -> &str { &self.name }
+
+    fn description(&self) -> &str { &self.description }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let service = env.service(&self.service_name)?;
         if !service.is_running() {
-            return Err(format!("Service '{}' is not running", self.name));
+            return Err(ServiceError::NotRunning(self.service_name.clone()).into());
         }
-        service
-            .stop()
-            .map_err(|e| format!("Failed to stop service '{}': {}", self.name, e))?;
+        let _span = info_span!("service", name = %service.name(), operation = "stop").entered();
+        service.stop()?;
         if let Some(wait_duration) = self.wait_after {
             std::thread::sleep(wait_duration);
         }
         Ok(())
     }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Stop(self.service_name.clone())]
+    }
 }
 
-pub trait Service: Debug {
-    type ServiceError;
-    fn start(&mut self) -> Result<(), Self::ServiceError>;
+pub trait Service: Debug + Send {
+    /// Name the service can be looked up by from steps
+    fn name(&self) -> &str;
+    fn start(&mut self) -> Result<(), ServiceError>;
     fn is_running(&self) -> bool;
-    fn stop(&mut self) -> Result<(), Self::ServiceError>;
+    fn stop(&mut self) -> Result<(), ServiceError>;
+    /// Whether the service is ready to be used, not just alive. Dependents
+    /// are only started once this holds, see [`StartAllServices`]
+    fn is_healthy(&mut self) -> bool { self.is_running() }
+    /// Replaces `{{port:name}}` and other placeholders in the configuration
+    /// of the service, see [`PortAllocator::substitute`], called before the
+    /// harness starts it
+    fn resolve_ports(&mut self, _ports: &PortAllocator) -> Result<(), ServiceError> { Ok(()) }
+    /// How [`SubProcessServiceStarter`] polls [`Service::is_healthy`] after
+    /// starting the service, it does not wait for the service if unset
+    fn readiness_policy(&self) -> Option<PollPolicy> { None }
+    /// OS process id of the running service, if it is backed by a process
+    fn pid(&self) -> Option<u32> { None }
+    /// Checks without blocking whether the service exited on its own, which
+    /// is what [`Service::exit_status`] reports
+    fn try_wait(&mut self) -> Option<ExitStatus> { self.exit_status() }
+    /// A check like [`Service::exit_status`] that can run on another thread
+    /// while steps use the service, letting [`TestHarness::monitor_crashes`]
+    /// notice an exit while a step is still running. Crashes of services
+    /// without one are only noticed after each step
+    fn exit_watch(&self) -> Option<ExitWatch> { None }
+    /// Status the service exited with on its own since it was last started,
+    /// e.g. to tell a crash from a service that was never started. Stopping
+    /// the service through the harness does not record a status
+    fn exit_status(&self) -> Option<ExitStatus> { None }
+    /// Writes a backup of the state of the service to `output`, see
+    /// [`Backup`]
+    fn backup(&mut self, _output: &Path) -> Result<(), ServiceError> {
+        Err(ServiceError::Unsupported {
+            service: self.name().to_string(),
+            operation: "backups",
+        })
+    }
+    /// Restores the state of the service from a backup written by
+    /// [`Service::backup`], see [`Restore`]
+    fn restore(&mut self, _input: &Path) -> Result<(), ServiceError> {
+        Err(ServiceError::Unsupported {
+            service: self.name().to_string(),
+            operation: "restoring backups",
+        })
+    }
+    /// External executables the service relies on, checked by
+    /// [`TestHarness::preflight_check`]
+    fn required_tools(&self) -> Vec<String> { Vec::new() }
+    /// Fully resolved command line the service was last started with, if it
+    /// runs a command, recorded in the [`RunReport`]
+    fn last_command_line(&self) -> Option<String> { None }
+    /// Lines the service printed so far, oldest first
+    fn output(&self) -> Vec<OutputLine> { Vec::new() }
+    /// Files the output of the service is written to, if any
+    fn log_files(&self) -> Option<LogFiles> { None }
 }
 
 pub struct SubProcessService {
     pub name: String,
     pub command: String,
     pub args: Vec<String>,
-    pub child: Option<Child>,
+    /// Variables set for the process in addition to, or with `clear_env`
+    /// instead of, those of the test process
+    pub env: HashMap<String, String>,
+    pub clear_env: bool,
+    /// Working directory of the process, that of the test process if unset.
+    /// Relative paths are resolved against the working directory of the test
+    /// process, join them to the root directory of the harness to run the
+    /// service from there
+    pub cwd: Option<PathBuf>,
+    pub stdin: StdinSource,
+    process: Arc<Mutex<Process>>,
+    /// Namespaces to launch the process in
+    #[cfg(all(feature = "sandbox", target_os = "linux"))]
+    pub sandbox: Option<Sandbox>,
+    pub stop_mode: StopMode,
+    /// Start the process in a process group of its own and signal the whole
+    /// group when stopping, so workers spawned by the process, e.g. by a
+    /// shell wrapper, are stopped with it. Only supported on Unix
+    pub process_group: bool,
+    /// Write stdout and stderr to these files instead of the log, e.g.
+    /// [`LogFiles::new`] for `{root_dir}/logs/{name}.{out,err}.log`
+    pub log_files: Option<LogFiles>,
+    /// Checked by [`Service::is_healthy`], so starting the service waits
+    /// until it passes according to `readiness_policy`
+    pub readiness: Option<ReadinessProbe>,
+    pub readiness_policy: PollPolicy,
+    started_at: Option<Instant>,
+    last_command_line: Option<String>,
+    output: Arc<Mutex<Vec<OutputLine>>>,
+}
+
+/// Checks without blocking whether a service exited on its own, see
+/// [`Service::exit_watch`]
+pub type ExitWatch = Box<dyn Fn() -> Option<ExitStatus> + Send>;
+
+/// The running child of a [`SubProcessService`], or how it exited
+#[derive(Default)]
+struct Process {
+    child: Option<Child>,
+    exit_status: Option<ExitStatus>,
+}
+
+impl Process {
+    /// Records the exit of a child that exited on its own, returning how it
+    /// exited
+    fn reap(&mut self, name: &str) -> Option<ExitStatus> {
+        let exited = self
+            .child
+            .as_mut()
+            .and_then(|child| child.try_wait().ok().flatten());
+        if let Some(status) = exited {
+            info!("Subprocess '{}' exited with {}", name, status);
+            self.child = None;
+            self.exit_status = Some(status);
+        }
+        self.exit_status
+    }
+}
+
+impl SubProcessService {
+    pub fn new(name: &str, command: &str, args: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            args,
+            env: HashMap::new(),
+            clear_env: false,
+            cwd: None,
+            stdin: StdinSource::default(),
+            process: Arc::default(),
+            #[cfg(all(feature = "sandbox", target_os = "linux"))]
+            sandbox: None,
+            stop_mode: StopMode::default(),
+            process_group: false,
+            log_files: None,
+            readiness: None,
+            readiness_policy: PollPolicy::default(),
+            started_at: None,
+            last_command_line: None,
+            output: Arc::default(),
+        }
+    }
+
+    /// Program and arguments that are actually executed
+    fn invocation(&self) -> (&str, Vec<&str>) {
+        #[cfg(all(feature = "sandbox", target_os = "linux"))]
+        if let Some(sandbox) = &self.sandbox {
+            let mut args = sandbox.unshare_args();
+            args.push("--");
+            args.push(&self.command);
+            args.extend(self.args.iter().map(String::as_str));
+            return ("unshare", args);
+        }
+        (
+            &self.command,
+            self.args.iter().map(String::as_str).collect(),
+        )
+    }
+
+    /// Locks the process state after recording the exit of a child that
+    /// exited on its own
+    fn process(&self) -> MutexGuard<'_, Process> {
+        let mut process = self.process.lock().unwrap();
+        process.reap(&self.name);
+        process
+    }
 }
 
 impl Debug for SubProcessService {
@@ -198,75 +1272,205 @@ impl Debug for SubProcessService {
 }
 
 impl Service for SubProcessService {
-    type ServiceError = String;
+    fn name(&self) -> &str { &self.name }
 
-    fn start(&mut self) -> Result<(), String> {
+    fn start(&mut self) -> Result<(), ServiceError> {
         if self.is_running() {
-            return Err(format!("Subprocess '{}' is already running", self.name));
+            return Err(ServiceError::AlreadyRunning(self.name.clone()));
         }
-        let mut cmd = Command::new(&self.command);
-        cmd.args(&self.args);
+        let (program, args) = self.invocation();
+        let mut cmd = Command::new(program);
+        cmd.args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if self.clear_env {
+            cmd.env_clear();
+        }
+        cmd.envs(&self.env);
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        #[cfg(unix)]
+        if self.process_group {
+            std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+        }
+        let path = match self.env.get("PATH") {
+            Some(path) => path.into(),
+            None if self.clear_env => Default::default(),
+            None => env::var_os("PATH").unwrap_or_default(),
+        };
+        let program = find_executable(program, &path)
+            .map_or_else(|| program.to_string(), |path| path.display().to_string());
+        let command_line = std::iter::once(program.as_str())
+            .chain(args)
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        info!("Starting subprocess '{}': {}", self.name, command_line);
+        self.last_command_line = Some(command_line);
 
-        match cmd.spawn() {
-            Ok(child) => {
-                self.child = Some(child);
-                Ok(())
-            }
-            Err(e) => Err(format!("Failed to start subprocess '{}': {}", self.name, e)),
+        let start_error = |source| ServiceError::Start {
+            service: self.name.clone(),
+            source,
+        };
+        let files = self
+            .log_files
+            .as_ref()
+            .map(LogFiles::open)
+            .transpose()
+            .map_err(start_error)?;
+        cmd.stdin(self.stdin.stdio().map_err(start_error)?);
+        self.started_at = Some(Instant::now());
+        let mut child = cmd.spawn().map_err(start_error)?;
+        self.stdin.feed(&self.name, &mut child);
+        output::capture(&self.name, &mut child, &self.output, files);
+        *self.process.lock().unwrap() = Process {
+            child: Some(child),
+            exit_status: None,
+        };
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool { self.process().child.is_some() }
+
+    fn is_healthy(&mut self) -> bool {
+        if !self.is_running() {
+            return false;
         }
+        let Some(probe) = &self.readiness else {
+            return true;
+        };
+        let started_at = self.started_at;
+        let output = || {
+            let mut output = self.output();
+            output.retain(|line| started_at.is_none_or(|started_at| line.at >= started_at));
+            output
+        };
+        probe
+            .check(output)
+            .inspect_err(|e| debug!("Service '{}' not ready: {}", self.name, e))
+            .is_ok()
+    }
+
+    fn readiness_policy(&self) -> Option<PollPolicy> {
+        self.readiness.as_ref().map(|_| self.readiness_policy)
     }
 
-    fn is_running(&self) -> bool { self.child.is_some() }
+    fn resolve_ports(&mut self, ports: &PortAllocator) -> Result<(), ServiceError> {
+        let url = match &mut self.readiness {
+            Some(ReadinessProbe::HttpGet { url, .. }) => Some(url),
+            Some(ReadinessProbe::Grpc(probe)) => Some(&mut probe.address),
+            _ => None,
+        };
+        ports.substitute_in(
+            &self.name,
+            std::iter::once(&mut self.command)
+                .chain(self.args.iter_mut())
+                .chain(self.env.values_mut())
+                .chain(url),
+        )
+    }
 
-    fn stop(&mut self) -> Result<(), String> {
-        if let Some(mut child) = self.child.take() {
-            return match child.kill() {
-                Ok(_) => Ok(()),
-                Err(e) => Err(format!("Failed to stop subprocess '{}': {}", self.name, e)),
-            };
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        let mut process = self.process();
+        if let Some(child) = process.child.as_mut() {
+            stop::stop_child(child, self.stop_mode, self.process_group).map_err(|source| {
+                ServiceError::Stop {
+                    service: self.name.clone(),
+                    source,
+                }
+            })?;
+            // Reap the process, so it does not linger as a zombie
+            let _ = child.wait();
+            process.child = None;
         }
         Ok(())
     }
+
+    fn pid(&self) -> Option<u32> { self.process().child.as_ref().map(Child::id) }
+
+    fn exit_watch(&self) -> Option<ExitWatch> {
+        let (process, name) = (self.process.clone(), self.name.clone());
+        Some(Box::new(move || process.lock().unwrap().reap(&name)))
+    }
+
+    fn exit_status(&self) -> Option<ExitStatus> { self.process().exit_status }
+
+    fn log_files(&self) -> Option<LogFiles> { self.log_files.clone() }
+
+    fn required_tools(&self) -> Vec<String> {
+        let mut tools = vec![self.command.clone()];
+        let (program, _) = self.invocation();
+        if program != self.command {
+            tools.push(program.to_string());
+        }
+        tools
+    }
+
+    fn last_command_line(&self) -> Option<String> { self.last_command_line.clone() }
+
+    fn output(&self) -> Vec<OutputLine> {
+        self.output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+/// Quotes an argument for display so the command line can be pasted into a
+/// POSIX shell
+pub(crate) fn shell_quote(arg: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
     use super::*;
+    #[cfg(target_os = "linux")]
+    use crate::test_util::is_alive;
 
     #[test]
     fn test_start_callapi_stop_python_serve() {
-        env_logger::init();
+        test_log::init();
         let mut harness = TestHarness::new("PythonServerTester", ".");
+        let port = harness.ports.tcp("python_http").unwrap();
 
-        harness.add_service(Box::new(SubProcessService {
-            name: "Python_HTTP_Service".to_string(),
-            command: "python3".to_string(),
-            args: vec![
+        harness.add_service(Box::new(SubProcessService::new(
+            "Python_HTTP_Service",
+            "python3",
+            vec![
                 "-m".to_string(),
                 "http.server".to_string(),
-                "12345".to_string(),
+                "{{port:python_http}}".to_string(),
             ],
-            child: None,
-        }));
+        )));
 
         harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
             name: "Python_HTTP_Service".to_string(),
             description: "Starts the Python HTTP server".to_string(),
-            service_idx: 0,
+            service_name: "Python_HTTP_Service".to_string(),
             wait_after: Some(Duration::from_secs(2)),
         })));
 
         harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
             name: "Call_API".to_string(),
             description: "Check API response being 200".to_string(),
-            futurefn: Box::new(|| {
-                Box::new(async {
-                    let response = reqwest::get("http://localhost:12345").await;
+            futurefn: Box::new(move |_| {
+                Box::new(async move {
+                    let response = reqwest::get(format!("http://localhost:{}", port)).await;
 
                     match response {
                         Ok(resp) =>
                             if resp.status() == 200 {
-                                Ok(())
+                                Ok(Value::Null)
                             } else {
                                 Err(format!("API call failed: Status code {}", resp.status()))
                             },
@@ -277,12 +1481,532 @@ mod tests {
         })));
 
         harness.add_step(TestStep::Service(Box::new(SubProcessServiceStopper {
-            name: "Python_HTTP_Service".to_string(),
+            name: "Stop_Python_HTTP_Service".to_string(),
             description: "Stops the Python HTTP server".to_string(),
-            service_idx: 0,
+            service_name: "Python_HTTP_Service".to_string(),
             wait_after: None,
         })));
 
         harness.execute().expect("Failed to execute test steps");
     }
+
+    #[test]
+    fn test_execute_on_provided_runtime_handle() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(3)
+            .enable_all()
+            .build()
+            .expect("Failed to build runtime");
+        let observed_workers = Arc::new(AtomicUsize::new(0));
+
+        let mut harness = TestHarness::new("ProvidedRuntimeTester", ".");
+        let observed = observed_workers.clone();
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Inspect_Runtime".to_string(),
+            description: "Records the worker count of the driving runtime".to_string(),
+            futurefn: Box::new(move |_| {
+                let observed = observed.clone();
+                Box::new(async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    let workers = Handle::current().metrics().num_workers();
+                    observed.store(workers, Ordering::SeqCst);
+                    Ok(Value::Null)
+                })
+            }),
+        })));
+
+        harness
+            .execute_on(runtime.handle())
+            .expect("Failed to execute test steps");
+        assert_eq!(observed_workers.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_async_steps_share_one_runtime() {
+        let mut harness = TestHarness::new("SharedRuntimeTester", ".");
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let mut sender = Some(sender);
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Spawn".to_string(),
+            description: "Spawns a task that outlives the step".to_string(),
+            futurefn: Box::new(move |_| {
+                let sender = sender.take();
+                Box::new(async move {
+                    let sender = sender.ok_or("Step ran twice")?;
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        let _ = sender.send("done");
+                    });
+                    Ok(Value::Null)
+                })
+            }),
+        })));
+        let mut receiver = Some(receiver);
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Join".to_string(),
+            description: "Waits for the task spawned by the previous step".to_string(),
+            futurefn: Box::new(move |_| {
+                let receiver = receiver.take();
+                Box::new(async move {
+                    let receiver = receiver.ok_or("Step ran twice")?;
+                    let message = receiver.await.map_err(|e| e.to_string())?;
+                    Ok(Value::String(message.to_string()))
+                })
+            }),
+        })));
+
+        let context = harness.context.clone();
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(context.get("Join"), Some(Value::String("done".to_string())));
+    }
+
+    #[test]
+    fn test_failing_optional_step_is_reported_as_warning() {
+        let mut harness = TestHarness::new("OptionalStepTester", ".");
+        harness.add_step_with(
+            TestStep::AsyncFn(Box::new(AsyncFnStep {
+                name: "Nice_To_Have".to_string(),
+                description: "Best-effort check that fails".to_string(),
+                futurefn: Box::new(|_| Box::new(async { Err("not available".to_string()) })),
+            })),
+            StepOptions {
+                required: false,
+                ..Default::default()
+            },
+        );
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Must_Pass".to_string(),
+            description: "Required check that passes".to_string(),
+            futurefn: Box::new(|_| Box::new(async { Ok(Value::Null) })),
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed());
+        let warnings = report
+            .warnings()
+            .map(|step| (step.name.as_str(), &step.status))
+            .collect::<Vec<_>>();
+        assert_eq!(warnings, vec![(
+            "Nice_To_Have",
+            &StepStatus::Warning("not available".to_string())
+        )]);
+        assert_eq!(report.steps[1].status, StepStatus::Passed);
+    }
+
+    fn flaky_probe(failures: usize, retry: RetryPolicy) -> (TestHarness, Arc<AtomicUsize>) {
+        let mut harness = TestHarness::new("RetryTester", ".");
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counted = attempts.clone();
+        harness.add_step_with(
+            TestStep::AsyncFn(Box::new(AsyncFnStep {
+                name: "Probe".to_string(),
+                description: "Fails until the service warmed up".to_string(),
+                futurefn: Box::new(move |_| {
+                    let attempt = counted.fetch_add(1, Ordering::SeqCst);
+                    Box::new(async move {
+                        if attempt < failures {
+                            Err("connection refused".to_string())
+                        } else {
+                            Ok(Value::Null)
+                        }
+                    })
+                }),
+            })),
+            StepOptions {
+                retry,
+                ..Default::default()
+            },
+        );
+        (harness, attempts)
+    }
+
+    #[test]
+    fn test_step_is_retried_with_backoff() {
+        let retry = RetryPolicy::exponential(3, Duration::from_millis(50));
+        let (harness, attempts) = flaky_probe(2, retry);
+        let started = Instant::now();
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert!(started.elapsed() >= Duration::from_millis(150));
+
+        let (harness, attempts) = flaky_probe(3, retry);
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert_eq!(
+            report.steps[0].status,
+            StepStatus::Failed("connection refused".to_string())
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[derive(Debug)]
+    struct FlakyStopService {
+        running: bool,
+        stop_attempts: Arc<AtomicUsize>,
+    }
+
+    impl Service for FlakyStopService {
+        fn name(&self) -> &str { "Flaky_Stop" }
+
+        fn start(&mut self) -> Result<(), ServiceError> {
+            self.running = true;
+            Ok(())
+        }
+
+        fn is_running(&self) -> bool { self.running }
+
+        fn stop(&mut self) -> Result<(), ServiceError> {
+            if self.stop_attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(ServiceError::failed(self.name(), "port still closing"));
+            }
+            self.running = false;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_cleanup_retries_failed_stop() {
+        let stop_attempts = Arc::new(AtomicUsize::new(0));
+        let mut harness = TestHarness::new("StopRetryTester", ".");
+        harness.stop_retries = 2;
+        harness.stop_retry_delay = Duration::from_millis(10);
+        harness.add_service(Box::new(FlakyStopService {
+            running: false,
+            stop_attempts: stop_attempts.clone(),
+        }));
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Flaky_Stop".to_string(),
+            description: "Starts the flaky service".to_string(),
+            service_name: "Flaky_Stop".to_string(),
+            wait_after: None,
+        })));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Fail".to_string(),
+            description: "Fails to trigger cleanup".to_string(),
+            futurefn: Box::new(|_| Box::new(async { Err("boom".to_string()) })),
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(!report.passed());
+        assert!(report.cleanup_failures.is_empty());
+        assert_eq!(stop_attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_records_resolved_command_line() {
+        let mut harness = TestHarness::new("CommandLineTester", ".");
+        let port = harness.ports.tcp("templated").unwrap();
+        // The arguments after the script are accepted and ignored by sh
+        harness.add_service(Box::new(SubProcessService::new("Templated", "sh", vec![
+            "-c".to_string(),
+            "exec sleep 30".to_string(),
+            "templated".to_string(),
+            "--port={{port:templated}}".to_string(),
+            "two words".to_string(),
+        ])));
+        assert_eq!(harness.services[0].last_command_line(), None);
+        // Fails the run if the service rejected its arguments and exited
+        harness.monitor_crashes("Templated");
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Templated".to_string(),
+            description: "Starts the templated service".to_string(),
+            service_name: "Templated".to_string(),
+            wait_after: None,
+        })));
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStopper {
+            name: "Stop_Templated".to_string(),
+            description: "Stops the templated service".to_string(),
+            service_name: "Templated".to_string(),
+            wait_after: None,
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        let (service, command_line) = &report.command_lines[0];
+        assert_eq!(service, "Templated");
+        let expected = format!(
+            "sh -c 'exec sleep 30' templated --port={} 'two words'",
+            port
+        );
+        assert!(command_line.ends_with(&expected), "{}", command_line);
+        assert!(command_line.starts_with('/'), "{}", command_line);
+    }
+
+    fn failing_plan(policy: FailurePolicy, later_step_ran: &Arc<AtomicUsize>) -> TestHarness {
+        let mut harness = TestHarness::new("FailurePolicyTester", ".");
+        harness.failure_policy = policy;
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Sleeper".to_string(),
+            description: "Starts the sleeper".to_string(),
+            service_name: "Sleeper".to_string(),
+            wait_after: None,
+        })));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Fail".to_string(),
+            description: "Fails".to_string(),
+            futurefn: Box::new(|_| Box::new(async { Err("boom".to_string()) })),
+        })));
+        let later_step_ran = later_step_ran.clone();
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Later".to_string(),
+            description: "Records that it ran".to_string(),
+            futurefn: Box::new(move |_| {
+                later_step_ran.fetch_add(1, Ordering::SeqCst);
+                Box::new(async { Ok(Value::Null) })
+            }),
+        })));
+        harness
+    }
+
+    #[test]
+    fn test_abort_policy_stops_run_and_returns_error() {
+        let later_step_ran = Arc::new(AtomicUsize::new(0));
+        let error = failing_plan(FailurePolicy::Abort, &later_step_ran)
+            .execute()
+            .unwrap_err();
+        assert!(
+            matches!(&error, HarnessError::StepFailed { index: 2, name, .. } if name == "Fail"),
+            "{:?}",
+            error
+        );
+        assert_eq!(error.to_string(), "Step 2 'Fail' failed: boom");
+        assert_eq!(later_step_ran.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_continue_policy_keeps_services_running() {
+        let later_step_ran = Arc::new(AtomicUsize::new(0));
+        let mut harness = failing_plan(FailurePolicy::Continue, &later_step_ran);
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStopper {
+            name: "Stop_Sleeper".to_string(),
+            description: "Stops the sleeper, which is still running".to_string(),
+            service_name: "Sleeper".to_string(),
+            wait_after: None,
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(!report.passed());
+        assert_eq!(later_step_ran.load(Ordering::SeqCst), 1);
+        assert_eq!(report.steps[3].status, StepStatus::Passed);
+    }
+
+    #[derive(Debug)]
+    struct PanickingStep(Arc<AtomicUsize>);
+
+    impl ServiceStepExecutor for PanickingStep {
+        fn name(&self) -> &str { "Panicking" }
+
+        fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+            let pid = env.service("Sleeper")?.pid().unwrap_or_default();
+            self.0.store(pid as usize, Ordering::SeqCst);
+            panic!("assertion in step failed");
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_panicking_step_fails_and_stops_services() {
+        let pid = Arc::new(AtomicUsize::new(0));
+        let mut harness = TestHarness::new("PanicTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        let [start, _] = start_stop("Sleeper");
+        harness.add_step(start);
+        harness.add_step(TestStep::Service(Box::new(PanickingStep(pid.clone()))));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert_eq!(
+            report.steps[1].status,
+            StepStatus::Failed("Step panicked: assertion in step failed".to_string())
+        );
+        let pid = pid.load(Ordering::SeqCst) as u32;
+        assert_ne!(pid, 0);
+        assert!(!is_alive(pid), "Sleeper {} is still running", pid);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_dropping_harness_stops_running_services() {
+        let mut harness = TestHarness::new("DropTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        harness.services[0]
+            .start()
+            .expect("Failed to start service");
+        let pid = harness.services[0].pid().expect("Sleeper has a process");
+        assert!(is_alive(pid));
+
+        drop(harness);
+        assert!(!is_alive(pid), "Sleeper {} is still running", pid);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_subprocess_env_cwd_and_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut service = SubProcessService::new("Configured", "sh", vec![
+            "-c".to_string(),
+            "read line; echo \"$GREETING ${HOME:-no home} $line $(pwd)\"".to_string(),
+        ]);
+        service
+            .env
+            .insert("GREETING".to_string(), "hello".to_string());
+        service.clear_env = true;
+        service.cwd = Some(dir.path().to_path_buf());
+        service.stdin = StdinSource::Bytes(b"from stdin\n".to_vec());
+        service.start().expect("Failed to start service");
+        let output = PollPolicy {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        }
+        .poll(|| service.output().pop().ok_or(()))
+        .expect("Service printed nothing");
+
+        let cwd = dir.path().canonicalize().unwrap();
+        assert_eq!(
+            output.line,
+            format!("hello no home from stdin {}", cwd.display())
+        );
+    }
+
+    #[test]
+    fn test_crashed_subprocess_is_not_running() {
+        let mut service = SubProcessService::new("Crasher", "sh", vec![
+            "-c".to_string(),
+            "exit 3".to_string(),
+        ]);
+        service.start().expect("Failed to start service");
+        PollPolicy {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        }
+        .poll(|| (!service.is_running()).then_some(()).ok_or(()))
+        .expect("Crashed service is still considered running");
+        assert_eq!(service.exit_status().and_then(|s| s.code()), Some(3));
+        assert_eq!(service.pid(), None);
+
+        service.args = vec!["-c".to_string(), "sleep 30".to_string()];
+        service.start().expect("Failed to restart service");
+        assert_eq!(service.exit_status(), None);
+        service.stop().expect("Failed to stop service");
+        assert_eq!(service.exit_status(), None);
+    }
+
+    fn start_stop(service_name: &str) -> [TestStep; 2] {
+        [
+            TestStep::Service(Box::new(SubProcessServiceStarter {
+                name: format!("Start_{}", service_name),
+                description: format!("Starts {}", service_name),
+                service_name: service_name.to_string(),
+                wait_after: None,
+            })),
+            TestStep::Service(Box::new(SubProcessServiceStopper {
+                name: format!("Stop_{}", service_name),
+                description: format!("Stops {}", service_name),
+                service_name: service_name.to_string(),
+                wait_after: None,
+            })),
+        ]
+    }
+
+    #[test]
+    fn test_steps_resolve_services_by_name() {
+        let mut harness = TestHarness::new("MultiServiceTester", ".");
+        for name in ["First", "Second"] {
+            harness.add_service(Box::new(SubProcessService::new(name, "sleep", vec![
+                "30".to_string()
+            ])));
+        }
+        let [start_second, stop_second] = start_stop("Second");
+        let [start_first, stop_first] = start_stop("First");
+        for step in [start_second, start_first, stop_second, stop_first] {
+            harness.add_step(step);
+        }
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        let started = report
+            .startup_order()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(started, vec!["Second", "First"]);
+    }
+
+    #[test]
+    fn test_unknown_service_name_is_reported() {
+        let mut harness = TestHarness::new("MultiServiceTester", ".");
+        let [start, _] = start_stop("Missing");
+        harness.add_step(start);
+
+        assert_eq!(
+            harness.execute().unwrap_err().to_string(),
+            "Invalid test plan: Step 1 'Start_Missing' refers to unknown service 'Missing'"
+        );
+    }
+
+    /// Records the spans created while it is the default subscriber
+    #[derive(Default)]
+    struct SpanRecorder(Mutex<Vec<String>>);
+
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool { true }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            struct Fields(String);
+
+            impl tracing::field::Visit for Fields {
+                fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn Debug) {
+                    self.0 += &format!(" {}={:?}", field.name(), value);
+                }
+            }
+
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            let mut spans = self.0.lock().unwrap();
+            spans.push(fields.0);
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, _event: &tracing::Event<'_>) {}
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn test_spans_per_test_step_and_service_operation() {
+        let mut harness = TestHarness::new("SpanTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        let [start, stop] = start_stop("Sleeper");
+        harness.add_step(start);
+        harness.add_step(stop);
+        let recorder = Arc::new(SpanRecorder::default());
+
+        let report = tracing::subscriber::with_default(recorder.clone(), || harness.execute())
+            .expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(*recorder.0.lock().unwrap(), [
+            "test name=SpanTester",
+            "step name=Start_Sleeper",
+            "service name=Sleeper operation=\"start\"",
+            "step name=Stop_Sleeper",
+            "service name=Sleeper operation=\"stop\"",
+        ]);
+    }
 }
@@ -0,0 +1,184 @@
+use std::sync::{Arc, Mutex, OnceLock};
+
+use libtest_mimic::{Arguments, Conclusion, Failed, Trial};
+
+use crate::{RunReport, StepStatus, TestHarness, TestSuite};
+
+/// Fails the trial of a failed run with why it failed, followed by the
+/// output of the services unless it was already printed
+fn check(report: &RunReport, nocapture: bool) -> Result<(), Failed> {
+    if report.passed() {
+        return Ok(());
+    }
+    let mut message = report.failures().join("\n");
+    if !nocapture {
+        for (service, excerpt) in &report.log_excerpts {
+            message += &format!("\n\n---- {} output ----\n{}", service, excerpt);
+        }
+    }
+    Err(message.into())
+}
+
+/// Executes the harness, with `--nocapture` printing the output of the
+/// services right away like libtest prints that of tests
+fn execute(harness: TestHarness, nocapture: bool) -> Result<RunReport, String> {
+    let report = harness.execute().map_err(|e| e.to_string())?;
+    if nocapture {
+        for (service, excerpt) in &report.log_excerpts {
+            println!("---- {} output ----\n{}", service, excerpt);
+        }
+    }
+    Ok(report)
+}
+
+/// A harness run once by whichever of its step trials runs first
+struct SharedRun {
+    harness: Mutex<Option<TestHarness>>,
+    report: OnceLock<Result<RunReport, String>>,
+}
+
+impl SharedRun {
+    fn report(&self, nocapture: bool) -> &Result<RunReport, String> {
+        self.report.get_or_init(|| {
+            let harness = self.harness.lock().unwrap().take();
+            execute(harness.expect("Harness runs once"), nocapture)
+        })
+    }
+}
+
+impl TestSuite {
+    /// A [libtest-mimic](libtest_mimic) trial per harness, named after its
+    /// test and failing with the failures of its run, so the harnesses show
+    /// up in `cargo test` and can be filtered by name
+    pub fn trials(self, args: &Arguments) -> Vec<Trial> {
+        let nocapture = args.nocapture;
+        self.into_harnesses()
+            .map(|harness| {
+                Trial::test(harness.test_name.clone(), move || {
+                    check(&execute(harness, nocapture)?, nocapture)
+                })
+            })
+            .collect()
+    }
+
+    /// Like [`TestSuite::trials`], but with an additional trial per step
+    /// named `test::step`, failing if the step failed. The harness runs as a
+    /// whole once for the first of its trials that is not filtered out, the
+    /// others report the outcome of that run
+    pub fn step_trials(self, args: &Arguments) -> Vec<Trial> {
+        let nocapture = args.nocapture;
+        let mut trials = Vec::new();
+        for harness in self.into_harnesses() {
+            let test_name = harness.test_name.clone();
+            let steps = harness
+                .steps
+                .iter()
+                .map(|planned| planned.step.name().to_string())
+                .collect::<Vec<_>>();
+            let run = Arc::new(SharedRun {
+                harness: Mutex::new(Some(harness)),
+                report: OnceLock::new(),
+            });
+            let shared = run.clone();
+            trials.push(Trial::test(test_name.clone(), move || {
+                check(shared.report(nocapture).as_ref()?, nocapture)
+            }));
+            for step in steps {
+                let run = run.clone();
+                let name = format!("{}::{}", test_name, step);
+                trials.push(Trial::test(name, move || {
+                    let report = run.report(nocapture).as_ref()?;
+                    match report.steps.iter().find(|report| report.name == step) {
+                        Some(report) => match &report.status {
+                            StepStatus::Failed(message) => Err(Failed::from(message)),
+                            _ => Ok(()),
+                        },
+                        None => Ok(()),
+                    }
+                }));
+            }
+        }
+        trials
+    }
+
+    /// Runs the suite as the `main` of a test target with `harness = false`,
+    /// taking the libtest arguments such as filters and `--nocapture` from
+    /// the command line:
+    ///
+    /// ```no_run
+    /// # use harness::{TestHarness, TestSuite};
+    /// let mut suite = TestSuite::new("e2e");
+    /// suite.add(TestHarness::new("Checkout", "tests/e2e"));
+    /// suite.run_as_tests().exit();
+    /// ```
+    pub fn run_as_tests(self) -> Conclusion {
+        let args = Arguments::from_args();
+        let trials = self.trials(&args);
+        libtest_mimic::run(&args, trials)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ShellCommandStep, TestStep};
+
+    fn shell(test_name: &str, scripts: &[&str]) -> TestHarness {
+        let mut harness = TestHarness::new(test_name, ".");
+        harness.failure_log_lines = 0;
+        for (idx, script) in scripts.iter().enumerate() {
+            harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+                &format!("Step_{}", idx + 1),
+                script,
+            ))));
+        }
+        harness
+    }
+
+    fn suite() -> TestSuite {
+        let mut suite = TestSuite::new("Suite");
+        suite.add(shell("Passing", &["true"]));
+        suite.add(shell("Failing", &["true", "false"]));
+        suite
+    }
+
+    #[test]
+    fn test_harnesses_as_trials() {
+        let args = Arguments {
+            test_threads: Some(2),
+            quiet: true,
+            ..Arguments::default()
+        };
+        let trials = suite().trials(&args);
+        assert_eq!(trials.iter().map(Trial::name).collect::<Vec<_>>(), [
+            "Passing", "Failing"
+        ]);
+        let conclusion = libtest_mimic::run(&args, trials);
+        assert_eq!((conclusion.num_passed, conclusion.num_failed), (1, 1));
+
+        let filtered = Arguments {
+            filter: Some("Passing".to_string()),
+            ..args
+        };
+        let conclusion = libtest_mimic::run(&filtered, suite().trials(&filtered));
+        assert_eq!((conclusion.num_passed, conclusion.num_filtered_out), (1, 1));
+    }
+
+    #[test]
+    fn test_steps_as_trials() {
+        let args = Arguments {
+            quiet: true,
+            ..Arguments::default()
+        };
+        let trials = suite().step_trials(&args);
+        assert_eq!(trials.iter().map(Trial::name).collect::<Vec<_>>(), [
+            "Passing",
+            "Passing::Step_1",
+            "Failing",
+            "Failing::Step_1",
+            "Failing::Step_2"
+        ]);
+        let conclusion = libtest_mimic::run(&args, trials);
+        assert_eq!((conclusion.num_passed, conclusion.num_failed), (3, 2));
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use tracing::error;
+
+use crate::{ReadinessProbe, RunReport, TestHarness};
+
+/// A probe run periodically in the background for the whole run, see
+/// [`TestHarness::monitor_liveness`]
+#[derive(Debug, Clone)]
+pub struct LivenessCheck {
+    /// Any probe but [`ReadinessProbe::LogPattern`], which only tells whether
+    /// a line was ever printed and is rejected by [`TestHarness::validate`]
+    pub probe: ReadinessProbe,
+    pub interval: Duration,
+    /// Consecutive failed probes after which the service is unhealthy
+    pub failure_threshold: u32,
+}
+
+impl LivenessCheck {
+    pub fn new(probe: ReadinessProbe, interval: Duration) -> Self {
+        Self {
+            probe,
+            interval,
+            failure_threshold: 3,
+        }
+    }
+}
+
+/// A service that became unhealthy or died while it was monitored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LivenessFailure {
+    pub service_name: String,
+    /// Number of the step, counting from 1 across setup and main steps,
+    /// during which the failure was detected
+    pub step_index: usize,
+    pub step_name: String,
+    pub error: String,
+}
+
+impl Display for LivenessFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Service '{}' died at step {} '{}': {}",
+            self.service_name, self.step_index, self.step_name, self.error
+        )
+    }
+}
+
+#[derive(Default)]
+struct MonitorState {
+    /// Probes only count while the service is expected to be running
+    active: AtomicBool,
+    stopped: Mutex<bool>,
+    wakeup: Condvar,
+    failure: Mutex<Option<String>>,
+}
+
+/// Runs a [`LivenessCheck`] on a background thread until dropped
+pub(crate) struct LivenessMonitor {
+    service_name: String,
+    state: Arc<MonitorState>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LivenessMonitor {
+    fn start(service_name: &str, check: LivenessCheck) -> Self {
+        let state = Arc::new(MonitorState::default());
+        let thread = {
+            let state = state.clone();
+            std::thread::spawn(move || {
+                let mut failures = 0;
+                let mut stopped = state.stopped.lock().unwrap();
+                while !*stopped {
+                    if state.active.load(Ordering::SeqCst) {
+                        match check.probe.check(Vec::new) {
+                            Ok(()) => failures = 0,
+                            Err(e) => {
+                                failures += 1;
+                                if failures >= check.failure_threshold {
+                                    state.failure.lock().unwrap().get_or_insert(e);
+                                }
+                            }
+                        }
+                    } else {
+                        failures = 0;
+                    }
+                    stopped = state
+                        .wakeup
+                        .wait_timeout(stopped, check.interval)
+                        .unwrap()
+                        .0;
+                }
+            })
+        };
+        Self {
+            service_name: service_name.to_string(),
+            state,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for LivenessMonitor {
+    fn drop(&mut self) {
+        *self.state.stopped.lock().unwrap() = true;
+        self.state.wakeup.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl TestHarness {
+    /// Runs `check` against `service_name` in the background while the
+    /// service is running during the run. Once it fails, or the service
+    /// exits on its own, the remaining steps fail pointing at the step during
+    /// which the service died
+    pub fn monitor_liveness(&mut self, service_name: &str, check: LivenessCheck) {
+        self.liveness_checks.push((service_name.to_string(), check));
+    }
+
+    pub(crate) fn start_liveness_monitors(&mut self) {
+        self.liveness_monitors = self
+            .liveness_checks
+            .iter()
+            .map(|(service_name, check)| LivenessMonitor::start(service_name, check.clone()))
+            .collect();
+    }
+
+    /// Records services that became unhealthy during the last step and arms
+    /// the monitors of the services that are running now
+    pub(crate) fn check_liveness(&mut self, report: &mut RunReport) {
+        let step_name = report
+            .steps
+            .last()
+            .map(|step| step.name.clone())
+            .unwrap_or_default();
+        for monitor in &self.liveness_monitors {
+            let Some(service) = self
+                .services
+                .iter()
+                .find(|service| service.name() == monitor.service_name)
+            else {
+                continue;
+            };
+            let running = service.is_running();
+            let was_active = monitor.state.active.swap(running, Ordering::SeqCst);
+            let failure = monitor.state.failure.lock().unwrap().take();
+            // A service stopped by the harness is not a failure
+            let error = match service.exit_status() {
+                Some(status) if was_active => Some(format!("exited with {}", status)),
+                _ if running => failure,
+                _ => None,
+            };
+            let Some(error) = error else {
+                continue;
+            };
+            if report
+                .liveness_failures
+                .iter()
+                .any(|failure| failure.service_name == monitor.service_name)
+            {
+                continue;
+            }
+            let failure = LivenessFailure {
+                service_name: monitor.service_name.clone(),
+                step_index: report.steps.len(),
+                step_name: step_name.clone(),
+                error,
+            };
+            error!("{}", failure);
+            report.liveness_failures.push(failure);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        AsyncFnStep, HarnessError, PollPolicy, StepStatus, SubProcessService,
+        SubProcessServiceStarter, TestStep,
+    };
+
+    fn sleep_step(name: &str) -> TestStep {
+        TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: name.to_string(),
+            description: "Waits while the service is monitored".to_string(),
+            futurefn: Box::new(|_| {
+                Box::new(async {
+                    tokio::time::sleep(Duration::from_millis(800)).await;
+                    Ok(Value::Null)
+                })
+            }),
+        }))
+    }
+
+    #[test]
+    fn test_service_becoming_unhealthy_fails_later_steps() {
+        let mut harness = TestHarness::new("LivenessTester", ".");
+        let port = harness.ports.tcp("flaky").unwrap();
+        // Stops accepting connections after a while but keeps running
+        let script = "import socket, time\ns = socket.socket()\ns.setsockopt(socket.SOL_SOCKET, \
+                      socket.SO_REUSEADDR, 1)\ns.bind(('127.0.0.1', {{port:flaky}}))\ns.listen()\n\
+                      time.sleep(0.3)\ns.close()\ntime.sleep(30)";
+        let mut service = SubProcessService::new("Flaky", "python3", vec![
+            "-c".to_string(),
+            script.to_string(),
+        ]);
+        service.readiness = Some(ReadinessProbe::TcpPort(port));
+        service.readiness_policy = PollPolicy {
+            interval: Duration::from_millis(20),
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        };
+        harness.add_service(Box::new(service));
+        harness.monitor_liveness("Flaky", LivenessCheck {
+            probe: ReadinessProbe::TcpPort(port),
+            interval: Duration::from_millis(50),
+            failure_threshold: 2,
+        });
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Start_Flaky".to_string(),
+            description: "Starts the flaky service".to_string(),
+            service_name: "Flaky".to_string(),
+            wait_after: None,
+        })));
+        harness.add_step(sleep_step("Wait"));
+        harness.add_step(sleep_step("After"));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(!report.passed());
+        assert_eq!(report.liveness_failures.len(), 1);
+        let failure = &report.liveness_failures[0];
+        assert_eq!(
+            (failure.step_index, failure.step_name.as_str()),
+            (2, "Wait")
+        );
+        assert_eq!(report.steps[1].status, StepStatus::Passed);
+        assert_eq!(
+            report.steps[2].status,
+            StepStatus::Failed(failure.to_string())
+        );
+        assert!(
+            failure
+                .to_string()
+                .starts_with("Service 'Flaky' died at step 2 'Wait'"),
+            "{}",
+            failure
+        );
+    }
+
+    #[test]
+    fn test_log_pattern_liveness_check_is_invalid() {
+        let mut harness = TestHarness::new("LivenessTester", ".");
+        harness.add_service(Box::new(SubProcessService::new(
+            "Logger",
+            "true",
+            Vec::new(),
+        )));
+        harness.monitor_liveness(
+            "Logger",
+            LivenessCheck::new(
+                ReadinessProbe::LogPattern(Regex::new("ready").unwrap()),
+                Duration::from_millis(50),
+            ),
+        );
+        let Err(HarnessError::InvalidPlan(problems)) = harness.validate() else {
+            panic!("Expected an invalid plan");
+        };
+        assert_eq!(problems, [
+            "Liveness check of service 'Logger' cannot use a log pattern probe"
+        ]);
+    }
+}
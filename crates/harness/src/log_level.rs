@@ -0,0 +1,140 @@
+use std::time::Instant;
+
+use log::Level;
+use regex::Regex;
+use tracing::info;
+
+use crate::{ServiceAction, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that fails if a service printed lines more severe than
+/// `max_level`, e.g. to gate a test on its services logging no errors.
+/// The level of a line is determined by the first of `patterns` it matches
+#[derive(Debug, Clone)]
+pub struct AssertNoLogLevel {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    /// Most severe level allowed
+    pub max_level: Level,
+    /// Only lines printed after this are checked, all lines if unset
+    pub since: Option<Instant>,
+    pub patterns: Vec<(Level, Regex)>,
+}
+
+impl AssertNoLogLevel {
+    /// Checks the output of the service with patterns matching the usual
+    /// upper-case level names such as `ERROR` and `WARN`
+    pub fn new(name: &str, service_name: &str, max_level: Level) -> Self {
+        let pattern = |regex| Regex::new(regex).expect("Invalid level pattern");
+        Self {
+            name: name.to_string(),
+            service_name: service_name.to_string(),
+            max_level,
+            since: None,
+            patterns: vec![
+                (Level::Error, pattern(r"\b(ERROR|FATAL|CRITICAL)\b")),
+                (Level::Warn, pattern(r"\bWARN(ING)?\b")),
+                (Level::Info, pattern(r"\bINFO\b")),
+                (Level::Debug, pattern(r"\bDEBUG\b")),
+                (Level::Trace, pattern(r"\bTRACE\b")),
+            ],
+        }
+    }
+}
+
+impl ServiceStepExecutor for AssertNoLogLevel {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let output = env.service(&self.service_name)?.output();
+        let checked = output
+            .iter()
+            .filter(|line| self.since.is_none_or(|since| line.at >= since))
+            .collect::<Vec<_>>();
+        let offending = checked
+            .iter()
+            .filter(|line| {
+                self.patterns
+                    .iter()
+                    .find(|(_, pattern)| pattern.is_match(&line.line))
+                    .is_some_and(|(level, _)| *level < self.max_level)
+            })
+            .map(|line| line.line.as_str())
+            .collect::<Vec<_>>();
+
+        if !offending.is_empty() {
+            return Err(StepError::Assertion(format!(
+                "Service '{}' logged {} line(s) above {}:\n{}",
+                self.service_name,
+                offending.len(),
+                self.max_level,
+                offending.join("\n")
+            )));
+        }
+        info!(
+            "Service '{}' logged nothing above {} in {} line(s)",
+            self.service_name,
+            self.max_level,
+            checked.len()
+        );
+        Ok(())
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{PollPolicy, SubProcessService, TestHarness};
+
+    /// Runs a service printing `lines` and checks its output once it exited
+    fn check_output(lines: &str) -> Result<(), StepError> {
+        let mut harness = TestHarness::new("LogLevelTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Logger", "printf", vec![
+            lines.to_string(),
+        ])));
+        harness.services[0]
+            .start()
+            .expect("Failed to start service");
+        let line_count = lines.lines().count();
+        PollPolicy {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        }
+        .poll(|| {
+            (harness.services[0].output().len() == line_count)
+                .then_some(())
+                .ok_or(())
+        })
+        .expect("Output was not captured");
+
+        let result = AssertNoLogLevel::new("Check_Levels", "Logger", Level::Warn)
+            .execute(&mut harness.step_env());
+        harness.services[0].stop().expect("Failed to stop service");
+        result
+    }
+
+    #[test]
+    fn test_error_line_fails() {
+        let error = check_output("INFO starting\nWARN slow disk\nERROR connection refused\n")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.ends_with("logged 1 line(s) above WARN:\nERROR connection refused"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_clean_output_passes() {
+        check_output("INFO starting\nWARN slow disk\nINFO ready\n")
+            .expect("Warnings are within the threshold");
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use serde_json::Value;
+use tracing::info;
+
+use crate::{ServiceAction, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that waits until a service printed a line matching `pattern` to
+/// stdout or stderr, e.g. `Listening on port \d+`, instead of sleeping for a
+/// fixed time after starting it. Fails once `timeout` expires or if the
+/// service exits before printing a matching line
+#[derive(Debug, Clone)]
+pub struct LogMatchStep {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    pub pattern: Regex,
+    pub timeout: Duration,
+    /// Only lines printed after this are matched, all lines if unset
+    pub since: Option<Instant>,
+    /// Stores the matching line in the [`crate::Context`] under this key
+    pub context_key: Option<String>,
+}
+
+impl LogMatchStep {
+    pub fn new(name: &str, service_name: &str, pattern: Regex, timeout: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            service_name: service_name.to_string(),
+            pattern,
+            timeout,
+            since: None,
+            context_key: None,
+        }
+    }
+}
+
+impl ServiceStepExecutor for LogMatchStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let started = Instant::now();
+        let service = env.service(&self.service_name)?;
+        let mut checked = 0;
+        let mut exited = None;
+        loop {
+            let output = service.output();
+            let matched = output
+                .iter()
+                .skip(checked)
+                .filter(|line| self.since.is_none_or(|since| line.at >= since))
+                .find(|line| self.pattern.is_match(&line.line));
+            if let Some(line) = matched {
+                info!(
+                    "Service '{}' printed '{}' after {:?}",
+                    self.service_name,
+                    line.line,
+                    started.elapsed()
+                );
+                if let Some(key) = &self.context_key {
+                    env.context.insert(key, Value::String(line.line.clone()));
+                }
+                return Ok(());
+            }
+            checked = output.len();
+
+            // Lines printed right before an exit may still be read, so the
+            // exit only fails the step once the output was checked again
+            if let Some(status) = exited {
+                return Err(StepError::Assertion(format!(
+                    "Service '{}' exited with {} without printing a line matching '{}'",
+                    self.service_name, status, self.pattern
+                )));
+            }
+            if started.elapsed() >= self.timeout {
+                return Err(StepError::Timeout {
+                    what: format!(
+                        "Service '{}' printed no line matching '{}'",
+                        self.service_name, self.pattern
+                    ),
+                    after: self.timeout,
+                });
+            }
+            exited = service.exit_status();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SubProcessService, TestHarness};
+
+    fn wait_for_match(script: &str, timeout: Duration) -> (Result<(), StepError>, Option<Value>) {
+        let mut harness = TestHarness::new("LogMatchTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Server", "sh", vec![
+            "-c".to_string(),
+            script.to_string(),
+        ])));
+        harness.services[0]
+            .start()
+            .expect("Failed to start service");
+        let mut step = LogMatchStep::new(
+            "Wait_Listening",
+            "Server",
+            Regex::new(r"Listening on port \d+").unwrap(),
+            timeout,
+        );
+        step.context_key = Some("listening".to_string());
+        let result = step.execute(&mut harness.step_env());
+        (result, harness.context.get("listening"))
+    }
+
+    #[test]
+    fn test_waits_for_matching_line() {
+        let (result, line) = wait_for_match(
+            "echo starting; sleep 0.3; echo Listening on port 8080 >&2; exec sleep 30",
+            Duration::from_secs(5),
+        );
+        result.expect("Line should have matched");
+        assert_eq!(line, Some(Value::from("Listening on port 8080")));
+    }
+
+    #[test]
+    fn test_times_out_without_match() {
+        let (result, _) =
+            wait_for_match("echo starting; exec sleep 30", Duration::from_millis(200));
+        let error = result.unwrap_err();
+        assert!(matches!(error, StepError::Timeout { .. }), "{}", error);
+    }
+
+    #[test]
+    fn test_fails_once_service_exited() {
+        let started = Instant::now();
+        let (result, _) = wait_for_match("echo crashing; exit 1", Duration::from_secs(10));
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("exited with"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
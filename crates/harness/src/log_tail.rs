@@ -0,0 +1,81 @@
+use tracing::error;
+
+use crate::{OutputStream, TestHarness};
+
+impl TestHarness {
+    /// Last [`TestHarness::failure_log_lines`] lines printed by each service
+    /// that is running or exited on its own, as `(service name, text)`
+    pub(crate) fn service_log_tails(&self) -> Vec<(String, String)> {
+        if self.failure_log_lines == 0 {
+            return Vec::new();
+        }
+        self.services
+            .iter()
+            .filter(|service| service.is_running() || service.exit_status().is_some())
+            .filter_map(|service| {
+                let output = service.output();
+                if output.is_empty() {
+                    return None;
+                }
+                let tail = output
+                    .iter()
+                    .skip(output.len().saturating_sub(self.failure_log_lines))
+                    .map(|line| match line.stream {
+                        OutputStream::Stdout => format!("  | {}", line.line),
+                        OutputStream::Stderr => format!("  ! {}", line.line),
+                    })
+                    .collect::<Vec<_>>();
+                Some((service.name().to_string(), tail.join("\n")))
+            })
+            .collect()
+    }
+
+    /// Logs the output tails of the services, so the cause of a failed step
+    /// shows up in CI logs without rerunning the test
+    pub(crate) fn dump_service_logs(&self) {
+        for (service_name, tail) in self.service_log_tails() {
+            error!("Last output of service '{}':\n{}", service_name, tail);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{PollPolicy, SubProcessService, TestHarness};
+
+    #[test]
+    fn test_tail_of_service_output() {
+        let mut harness = TestHarness::new("LogTailTester", ".");
+        harness.failure_log_lines = 2;
+        harness.add_service(Box::new(SubProcessService::new("Chatty", "sh", vec![
+            "-c".to_string(),
+            "echo one; echo two; sleep 0.2; echo three >&2; exec sleep 30".to_string(),
+        ])));
+        harness.add_service(Box::new(SubProcessService::new("Idle", "sleep", vec![
+            "30".to_string(),
+        ])));
+        for service in &mut harness.services {
+            service.start().expect("Failed to start service");
+        }
+        PollPolicy {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        }
+        .poll(|| {
+            (harness.services[0].output().len() == 3)
+                .then_some(())
+                .ok_or(())
+        })
+        .expect("Output was not captured");
+
+        assert_eq!(harness.service_log_tails(), vec![(
+            "Chatty".to_string(),
+            "  | two\n  ! three".to_string()
+        )]);
+        harness.failure_log_lines = 0;
+        assert!(harness.service_log_tails().is_empty());
+    }
+}
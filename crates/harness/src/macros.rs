@@ -0,0 +1,159 @@
+//! Declarative macros for defining services and steps inline, expanding to
+//! the structs they stand for
+
+/// A [`crate::SubProcessService`] running `cmd` with optional arguments,
+/// environment variables and readiness probe:
+///
+/// ```
+/// # use harness::{service, ReadinessProbe};
+/// let api = service!(
+///     name: "Api",
+///     cmd: "cargo",
+///     args: ["run", "--bin", "api", "--", "--port", "{{port:api}}"],
+///     env: { "RUST_LOG" => "debug" },
+///     readiness: ReadinessProbe::TcpPort(8080),
+/// );
+/// assert_eq!(api.args.len(), 6);
+/// ```
+#[macro_export]
+macro_rules! service {
+    (
+        name: $name:expr,
+        cmd: $cmd:expr
+        $(, args: [$($arg:expr),* $(,)?])?
+        $(, env: {$($key:expr => $value:expr),* $(,)?})?
+        $(, readiness: $readiness:expr)?
+        $(,)?
+    ) => {{
+        #[allow(unused_mut)]
+        let mut service = $crate::SubProcessService::new(
+            $name,
+            $cmd,
+            vec![$($(::std::string::ToString::to_string(&$arg)),*)?],
+        );
+        $($(
+            service.env.insert(
+                ::std::string::ToString::to_string(&$key),
+                ::std::string::ToString::to_string(&$value),
+            );
+        )*)?
+        $(service.readiness = Some($readiness);)?
+        service
+    }};
+}
+
+/// A step of one of these kinds, named like the steps of
+/// [`crate::config`] definitions:
+///
+/// - `step!(start "Api")` and `step!(stop "Api")`, a
+///   [`crate::SubProcessServiceStarter`] and
+///   [`crate::SubProcessServiceStopper`]
+/// - `step!(http GET "http://localhost:{{port:api}}/health" expect 200)`, an
+///   [`crate::HttpRequestStep`] with an optional `body` before `expect`,
+///   expecting status 200 unless `expect` is given
+/// - `step!(shell "make seed")`, a [`crate::ShellCommandStep`]
+///
+/// URLs and bodies other than literals are put in parentheses:
+///
+/// ```
+/// # use harness::step;
+/// let url = "http://localhost:{{port:api}}/orders";
+/// let create = step!(http POST (url) body r#"{"sku": 1}"# expect 201);
+/// assert_eq!(create.name, "POST http://localhost:{{port:api}}/orders");
+/// ```
+#[macro_export]
+macro_rules! step {
+    (start $service:expr) => {{
+        let service_name: &str = $service;
+        $crate::SubProcessServiceStarter {
+            name: format!("Start_{}", service_name),
+            description: format!("Starts {}", service_name),
+            service_name: service_name.to_string(),
+            wait_after: None,
+        }
+    }};
+    (stop $service:expr) => {{
+        let service_name: &str = $service;
+        $crate::SubProcessServiceStopper {
+            name: format!("Stop_{}", service_name),
+            description: format!("Stops {}", service_name),
+            service_name: service_name.to_string(),
+            wait_after: None,
+        }
+    }};
+    (http $method:ident $url:tt $(body $body:tt)? $(expect $status:expr)?) => {{
+        let url: &str = $url;
+        let mut step = $crate::HttpRequestStep::get(
+            &format!("{} {}", stringify!($method), url),
+            url,
+        );
+        step.method = stringify!($method).to_string();
+        $(step.body = Some(::std::string::ToString::to_string(&$body));)?
+        $(step.expect_status = Some($status);)?
+        step
+    }};
+    (shell $script:expr) => {{
+        let script: &str = $script;
+        $crate::ShellCommandStep::sh(script, script)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{ReadinessProbe, ServiceStepExecutor, TestHarness, TestStep};
+
+    #[test]
+    fn test_service_macro() {
+        let api = service!(name: "Api", cmd: "cargo", args: ["run", 8080]);
+        assert_eq!((api.name.as_str(), api.command.as_str()), ("Api", "cargo"));
+        assert_eq!(api.args, ["run", "8080"]);
+        assert!(api.env.is_empty() && api.readiness.is_none());
+
+        let db = service!(
+            name: "Db",
+            cmd: "postgres",
+            env: { "PGDATA" => "/tmp/pg", "PGPORT" => 5432 },
+            readiness: ReadinessProbe::TcpPort(5432),
+        );
+        assert!(db.args.is_empty());
+        assert_eq!(
+            db.env,
+            HashMap::from([
+                ("PGDATA".to_string(), "/tmp/pg".to_string()),
+                ("PGPORT".to_string(), "5432".to_string()),
+            ])
+        );
+        assert!(matches!(db.readiness, Some(ReadinessProbe::TcpPort(5432))));
+    }
+
+    #[test]
+    fn test_step_macro() {
+        let start = step!(start "Api");
+        assert_eq!(
+            (start.name(), start.service_name.as_str()),
+            ("Start_Api", "Api")
+        );
+        assert_eq!(step!(stop "Api").name(), "Stop_Api");
+
+        let health = step!(http GET "http://localhost:{{port:api}}/health");
+        assert_eq!(health.name, "GET http://localhost:{{port:api}}/health");
+        assert_eq!(
+            (health.method.as_str(), health.expect_status),
+            ("GET", Some(200))
+        );
+        let delete = step!(http DELETE "http://localhost/items/1" expect 204);
+        assert_eq!(
+            (delete.method.as_str(), delete.expect_status, delete.body),
+            ("DELETE", Some(204), None)
+        );
+
+        let mut harness = TestHarness::new("MacroTester", ".");
+        harness.add_step(TestStep::Service(Box::new(
+            step!(shell "test -n \"$HOME\""),
+        )));
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+    }
+}
@@ -0,0 +1,274 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use tracing::error;
+
+use crate::{ExitWatch, RunReport, TestHarness, UnexpectedExit};
+
+/// How often the [`CrashWatcher`] checks the services
+const WATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+struct WatchState {
+    /// Name of the step that finished last
+    after_step: Mutex<String>,
+    exits: Mutex<Vec<UnexpectedExit>>,
+    stopped: Mutex<bool>,
+    wakeup: Condvar,
+}
+
+/// Checks the [`ExitWatch`] of the crash-monitored services on a background
+/// thread until dropped, so an exit during a long step is recorded when it
+/// happens
+pub(crate) struct CrashWatcher {
+    state: Arc<WatchState>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CrashWatcher {
+    fn start(mut watches: Vec<(String, ExitWatch)>) -> Self {
+        let state = Arc::new(WatchState::default());
+        let thread = {
+            let state = state.clone();
+            std::thread::spawn(move || {
+                let mut stopped = state.stopped.lock().unwrap();
+                while !*stopped && !watches.is_empty() {
+                    watches.retain(|(service_name, watch)| {
+                        let Some(status) = watch() else {
+                            return true;
+                        };
+                        state.exits.lock().unwrap().push(UnexpectedExit {
+                            service_name: service_name.clone(),
+                            status,
+                            detected_at: SystemTime::now(),
+                            after_step: state.after_step.lock().unwrap().clone(),
+                        });
+                        false
+                    });
+                    stopped = state
+                        .wakeup
+                        .wait_timeout(stopped, WATCH_INTERVAL)
+                        .unwrap()
+                        .0;
+                }
+            })
+        };
+        Self {
+            state,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for CrashWatcher {
+    fn drop(&mut self) {
+        *self.state.stopped.lock().unwrap() = true;
+        self.state.wakeup.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl TestHarness {
+    /// Records any unexpected exit of `service_name` during the run as a
+    /// failure in the [`RunReport`]. Services with a
+    /// [`crate::Service::exit_watch`], such as subprocesses, are watched in
+    /// the background, so an exit is recorded within milliseconds even
+    /// while a long step runs. Other services are checked after every step.
+    /// Stopping the service through the harness is not considered
+    /// unexpected
+    pub fn monitor_crashes(&mut self, service_name: &str) {
+        self.crash_monitored.push(service_name.to_string());
+    }
+
+    pub(crate) fn start_crash_watcher(&mut self) {
+        let watches = self
+            .services
+            .iter()
+            .filter(|service| {
+                self.crash_monitored
+                    .iter()
+                    .any(|name| name == service.name())
+            })
+            .filter_map(|service| Some((service.name().to_string(), service.exit_watch()?)))
+            .collect::<Vec<_>>();
+        self.crash_watcher = (!watches.is_empty()).then(|| CrashWatcher::start(watches));
+    }
+
+    pub(crate) fn check_crashes(&mut self, report: &mut RunReport) {
+        let after_step = report
+            .steps
+            .last()
+            .map(|step| step.name.clone())
+            .unwrap_or_default();
+        if let Some(watcher) = &self.crash_watcher {
+            let exits = std::mem::take(&mut *watcher.state.exits.lock().unwrap());
+            for exit in exits {
+                // The check below may have noticed the exit first
+                if report
+                    .unexpected_exits
+                    .iter()
+                    .any(|reported| reported.service_name == exit.service_name)
+                {
+                    continue;
+                }
+                error!("{}", exit);
+                report.unexpected_exits.push(exit);
+            }
+            *watcher.state.after_step.lock().unwrap() = after_step.clone();
+        }
+        for service in &mut self.services {
+            if !self
+                .crash_monitored
+                .iter()
+                .any(|name| name == service.name())
+                || report
+                    .unexpected_exits
+                    .iter()
+                    .any(|exit| exit.service_name == service.name())
+            {
+                continue;
+            }
+            if let Some(status) = service.exit_status() {
+                report.unexpected_exits.push(UnexpectedExit {
+                    service_name: service.name().to_string(),
+                    status,
+                    detected_at: SystemTime::now(),
+                    after_step: after_step.clone(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::time::Duration;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        AsyncFnStep, ServiceStepExecutor, StepEnv, StepError, SubProcessService,
+        SubProcessServiceStarter, TestStep,
+    };
+
+    #[derive(Debug)]
+    struct KillService(&'static str);
+
+    impl ServiceStepExecutor for KillService {
+        fn name(&self) -> &str { "Kill_Service" }
+
+        fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+            let pid = env
+                .service(self.0)?
+                .pid()
+                .ok_or_else(|| StepError::Failed("Service has no process".to_string()))?;
+            Command::new("kill")
+                .args(["-9", &pid.to_string()])
+                .status()
+                .map_err(|e| format!("Failed to run kill: {}", e))?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_crash_monitor_reports_service_killed_mid_run() {
+        let mut harness = TestHarness::new("CrashMonitorTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        harness.monitor_crashes("Sleeper");
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Sleeper".to_string(),
+            description: "Starts the sleeper".to_string(),
+            service_name: "Sleeper".to_string(),
+            wait_after: None,
+        })));
+        harness.add_step(TestStep::Service(Box::new(KillService("Sleeper"))));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Keep_Going".to_string(),
+            description: "Some other work while the service is down".to_string(),
+            futurefn: Box::new(|_| {
+                Box::new(async {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    Ok(Value::Null)
+                })
+            }),
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(!report.passed());
+        assert_eq!(report.unexpected_exits.len(), 1);
+        let unexpected_exit = &report.unexpected_exits[0];
+        assert_eq!(unexpected_exit.service_name, "Sleeper");
+        assert!(!unexpected_exit.status.success());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(unexpected_exit.status.signal(), Some(9));
+        }
+    }
+
+    /// Kills the service 100ms into a step that takes a second
+    #[derive(Debug)]
+    struct KillDuringLongStep(&'static str);
+
+    impl ServiceStepExecutor for KillDuringLongStep {
+        fn name(&self) -> &str { "Long_Step" }
+
+        fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+            let pid = env
+                .service(self.0)?
+                .pid()
+                .ok_or_else(|| StepError::Failed("Service has no process".to_string()))?;
+            Command::new("sh")
+                .args(["-c", &format!("sleep 0.1; kill -9 {}", pid)])
+                .spawn()
+                .map_err(|e| format!("Failed to run kill: {}", e))?;
+            std::thread::sleep(Duration::from_secs(1));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_crash_during_long_step_is_detected_when_it_happens() {
+        let mut harness = TestHarness::new("CrashMonitorTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        harness.monitor_crashes("Sleeper");
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Start_Sleeper".to_string(),
+            description: "Starts the sleeper".to_string(),
+            service_name: "Sleeper".to_string(),
+            wait_after: None,
+        })));
+        harness.add_step(TestStep::Service(Box::new(KillDuringLongStep("Sleeper"))));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert_eq!(
+            report.unexpected_exits.len(),
+            1,
+            "{:?}",
+            report.unexpected_exits
+        );
+        let unexpected_exit = &report.unexpected_exits[0];
+        assert_eq!(unexpected_exit.after_step, "Start_Sleeper");
+        let long_step = &report.steps[1];
+        let step_started = report.started_at + (long_step.started.unwrap() - report.started);
+        let detected_after = unexpected_exit
+            .detected_at
+            .duration_since(step_started)
+            .unwrap();
+        assert!(
+            detected_after < Duration::from_millis(600),
+            "Exit detected {:?} into a step of {:?}",
+            detected_after,
+            long_step.duration
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use tracing::info;
+
+use crate::container::unique_resource_name;
+use crate::{container_runtime_from_env, ContainerRuntime, Service, ServiceError, TestHarness};
+
+/// An isolated container network, created on start and removed on stop.
+/// [`crate::DockerService`]s that [`join`](crate::DockerService::join) it
+/// reach each other by their network aliases instead of host ports
+#[derive(Debug, Clone)]
+pub struct ContainerNetwork {
+    pub name: String,
+    /// Name of the network in the runtime, unique per test process by
+    /// default
+    pub network_name: String,
+    pub driver: String,
+    /// Containers on the network cannot reach the outside world
+    pub internal: bool,
+    pub runtime: Arc<dyn ContainerRuntime>,
+    created: bool,
+}
+
+impl ContainerNetwork {
+    /// A bridge network managed with the runtime selected by
+    /// [`container_runtime_from_env`]
+    pub fn new(name: &str) -> Self { Self::with_runtime(name, container_runtime_from_env()) }
+
+    pub fn with_runtime(name: &str, runtime: Arc<dyn ContainerRuntime>) -> Self {
+        Self {
+            name: name.to_string(),
+            network_name: unique_resource_name(name),
+            driver: "bridge".to_string(),
+            internal: false,
+            runtime,
+            created: false,
+        }
+    }
+
+    /// Arguments of the CLI creating the network
+    fn create_args(&self) -> Vec<&str> {
+        let mut args = vec!["network", "create", "--driver", &self.driver];
+        if self.internal {
+            args.push("--internal");
+        }
+        args.push(&self.network_name);
+        args
+    }
+}
+
+impl Service for ContainerNetwork {
+    fn name(&self) -> &str { &self.name }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        if self.created {
+            return Err(ServiceError::AlreadyRunning(self.name.clone()));
+        }
+        info!("Creating network '{}'", self.network_name);
+        self.runtime.run(&self.name, &self.create_args())?;
+        self.created = true;
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.created
+            && self
+                .runtime
+                .run(&self.name, &["network", "inspect", &self.network_name])
+                .is_ok()
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        if self.created {
+            self.runtime
+                .run(&self.name, &["network", "rm", &self.network_name])?;
+            self.created = false;
+        }
+        Ok(())
+    }
+
+    fn required_tools(&self) -> Vec<String> { vec![self.runtime.program().to_string()] }
+}
+
+impl TestHarness {
+    /// Adds the network, which the services added after it can join. It is
+    /// created before and removed after the services joining it, as long as
+    /// these are declared to depend on it with
+    /// [`TestHarness::add_dependency`] or added after it
+    pub fn add_network(&mut self, network: ContainerNetwork) {
+        self.add_service(Box::new(network));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Docker;
+
+    #[test]
+    fn test_create_args() {
+        let mut network = ContainerNetwork::with_runtime("Backend", Arc::new(Docker));
+        network.internal = true;
+        let network_name = format!("harness-backend-{}", std::process::id());
+        assert_eq!(network.create_args(), [
+            "network",
+            "create",
+            "--driver",
+            "bridge",
+            "--internal",
+            &network_name,
+        ]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::env;
+use std::time::Duration;
+
+use crate::TestHarness;
+
+/// Settings that can be toggled from the command line of a test binary or
+/// the environment without recompiling, see [`HarnessOptions::from_args`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HarnessOptions {
+    /// Leave services running when a step fails, to inspect them
+    pub keep_alive: bool,
+    /// Only execute these steps and their prerequisites
+    pub filter: Option<Vec<String>>,
+    /// Interval at which still running steps are logged
+    pub heartbeat: Option<Duration>,
+}
+
+impl HarnessOptions {
+    /// Reads the options from the arguments of the process and the
+    /// environment, see [`HarnessOptions::parse`]
+    pub fn from_args() -> Result<Self, String> {
+        Self::parse(env::args().skip(1), |name| env::var(name).ok())
+    }
+
+    /// Parses the options from `args` and the variables returned by `env`,
+    /// where arguments take precedence:
+    ///
+    /// - `--harness-keep-alive` or `HARNESS_KEEP_ALIVE=1`
+    /// - `--harness-filter=a,b` or `HARNESS_FILTER=a,b`
+    /// - `--harness-heartbeat=SECS` or `HARNESS_HEARTBEAT=SECS`
+    ///
+    /// Other arguments are ignored, so they can be passed alongside those of
+    /// the test runner
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, String> {
+        let mut options = Self::default();
+        if let Some(keep_alive) = env("HARNESS_KEEP_ALIVE") {
+            options.keep_alive = parse_bool("HARNESS_KEEP_ALIVE", &keep_alive)?;
+        }
+        if let Some(filter) = env("HARNESS_FILTER") {
+            options.filter = Some(parse_list(&filter));
+        }
+        if let Some(heartbeat) = env("HARNESS_HEARTBEAT") {
+            options.heartbeat = Some(parse_secs("HARNESS_HEARTBEAT", &heartbeat)?);
+        }
+
+        for arg in args {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            match (flag, value) {
+                ("--harness-keep-alive", None) => options.keep_alive = true,
+                ("--harness-keep-alive", Some(value)) =>
+                    options.keep_alive = parse_bool(flag, value)?,
+                ("--harness-filter", Some(value)) => options.filter = Some(parse_list(value)),
+                ("--harness-heartbeat", Some(value)) =>
+                    options.heartbeat = Some(parse_secs(flag, value)?),
+                ("--harness-filter" | "--harness-heartbeat", None) =>
+                    return Err(format!("Missing value for {}", flag)),
+                _ => {}
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" | "" => Ok(false),
+        _ => Err(format!(
+            "Invalid value '{}' for {}, expected a boolean",
+            value, name
+        )),
+    }
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn parse_secs(name: &str, value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("Invalid value '{}' for {}, expected seconds", value, name))
+}
+
+impl TestHarness {
+    /// Applies options, e.g. parsed by [`HarnessOptions::from_args`]
+    pub fn apply_options(&mut self, options: HarnessOptions) {
+        self.keep_alive |= options.keep_alive;
+        self.heartbeat = options.heartbeat.or(self.heartbeat);
+        if options.filter.is_some() {
+            self.filter = options.filter;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args_and_env() {
+        let args = [
+            "--nocapture",
+            "--harness-filter=Start_Db, Query",
+            "--harness-keep-alive",
+        ]
+        .map(ToString::to_string);
+        let options = HarnessOptions::parse(args, |name| match name {
+            "HARNESS_FILTER" => Some("Ignored".to_string()),
+            "HARNESS_HEARTBEAT" => Some("2.5".to_string()),
+            _ => None,
+        })
+        .expect("Failed to parse options");
+
+        assert_eq!(options, HarnessOptions {
+            keep_alive: true,
+            filter: Some(vec!["Start_Db".to_string(), "Query".to_string()]),
+            heartbeat: Some(Duration::from_millis(2500)),
+        });
+
+        let mut harness = TestHarness::new("OptionsTester", ".");
+        harness.apply_options(options);
+        assert!(harness.keep_alive);
+        assert_eq!(harness.heartbeat, Some(Duration::from_millis(2500)));
+        assert_eq!(
+            harness.filter,
+            Some(vec!["Start_Db".to_string(), "Query".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_values() {
+        let error =
+            HarnessOptions::parse(["--harness-heartbeat=soon".to_string()], |_| None).unwrap_err();
+        assert!(error.contains("--harness-heartbeat"), "{}", error);
+    }
+}
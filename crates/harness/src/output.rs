@@ -0,0 +1,153 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use tracing::{debug, warn};
+
+/// Stream a line of service output was printed to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// A line printed by a service, see [`crate::Service::output`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLine {
+    pub stream: OutputStream,
+    pub line: String,
+    /// When the line was read from the service
+    pub at: Instant,
+}
+
+/// Files the output of a service is written to, see
+/// [`crate::Service::log_files`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFiles {
+    pub stdout: PathBuf,
+    pub stderr: PathBuf,
+}
+
+impl LogFiles {
+    /// `{root_dir}/logs/{service_name}.out.log` and
+    /// `{root_dir}/logs/{service_name}.err.log`
+    pub fn new(root_dir: impl AsRef<Path>, service_name: &str) -> Self {
+        let dir = root_dir.as_ref().join("logs");
+        Self {
+            stdout: dir.join(format!("{}.out.log", service_name)),
+            stderr: dir.join(format!("{}.err.log", service_name)),
+        }
+    }
+
+    /// Opens both files for appending, creating them and their directories
+    /// if needed, so the output of restarts is kept
+    pub(crate) fn open(&self) -> io::Result<(File, File)> {
+        let open = |path: &Path| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            OpenOptions::new().create(true).append(true).open(path)
+        };
+        Ok((open(&self.stdout)?, open(&self.stderr)?))
+    }
+}
+
+/// Collects the piped stdout and stderr of the child into `lines` on
+/// background threads, which end once the child closes its output. With
+/// `files`, lines are also written to them instead of being logged
+pub(crate) fn capture(
+    service_name: &str,
+    child: &mut Child,
+    lines: &Arc<Mutex<Vec<OutputLine>>>,
+    files: Option<(File, File)>,
+) {
+    let (stdout_file, stderr_file) = files.unzip();
+    if let Some(stdout) = child.stdout.take() {
+        spawn_reader(
+            service_name,
+            OutputStream::Stdout,
+            stdout,
+            lines,
+            stdout_file,
+        );
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_reader(
+            service_name,
+            OutputStream::Stderr,
+            stderr,
+            lines,
+            stderr_file,
+        );
+    }
+}
+
+fn spawn_reader(
+    service_name: &str,
+    stream: OutputStream,
+    output: impl Read + Send + 'static,
+    lines: &Arc<Mutex<Vec<OutputLine>>>,
+    mut file: Option<File>,
+) {
+    let service_name = service_name.to_string();
+    let lines = lines.clone();
+    std::thread::spawn(move || {
+        for line in BufReader::new(output).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match file.as_mut() {
+                Some(file) =>
+                    if let Err(e) = writeln!(file, "{}", line) {
+                        warn!(
+                            "Failed to write output of '{}' to file: {}",
+                            service_name, e
+                        );
+                    },
+                None => debug!("[{}] {}", service_name, line),
+            }
+            lines
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .push(OutputLine {
+                    stream,
+                    line,
+                    at: Instant::now(),
+                });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{PollPolicy, Service, SubProcessService};
+
+    #[test]
+    fn test_output_is_written_to_log_files() {
+        let root = tempfile::tempdir().unwrap();
+        let mut service = SubProcessService::new("Chatty", "sh", vec![
+            "-c".to_string(),
+            "echo to stdout; echo to stderr >&2".to_string(),
+        ]);
+        service.log_files = Some(LogFiles::new(root.path(), "Chatty"));
+        service.start().expect("Failed to start service");
+        PollPolicy {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        }
+        .poll(|| (service.output().len() == 2).then_some(()).ok_or(()))
+        .expect("Output was not captured");
+
+        let files = service.log_files().expect("Service has log files");
+        assert_eq!(files.stdout, root.path().join("logs/Chatty.out.log"));
+        assert_eq!(fs::read_to_string(&files.stdout).unwrap(), "to stdout\n");
+        assert_eq!(fs::read_to_string(&files.stderr).unwrap(), "to stderr\n");
+    }
+}
@@ -0,0 +1,150 @@
+use std::panic::{self, PanicHookInfo};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+
+use tracing::error;
+
+use crate::Service;
+
+/// Lines of output logged per service when a step panics
+const RECENT_OUTPUT_LINES: usize = 10;
+
+type PanicHook = dyn Fn(&PanicHookInfo<'_>) + Send + Sync;
+
+/// Harness state as of the start of the current step
+#[derive(Debug, Default)]
+struct PanicState {
+    /// Cleared once the harness finished, if the hook could not be removed
+    active: bool,
+    step: String,
+    /// Name, whether it is running and the recent output of each service
+    services: Vec<(String, bool, Vec<String>)>,
+}
+
+/// Panic hook that logs the state of the harness before the panic unwinds,
+/// installed while the harness runs and replaced by the previous hook when
+/// dropped. Only panics on the thread running the harness are annotated.
+/// While that thread unwinds the hook cannot be replaced, in which case it
+/// stays installed but only forwards to the previous hook
+pub(crate) struct PanicHookGuard {
+    state: Arc<Mutex<PanicState>>,
+    previous: Arc<PanicHook>,
+}
+
+impl PanicHookGuard {
+    pub(crate) fn install(test_name: &str) -> Self {
+        let state = Arc::new(Mutex::new(PanicState {
+            active: true,
+            ..Default::default()
+        }));
+        let previous: Arc<PanicHook> = Arc::from(panic::take_hook());
+        let harness_thread = thread::current().id();
+        let hook_state = state.clone();
+        let hook_previous = previous.clone();
+        let test_name = test_name.to_string();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().id() == harness_thread {
+                log_state(&test_name, harness_thread, &hook_state);
+            }
+            hook_previous(info);
+        }));
+        Self { state, previous }
+    }
+
+    /// Records the step that is about to execute and the current services
+    pub(crate) fn enter_step(&self, step: &str, services: &[Box<dyn Service>]) {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.step = step.to_string();
+        state.services = services
+            .iter()
+            .map(|service| {
+                let output = service.output();
+                let recent = output[output.len().saturating_sub(RECENT_OUTPUT_LINES)..]
+                    .iter()
+                    .map(|line| line.line.clone())
+                    .collect();
+                (service.name().to_string(), service.is_running(), recent)
+            })
+            .collect();
+    }
+}
+
+impl Drop for PanicHookGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .active = false;
+            return;
+        }
+        let previous = self.previous.clone();
+        panic::set_hook(Box::new(move |info| previous(info)));
+    }
+}
+
+fn log_state(test_name: &str, thread: ThreadId, state: &Mutex<PanicState>) {
+    let state = state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !state.active {
+        return;
+    }
+    let mut message = format!(
+        "Panic in step '{}' of test '{}' on {:?}, services:",
+        state.step, test_name, thread
+    );
+    for (name, running, recent_output) in &state.services {
+        message.push_str(&format!(
+            "\n  {} ({})",
+            name,
+            if *running { "running" } else { "stopped" }
+        ));
+        for line in recent_output {
+            message.push_str(&format!("\n    | {}", line));
+        }
+    }
+    error!("{}", message);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::test_util::DelayedReadyService;
+    use crate::{test_log, AsyncFnStep, TestHarness, TestStep};
+
+    #[test]
+    fn test_panic_logs_step_and_services() {
+        test_log::init();
+        let mut harness = TestHarness::new("PanicHookTester", ".");
+        harness.panic_hook = true;
+        harness.add_service(Box::new(DelayedReadyService::new(
+            "Bystander_Service",
+            std::time::Duration::ZERO,
+        )));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Exploding_Step".to_string(),
+            description: "Panics".to_string(),
+            futurefn: Box::new(|_| {
+                Box::new(async {
+                    if true {
+                        panic!("exploded");
+                    }
+                    Ok(Value::Null)
+                })
+            }),
+        })));
+
+        let report = harness.execute().expect("Panic was not caught");
+        assert!(!report.passed());
+        let logged = test_log::captured()
+            .into_iter()
+            .find(|line| line.contains("Panic in step 'Exploding_Step'"))
+            .expect("Panic was not logged");
+        assert!(logged.contains("Bystander_Service (stopped)"), "{}", logged);
+    }
+}
@@ -0,0 +1,203 @@
+use std::future::{poll_fn, Future};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::Poll;
+use std::thread;
+
+use serde_json::Value;
+use tracing::{info, Span};
+
+use crate::{StepEnv, StepError, TestStep};
+
+type StepFuture = Pin<Box<dyn Future<Output = Result<Value, String>>>>;
+
+/// Drives all futures concurrently, returning their outputs in order
+async fn join_all(futures: Vec<StepFuture>) -> Vec<Result<Value, String>> {
+    let mut pending = futures.into_iter().map(Some).collect::<Vec<_>>();
+    let mut outputs = pending.iter().map(|_| None).collect::<Vec<_>>();
+    poll_fn(|cx| {
+        for (future, output) in pending.iter_mut().zip(outputs.iter_mut()) {
+            if let Some(polled) = future.as_mut() {
+                if let Poll::Ready(result) = polled.as_mut().poll(cx) {
+                    *output = Some(result);
+                    *future = None;
+                }
+            }
+        }
+        if pending.iter().all(Option::is_none) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    outputs.into_iter().flatten().collect()
+}
+
+impl StepEnv<'_> {
+    /// Executes the steps of a [`TestStep::Parallel`] group concurrently:
+    /// async steps together on the runtime of the run, all other steps on
+    /// threads of their own. Steps on threads cannot access the services.
+    /// Every step runs to completion, failures are collected afterwards
+    pub(crate) fn execute_parallel(&mut self, steps: &mut [TestStep]) -> Result<(), StepError> {
+        let (root_dir, dependencies, context, artifacts, ports) = (
+            self.root_dir,
+            self.dependencies,
+            self.context,
+            self.artifacts,
+            self.ports,
+        );
+        let (runtime, interrupt) = (self.runtime, self.interrupt);
+        let mut results = Vec::new();
+        thread::scope(|scope| {
+            let mut async_names = Vec::new();
+            let mut futures = Vec::new();
+            let mut threads = Vec::new();
+            let span = Span::current();
+            for step in steps.iter_mut() {
+                let name = step.name().to_string();
+                if let TestStep::AsyncFn(async_step) = step {
+                    futures.push(Box::into_pin((async_step.futurefn)(context)));
+                    async_names.push(name);
+                    continue;
+                }
+                let span = span.clone();
+                let thread = scope.spawn(move || {
+                    let _span = span.entered();
+                    let mut env = StepEnv {
+                        root_dir,
+                        services: &mut [],
+                        dependencies,
+                        context,
+                        artifacts,
+                        ports,
+                        startup_log: &mut Vec::new(),
+                        ready_log: &mut Vec::new(),
+                        skip_reason: &mut None,
+                        runtime,
+                        interrupt,
+                    };
+                    catch_unwind(AssertUnwindSafe(|| env.execute_step(step)))
+                        .unwrap_or_else(|payload| Err(StepError::panicked(&*payload)))
+                });
+                threads.push((name, thread));
+            }
+
+            match self.block_on(join_all(futures)) {
+                Ok(outputs) =>
+                    for (name, output) in async_names.into_iter().zip(outputs) {
+                        let result = output.map(|value| context.insert(&name, value));
+                        results.push((name, result.map_err(StepError::from)));
+                    },
+                Err(StepError::Interrupted(signal)) => results.extend(
+                    async_names
+                        .into_iter()
+                        .map(|name| (name, Err(StepError::Interrupted(signal)))),
+                ),
+                Err(e) => {
+                    let message = e.to_string();
+                    results.extend(
+                        async_names
+                            .into_iter()
+                            .map(|name| (name, Err(StepError::Failed(message.clone())))),
+                    );
+                }
+            }
+            for (name, thread) in threads {
+                let result = thread
+                    .join()
+                    .unwrap_or_else(|payload| Err(StepError::panicked(&*payload)));
+                results.push((name, result));
+            }
+        });
+
+        let total = results.len();
+        let mut failures = Vec::new();
+        for (name, result) in results {
+            match result {
+                Ok(()) => info!("Parallel step '{}' passed", name),
+                Err(StepError::Interrupted(signal)) => return Err(StepError::Interrupted(signal)),
+                Err(e) => failures.push(format!("'{}': {}", name, e)),
+            }
+        }
+        if failures.is_empty() {
+            return Ok(());
+        }
+        Err(StepError::Failed(format!(
+            "{} of {} parallel steps failed: {}",
+            failures.len(),
+            total,
+            failures.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{AsyncFnStep, TestHarness, WaitUntilStep};
+
+    fn sleeper(name: &str, result: Result<Value, String>) -> TestStep {
+        TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: name.to_string(),
+            description: "Sleeps before returning".to_string(),
+            futurefn: Box::new(move |_| {
+                let result = result.clone();
+                Box::new(async move {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    result
+                })
+            }),
+        }))
+    }
+
+    fn blocking(name: &str) -> TestStep {
+        let started = Instant::now();
+        let mut step = WaitUntilStep::new(
+            name,
+            Box::new(move || Ok(started.elapsed() >= Duration::from_millis(300))),
+        );
+        step.interval = Duration::from_millis(50);
+        TestStep::Service(Box::new(step))
+    }
+
+    #[test]
+    fn test_parallel_steps_run_concurrently() {
+        let mut harness = TestHarness::new("ParallelTester", ".");
+        harness.add_step(TestStep::Parallel(vec![
+            sleeper("Probe A", Ok(Value::from("a"))),
+            sleeper("Probe B", Ok(Value::from("b"))),
+            blocking("Probe C"),
+            blocking("Probe D"),
+        ]));
+        let context = harness.context.clone();
+
+        let started = Instant::now();
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert!(started.elapsed() < Duration::from_millis(900));
+        assert_eq!(context.get("Probe B"), Some(Value::from("b")));
+    }
+
+    #[test]
+    fn test_parallel_failures_are_collected() {
+        let mut harness = TestHarness::new("ParallelTester", ".");
+        let mut step = TestStep::Parallel(vec![
+            sleeper("Probe A", Err("connection refused".to_string())),
+            sleeper("Probe B", Ok(Value::Null)),
+            sleeper("Probe C", Err("status 503".to_string())),
+        ]);
+
+        let error = harness
+            .step_env()
+            .execute_step(&mut step)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "2 of 3 parallel steps failed: 'Probe A': connection refused; 'Probe C': status 503"
+        );
+    }
+}
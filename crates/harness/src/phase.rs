@@ -0,0 +1,185 @@
+use std::time::Instant;
+
+use tracing::{error, info, warn};
+
+use crate::panic::PanicHookGuard;
+use crate::{
+    HarnessError, PlannedStep, RunReport, StepOptions, StepReport, StepStatus, TestHarness,
+    TestStep,
+};
+
+impl TestHarness {
+    /// Adds a step executed before the main steps. If a setup step fails,
+    /// the remaining setup and all main steps are skipped, while teardown
+    /// still runs
+    pub fn add_setup(&mut self, step: TestStep) {
+        self.setup.push(PlannedStep {
+            step,
+            options: StepOptions::default(),
+        });
+    }
+
+    /// Adds a step executed after the main steps, even if one of them failed
+    /// or the run was aborted. Teardown steps run in the order they were
+    /// added, and each runs regardless of whether the previous ones failed
+    pub fn add_teardown(&mut self, step: TestStep) {
+        self.teardown.push(PlannedStep {
+            step,
+            options: StepOptions::default(),
+        });
+    }
+
+    /// Executes the setup steps until one fails, returning its failure
+    pub(crate) fn run_setup(
+        &mut self,
+        report: &mut RunReport,
+        panic_hook: Option<&PanicHookGuard>,
+    ) -> Result<(), HarnessError> {
+        let setup = std::mem::take(&mut self.setup);
+        let total = setup.len();
+        let mut failure = None;
+        for (idx, PlannedStep { mut step, options }) in setup.into_iter().enumerate() {
+            let name = step.name().to_string();
+            if failure.is_some() {
+                let status = StepStatus::Skipped("setup failed".to_string());
+                report.steps.push(StepReport::new(&step, status));
+                continue;
+            }
+            info!("Executing setup step {}/{}: {}", idx + 1, total, name);
+            let progress = self.show_progress(&format!("setup {}/{}", idx + 1, total), &step);
+            let started = Instant::now();
+            let result = self.attempt_step(&mut step, &options, panic_hook);
+            drop(progress);
+            let status = match result {
+                Ok(()) => self.passed_or_skipped(),
+                Err(e) => {
+                    error!("Setup step '{}' failed: {}", name, e);
+                    self.clean_up_after_failure(report);
+                    let status = StepStatus::Failed(e.to_string());
+                    failure = Some(HarnessError::StepFailed {
+                        index: idx + 1,
+                        name: name.clone(),
+                        source: e,
+                    });
+                    status
+                }
+            };
+            report.steps.push(StepReport {
+                started: Some(started),
+                duration: started.elapsed(),
+                ..StepReport::new(&step, status)
+            });
+            self.check_crashes(report);
+            self.check_liveness(report);
+        }
+        failure.map_or(Ok(()), Err)
+    }
+
+    /// Executes all teardown steps, recording failures without stopping
+    pub(crate) fn run_teardown(
+        &mut self,
+        report: &mut RunReport,
+        panic_hook: Option<&PanicHookGuard>,
+    ) {
+        let teardown = std::mem::take(&mut self.teardown);
+        let total = teardown.len();
+        for (idx, PlannedStep { mut step, options }) in teardown.into_iter().enumerate() {
+            let name = step.name().to_string();
+            info!("Executing teardown step {}/{}: {}", idx + 1, total, name);
+            let progress = self.show_progress(&format!("teardown {}/{}", idx + 1, total), &step);
+            let started = Instant::now();
+            let result = self.attempt_step(&mut step, &options, panic_hook);
+            drop(progress);
+            let status = match result {
+                Ok(()) => self.passed_or_skipped(),
+                Err(e) => {
+                    warn!("Teardown step '{}' failed: {}", name, e);
+                    StepStatus::Failed(e.to_string())
+                }
+            };
+            report.steps.push(StepReport {
+                started: Some(started),
+                duration: started.elapsed(),
+                ..StepReport::new(&step, status)
+            });
+        }
+    }
+
+    pub(crate) fn passed_or_skipped(&mut self) -> StepStatus {
+        self.skip_reason
+            .take()
+            .map_or(StepStatus::Passed, StepStatus::Skipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{AsyncFnStep, FailurePolicy};
+
+    fn record(name: &str, events: &Arc<Mutex<Vec<String>>>, fails: bool) -> TestStep {
+        let events = events.clone();
+        let event = name.to_string();
+        TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: name.to_string(),
+            description: format!("Records {}", name),
+            futurefn: Box::new(move |_| {
+                events.lock().unwrap().push(event.clone());
+                Box::new(async move {
+                    if fails {
+                        Err("boom".to_string())
+                    } else {
+                        Ok(Value::Null)
+                    }
+                })
+            }),
+        }))
+    }
+
+    #[test]
+    fn test_teardown_runs_after_aborted_step() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("PhaseTester", ".");
+        harness.failure_policy = FailurePolicy::Abort;
+        harness.add_setup(record("setup", &events, false));
+        harness.add_step(record("fails", &events, true));
+        harness.add_step(record("skipped", &events, false));
+        harness.add_teardown(record("teardown_1", &events, true));
+        harness.add_teardown(record("teardown_2", &events, false));
+
+        let error = harness.execute().unwrap_err();
+        assert_eq!(error.to_string(), "Step 1 'fails' failed: boom");
+        assert_eq!(*events.lock().unwrap(), vec![
+            "setup",
+            "fails",
+            "teardown_1",
+            "teardown_2"
+        ]);
+    }
+
+    #[test]
+    fn test_failed_setup_skips_main_steps() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut harness = TestHarness::new("PhaseTester", ".");
+        harness.add_setup(record("setup", &events, true));
+        harness.add_step(record("main", &events, false));
+        harness.add_teardown(record("teardown", &events, false));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert_eq!(*events.lock().unwrap(), vec!["setup", "teardown"]);
+        let statuses = report
+            .steps
+            .iter()
+            .map(|step| &step.status)
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, vec![
+            &StepStatus::Failed("boom".to_string()),
+            &StepStatus::Skipped("setup failed".to_string()),
+            &StepStatus::Passed,
+        ]);
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::{BTreeSet, HashMap};
+use std::net::{TcpListener, UdpSocket};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::{env, io};
+
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::{Context, ServiceError};
+
+/// Ports handed out by any allocator in this process, so harnesses running
+/// in parallel never get the same port
+static HANDED_OUT: Mutex<BTreeSet<u16>> = Mutex::new(BTreeSet::new());
+
+/// `$${` escaping a literal `${`, `${ENV_VAR}`, `{{context.key}}` and
+/// `{{kind:name}}`, in this order of capture groups
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(\$\$\{)",
+        r"|\$\{([A-Za-z_][A-Za-z0-9_]*)\}",
+        r"|\{\{context\.([A-Za-z0-9_.-]+)\}\}",
+        r"|\{\{([a-z]+):([A-Za-z0-9_.-]+)\}\}",
+    ))
+    .expect("Invalid pattern")
+});
+
+/// Free ports reserved by name for the services of a harness, see
+/// [`crate::TestHarness::ports`]. A port is picked by binding port 0 on
+/// localhost and released right away, so another process may still take it
+/// before the service binds it, but ports never collide within the process.
+/// Clones share the same reservations. It also substitutes other
+/// placeholders defined with [`PortAllocator::define`], environment
+/// variables and, while executing steps, values of the [`Context`]
+#[derive(Debug, Clone, Default)]
+pub struct PortAllocator {
+    ports: Arc<Mutex<HashMap<String, u16>>>,
+    /// Values of other placeholders, keyed by `kind:name`
+    values: Arc<Mutex<HashMap<String, String>>>,
+    /// Context of the run for `{{context.key}}` placeholders
+    context: Option<Context>,
+}
+
+impl PortAllocator {
+    pub fn new() -> Self { Self::default() }
+
+    /// Port reserved for `name`, reserving a free TCP port if there is none
+    pub fn tcp(&self, name: &str) -> io::Result<u16> {
+        self.reserve(name, || {
+            Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+        })
+    }
+
+    /// Port reserved for `name`, reserving a free UDP port if there is none
+    pub fn udp(&self, name: &str) -> io::Result<u16> {
+        self.reserve(name, || {
+            Ok(UdpSocket::bind("127.0.0.1:0")?.local_addr()?.port())
+        })
+    }
+
+    /// Reserves `port` for `name`, e.g. a fixed port from a
+    /// [`crate::SuiteConfig`], replacing any port reserved before
+    pub fn assign(&self, name: &str, port: u16) {
+        HANDED_OUT.lock().unwrap().insert(port);
+        self.ports.lock().unwrap().insert(name.to_string(), port);
+    }
+
+    /// Port reserved for `name`, if any
+    pub fn get(&self, name: &str) -> Option<u16> { self.ports.lock().unwrap().get(name).copied() }
+
+    /// Defines the value of `{{kind:name}}` placeholders, e.g. the path of
+    /// a [`crate::TempDirFixture`] for `{{tempdir:name}}`
+    pub fn define(&self, kind: &str, name: &str, value: &str) {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(format!("{}:{}", kind, name), value.to_string());
+    }
+
+    /// The allocator substituting `{{context.key}}` placeholders with the
+    /// values of `context`, sharing the reservations of this one
+    pub(crate) fn with_context(&self, context: &Context) -> Self {
+        Self {
+            context: Some(context.clone()),
+            ..self.clone()
+        }
+    }
+
+    /// Replaces the placeholders in `text`, once, so substituted values are
+    /// never expanded again:
+    ///
+    /// - `{{port:name}}` with the port reserved for `name`, reserving a free
+    ///   TCP port for names without one
+    /// - `${ENV_VAR}` with the value of the environment variable if set, `$${`
+    ///   gives a literal `${`
+    /// - `{{context.key}}` with the value stored in the [`Context`] under
+    ///   `key`, or nested in it by the rest of a dotted key such as `order.id`,
+    ///   where strings are inserted as they are and other values as JSON
+    /// - placeholders defined with [`PortAllocator::define`] with their value
+    ///
+    /// Missing context values are errors, other placeholders are left as
+    /// they are
+    pub fn substitute(&self, text: &str) -> io::Result<String> {
+        let mut error = None;
+        let substituted = PLACEHOLDER.replace_all(text, |captures: &Captures<'_>| {
+            let value = if captures.get(1).is_some() {
+                Ok("${".to_string())
+            } else if let Some(var) = captures.get(2) {
+                // Left to the shell of scripts, which may define it itself
+                Ok(env::var(var.as_str()).unwrap_or_else(|_| captures[0].to_string()))
+            } else if let Some(key) = captures.get(3) {
+                self.context_value(key.as_str())
+            } else if &captures[4] == "port" {
+                self.tcp(&captures[5]).map(|port| port.to_string())
+            } else {
+                let values = self.values.lock().unwrap();
+                let key = format!("{}:{}", &captures[4], &captures[5]);
+                Ok(values
+                    .get(&key)
+                    .cloned()
+                    .unwrap_or_else(|| captures[0].to_string()))
+            };
+            value.unwrap_or_else(|e| {
+                error.get_or_insert(e);
+                String::new()
+            })
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(substituted.into_owned()),
+        }
+    }
+
+    /// Substitutes the placeholders in the configuration values of
+    /// `service_name` in place
+    pub(crate) fn substitute_in<'a>(
+        &self,
+        service_name: &str,
+        texts: impl IntoIterator<Item = &'a mut String>,
+    ) -> Result<(), ServiceError> {
+        for text in texts {
+            *text = self
+                .substitute(text)
+                .map_err(|source| ServiceError::Start {
+                    service: service_name.to_string(),
+                    source,
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Value of a `{{context.key}}` placeholder, looking up the whole key
+    /// first and then the first segment with the rest as a path into it
+    fn context_value(&self, key: &str) -> io::Result<String> {
+        let value = self.context.as_ref().and_then(|context| {
+            context.get(key).or_else(|| {
+                let (key, path) = key.split_once('.')?;
+                context.pointer(key, &format!("/{}", path.replace('.', "/")))
+            })
+        });
+        match value {
+            Some(Value::String(text)) => Ok(text),
+            Some(value) => Ok(value.to_string()),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No value '{}' in the context", key),
+            )),
+        }
+    }
+
+    fn reserve(&self, name: &str, pick: impl Fn() -> io::Result<u16>) -> io::Result<u16> {
+        let mut ports = self.ports.lock().unwrap();
+        if let Some(port) = ports.get(name) {
+            return Ok(*port);
+        }
+        // The OS may hand out a port again once it was released
+        for _ in 0..100 {
+            let port = pick()?;
+            if HANDED_OUT.lock().unwrap().insert(port) {
+                ports.insert(name.to_string(), port);
+                return Ok(port);
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("No free port left to reserve for '{}'", name),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        PollPolicy, ReadinessProbe, ServiceStepExecutor, SubProcessService,
+        SubProcessServiceStarter, TestHarness,
+    };
+
+    #[test]
+    fn test_substitutes_reserved_ports() {
+        let ports = PortAllocator::new();
+        let api = ports.tcp("api").unwrap();
+        assert_eq!(ports.tcp("api").unwrap(), api);
+        let metrics = ports.udp("metrics").unwrap();
+        assert_ne!(api, metrics);
+
+        let url = ports
+            .substitute("http://localhost:{{port:api}}/health?admin={{port:admin}}")
+            .unwrap();
+        let admin = ports.get("admin").expect("Placeholder reserved a port");
+        assert_eq!(
+            url,
+            format!("http://localhost:{}/health?admin={}", api, admin)
+        );
+        assert!(![api, metrics].contains(&admin));
+        assert_eq!(ports.substitute("{{port:}}").unwrap(), "{{port:}}");
+
+        // Another harness never gets the same ports
+        let other = PortAllocator::new();
+        assert!(![api, metrics, admin].contains(&other.tcp("api").unwrap()));
+    }
+
+    #[test]
+    fn test_substitutes_env_and_context() {
+        let context = Context::new();
+        context.insert("user", Value::from("alice"));
+        context.insert("Create_Order", serde_json::json!({"id": 42, "tags": ["a"]}));
+        let ports = PortAllocator::new().with_context(&context);
+
+        assert_eq!(
+            ports
+                .substitute(
+                    "/${CARGO_PKG_NAME}/{{context.user}}/{{context.Create_Order.id}}?$${HOME}"
+                )
+                .unwrap(),
+            "/harness/alice/42?${HOME}"
+        );
+        assert_eq!(
+            ports.substitute("{{context.Create_Order.tags}}").unwrap(),
+            r#"["a"]"#
+        );
+        assert_eq!(
+            ports.substitute("${HARNESS_UNSET_VARIABLE}").unwrap(),
+            "${HARNESS_UNSET_VARIABLE}"
+        );
+        assert_eq!(
+            PortAllocator::new()
+                .substitute("{{context.user}}")
+                .unwrap_err()
+                .to_string(),
+            "No value 'user' in the context"
+        );
+        // Substituted values are not expanded again
+        context.insert("raw", Value::from("${HOME}"));
+        assert_eq!(ports.substitute("{{context.raw}}").unwrap(), "${HOME}");
+    }
+
+    #[test]
+    fn test_service_started_on_reserved_port() {
+        let mut harness = TestHarness::new("PortTester", ".");
+        let mut service = SubProcessService::new("Web", "python3", vec![
+            "-m".to_string(),
+            "http.server".to_string(),
+            "{{port:web}}".to_string(),
+            "--bind".to_string(),
+            "127.0.0.1".to_string(),
+        ]);
+        service.readiness = Some(ReadinessProbe::HttpGet {
+            url: "http://127.0.0.1:{{port:web}}/".to_string(),
+            expect_status: 200,
+        });
+        service.readiness_policy = PollPolicy {
+            interval: Duration::from_millis(20),
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        };
+        harness.add_service(Box::new(service));
+
+        SubProcessServiceStarter {
+            name: "Start_Web".to_string(),
+            description: "Starts the web server".to_string(),
+            service_name: "Web".to_string(),
+            wait_after: None,
+        }
+        .execute(&mut harness.step_env())
+        .expect("Web server did not get ready on its port");
+        let port = harness.ports.get("web").expect("Port was reserved");
+        let command_line = harness.services[0].last_command_line().unwrap();
+        assert!(
+            command_line.contains(&format!("http.server {} ", port)),
+            "{}",
+            command_line
+        );
+    }
+}
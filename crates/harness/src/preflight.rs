@@ -0,0 +1,130 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+
+use crate::TestHarness;
+
+/// An external executable required by a service or step that could not be
+/// found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingTool {
+    pub tool: String,
+    /// Service or step that requires the tool
+    pub required_by: String,
+}
+
+impl Display for MissingTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (required by {})", self.tool, self.required_by)
+    }
+}
+
+impl TestHarness {
+    /// Verifies that the external executables required by the configured
+    /// services and steps exist, returning the ones that are missing. This is
+    /// also run by [`TestHarness::execute`] before any step, so a missing
+    /// `docker` is reported up front instead of failing midway through a run
+    pub fn preflight_check(&self) -> Vec<MissingTool> {
+        self.preflight_check_in(&env::var_os("PATH").unwrap_or_default())
+    }
+
+    /// Like [`TestHarness::preflight_check`], but resolves tools against the
+    /// given `PATH`-style search path
+    pub(crate) fn preflight_check_in(&self, search_path: &OsStr) -> Vec<MissingTool> {
+        let services = self.services.iter().map(|service| {
+            (
+                format!("service '{}'", service.name()),
+                service.required_tools(),
+            )
+        });
+        let steps = self
+            .steps
+            .iter()
+            .flat_map(|planned| planned.step.executors())
+            .map(|step_executor| {
+                (
+                    format!("step '{}'", step_executor.name()),
+                    step_executor.required_tools(),
+                )
+            });
+
+        services
+            .chain(steps)
+            .flat_map(|(required_by, tools)| {
+                tools
+                    .into_iter()
+                    .filter(|tool| find_executable(tool, search_path).is_none())
+                    .map(move |tool| MissingTool {
+                        tool,
+                        required_by: required_by.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Resolves an executable either as a path, if it contains a separator, or by
+/// searching the given `PATH`-style search path
+pub(crate) fn find_executable(tool: &str, search_path: &OsStr) -> Option<PathBuf> {
+    let tool_path = Path::new(tool);
+    if tool_path.components().count() > 1 {
+        return is_executable(tool_path).then(|| tool_path.to_path_buf());
+    }
+    env::split_paths(search_path)
+        .map(|dir| dir.join(tool))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool { path.is_file() || path.with_extension("exe").is_file() }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ServiceStepExecutor, StepEnv, StepError, TestStep};
+
+    #[derive(Debug)]
+    struct DockerStep;
+
+    impl ServiceStepExecutor for DockerStep {
+        fn name(&self) -> &str { "DockerStep" }
+
+        fn execute(&self, _env: &mut StepEnv<'_>) -> Result<(), StepError> { Ok(()) }
+
+        fn required_tools(&self) -> Vec<String> { vec!["docker".to_string()] }
+    }
+
+    #[test]
+    fn test_preflight_reports_missing_docker() {
+        let mut harness = TestHarness::new("PreflightTester", ".");
+        harness.add_step(TestStep::Service(Box::new(DockerStep)));
+
+        let empty_dir = tempfile::tempdir().unwrap();
+        let missing = harness.preflight_check_in(empty_dir.path().as_os_str());
+        assert_eq!(missing, vec![MissingTool {
+            tool: "docker".to_string(),
+            required_by: "step 'DockerStep'".to_string(),
+        }]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let fake_docker = empty_dir.path().join("docker");
+            std::fs::write(&fake_docker, "#!/bin/sh\n").unwrap();
+            std::fs::set_permissions(&fake_docker, std::fs::Permissions::from_mode(0o755)).unwrap();
+            assert!(harness
+                .preflight_check_in(empty_dir.path().as_os_str())
+                .is_empty());
+        }
+    }
+}
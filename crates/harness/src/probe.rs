@@ -0,0 +1,250 @@
+use std::cell::RefCell;
+use std::fmt::{self, Debug};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::{Context, ServiceStepExecutor, StepEnv, StepError};
+
+/// How often and for how long a condition is polled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollPolicy {
+    /// Grace period before the first check, so a probe does not race the
+    /// service binding its port and succeed against a lingering socket of an
+    /// earlier run
+    pub initial_delay: Duration,
+    pub interval: Duration,
+    /// Maximum time spent polling, excluding the initial delay
+    pub timeout: Duration,
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::ZERO,
+            interval: Duration::from_millis(100),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl PollPolicy {
+    /// Runs `check` after the initial delay until it succeeds or the timeout
+    /// expires, in which case the last error is returned. `check` runs at
+    /// least once
+    pub fn poll<T, E>(&self, mut check: impl FnMut() -> Result<T, E>) -> Result<T, E> {
+        std::thread::sleep(self.initial_delay);
+        let started = Instant::now();
+        loop {
+            match check() {
+                Ok(value) => return Ok(value),
+                Err(e) if started.elapsed() >= self.timeout => return Err(e),
+                Err(_) => std::thread::sleep(self.interval),
+            }
+        }
+    }
+}
+
+/// An assertion over the values shared between steps
+pub type Assertion = Box<dyn Fn(&Context) -> Result<(), String> + Send>;
+
+/// A step that re-runs an assertion every `interval` until it holds, failing
+/// with the last failure once `timeout` expires, e.g. to wait for an
+/// eventually consistent read to catch up with a write
+pub struct EventuallyAssert {
+    /// Name the step is reported under
+    pub name: String,
+    pub assertion: Assertion,
+    pub timeout: Duration,
+    pub interval: Duration,
+}
+
+impl Debug for EventuallyAssert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventuallyAssert")
+            .field("name", &self.name)
+            .field("timeout", &self.timeout)
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+impl ServiceStepExecutor for EventuallyAssert {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let policy = PollPolicy {
+            interval: self.interval,
+            timeout: self.timeout,
+            ..Default::default()
+        };
+        let mut attempts = 0;
+        policy
+            .poll(|| {
+                attempts += 1;
+                (self.assertion)(env.context)
+            })
+            .map_err(|e| StepError::Timeout {
+                what: format!("Assertion did not hold ({} attempts): {}", attempts, e),
+                after: self.timeout,
+            })?;
+        info!("Assertion held after {} attempt(s)", attempts);
+        Ok(())
+    }
+}
+
+/// A condition awaited by [`WaitUntilStep`]. `Ok(false)` means not yet,
+/// errors fail the step right away
+pub type Predicate = Box<dyn FnMut() -> Result<bool, String> + Send>;
+
+/// A step that checks a predicate every `interval` until it returns true,
+/// failing once `timeout` expires, e.g. to wait for a queue to drain or a
+/// metric to cross a threshold instead of sleeping
+pub struct WaitUntilStep {
+    /// What is awaited, used in the step name and timeout errors
+    pub description: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+    predicate: RefCell<Predicate>,
+}
+
+impl WaitUntilStep {
+    /// Checks `predicate` with the intervals of [`PollPolicy::default`]
+    pub fn new(description: &str, predicate: Predicate) -> Self {
+        let policy = PollPolicy::default();
+        Self {
+            description: description.to_string(),
+            interval: policy.interval,
+            timeout: policy.timeout,
+            predicate: RefCell::new(predicate),
+        }
+    }
+}
+
+impl Debug for WaitUntilStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitUntilStep")
+            .field("description", &self.description)
+            .field("interval", &self.interval)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl ServiceStepExecutor for WaitUntilStep {
+    fn name(&self) -> &str { &self.description }
+
+    fn execute(&self, _env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let policy = PollPolicy {
+            interval: self.interval,
+            timeout: self.timeout,
+            ..Default::default()
+        };
+        let mut predicate = self.predicate.borrow_mut();
+        let mut attempts = 0;
+        policy
+            .poll(|| {
+                attempts += 1;
+                match predicate() {
+                    Ok(false) => Err(()),
+                    result => Ok(result),
+                }
+            })
+            .map_err(|()| StepError::Timeout {
+                what: format!("{} ({} attempts)", self.description, attempts),
+                after: self.timeout,
+            })?
+            .map_err(|e| format!("{}: {}", self.description, e))?;
+        info!("{} after {} attempt(s)", self.description, attempts);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_poll_waits_initial_delay_before_first_check() {
+        let policy = PollPolicy {
+            initial_delay: Duration::from_millis(300),
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(1),
+        };
+        let started = Instant::now();
+        let mut first_check = None;
+        let result: Result<(), ()> = policy.poll(|| {
+            first_check.get_or_insert_with(|| started.elapsed());
+            Ok(())
+        });
+
+        assert!(result.is_ok());
+        let first_check = first_check.expect("Probe was never checked");
+        assert!(
+            first_check >= Duration::from_millis(300),
+            "First check after {:?}",
+            first_check
+        );
+    }
+
+    #[test]
+    fn test_eventually_assert_passes_on_third_attempt() {
+        let mut harness = TestHarness::new("EventuallyTester", ".");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let step = EventuallyAssert {
+            name: "Wait_For_Replica".to_string(),
+            assertion: Box::new(move |_| match counted.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err("replica is behind".to_string()),
+                _ => Ok(()),
+            }),
+            timeout: Duration::from_secs(5),
+            interval: Duration::from_millis(10),
+        };
+        let started = Instant::now();
+        step.execute(&mut harness.step_env())
+            .expect("Assertion should eventually hold");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_wait_until_predicate_holds() {
+        let mut harness = TestHarness::new("WaitUntilTester", ".");
+        let mut queue_len = 3;
+        let mut step = WaitUntilStep::new(
+            "Queue drained",
+            Box::new(move || {
+                queue_len -= 1;
+                Ok(queue_len == 0)
+            }),
+        );
+        step.interval = Duration::from_millis(10);
+        step.execute(&mut harness.step_env())
+            .expect("Queue should drain");
+
+        let mut step = WaitUntilStep::new("Queue drained", Box::new(|| Ok(false)));
+        step.interval = Duration::from_millis(10);
+        step.timeout = Duration::from_millis(50);
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Timed out after 50ms: Queue drained ("),
+            "{}",
+            error
+        );
+
+        let step = WaitUntilStep::new("Queue drained", Box::new(|| Err("broker gone".to_string())));
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Queue drained: broker gone");
+    }
+}
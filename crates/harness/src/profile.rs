@@ -0,0 +1,211 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::{PollPolicy, ServiceAction, ServiceStepExecutor, StepEnv, StepError};
+
+/// How a profile of a service is collected
+#[derive(Debug, Clone)]
+pub enum Profiler {
+    /// Samples the process with `perf record` for the duration
+    Perf,
+    /// Sends `signal` (e.g. `USR1`) to the service, which is expected to write
+    /// a profile such as a pprof dump to `dump_path` within the duration
+    Signal { signal: String, dump_path: PathBuf },
+}
+
+/// A step that captures a CPU or heap profile of a running service and
+/// writes it to `output`. Paths are relative to the root directory of the
+/// test
+#[derive(Debug, Clone)]
+pub struct CaptureProfile {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    pub profiler: Profiler,
+    pub duration: Duration,
+    pub output: PathBuf,
+}
+
+impl ServiceStepExecutor for CaptureProfile {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let pid = env
+            .service(&self.service_name)?
+            .pid()
+            .ok_or_else(|| format!("Service '{}' has no running process", self.service_name))?;
+        let output = env.resolve(&self.output);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                StepError::io(
+                    format!("Failed to create directory {}", parent.display()),
+                    e,
+                )
+            })?;
+        }
+
+        match &self.profiler {
+            Profiler::Perf => {
+                let result = Command::new("perf")
+                    .args(["record", "-g", "-p", &pid.to_string(), "-o"])
+                    .arg(&output)
+                    .args(["--", "sleep", &self.duration.as_secs_f64().to_string()])
+                    .output()
+                    .map_err(|e| StepError::io("Failed to run perf", e))?;
+                if !result.status.success() {
+                    return Err(StepError::Failed(format!(
+                        "perf record failed with {}: {}",
+                        result.status,
+                        String::from_utf8_lossy(&result.stderr).trim()
+                    )));
+                }
+            }
+            Profiler::Signal { signal, dump_path } => {
+                let dump_path = env.resolve(dump_path);
+                // A dump left over from an earlier capture must not be
+                // mistaken for the new one
+                let _ = fs::remove_file(&dump_path);
+                let status = Command::new("kill")
+                    .args(["-s", signal, &pid.to_string()])
+                    .status()
+                    .map_err(|e| StepError::io("Failed to run kill", e))?;
+                if !status.success() {
+                    return Err(StepError::Failed(format!(
+                        "Failed to send {} to service '{}'",
+                        signal, self.service_name
+                    )));
+                }
+                PollPolicy {
+                    interval: Duration::from_millis(50),
+                    timeout: self.duration,
+                    ..Default::default()
+                }
+                .poll(|| dump_path.exists().then_some(()).ok_or(()))
+                .map_err(|_| StepError::Timeout {
+                    what: format!(
+                        "Service '{}' did not write a profile to {}",
+                        self.service_name,
+                        dump_path.display()
+                    ),
+                    after: self.duration,
+                })?;
+                fs::copy(&dump_path, &output).map_err(|e| {
+                    StepError::io(
+                        format!(
+                            "Failed to copy profile {} to {}",
+                            dump_path.display(),
+                            output.display()
+                        ),
+                        e,
+                    )
+                })?;
+            }
+        }
+        info!(
+            "Captured profile of service '{}' into {}",
+            self.service_name,
+            output.display()
+        );
+        Ok(())
+    }
+
+    fn required_tools(&self) -> Vec<String> {
+        match self.profiler {
+            Profiler::Perf => vec!["perf".to_string()],
+            Profiler::Signal { .. } => vec!["kill".to_string()],
+        }
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preflight::find_executable;
+    use crate::{SubProcessService, TestHarness};
+
+    fn harness_with_service(root: &std::path::Path, script: &str) -> TestHarness {
+        let mut harness = TestHarness::new("ProfileTester", root.to_str().unwrap());
+        harness.add_service(Box::new(SubProcessService::new(
+            "Profiled",
+            "python3",
+            vec!["-c".to_string(), script.to_string()],
+        )));
+        harness.services[0]
+            .start()
+            .expect("Failed to start profiled service");
+        harness
+    }
+
+    #[test]
+    fn test_capture_profile_with_perf() {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        if find_executable("perf", &path).is_none() {
+            eprintln!("Skipping test_capture_profile_with_perf: perf is not installed");
+            return;
+        }
+        let root = tempfile::tempdir().unwrap();
+        let mut harness = harness_with_service(root.path(), "while True: pass");
+        let step = CaptureProfile {
+            name: "Profile_Service".to_string(),
+            service_name: "Profiled".to_string(),
+            profiler: Profiler::Perf,
+            duration: Duration::from_secs(1),
+            output: PathBuf::from("profiles/perf.data"),
+        };
+        let result = step.execute(&mut harness.step_env());
+        harness.services[0].stop().unwrap();
+
+        if let Err(e) = &result {
+            let e = e.to_string();
+            if e.contains("perf_event_paranoid") || e.contains("Permission") {
+                eprintln!("Skipping test_capture_profile_with_perf: {}", e);
+                return;
+            }
+        }
+        result.expect("Failed to capture perf profile");
+        assert!(root.path().join("profiles/perf.data").exists());
+    }
+
+    #[test]
+    fn test_capture_profile_with_signal() {
+        let root = tempfile::tempdir().unwrap();
+        let dump_path = root.path().join("dump.pprof");
+        let script = format!(
+            "import os, signal, time\n\
+             def dump(*_):\n    open('{0}.tmp', 'w').write('profile')\n    os.rename('{0}.tmp', \
+             '{0}')\n\
+             signal.signal(signal.SIGUSR1, dump)\n\
+             time.sleep(30)",
+            dump_path.display()
+        );
+        let mut harness = harness_with_service(root.path(), &script);
+        // Give the interpreter time to install the signal handler
+        std::thread::sleep(Duration::from_millis(500));
+        let step = CaptureProfile {
+            name: "Profile_Service".to_string(),
+            service_name: "Profiled".to_string(),
+            profiler: Profiler::Signal {
+                signal: "USR1".to_string(),
+                dump_path: PathBuf::from("dump.pprof"),
+            },
+            duration: Duration::from_secs(5),
+            output: PathBuf::from("profiles/app.pprof"),
+        };
+        let result = step.execute(&mut harness.step_env());
+        harness.services[0].stop().unwrap();
+
+        result.expect("Failed to capture signal profile");
+        assert_eq!(
+            fs::read_to_string(root.path().join("profiles/app.pprof")).unwrap(),
+            "profile"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::{TestHarness, TestStep};
+
+/// How often the progress is redrawn
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The progress of a step with its elapsed time, followed by a line per
+/// service
+fn render(label: &str, elapsed: Duration, services: &[String]) -> Vec<String> {
+    let mut lines = vec![format!("{} ({}s)…", label, elapsed.as_secs())];
+    lines.extend(services.iter().map(|service| format!("  {}", service)));
+    lines
+}
+
+/// Replaces the `drawn` lines last written to stdout with `lines`,
+/// returning how many lines are shown now
+fn redraw(drawn: usize, lines: &[String]) -> usize {
+    let mut stdout = io::stdout().lock();
+    if drawn > 0 {
+        // Move to the start of the first drawn line and clear from there
+        let _ = write!(stdout, "\x1b[{}F\x1b[J", drawn);
+    }
+    for line in lines {
+        let _ = writeln!(stdout, "{}", line);
+    }
+    let _ = stdout.flush();
+    lines.len()
+}
+
+/// Shows the running step with its elapsed time and the status of the
+/// services on the terminal until dropped, which clears it again
+pub(crate) struct Progress {
+    stop: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Progress {
+    fn start(label: String, services: Vec<String>) -> Self {
+        let (stop, stopped) = mpsc::channel();
+        let started = Instant::now();
+        let thread = std::thread::spawn(move || {
+            let mut drawn = redraw(0, &render(&label, started.elapsed(), &services));
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(REDRAW_INTERVAL) {
+                drawn = redraw(drawn, &render(&label, started.elapsed(), &services));
+            }
+            redraw(drawn, &[]);
+        });
+        Self {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl TestHarness {
+    /// Starts showing the progress of `step` at `position`, e.g. `step 3/7`,
+    /// if [`TestHarness::progress`] is enabled and stdout is a terminal. The
+    /// status of the services is that from before the step
+    pub(crate) fn show_progress(&self, position: &str, step: &TestStep) -> Option<Progress> {
+        if !self.progress || !io::stdout().is_terminal() {
+            return None;
+        }
+        let what = match step.description() {
+            "" => step.name(),
+            description => description,
+        };
+        let services = self
+            .services
+            .iter()
+            .map(|service| {
+                let status = match service.exit_status() {
+                    _ if service.is_running() => "running".to_string(),
+                    Some(status) => format!("exited with {}", status),
+                    None => "stopped".to_string(),
+                };
+                format!("{}: {}", service.name(), status)
+            })
+            .collect();
+        Some(Progress::start(format!("{}: {}", position, what), services))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_progress() {
+        let services = vec!["Api: running".to_string(), "Db: stopped".to_string()];
+        assert_eq!(
+            render(
+                "step 3/7: Waits for readiness",
+                Duration::from_millis(12_400),
+                &services
+            ),
+            vec![
+                "step 3/7: Waits for readiness (12s)…",
+                "  Api: running",
+                "  Db: stopped",
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use reqwest::Url;
+
+use crate::{GrpcHealthProbe, OutputLine};
+
+/// How long a single network probe may take to connect and respond
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A check whether a [`crate::SubProcessService`] is ready to be used, run
+/// by [`crate::SubProcessServiceStarter`] until it passes, see
+/// [`crate::SubProcessService::readiness`]
+#[derive(Debug, Clone)]
+pub enum ReadinessProbe {
+    /// A TCP connection to the port on localhost is accepted
+    TcpPort(u16),
+    /// A plain HTTP GET of `url` responds with `expect_status`
+    HttpGet { url: String, expect_status: u16 },
+    /// The command exits successfully
+    Command { program: String, args: Vec<String> },
+    /// The service printed a line matching the pattern since it was started
+    LogPattern(Regex),
+    /// The service reports `SERVING` to a gRPC health check
+    Grpc(GrpcHealthProbe),
+}
+
+impl ReadinessProbe {
+    /// Runs the probe once against the service, given its output since it
+    /// was started
+    pub(crate) fn check(&self, output: impl FnOnce() -> Vec<OutputLine>) -> Result<(), String> {
+        match self {
+            Self::TcpPort(port) => {
+                let addr = SocketAddr::from(([127, 0, 0, 1], *port));
+                TcpStream::connect_timeout(&addr, PROBE_TIMEOUT)
+                    .map(drop)
+                    .map_err(|e| format!("Failed to connect to {}: {}", addr, e))
+            }
+            Self::HttpGet { url, expect_status } => {
+                let status = http_get_status(url)?;
+                if status == *expect_status {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "GET {} responded with {}, expected {}",
+                        url, status, expect_status
+                    ))
+                }
+            }
+            Self::Command { program, args } => {
+                let status = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("{} exited with {}", program, status))
+                }
+            }
+            Self::LogPattern(pattern) => output()
+                .iter()
+                .any(|line| pattern.is_match(&line.line))
+                .then_some(())
+                .ok_or_else(|| format!("No line matching '{}' yet", pattern)),
+            Self::Grpc(probe) => probe.check(),
+        }
+    }
+}
+
+/// Status code of a plain HTTP GET of `url`, without following redirects
+fn http_get_status(url: &str) -> Result<u16, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if parsed.scheme() != "http" {
+        return Err(format!("Only http URLs can be probed, not {}", url));
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| format!("No host in {}", url))?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let addr = (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("Failed to resolve {}", host))?;
+
+    let started = Instant::now();
+    let mut stream = TcpStream::connect_timeout(&addr, PROBE_TIMEOUT)
+        .map_err(|e| format!("Failed to connect to {}: {}", addr, e))?;
+    let _ = stream.set_read_timeout(Some(PROBE_TIMEOUT));
+    let path = match parsed.query() {
+        Some(query) => format!("{}?{}", parsed.path(), query),
+        None => parsed.path().to_string(),
+    };
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    )
+    .map_err(|e| format!("Failed to request {}: {}", url, e))?;
+
+    // Only the status line is needed
+    let mut response = Vec::new();
+    let mut buf = [0; 256];
+    while !response.contains(&b'\n') && started.elapsed() < PROBE_TIMEOUT {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => response.extend_from_slice(&buf[..n]),
+            Err(e) => return Err(format!("Failed to read the response of {}: {}", url, e)),
+        }
+    }
+    String::from_utf8_lossy(&response)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| format!("Invalid response from {}", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        PollPolicy, ServiceStepExecutor, SubProcessService, SubProcessServiceStarter, TestHarness,
+    };
+
+    #[test]
+    fn test_tcp_port_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        ReadinessProbe::TcpPort(port)
+            .check(Vec::new)
+            .expect("Port is listening");
+        drop(listener);
+        ReadinessProbe::TcpPort(port)
+            .check(Vec::new)
+            .expect_err("Port is closed");
+    }
+
+    fn start_with_probe(args: &[&str], probe: ReadinessProbe) -> TestHarness {
+        let mut service = SubProcessService::new(
+            "Server",
+            "python3",
+            args.iter().map(ToString::to_string).collect(),
+        );
+        service.readiness = Some(probe);
+        service.readiness_policy = PollPolicy {
+            interval: Duration::from_millis(20),
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let mut harness = TestHarness::new("ReadinessTester", ".");
+        harness.add_service(Box::new(service));
+        let starter = SubProcessServiceStarter {
+            name: "Start_Server".to_string(),
+            description: "Starts the server".to_string(),
+            service_name: "Server".to_string(),
+            wait_after: None,
+        };
+        starter
+            .execute(&mut harness.step_env())
+            .expect("Server did not get ready");
+        harness
+    }
+
+    #[test]
+    fn test_starter_waits_for_http_probe() {
+        let mut harness = start_with_probe(
+            &[
+                "-m",
+                "http.server",
+                "{{port:server}}",
+                "--bind",
+                "127.0.0.1",
+            ],
+            ReadinessProbe::HttpGet {
+                url: "http://127.0.0.1:{{port:server}}/".to_string(),
+                expect_status: 200,
+            },
+        );
+        let port = harness.ports.tcp("server").unwrap();
+        assert_eq!(
+            http_get_status(&format!("http://127.0.0.1:{}/", port)),
+            Ok(200)
+        );
+        harness.services[0].stop().expect("Failed to stop service");
+    }
+
+    #[test]
+    fn test_starter_waits_for_log_pattern() {
+        let mut harness = start_with_probe(
+            &[
+                "-c",
+                "import time; time.sleep(0.3); print('ready', flush=True); time.sleep(30)",
+            ],
+            ReadinessProbe::LogPattern(Regex::new("^ready$").unwrap()),
+        );
+        assert!(harness.services[0].is_healthy());
+        assert_eq!(harness.services[0].output().len(), 1);
+        harness.services[0].stop().expect("Failed to stop service");
+    }
+}
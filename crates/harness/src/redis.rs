@@ -0,0 +1,484 @@
+use std::fmt::{self, Display};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use tracing::info;
+
+use crate::{
+    container_runtime_from_env, ContainerRuntime, Context, DockerService, LogFiles, OutputLine,
+    PollPolicy, PortAllocator, Service, ServiceError, ServiceStepExecutor, StepEnv, StepError,
+    SubProcessService, TestHarness,
+};
+
+/// Where a [`RedisService`] runs the server
+#[derive(Debug, Clone)]
+pub enum RedisBackend {
+    /// A container of `image`, e.g. `redis:7`
+    Container {
+        image: String,
+        runtime: Arc<dyn ContainerRuntime>,
+    },
+    /// The `redis-server` binary on the PATH
+    Local,
+}
+
+/// A Redis server on `127.0.0.1` that counts as healthy once it answers
+/// `PING`. Once started, its URL is stored in the [`Context`] under
+/// `context_key`, see [`TestHarness::add_redis`]. Steps can also reach it at
+/// `127.0.0.1:{{port:name}}`, see [`crate::PortAllocator`]
+#[derive(Debug)]
+pub struct RedisService {
+    pub name: String,
+    pub backend: RedisBackend,
+    /// Port the server listens on, reserved from the [`PortAllocator`] of
+    /// the harness if unset
+    pub port: Option<u16>,
+    /// Further arguments of `redis-server`, e.g. `--appendonly yes`
+    pub args: Vec<String>,
+    /// How [`crate::SubProcessServiceStarter`] waits for `PING`
+    pub readiness_policy: PollPolicy,
+    pub context_key: String,
+    /// Context the URL is stored in
+    pub context: Option<Context>,
+    server: Option<Box<dyn Service>>,
+}
+
+impl RedisService {
+    /// A server in a container of `image` run with the runtime selected by
+    /// [`container_runtime_from_env`]
+    pub fn container(name: &str, image: &str) -> Self {
+        Self::with_backend(name, RedisBackend::Container {
+            image: image.to_string(),
+            runtime: container_runtime_from_env(),
+        })
+    }
+
+    /// A server run from the local `redis-server`
+    pub fn local(name: &str) -> Self { Self::with_backend(name, RedisBackend::Local) }
+
+    pub fn with_backend(name: &str, backend: RedisBackend) -> Self {
+        Self {
+            name: name.to_string(),
+            backend,
+            port: None,
+            args: Vec::new(),
+            readiness_policy: PollPolicy::default(),
+            context_key: "redis_url".to_string(),
+            context: None,
+            server: None,
+        }
+    }
+
+    /// URL of the server, once its port is known
+    pub fn url(&self) -> Option<String> {
+        self.port.map(|port| format!("redis://127.0.0.1:{}", port))
+    }
+
+    /// Arguments of `redis-server` listening on `port`
+    fn server_args(&self, port: u16) -> Vec<String> {
+        let mut args = vec![
+            "--port".to_string(),
+            port.to_string(),
+            "--bind".to_string(),
+            "127.0.0.1".to_string(),
+            "--save".to_string(),
+            String::new(),
+        ];
+        args.extend(self.args.iter().cloned());
+        args
+    }
+
+    /// The service running the server process or container
+    fn server(&self, port: u16) -> Box<dyn Service> {
+        match &self.backend {
+            RedisBackend::Container { image, runtime } => {
+                let mut server = DockerService::with_runtime(&self.name, image, runtime.clone());
+                server.ports = vec![(port, 6379)];
+                // Listens on all interfaces of the container on the usual port
+                server.args = std::iter::once("redis-server".to_string())
+                    .chain(self.args.iter().cloned())
+                    .collect();
+                Box::new(server)
+            }
+            RedisBackend::Local => Box::new(SubProcessService::new(
+                &self.name,
+                "redis-server",
+                self.server_args(port),
+            )),
+        }
+    }
+}
+
+impl Service for RedisService {
+    fn name(&self) -> &str { &self.name }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        if self.is_running() {
+            return Err(ServiceError::AlreadyRunning(self.name.clone()));
+        }
+        let port = self.port.ok_or_else(|| {
+            ServiceError::failed(
+                &self.name,
+                "No port set, add the service to a harness to reserve one",
+            )
+        })?;
+        let mut server = self.server(port);
+        server.start()?;
+        self.server = Some(server);
+        if let (Some(context), Some(url)) = (&self.context, self.url()) {
+            context.insert(&self.context_key, Value::String(url));
+        }
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.server
+            .as_ref()
+            .is_some_and(|server| server.is_running())
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        match &mut self.server {
+            Some(server) => server.stop(),
+            None => Ok(()),
+        }
+    }
+
+    fn is_healthy(&mut self) -> bool {
+        self.is_running()
+            && self.port.is_some_and(|port| {
+                command(&format!("127.0.0.1:{}", port), &["PING"])
+                    .is_ok_and(|reply| reply == RedisReply::Simple("PONG".to_string()))
+            })
+    }
+
+    fn resolve_ports(&mut self, ports: &PortAllocator) -> Result<(), ServiceError> {
+        if self.port.is_none() {
+            let port = ports
+                .tcp(&self.name)
+                .map_err(|e| ServiceError::failed(&self.name, e.to_string()))?;
+            self.port = Some(port);
+        }
+        Ok(())
+    }
+
+    fn readiness_policy(&self) -> Option<PollPolicy> { Some(self.readiness_policy) }
+
+    fn pid(&self) -> Option<u32> { self.server.as_ref().and_then(|server| server.pid()) }
+
+    fn exit_status(&self) -> Option<ExitStatus> {
+        self.server.as_ref().and_then(|server| server.exit_status())
+    }
+
+    fn required_tools(&self) -> Vec<String> {
+        match &self.backend {
+            RedisBackend::Container { runtime, .. } => vec![runtime.program().to_string()],
+            RedisBackend::Local => vec!["redis-server".to_string()],
+        }
+    }
+
+    fn last_command_line(&self) -> Option<String> {
+        self.server
+            .as_ref()
+            .and_then(|server| server.last_command_line())
+    }
+
+    fn output(&self) -> Vec<OutputLine> {
+        self.server
+            .as_ref()
+            .map(|server| server.output())
+            .unwrap_or_default()
+    }
+
+    fn log_files(&self) -> Option<LogFiles> {
+        self.server.as_ref().and_then(|server| server.log_files())
+    }
+}
+
+impl TestHarness {
+    /// Adds the server, storing its URL in the context of the harness once
+    /// it started
+    pub fn add_redis(&mut self, mut redis: RedisService) {
+        redis.context = Some(self.context.clone());
+        self.add_service(Box::new(redis));
+    }
+}
+
+/// A reply of a Redis server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedisReply {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    /// A bulk string, `None` for a missing value
+    Bulk(Option<String>),
+    Array(Vec<RedisReply>),
+    Nil,
+}
+
+impl RedisReply {
+    /// The reply as JSON, e.g. to store it in the [`Context`]
+    pub fn to_json(&self) -> Value {
+        match self {
+            RedisReply::Simple(value) => Value::String(value.clone()),
+            RedisReply::Error(message) => serde_json::json!({ "error": message }),
+            RedisReply::Integer(value) => Value::from(*value),
+            RedisReply::Bulk(value) => value.clone().map_or(Value::Null, Value::String),
+            RedisReply::Array(values) => values.iter().map(RedisReply::to_json).collect(),
+            RedisReply::Nil => Value::Null,
+        }
+    }
+}
+
+impl Display for RedisReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedisReply::Simple(value) => write!(f, "{}", value),
+            RedisReply::Error(message) => write!(f, "(error) {}", message),
+            RedisReply::Integer(value) => write!(f, "(integer) {}", value),
+            RedisReply::Bulk(Some(value)) => write!(f, "{:?}", value),
+            RedisReply::Bulk(None) | RedisReply::Nil => write!(f, "(nil)"),
+            RedisReply::Array(values) => {
+                let values = values.iter().map(ToString::to_string).collect::<Vec<_>>();
+                write!(f, "[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+fn read_reply(reader: &mut impl BufRead) -> Result<RedisReply, String> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read reply: {}", e))?;
+    let line = line.trim_end_matches("\r\n");
+    let parse_len = |len: &str| {
+        len.parse::<i64>()
+            .map_err(|_| format!("Invalid length in reply '{}'", line))
+    };
+    let (kind, rest) = line.split_at_checked(1).ok_or("Connection closed")?;
+    match kind {
+        "+" => Ok(RedisReply::Simple(rest.to_string())),
+        "-" => Ok(RedisReply::Error(rest.to_string())),
+        ":" => Ok(RedisReply::Integer(parse_len(rest)?)),
+        "$" => {
+            let Ok(len) = usize::try_from(parse_len(rest)?) else {
+                return Ok(RedisReply::Bulk(None));
+            };
+            let mut value = vec![0; len + 2];
+            reader
+                .read_exact(&mut value)
+                .map_err(|e| format!("Failed to read reply: {}", e))?;
+            value.truncate(len);
+            Ok(RedisReply::Bulk(Some(
+                String::from_utf8_lossy(&value).into_owned(),
+            )))
+        }
+        "*" => {
+            let Ok(len) = usize::try_from(parse_len(rest)?) else {
+                return Ok(RedisReply::Nil);
+            };
+            (0..len)
+                .map(|_| read_reply(reader))
+                .collect::<Result<_, _>>()
+                .map(RedisReply::Array)
+        }
+        "_" => Ok(RedisReply::Nil),
+        _ => Err(format!("Unsupported reply '{}'", line)),
+    }
+}
+
+/// Sends one command to the Redis server at `address` and reads its reply
+pub(crate) fn command(address: &str, args: &[&str]) -> Result<RedisReply, String> {
+    let timeout = Some(Duration::from_secs(5));
+    let mut stream = TcpStream::connect(address)
+        .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+    stream
+        .set_read_timeout(timeout)
+        .and_then(|()| stream.set_write_timeout(timeout))
+        .map_err(|e| e.to_string())?;
+    let mut request = format!("*{}\r\n", args.len());
+    for arg in args {
+        request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("Failed to send command to {}: {}", address, e))?;
+    read_reply(&mut BufReader::new(stream))
+}
+
+/// A step sending a command to a Redis server and checking the reply, e.g.
+/// `EXISTS key` expecting `RedisReply::Integer(1)`. The address and
+/// arguments may contain `{{port:name}}` placeholders, see
+/// [`crate::PortAllocator`]
+#[derive(Debug, Clone)]
+pub struct RedisCommandStep {
+    /// Name the step is reported under
+    pub name: String,
+    /// `host:port` of the server
+    pub address: String,
+    pub command: Vec<String>,
+    /// Reply the server must send, any reply but an error passes if unset
+    pub expect: Option<RedisReply>,
+    /// Stores the reply in the [`Context`] under this key, see
+    /// [`RedisReply::to_json`]
+    pub context_key: Option<String>,
+}
+
+impl RedisCommandStep {
+    /// Sends `command` to the [`RedisService`] named `service_name`
+    pub fn new(name: &str, service_name: &str, command: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            address: format!("127.0.0.1:{{{{port:{}}}}}", service_name),
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            expect: None,
+            context_key: None,
+        }
+    }
+}
+
+impl ServiceStepExecutor for RedisCommandStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let address = env.substitute_ports(&self.address)?;
+        let args = self
+            .command
+            .iter()
+            .map(|arg| env.substitute_ports(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        let description = args.join(" ");
+        let reply = command(&address, &args)?;
+        info!("{} replied to '{}' with {}", address, description, reply);
+
+        match &self.expect {
+            Some(expected) if *expected != reply => {
+                return Err(StepError::Assertion(format!(
+                    "{} replied to '{}' with {}, expected {}",
+                    address, description, reply, expected
+                )));
+            }
+            None if matches!(reply, RedisReply::Error(_)) => {
+                return Err(StepError::Failed(format!(
+                    "{} replied to '{}' with {}",
+                    address, description, reply
+                )));
+            }
+            _ => {}
+        }
+        if let Some(key) = &self.context_key {
+            env.context.insert(key, reply.to_json());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::Docker;
+
+    /// Serves `SET`, `GET` and `EXISTS` over RESP on `port`
+    fn serve_fake_redis(port: u16) {
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        let keys = Arc::new(Mutex::new(HashMap::new()));
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let mut writer = stream.try_clone().unwrap();
+                let mut reader = BufReader::new(stream);
+                let Ok(RedisReply::Array(args)) = read_reply(&mut reader) else {
+                    continue;
+                };
+                let args = args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        RedisReply::Bulk(Some(arg)) => Some(arg.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let mut keys = keys.lock().unwrap();
+                let reply = match args.as_slice() {
+                    ["SET", key, value] => {
+                        keys.insert(key.to_string(), value.to_string());
+                        "+OK\r\n".to_string()
+                    }
+                    ["GET", key] => match keys.get(*key) {
+                        Some(value) => format!("${}\r\n{}\r\n", value.len(), value),
+                        None => "$-1\r\n".to_string(),
+                    },
+                    ["EXISTS", key] => format!(":{}\r\n", keys.contains_key(*key) as u8),
+                    _ => "-ERR unknown command\r\n".to_string(),
+                };
+                let _ = writer.write_all(reply.as_bytes());
+            }
+        });
+    }
+
+    #[test]
+    fn test_command_step_checks_reply() {
+        let mut harness = TestHarness::new("RedisTester", ".");
+        serve_fake_redis(harness.ports.tcp("Cache").unwrap());
+        let mut run = |command: &[&str], expect: Option<RedisReply>| {
+            let mut step = RedisCommandStep::new(&command.join("_"), "Cache", command);
+            step.expect = expect;
+            step.context_key = Some("reply".to_string());
+            step.execute(&mut harness.step_env())
+                .map(|()| harness.context.get("reply").unwrap())
+                .map_err(|e| e.to_string())
+        };
+
+        run(&["SET", "session", "alice"], None).expect("SET should pass");
+        assert_eq!(
+            run(&["GET", "session"], None).unwrap(),
+            Value::String("alice".to_string())
+        );
+        run(&["EXISTS", "session"], Some(RedisReply::Integer(1))).expect("Key should exist");
+        let error = run(&["EXISTS", "order"], Some(RedisReply::Integer(1))).unwrap_err();
+        assert!(
+            error.ends_with("replied to 'EXISTS order' with (integer) 0, expected (integer) 1"),
+            "{}",
+            error
+        );
+        assert_eq!(run(&["GET", "order"], None).unwrap(), Value::Null);
+        let error = run(&["FLUSHALL"], None).unwrap_err();
+        assert!(error.ends_with("(error) ERR unknown command"), "{}", error);
+    }
+
+    #[test]
+    fn test_server_args_and_url() {
+        let mut redis = RedisService::local("Cache");
+        redis.args = vec!["--appendonly".to_string(), "yes".to_string()];
+        assert_eq!(redis.url(), None);
+        redis.port = Some(16379);
+        assert_eq!(redis.url().unwrap(), "redis://127.0.0.1:16379");
+        assert_eq!(redis.server_args(16379), [
+            "--port",
+            "16379",
+            "--bind",
+            "127.0.0.1",
+            "--save",
+            "",
+            "--appendonly",
+            "yes",
+        ]);
+
+        let redis = RedisService::with_backend("Cache", RedisBackend::Container {
+            image: "redis:7".to_string(),
+            runtime: Arc::new(Docker),
+        });
+        assert_eq!(redis.required_tools(), ["docker"]);
+        let mut harness = TestHarness::new("RedisTester", ".");
+        harness.add_redis(redis);
+        assert!(harness.services[0].start().is_err(), "No port reserved yet");
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::{self, Display};
+use std::process::ExitStatus;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{LivenessFailure, LogFiles, TestStep};
+
+/// Outcome of a single step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepStatus {
+    Passed,
+    Failed(String),
+    /// An optional step failed, which does not fail the test
+    Warning(String),
+    /// The step was not executed for the given reason
+    Skipped(String),
+}
+
+/// Outcome of a step, as recorded by the harness
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    pub name: String,
+    pub description: String,
+    pub status: StepStatus,
+    /// When the step started executing, unset if it did not
+    pub started: Option<Instant>,
+    /// How long the step executed including retries, zero if it did not
+    pub duration: Duration,
+}
+
+impl StepReport {
+    /// Report of a step that was not executed
+    pub(crate) fn new(step: &TestStep, status: StepStatus) -> Self {
+        Self {
+            name: step.name().to_string(),
+            description: step.description().to_string(),
+            status,
+            started: None,
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+/// Summary of a test execution returned by [`crate::TestHarness::execute`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunReport {
+    pub test_name: String,
+    /// When the run started
+    pub started_at: SystemTime,
+    /// When the run started, the reference for the offsets in
+    /// [`RunReport::to_json`]
+    pub started: Instant,
+    /// How long the run took including setup and teardown
+    pub duration: Duration,
+    /// Names of the services of the harness, in the order they were added
+    pub services: Vec<String>,
+    pub steps: Vec<StepReport>,
+    /// Exits of crash-monitored services that were not caused by the harness
+    pub unexpected_exits: Vec<UnexpectedExit>,
+    /// Services that failed their liveness check, see
+    /// [`crate::TestHarness::monitor_liveness`]
+    pub liveness_failures: Vec<LivenessFailure>,
+    /// Services that could not be stopped while tearing down after a failure
+    pub cleanup_failures: Vec<String>,
+    /// Services started during the run with the time they were started
+    pub startup_log: Vec<(String, Instant)>,
+    /// Services awaited to become healthy with the time they did
+    pub ready_log: Vec<(String, Instant)>,
+    /// Services with the command lines they were last started with
+    pub command_lines: Vec<(String, String)>,
+    /// Services with the files their output was written to
+    pub log_files: Vec<(String, LogFiles)>,
+    /// Last lines printed by the services, see
+    /// [`crate::TestHarness::failure_log_lines`]
+    pub log_excerpts: Vec<(String, String)>,
+    /// Steps that were not selected but executed as prerequisites of a
+    /// selected step, see [`crate::TestHarness::run_only`]
+    pub auto_included: Vec<String>,
+}
+
+impl RunReport {
+    pub(crate) fn new(test_name: &str) -> Self {
+        Self {
+            test_name: test_name.to_string(),
+            started_at: SystemTime::now(),
+            started: Instant::now(),
+            duration: Duration::ZERO,
+            services: Vec::new(),
+            steps: Vec::new(),
+            unexpected_exits: Vec::new(),
+            liveness_failures: Vec::new(),
+            cleanup_failures: Vec::new(),
+            startup_log: Vec::new(),
+            ready_log: Vec::new(),
+            command_lines: Vec::new(),
+            log_files: Vec::new(),
+            log_excerpts: Vec::new(),
+            auto_included: Vec::new(),
+        }
+    }
+
+    /// Whether no required step failed and no monitored service crashed or
+    /// became unhealthy
+    pub fn passed(&self) -> bool {
+        self.unexpected_exits.is_empty()
+            && self.liveness_failures.is_empty()
+            && !self
+                .steps
+                .iter()
+                .any(|step| matches!(step.status, StepStatus::Failed(_)))
+    }
+
+    /// Causes of the failure of the run as text, empty if it passed
+    pub fn failures(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter_map(|step| match &step.status {
+                StepStatus::Failed(message) =>
+                    Some(format!("Step '{}' failed: {}", step.name, message)),
+                _ => None,
+            })
+            .chain(self.unexpected_exits.iter().map(ToString::to_string))
+            .chain(self.liveness_failures.iter().map(ToString::to_string))
+            .chain(self.cleanup_failures.iter().cloned())
+            .collect()
+    }
+
+    /// Services in the order they were started, with their start times
+    pub fn startup_order(&self) -> Vec<(String, Instant)> {
+        let mut startup_order = self.startup_log.clone();
+        startup_order.sort_by_key(|(_, started_at)| *started_at);
+        startup_order
+    }
+
+    /// Steps that were optional and failed
+    pub fn warnings(&self) -> impl Iterator<Item = &StepReport> {
+        self.steps
+            .iter()
+            .filter(|step| matches!(step.status, StepStatus::Warning(_)))
+    }
+}
+
+/// A crash-monitored service that exited while the test was running
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnexpectedExit {
+    pub service_name: String,
+    pub status: ExitStatus,
+    /// When the exit was detected, within milliseconds of the exit for
+    /// services with a [`crate::Service::exit_watch`], otherwise at the
+    /// latest right after the step following `after_step` finished
+    pub detected_at: SystemTime,
+    /// Step that finished last before the exit was detected
+    pub after_step: String,
+}
+
+impl Display for UnexpectedExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let detected_at = self
+            .detected_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(
+            f,
+            "Service '{}' exited unexpectedly with {} (detected at {:.3}s since epoch, after step \
+             '{}')",
+            self.service_name,
+            self.status,
+            detected_at.as_secs_f64(),
+            self.after_step
+        )
+    }
+}
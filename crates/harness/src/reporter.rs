@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::PathBuf;
+
+use tracing::{info, warn};
+
+use crate::{RunReport, TestHarness};
+
+/// Format of a report file written after a run, see
+/// [`TestHarness::write_report`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// A self-contained HTML page, see [`RunReport::to_html`]
+    Html,
+    /// JUnit XML, see [`RunReport::to_junit_xml`]
+    JUnit,
+    /// Pretty printed JSON, see [`RunReport::to_json`]
+    Json,
+    /// Test Anything Protocol, see [`RunReport::to_tap`]
+    Tap,
+}
+
+impl RunReport {
+    /// Renders the report in `format`
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Html => self.to_html(),
+            ReportFormat::JUnit => self.to_junit_xml(),
+            ReportFormat::Tap => self.to_tap(),
+            ReportFormat::Json =>
+                serde_json::to_string_pretty(&self.to_json()).unwrap_or_default() + "\n",
+        }
+    }
+}
+
+impl TestHarness {
+    /// Writes the report of the run in `format` to `path`, relative to the
+    /// root directory, once the run finished, also if it was aborted. A path
+    /// of `-` prints the report to stdout instead, e.g. for TAP consumers
+    pub fn write_report(&mut self, format: ReportFormat, path: impl Into<PathBuf>) {
+        self.reports.push((format, path.into()));
+    }
+
+    /// Writes the reports requested with [`TestHarness::write_report`].
+    /// Failures to write are logged, they do not change the outcome of the
+    /// run
+    pub(crate) fn write_reports(&self, report: &RunReport) {
+        for (format, path) in &self.reports {
+            if path.as_os_str() == "-" {
+                print!("{}", report.render(*format));
+                continue;
+            }
+            let path = PathBuf::from(&self.root_dir).join(path);
+            let written = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| fs::write(&path, report.render(*format)));
+            match written {
+                Ok(()) => info!("Wrote {:?} report to {}", format, path.display()),
+                Err(e) => warn!(
+                    "Failed to write {:?} report to {}: {}",
+                    format,
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ShellCommandStep, TestStep};
+
+    #[test]
+    fn test_report_written_after_run() {
+        let root = tempfile::tempdir().unwrap();
+        let mut harness = TestHarness::new("ReportTester", root.path().to_str().unwrap());
+        harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+            "Fail", "exit 3",
+        ))));
+        harness.write_report(ReportFormat::JUnit, "reports/junit.xml");
+        harness.write_report(ReportFormat::Json, "reports/run.json");
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(!report.passed());
+        let xml = fs::read_to_string(root.path().join("reports/junit.xml")).unwrap();
+        assert!(
+            xml.contains(r#"<testsuite name="ReportTester" tests="1" failures="1""#),
+            "{}",
+            xml
+        );
+        let json = fs::read_to_string(root.path().join("reports/run.json")).unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(json["steps"][0]["status"], "failed");
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt::{self, Display};
+
+use tracing::{info, warn};
+
+use crate::{HarnessError, RunReport, TestHarness};
+
+/// Outcome of [`TestHarness::execute_with_retries`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryOutcome {
+    /// The first attempt passed
+    Passed,
+    /// A retry passed after earlier attempts failed, which counts as passing
+    /// but marks the test as flaky
+    FlakyPass,
+    /// No attempt passed
+    Failed,
+}
+
+impl Display for RetryOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RetryOutcome::Passed => "passed",
+            RetryOutcome::FlakyPass => "flaky-pass",
+            RetryOutcome::Failed => "failed",
+        })
+    }
+}
+
+/// Every attempt of [`TestHarness::execute_with_retries`]. Displays as a
+/// line per attempt with the causes of failures, followed by the outcome
+#[derive(Debug)]
+pub struct RetriedRun {
+    pub test_name: String,
+    /// Outcome of every attempt, the last one passed unless all failed
+    pub attempts: Vec<Result<RunReport, HarnessError>>,
+}
+
+impl RetriedRun {
+    pub fn outcome(&self) -> RetryOutcome {
+        match self.attempts.iter().position(passed) {
+            Some(0) => RetryOutcome::Passed,
+            Some(_) => RetryOutcome::FlakyPass,
+            None => RetryOutcome::Failed,
+        }
+    }
+
+    /// Whether an attempt passed, including flaky passes
+    pub fn passed(&self) -> bool { self.outcome() != RetryOutcome::Failed }
+
+    /// Report of the last attempt, `None` if it could not run
+    pub fn last_report(&self) -> Option<&RunReport> { self.attempts.last()?.as_ref().ok() }
+}
+
+impl Display for RetriedRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, attempt) in self.attempts.iter().enumerate() {
+            match attempt {
+                Ok(report) => {
+                    let status = if report.passed() { "PASS" } else { "FAIL" };
+                    writeln!(
+                        f,
+                        "{} attempt {} ({:.3}s)",
+                        status,
+                        idx + 1,
+                        report.duration.as_secs_f64()
+                    )?;
+                    for failure in report.failures() {
+                        writeln!(f, "     {}", failure)?;
+                    }
+                }
+                Err(e) => writeln!(f, "ERROR attempt {}: {}", idx + 1, e)?,
+            }
+        }
+        write!(
+            f,
+            "\nTest {} {} after {} attempts",
+            self.test_name,
+            self.outcome(),
+            self.attempts.len()
+        )
+    }
+}
+
+fn passed(attempt: &Result<RunReport, HarnessError>) -> bool {
+    attempt.as_ref().is_ok_and(RunReport::passed)
+}
+
+impl TestHarness {
+    /// Executes the harness built by `build` and, if the run failed or could
+    /// not run, executes a freshly built one again up to `retries` times.
+    /// Every attempt gets a harness of its own as steps cannot be rerun, the
+    /// previous one is dropped first, which stops its services and tears
+    /// down its fixtures. A retry that passes makes the test a
+    /// [`RetryOutcome::FlakyPass`], so suites can quarantine flaky tests
+    /// without hiding them
+    pub fn execute_with_retries(
+        retries: u32,
+        mut build: impl FnMut() -> TestHarness,
+    ) -> RetriedRun {
+        let mut attempts = Vec::new();
+        let mut test_name = String::new();
+        let max_attempts = retries.saturating_add(1);
+        for attempt in 1..=max_attempts {
+            let harness = build();
+            test_name.clone_from(&harness.test_name);
+            if attempt > 1 {
+                info!(
+                    "Retrying test {}, attempt {}/{}",
+                    test_name, attempt, max_attempts
+                );
+            }
+            let outcome = harness.execute();
+            let done = passed(&outcome);
+            attempts.push(outcome);
+            if done {
+                break;
+            }
+            warn!("Attempt {} of test {} failed", attempt, test_name);
+        }
+        RetriedRun {
+            test_name,
+            attempts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{ShellCommandStep, TestStep};
+
+    /// A harness whose step passes only in the attempts listed in `passing`
+    fn build<'a>(calls: &'a AtomicUsize, passing: &'a [usize]) -> impl FnMut() -> TestHarness + 'a {
+        move || {
+            let attempt = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            let script = if passing.contains(&attempt) {
+                "true"
+            } else {
+                "false"
+            };
+            let mut harness = TestHarness::new("RetryTester", ".");
+            harness.failure_log_lines = 0;
+            harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+                "Check", script,
+            ))));
+            harness
+        }
+    }
+
+    #[test]
+    fn test_retry_after_failure_is_flaky_pass() {
+        let calls = AtomicUsize::new(0);
+        let run = TestHarness::execute_with_retries(3, build(&calls, &[2]));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(run.outcome(), RetryOutcome::FlakyPass);
+        assert!(run.passed() && run.last_report().is_some_and(RunReport::passed));
+        let text = run.to_string();
+        assert!(text.starts_with("FAIL attempt 1"), "{}", text);
+        assert!(text.contains("\n     Step 'Check' failed"), "{}", text);
+        assert!(text.contains("\nPASS attempt 2"), "{}", text);
+        assert!(
+            text.ends_with("Test RetryTester flaky-pass after 2 attempts"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn test_retries_exhausted_or_unneeded() {
+        let calls = AtomicUsize::new(0);
+        let run = TestHarness::execute_with_retries(2, build(&calls, &[]));
+        assert_eq!(
+            (run.attempts.len(), run.outcome()),
+            (3, RetryOutcome::Failed)
+        );
+
+        let calls = AtomicUsize::new(0);
+        let run = TestHarness::execute_with_retries(2, build(&calls, &[1]));
+        assert_eq!(
+            (run.attempts.len(), run.outcome()),
+            (1, RetryOutcome::Passed)
+        );
+    }
+}
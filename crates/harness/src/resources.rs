@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, MINIMUM_CPU_UPDATE_INTERVAL};
+use tracing::info;
+
+use crate::{ServiceAction, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that waits until the CPU usage of a service stays below a
+/// threshold for a stable window, e.g. to let startup work such as JIT warmup
+/// or index building settle before measuring
+#[derive(Debug, Clone)]
+pub struct WaitForCpuIdle {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    /// Threshold in percent of a single core
+    pub below_percent: f32,
+    /// How long usage has to stay below the threshold
+    pub stable_for: Duration,
+    pub timeout: Duration,
+}
+
+impl ServiceStepExecutor for WaitForCpuIdle {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let pid = env
+            .service(&self.service_name)?
+            .pid()
+            .ok_or_else(|| format!("Service '{}' has no running process", self.service_name))?;
+        let pid = Pid::from_u32(pid);
+        let refresh_kind = ProcessRefreshKind::nothing().with_cpu();
+        let mut system = System::new();
+        // CPU usage is computed between two refreshes, the first one only
+        // establishes the baseline
+        system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, refresh_kind);
+
+        let started = Instant::now();
+        let mut idle_since = None;
+        loop {
+            std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+            system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, refresh_kind);
+            let usage = system
+                .process(pid)
+                .map(|process| process.cpu_usage())
+                .ok_or_else(|| {
+                    format!(
+                        "Service '{}' exited while waiting for its CPU usage to settle",
+                        self.service_name
+                    )
+                })?;
+
+            if usage < self.below_percent {
+                let idle_since = *idle_since.get_or_insert_with(Instant::now);
+                if idle_since.elapsed() >= self.stable_for {
+                    info!(
+                        "Service '{}' CPU usage settled at {:.1}% after {:?}",
+                        self.service_name,
+                        usage,
+                        started.elapsed()
+                    );
+                    return Ok(());
+                }
+            } else {
+                idle_since = None;
+            }
+
+            if started.elapsed() >= self.timeout {
+                return Err(StepError::Assertion(format!(
+                    "Service '{}' CPU usage did not settle below {}% within {:?}, last observed \
+                     {:.1}%",
+                    self.service_name, self.below_percent, self.timeout, usage
+                )));
+            }
+        }
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SubProcessService, TestHarness};
+
+    #[test]
+    fn test_wait_for_cpu_idle_after_busy_startup() {
+        let mut harness = TestHarness::new("CpuIdleTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Busy_Then_Idle", "python3", vec![
+                "-c".to_string(),
+                "import time\nend = time.time() + 1.5\nwhile time.time() < end: pass\ntime.sleep(30)"
+                    .to_string(),
+            ])));
+        harness.services[0]
+            .start()
+            .expect("Failed to start busy service");
+
+        let step = WaitForCpuIdle {
+            name: "Wait_For_Idle".to_string(),
+            service_name: "Busy_Then_Idle".to_string(),
+            below_percent: 10.0,
+            stable_for: Duration::from_millis(500),
+            timeout: Duration::from_secs(15),
+        };
+        let started = Instant::now();
+        let result = step.execute(&mut harness.step_env());
+        let elapsed = started.elapsed();
+        harness.services[0]
+            .stop()
+            .expect("Failed to stop busy service");
+
+        result.expect("CPU usage should settle once the service idles");
+        assert!(
+            elapsed >= Duration::from_secs(1),
+            "Step unblocked while the service was still busy: {:?}",
+            elapsed
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+/// How often a failing step is attempted before it is marked failed, see
+/// [`crate::StepOptions::retry`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total including the first one, a step is not retried if
+    /// this is 1
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retries `max_attempts - 1` times with exponential backoff starting at
+    /// `backoff`
+    pub fn exponential(max_attempts: u32, backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+        }
+    }
+
+    /// Delay before the given retry, counting from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.backoff.saturating_mul(factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_per_retry() {
+        let policy = RetryPolicy::exponential(4, Duration::from_millis(100));
+        let delays = (1..policy.max_attempts)
+            .map(|retry| policy.delay(retry))
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            Duration::from_millis(400),
+        ]);
+    }
+}
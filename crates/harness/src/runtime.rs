@@ -0,0 +1,61 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tokio::runtime::{Handle, Runtime};
+
+use crate::StepError;
+
+/// Runtime shared by everything a harness drives asynchronously, async steps
+/// and services alike. It is
+/// either provided with [`crate::TestHarness::execute_on`] or created on
+/// first use and then kept for the rest of the run, so tasks spawned by one
+/// call, e.g. a server started by an async service, outlive it
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HarnessRuntime {
+    inner: Arc<Mutex<Option<RuntimeKind>>>,
+}
+
+#[derive(Debug)]
+enum RuntimeKind {
+    Provided(Handle),
+    Owned(Runtime),
+}
+
+impl RuntimeKind {
+    fn handle(&self) -> &Handle {
+        match self {
+            Self::Provided(handle) => handle,
+            Self::Owned(runtime) => runtime.handle(),
+        }
+    }
+}
+
+impl HarnessRuntime {
+    /// Drives async work on `handle` instead of a runtime of its own
+    pub(crate) fn provide(&self, handle: Handle) {
+        *self.lock() = Some(RuntimeKind::Provided(handle));
+    }
+
+    pub(crate) fn handle(&self) -> Result<Handle, StepError> {
+        let mut runtime = self.lock();
+        if let Some(kind) = runtime.as_ref() {
+            return Ok(kind.handle().clone());
+        }
+        let owned = Runtime::new().map_err(|e| StepError::io("Failed to create runtime", e))?;
+        let handle = owned.handle().clone();
+        *runtime = Some(RuntimeKind::Owned(owned));
+        Ok(handle)
+    }
+
+    /// Drives a future to completion, must not be called from within an
+    /// async context
+    pub(crate) fn block_on<F: Future>(&self, future: F) -> Result<F::Output, StepError> {
+        Ok(self.handle()?.block_on(future))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<RuntimeKind>> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,90 @@
+use std::process::{Command, Stdio};
+
+/// Linux namespaces a [`crate::SubProcessService`] is launched in, using
+/// `unshare`. Creating namespaces requires `CAP_SYS_ADMIN` unless `user` is
+/// set, see [`Sandbox::is_available`]. With a new PID namespace the process
+/// of the service is `unshare`, which kills the sandboxed command when it
+/// exits
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sandbox {
+    /// New PID namespace in which the command is PID 1, with its own `/proc`
+    pub pid: bool,
+    /// New network namespace with only a loopback interface, which is down
+    pub network: bool,
+    /// New mount namespace, so mounts do not leak to the host
+    pub mount: bool,
+    /// New user namespace mapping the current user to root, allows creating
+    /// the other namespaces without privileges where the kernel permits it
+    pub user: bool,
+}
+
+impl Sandbox {
+    /// Arguments passed to `unshare` before the command
+    pub(crate) fn unshare_args(&self) -> Vec<&'static str> {
+        let mut args = Vec::new();
+        if self.user {
+            args.extend(["--user", "--map-root-user"]);
+        }
+        if self.pid {
+            args.extend(["--pid", "--fork", "--kill-child", "--mount-proc"]);
+        }
+        if self.network {
+            args.push("--net");
+        }
+        if self.mount {
+            args.push("--mount");
+        }
+        args
+    }
+
+    /// Whether the namespaces can be created by this process, checked by
+    /// launching `true` in them
+    pub fn is_available(&self) -> bool {
+        Command::new("unshare")
+            .args(self.unshare_args())
+            .args(["--", "true"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{PollPolicy, Service, SubProcessService};
+
+    #[test]
+    fn test_process_is_pid_1_in_new_pid_namespace() {
+        let sandbox = Sandbox {
+            pid: true,
+            ..Default::default()
+        };
+        if !sandbox.is_available() {
+            eprintln!("Skipping, PID namespaces are not available");
+            return;
+        }
+        let mut service = SubProcessService::new("Sandboxed", "sh", vec![
+            "-c".to_string(),
+            "echo $$".to_string(),
+        ]);
+        service.sandbox = Some(sandbox);
+        service.start().expect("Failed to start sandboxed service");
+
+        let output = PollPolicy {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+            ..Default::default()
+        }
+        .poll(|| service.output().pop().ok_or(()))
+        .expect("Sandboxed service printed nothing");
+        service.stop().expect("Failed to stop sandboxed service");
+        assert_eq!(output.line, "1");
+        assert!(service
+            .last_command_line()
+            .is_some_and(|command_line| command_line.contains("--pid")));
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{HashMap, HashSet};
+
+use tracing::info;
+
+use crate::graph::dependency_order;
+use crate::{HarnessError, RunReport, TestHarness};
+
+impl TestHarness {
+    /// Executes only the named steps together with their prerequisites (see
+    /// [`crate::StepOptions::prerequisites`]), recording all other steps as
+    /// skipped. Prerequisites that were not named are listed in
+    /// [`RunReport::auto_included`]
+    pub fn run_only(mut self, step_names: &[String]) -> Result<RunReport, HarnessError> {
+        self.filter = Some(step_names.to_vec());
+        self.run(None)
+    }
+
+    /// Names of the steps selected by `step_names` including their
+    /// prerequisites, and the prerequisites that were not named in step order
+    pub(crate) fn select(
+        &self,
+        step_names: &[String],
+    ) -> Result<(HashSet<String>, Vec<String>), HarnessError> {
+        let known = self
+            .steps
+            .iter()
+            .map(|planned| planned.step.name())
+            .collect::<HashSet<_>>();
+        let mut selection = HashSet::new();
+        let mut pending = step_names.to_vec();
+        while let Some(name) = pending.pop() {
+            if !known.contains(name.as_str()) {
+                return Err(HarnessError::UnknownStep(name));
+            }
+            if !selection.insert(name.clone()) {
+                continue;
+            }
+            for planned in &self.steps {
+                if planned.step.name() == name {
+                    pending.extend(planned.options.prerequisites.iter().cloned());
+                    pending.extend(planned.options.depends_on.iter().cloned());
+                }
+            }
+        }
+
+        let mut auto_included = Vec::new();
+        for planned in &self.steps {
+            let name = planned.step.name();
+            if selection.contains(name)
+                && !step_names.iter().any(|selected| selected == name)
+                && !auto_included.iter().any(|included| included == name)
+            {
+                info!("Including step '{}' as a prerequisite", name);
+                auto_included.push(name.to_string());
+            }
+        }
+
+        Ok((selection, auto_included))
+    }
+
+    /// Positions of the steps in execution order, where every step comes
+    /// after the steps it depends on (see [`crate::StepOptions::depends_on`])
+    /// and the order they were added in is kept otherwise
+    pub(crate) fn step_order(&self) -> Result<Vec<usize>, String> {
+        let names = self
+            .steps
+            .iter()
+            .map(|planned| planned.step.name().to_string())
+            .collect::<Vec<_>>();
+        let mut dependencies = HashMap::<String, Vec<String>>::new();
+        for planned in &self.steps {
+            dependencies
+                .entry(planned.step.name().to_string())
+                .or_default()
+                .extend(planned.options.depends_on.iter().cloned());
+        }
+        dependency_order(&names, &dependencies, "step")
+    }
+
+    /// Sorts the steps into execution order, see [`TestHarness::step_order`]
+    pub(crate) fn order_steps(&mut self) -> Result<(), HarnessError> {
+        let order = self
+            .step_order()
+            .map_err(|problem| HarnessError::InvalidPlan(vec![problem]))?;
+        let mut steps = std::mem::take(&mut self.steps)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        self.steps = order
+            .into_iter()
+            .filter_map(|idx| steps[idx].take())
+            .collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        AsyncFnStep, ServiceStepExecutor, StepEnv, StepError, StepOptions, StepStatus,
+        SubProcessService, SubProcessServiceStarter, TestStep,
+    };
+
+    #[derive(Debug)]
+    struct AssertSleeperRunning;
+
+    impl ServiceStepExecutor for AssertSleeperRunning {
+        fn name(&self) -> &str { "AssertSleeperRunning" }
+
+        fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+            let running = env.service("Sleeper")?.is_running();
+            let result = running
+                .then_some(())
+                .ok_or_else(|| StepError::Assertion("Sleeper is not running".to_string()));
+            env.service("Sleeper")?.stop()?;
+            result
+        }
+    }
+
+    #[test]
+    fn test_run_only_includes_prerequisites() {
+        let mut harness = TestHarness::new("RunOnlyTester", ".");
+        harness.add_service(Box::new(SubProcessService::new("Sleeper", "sleep", vec![
+            "30".to_string(),
+        ])));
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Sleeper".to_string(),
+            description: "Starts the sleeper".to_string(),
+            service_name: "Sleeper".to_string(),
+            wait_after: Some(Duration::from_millis(10)),
+        })));
+        harness.add_step(TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: "Unrelated".to_string(),
+            description: "Fails if it is executed".to_string(),
+            futurefn: Box::new(|_| Box::new(async { Err("should not run".to_string()) })),
+        })));
+        harness.add_step_with(
+            TestStep::Service(Box::new(AssertSleeperRunning)),
+            StepOptions {
+                prerequisites: vec!["Sleeper".to_string()],
+                ..Default::default()
+            },
+        );
+
+        let report = harness
+            .run_only(&["AssertSleeperRunning".to_string()])
+            .expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(report.auto_included, vec!["Sleeper".to_string()]);
+        assert_eq!(report.steps[0].status, StepStatus::Passed);
+        assert_eq!(
+            report.steps[1].status,
+            StepStatus::Skipped("not selected".to_string())
+        );
+        assert_eq!(report.steps[2].status, StepStatus::Passed);
+    }
+
+    fn probe(name: &str, result: Result<Value, String>) -> TestStep {
+        TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: name.to_string(),
+            description: format!("Probes {}", name),
+            futurefn: Box::new(move |_| {
+                let result = result.clone();
+                Box::new(async move { result })
+            }),
+        }))
+    }
+
+    fn depending_on(names: &[&str]) -> StepOptions {
+        StepOptions {
+            depends_on: names.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_steps_run_after_their_dependencies() {
+        let mut harness = TestHarness::new("DependsOnTester", ".");
+        harness.add_step_with(
+            probe("Teardown", Ok(Value::Null)),
+            depending_on(&["A", "B"]),
+        );
+        harness.add_step_with(probe("Verify", Ok(Value::Null)), depending_on(&["A", "C"]));
+        harness.add_step(probe("A", Ok(Value::Null)));
+        harness.add_step(probe("B", Ok(Value::Null)));
+        harness.add_step_with(probe("C", Err("unreachable".to_string())), StepOptions {
+            required: false,
+            ..Default::default()
+        });
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        let names = report
+            .steps
+            .iter()
+            .map(|step| step.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["A", "B", "Teardown", "C", "Verify"]);
+        assert_eq!(report.steps[2].status, StepStatus::Passed);
+        assert_eq!(
+            report.steps[4].status,
+            StepStatus::Skipped("dependency 'C' did not pass".to_string())
+        );
+    }
+
+    #[test]
+    fn test_dependency_cycle_fails_before_running() {
+        let mut harness = TestHarness::new("DependsOnTester", ".");
+        harness.add_step_with(probe("A", Ok(Value::Null)), depending_on(&["C"]));
+        harness.add_step_with(probe("B", Ok(Value::Null)), depending_on(&["A"]));
+        harness.add_step_with(probe("C", Ok(Value::Null)), depending_on(&["B"]));
+
+        let error = harness.execute().unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Invalid test plan: Dependency cycle between steps 'A' -> 'C' -> 'B' -> 'A'"
+        );
+    }
+}
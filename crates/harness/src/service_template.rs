@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+use crate::{ServiceError, SubProcessService};
+
+static PARAMETER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{([A-Za-z0-9_]+)\}\}").expect("Invalid pattern"));
+
+/// Definition of a [`SubProcessService`] with `{{param}}` placeholders in its
+/// name, command, arguments, environment and working directory, e.g. to
+/// start several instances of a node that differ only in their index.
+/// Placeholders of reserved ports such as `{{port:node-{{index}}}}` are left
+/// to the [`crate::PortAllocator`] once the parameters are filled in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceTemplate {
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub cwd: Option<String>,
+}
+
+impl ServiceTemplate {
+    pub fn new(name: &str, command: &str, args: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            args,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a service with the placeholders replaced by `params`, failing
+    /// if a placeholder has no parameter
+    pub fn instantiate(&self, params: &[(&str, &str)]) -> Result<SubProcessService, ServiceError> {
+        let params = params.iter().copied().collect::<HashMap<_, _>>();
+        let fill = |text: &str| {
+            let mut missing = None;
+            let filled = PARAMETER.replace_all(text, |captures: &Captures<'_>| {
+                let param = &captures[1];
+                params.get(param).map_or_else(
+                    || {
+                        missing.get_or_insert_with(|| param.to_string());
+                        String::new()
+                    },
+                    ToString::to_string,
+                )
+            });
+            match missing {
+                Some(param) => Err(ServiceError::failed(
+                    &self.name,
+                    format!("No value for parameter '{}' in '{}'", param, text),
+                )),
+                None => Ok(filled.into_owned()),
+            }
+        };
+
+        let args = self
+            .args
+            .iter()
+            .map(|arg| fill(arg))
+            .collect::<Result<_, _>>()?;
+        let mut service = SubProcessService::new(&fill(&self.name)?, &fill(&self.command)?, args);
+        for (key, value) in &self.env {
+            service.env.insert(fill(key)?, fill(value)?);
+        }
+        service.cwd = self
+            .cwd
+            .as_deref()
+            .map(fill)
+            .transpose()?
+            .map(PathBuf::from);
+        Ok(service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instantiate_nodes() {
+        let mut template = ServiceTemplate::new("node-{{index}}", "node", vec![
+            "--id={{index}}".to_string(),
+            "--listen=127.0.0.1:{{port:node-{{index}}}}".to_string(),
+        ]);
+        template
+            .env
+            .insert("DATA_DIR".to_string(), "data/{{index}}".to_string());
+
+        let node = template.instantiate(&[("index", "2")]).unwrap();
+        assert_eq!(node.name, "node-2");
+        assert_eq!(node.args, ["--id=2", "--listen=127.0.0.1:{{port:node-2}}"]);
+        assert_eq!(node.env["DATA_DIR"], "data/2");
+
+        let error = template.instantiate(&[]).unwrap_err().to_string();
+        assert!(
+            error.contains("No value for parameter 'index'"),
+            "{}",
+            error
+        );
+    }
+}
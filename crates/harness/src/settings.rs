@@ -0,0 +1,326 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::Duration;
+use std::{env, fs};
+
+use serde_json::Value as JsonValue;
+use toml::{Table, Value};
+
+use crate::config::ConfigError;
+use crate::TestHarness;
+
+/// Environment variable selecting the profile applied by
+/// [`TestHarness::from_config`]
+pub const PROFILE_VAR: &str = "HARNESS_PROFILE";
+
+/// Defaults for harnesses, as read from a section of a [`SuiteConfig`].
+/// Unset values leave those of the harness as they are
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HarnessSettings {
+    /// Root directory of the tests, relative to the configuration file
+    pub root_dir: Option<String>,
+    pub heartbeat: Option<Duration>,
+    pub stop_retries: Option<u32>,
+    pub stop_retry_delay: Option<Duration>,
+    pub failure_log_lines: Option<usize>,
+    pub keep_alive: Option<bool>,
+    pub progress: Option<bool>,
+    /// Fixed ports by name, substituted for `{{port:name}}` placeholders
+    /// instead of reserving free ones
+    pub ports: BTreeMap<String, u16>,
+    /// Values stored in the [`crate::Context`] and substituted for
+    /// `{{config:name}}` placeholders, e.g. paths that differ per machine
+    pub values: BTreeMap<String, String>,
+}
+
+impl HarnessSettings {
+    /// The settings with those set in `overrides` replacing them
+    pub fn merge(mut self, overrides: &Self) -> Self {
+        self.root_dir = overrides.root_dir.clone().or(self.root_dir);
+        self.heartbeat = overrides.heartbeat.or(self.heartbeat);
+        self.stop_retries = overrides.stop_retries.or(self.stop_retries);
+        self.stop_retry_delay = overrides.stop_retry_delay.or(self.stop_retry_delay);
+        self.failure_log_lines = overrides.failure_log_lines.or(self.failure_log_lines);
+        self.keep_alive = overrides.keep_alive.or(self.keep_alive);
+        self.progress = overrides.progress.or(self.progress);
+        self.ports.extend(overrides.ports.clone());
+        self.values.extend(overrides.values.clone());
+        self
+    }
+
+    fn parse(table: &Table, at: &str) -> Result<Self, ConfigError> {
+        let invalid = |key: &str, message: &str| ConfigError::Invalid {
+            at: if at.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", at, key)
+            },
+            message: message.to_string(),
+        };
+        let secs = |key: &str, value: &Value| {
+            value
+                .as_float()
+                .or_else(|| value.as_integer().map(|secs| secs as f64))
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                .ok_or_else(|| invalid(key, "expected seconds"))
+        };
+        let count = |key: &str, value: &Value| {
+            value
+                .as_integer()
+                .and_then(|count| u32::try_from(count).ok())
+                .ok_or_else(|| invalid(key, "expected a count"))
+        };
+        let flag = |key: &str, value: &Value| {
+            value
+                .as_bool()
+                .ok_or_else(|| invalid(key, "expected true or false"))
+        };
+
+        let mut settings = Self::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "root_dir" =>
+                    settings.root_dir = Some(
+                        value
+                            .as_str()
+                            .ok_or_else(|| invalid(key, "expected a path"))?
+                            .to_string(),
+                    ),
+                "heartbeat" => settings.heartbeat = Some(secs(key, value)?),
+                "stop_retries" => settings.stop_retries = Some(count(key, value)?),
+                "stop_retry_delay" => settings.stop_retry_delay = Some(secs(key, value)?),
+                "failure_log_lines" =>
+                    settings.failure_log_lines = Some(count(key, value)? as usize),
+                "keep_alive" => settings.keep_alive = Some(flag(key, value)?),
+                "progress" => settings.progress = Some(flag(key, value)?),
+                "ports" => {
+                    let ports = value
+                        .as_table()
+                        .ok_or_else(|| invalid(key, "expected a table"))?;
+                    for (name, port) in ports {
+                        let port = port
+                            .as_integer()
+                            .and_then(|port| u16::try_from(port).ok())
+                            .ok_or_else(|| {
+                                invalid(&format!("ports.{}", name), "expected a port")
+                            })?;
+                        settings.ports.insert(name.clone(), port);
+                    }
+                }
+                "values" => {
+                    let values = value
+                        .as_table()
+                        .ok_or_else(|| invalid(key, "expected a table"))?;
+                    for (name, value) in values {
+                        let value = match value {
+                            Value::String(text) => text.clone(),
+                            Value::Integer(_) | Value::Float(_) | Value::Boolean(_) =>
+                                value.to_string(),
+                            _ =>
+                                return Err(invalid(
+                                    &format!("values.{}", name),
+                                    "expected a string, number or boolean",
+                                )),
+                        };
+                        settings.values.insert(name.clone(), value);
+                    }
+                }
+                _ => return Err(invalid(key, "unknown setting")),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// Settings shared by the tests of a suite, read from a `harness.toml`:
+/// top-level defaults and named profiles overriding them, e.g. for running
+/// locally and in CI:
+///
+/// ```toml
+/// root_dir = "tests/e2e"
+/// heartbeat = 30
+/// failure_log_lines = 50
+///
+/// [ports]
+/// api = 8080
+///
+/// [profiles.ci]
+/// root_dir = "/workspace/e2e"
+/// stop_retries = 2
+/// failure_log_lines = 500
+/// values = { data_dir = "/cache/data" }
+/// ```
+///
+/// Durations are in seconds, see [`HarnessSettings`] for all settings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SuiteConfig {
+    pub defaults: HarnessSettings,
+    pub profiles: HashMap<String, HarnessSettings>,
+}
+
+impl SuiteConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut table = text.parse::<Table>()?;
+        let mut profiles = HashMap::new();
+        if let Some(value) = table.remove("profiles") {
+            let Value::Table(sections) = value else {
+                return Err(ConfigError::Invalid {
+                    at: "profiles".to_string(),
+                    message: "expected a table".to_string(),
+                });
+            };
+            for (name, section) in sections {
+                let at = format!("profiles.{}", name);
+                let Value::Table(section) = section else {
+                    return Err(ConfigError::Invalid {
+                        at,
+                        message: "expected a table".to_string(),
+                    });
+                };
+                profiles.insert(name, HarnessSettings::parse(&section, &at)?);
+            }
+        }
+        Ok(Self {
+            defaults: HarnessSettings::parse(&table, "")?,
+            profiles,
+        })
+    }
+
+    /// The defaults with `profile` applied, if any
+    pub fn settings(&self, profile: Option<&str>) -> Result<HarnessSettings, ConfigError> {
+        let Some(profile) = profile else {
+            return Ok(self.defaults.clone());
+        };
+        let overrides = self.profiles.get(profile).ok_or_else(|| {
+            let mut known = self.profiles.keys().cloned().collect::<Vec<_>>();
+            known.sort();
+            ConfigError::Invalid {
+                at: "profiles".to_string(),
+                message: format!(
+                    "unknown profile '{}', expected one of: {}",
+                    profile,
+                    known.join(", ")
+                ),
+            }
+        })?;
+        Ok(self.defaults.clone().merge(overrides))
+    }
+}
+
+impl TestHarness {
+    /// Creates a harness with the settings of the suite configuration at
+    /// `path`, applying the profile named by `HARNESS_PROFILE` if set. The
+    /// root directory is resolved against the directory of the file
+    pub fn from_config(test_name: &str, path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let profile = env::var(PROFILE_VAR)
+            .ok()
+            .filter(|profile| !profile.is_empty());
+        let settings = SuiteConfig::load(path)?.settings(profile.as_deref())?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let root_dir = dir.join(settings.root_dir.as_deref().unwrap_or("."));
+        let mut harness = Self::new(test_name, &root_dir.to_string_lossy());
+        harness.apply_settings(&settings);
+        Ok(harness)
+    }
+
+    /// Applies the settings except for the root directory, which is fixed
+    /// when the harness is created
+    pub fn apply_settings(&mut self, settings: &HarnessSettings) {
+        self.heartbeat = settings.heartbeat.or(self.heartbeat);
+        self.stop_retries = settings.stop_retries.unwrap_or(self.stop_retries);
+        self.stop_retry_delay = settings.stop_retry_delay.unwrap_or(self.stop_retry_delay);
+        self.failure_log_lines = settings.failure_log_lines.unwrap_or(self.failure_log_lines);
+        self.keep_alive = settings.keep_alive.unwrap_or(self.keep_alive);
+        self.progress = settings.progress.unwrap_or(self.progress);
+        for (name, port) in &settings.ports {
+            self.ports.assign(name, *port);
+        }
+        for (name, value) in &settings.values {
+            self.context.insert(name, JsonValue::from(value.as_str()));
+            self.ports.define("config", name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+root_dir = "e2e"
+heartbeat = 30
+failure_log_lines = 50
+
+[ports]
+api = 18211
+
+[values]
+data_dir = "/tmp/data"
+
+[profiles.ci]
+root_dir = "/workspace/e2e"
+stop_retry_delay = 0.25
+failure_log_lines = 500
+ports = { api = 18212 }
+"#;
+
+    #[test]
+    fn test_profile_overrides_defaults() {
+        let config = SuiteConfig::parse(CONFIG).expect("Failed to parse configuration");
+        let local = config.settings(None).unwrap();
+        let ci = config.settings(Some("ci")).unwrap();
+        assert_eq!(local.root_dir.as_deref(), Some("e2e"));
+        assert_eq!(ci.root_dir.as_deref(), Some("/workspace/e2e"));
+        assert_eq!(ci.heartbeat, Some(Duration::from_secs(30)));
+        assert_eq!(ci.failure_log_lines, Some(500));
+        assert_eq!(ci.ports["api"], 18212);
+        assert_eq!(ci.values["data_dir"], "/tmp/data");
+        assert_eq!(
+            config.settings(Some("nightly")).unwrap_err().to_string(),
+            "Invalid configuration at profiles: unknown profile 'nightly', expected one of: ci"
+        );
+        assert_eq!(
+            SuiteConfig::parse("[profiles.ci]\nheartbeat = \"soon\"")
+                .unwrap_err()
+                .to_string(),
+            "Invalid configuration at profiles.ci.heartbeat: expected seconds"
+        );
+
+        let mut harness = TestHarness::new("SettingsTester", ".");
+        harness.apply_settings(&ci);
+        assert_eq!(harness.stop_retry_delay, Duration::from_millis(250));
+        assert_eq!(harness.failure_log_lines, 500);
+        assert_eq!(
+            harness
+                .ports
+                .substitute("http://localhost:{{port:api}}{{config:data_dir}}")
+                .unwrap(),
+            "http://localhost:18212/tmp/data"
+        );
+        assert_eq!(
+            harness.context.get("data_dir"),
+            Some(JsonValue::from("/tmp/data"))
+        );
+    }
+
+    #[test]
+    fn test_from_config_resolves_root_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("harness.toml");
+        fs::write(&path, "root_dir = \"e2e\"\nkeep_alive = true").unwrap();
+
+        let harness = TestHarness::from_config("SettingsTester", &path).unwrap();
+        assert_eq!(harness.root_dir, dir.path().join("e2e").to_string_lossy());
+        assert!(harness.keep_alive);
+    }
+}
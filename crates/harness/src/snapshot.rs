@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
+use serde_json::Value;
+use tracing::info;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+/// Environment variable that makes [`SnapshotStep`] write snapshots instead
+/// of comparing against them when set to anything but `0`
+pub const UPDATE_SNAPSHOTS_VAR: &str = "HARNESS_UPDATE_SNAPSHOTS";
+
+/// What a [`SnapshotStep`] compares
+#[derive(Debug, Clone)]
+pub enum SnapshotSource {
+    /// A value in the [`crate::Context`], e.g. a response body or command
+    /// output stored by an earlier step. Strings are compared as they are,
+    /// other values as pretty printed JSON
+    Context(String),
+    /// A file, relative to the root directory of the test
+    File(PathBuf),
+}
+
+/// A step comparing output against a golden file at
+/// `{root_dir}/snapshots/{name}.snap`. In update mode, see
+/// [`UPDATE_SNAPSHOTS_VAR`], the golden file is written instead
+#[derive(Debug, Clone)]
+pub struct SnapshotStep {
+    pub name: String,
+    pub source: SnapshotSource,
+    pub update: bool,
+}
+
+impl SnapshotStep {
+    /// A snapshot in update mode if [`UPDATE_SNAPSHOTS_VAR`] is set
+    pub fn new(name: &str, source: SnapshotSource) -> Self {
+        Self {
+            name: name.to_string(),
+            source,
+            update: env::var(UPDATE_SNAPSHOTS_VAR).is_ok_and(|value| value != "0"),
+        }
+    }
+}
+
+/// Lines differing between the snapshot and the actual output, prefixed
+/// with their line number
+fn line_diff(expected: &str, actual: &str) -> Vec<String> {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    let mut diff = Vec::new();
+    for idx in 0..expected.len().max(actual.len()) {
+        let (expected, actual) = (expected.get(idx), actual.get(idx));
+        if expected == actual {
+            continue;
+        }
+        if let Some(line) = expected {
+            diff.push(format!("{:>4} - {}", idx + 1, line));
+        }
+        if let Some(line) = actual {
+            diff.push(format!("{:>4} + {}", idx + 1, line));
+        }
+    }
+    diff
+}
+
+impl ServiceStepExecutor for SnapshotStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let actual = match &self.source {
+            SnapshotSource::Context(key) => match env.context.get(key) {
+                Some(Value::String(text)) => text,
+                Some(value) => serde_json::to_string_pretty(&value).unwrap_or_default() + "\n",
+                None =>
+                    return Err(StepError::Assertion(format!(
+                        "No context value stored under '{}'",
+                        key
+                    ))),
+            },
+            SnapshotSource::File(path) => {
+                let path = env.resolve(path);
+                fs::read_to_string(&path)
+                    .map_err(|e| StepError::io(format!("Failed to read {}", path.display()), e))?
+            }
+        };
+
+        let snapshot = env.resolve("snapshots").join(format!("{}.snap", self.name));
+        if self.update {
+            fs::create_dir_all(env.resolve("snapshots"))
+                .and_then(|()| fs::write(&snapshot, &actual))
+                .map_err(|e| StepError::io(format!("Failed to write {}", snapshot.display()), e))?;
+            info!("Updated snapshot {}", snapshot.display());
+            return Ok(());
+        }
+        let expected = match fs::read_to_string(&snapshot) {
+            Ok(expected) => expected,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(StepError::Assertion(format!(
+                    "No snapshot at {}, run with {}=1 to create it",
+                    snapshot.display(),
+                    UPDATE_SNAPSHOTS_VAR
+                )));
+            }
+            Err(e) => {
+                return Err(StepError::io(
+                    format!("Failed to read {}", snapshot.display()),
+                    e,
+                ));
+            }
+        };
+        if expected != actual {
+            let mut diff = line_diff(&expected, &actual);
+            if diff.is_empty() {
+                diff.push("     (line endings differ)".to_string());
+            }
+            return Err(StepError::Assertion(format!(
+                "Output differs from snapshot {}, run with {}=1 to update it:\n{}",
+                snapshot.display(),
+                UPDATE_SNAPSHOTS_VAR,
+                diff.join("\n")
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::TestHarness;
+
+    #[test]
+    fn test_snapshot_update_and_compare() {
+        let root = tempfile::tempdir().unwrap();
+        let mut harness = TestHarness::new("SnapshotTester", root.path().to_str().unwrap());
+        harness
+            .context
+            .insert("order", json!({ "id": 1, "status": "paid" }));
+        let mut step = SnapshotStep::new("order", SnapshotSource::Context("order".to_string()));
+        step.update = false;
+
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("run with HARNESS_UPDATE_SNAPSHOTS=1 to create it"),
+            "{}",
+            error
+        );
+        step.update = true;
+        step.execute(&mut harness.step_env())
+            .expect("Failed to write snapshot");
+        assert_eq!(
+            fs::read_to_string(root.path().join("snapshots/order.snap")).unwrap(),
+            "{\n  \"id\": 1,\n  \"status\": \"paid\"\n}\n"
+        );
+
+        step.update = false;
+        step.execute(&mut harness.step_env())
+            .expect("Output matches snapshot");
+        harness
+            .context
+            .insert("order", json!({ "id": 1, "status": "refunded" }));
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.ends_with("   3 -   \"status\": \"paid\"\n   3 +   \"status\": \"refunded\""),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_file_snapshot() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("snapshots")).unwrap();
+        fs::write(
+            root.path().join("snapshots/export.snap"),
+            "id,name\n1,alice\n",
+        )
+        .unwrap();
+        fs::write(root.path().join("export.csv"), "id,name\n1,alice\n").unwrap();
+        let mut harness = TestHarness::new("SnapshotTester", root.path().to_str().unwrap());
+        let mut step = SnapshotStep::new("export", SnapshotSource::File("export.csv".into()));
+        step.update = false;
+        step.execute(&mut harness.step_env())
+            .expect("File matches snapshot");
+    }
+}
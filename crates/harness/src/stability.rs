@@ -0,0 +1,321 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{HarnessError, RunReport, StepStatus, TestHarness};
+
+/// Outcomes and timings of a step over the iterations of
+/// [`TestHarness::check_stability`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepStability {
+    pub name: String,
+    pub passed: usize,
+    /// Iterations the step failed in, including failures of optional steps
+    pub failed: usize,
+    /// Mean duration of the iterations the step was executed in
+    pub mean: Duration,
+    /// Standard deviation of the durations, the root of their variance
+    pub std_dev: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl StepStability {
+    /// Whether the step both passed and failed
+    pub fn is_flaky(&self) -> bool { self.passed > 0 && self.failed > 0 }
+}
+
+/// Every iteration of [`TestHarness::check_stability`]. Displays as the
+/// distribution of outcomes followed by a table of the steps, flaky ones
+/// marked, as logged at the end of the check
+#[derive(Debug)]
+pub struct StabilityReport {
+    pub test_name: String,
+    /// Outcome of every iteration, in the order they ran
+    pub iterations: Vec<Result<RunReport, HarnessError>>,
+}
+
+impl StabilityReport {
+    /// Iterations that ran and passed
+    pub fn passed_count(&self) -> usize {
+        self.iterations
+            .iter()
+            .filter(|outcome| outcome.as_ref().is_ok_and(RunReport::passed))
+            .count()
+    }
+
+    /// Iterations that ran and failed
+    pub fn failed_count(&self) -> usize {
+        self.iterations
+            .iter()
+            .filter(|outcome| outcome.as_ref().is_ok_and(|report| !report.passed()))
+            .count()
+    }
+
+    /// Iterations that could not run or were aborted
+    pub fn error_count(&self) -> usize {
+        self.iterations
+            .iter()
+            .filter(|outcome| outcome.is_err())
+            .count()
+    }
+
+    /// Share of the iterations that passed, between 0 and 1
+    pub fn pass_rate(&self) -> f64 {
+        if self.iterations.is_empty() {
+            return 0.0;
+        }
+        self.passed_count() as f64 / self.iterations.len() as f64
+    }
+
+    /// Whether every iteration passed, which is what a test should show
+    /// before it is promoted to a required check
+    pub fn is_stable(&self) -> bool {
+        !self.iterations.is_empty() && self.passed_count() == self.iterations.len()
+    }
+
+    /// Statistics of the executed steps, in the order they first ran
+    pub fn steps(&self) -> Vec<StepStability> {
+        let mut samples: Vec<(String, usize, usize, Vec<Duration>)> = Vec::new();
+        let executed = self
+            .iterations
+            .iter()
+            .filter_map(|outcome| outcome.as_ref().ok())
+            .flat_map(|report| &report.steps)
+            .filter(|step| step.started.is_some());
+        for step in executed {
+            let idx = match samples.iter().position(|(name, ..)| *name == step.name) {
+                Some(idx) => idx,
+                None => {
+                    samples.push((step.name.clone(), 0, 0, Vec::new()));
+                    samples.len() - 1
+                }
+            };
+            let (_, passed, failed, durations) = &mut samples[idx];
+            match step.status {
+                StepStatus::Passed => *passed += 1,
+                StepStatus::Failed(_) | StepStatus::Warning(_) => *failed += 1,
+                StepStatus::Skipped(_) => {}
+            }
+            durations.push(step.duration);
+        }
+        samples
+            .into_iter()
+            .map(|(name, passed, failed, durations)| {
+                let secs = durations
+                    .iter()
+                    .map(Duration::as_secs_f64)
+                    .collect::<Vec<_>>();
+                let mean = secs.iter().sum::<f64>() / secs.len() as f64;
+                let variance =
+                    secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / secs.len() as f64;
+                StepStability {
+                    name,
+                    passed,
+                    failed,
+                    mean: Duration::from_secs_f64(mean),
+                    std_dev: Duration::from_secs_f64(variance.sqrt()),
+                    min: durations.iter().copied().min().unwrap_or_default(),
+                    max: durations.iter().copied().max().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    /// The report as JSON, for CI to decide whether to promote the test
+    pub fn to_json(&self) -> Value {
+        let steps = self
+            .steps()
+            .iter()
+            .map(|step| {
+                json!({
+                    "name": step.name,
+                    "passed": step.passed,
+                    "failed": step.failed,
+                    "flaky": step.is_flaky(),
+                    "mean_secs": step.mean.as_secs_f64(),
+                    "std_dev_secs": step.std_dev.as_secs_f64(),
+                    "min_secs": step.min.as_secs_f64(),
+                    "max_secs": step.max.as_secs_f64(),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "test": self.test_name,
+            "iterations": self.iterations.len(),
+            "passed": self.passed_count(),
+            "failed": self.failed_count(),
+            "errors": self.error_count(),
+            "pass_rate": self.pass_rate(),
+            "stable": self.is_stable(),
+            "steps": steps,
+        })
+    }
+}
+
+impl Display for StabilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Test {} over {} iterations: {} passed, {} failed, {} errors ({:.1}% passed)",
+            self.test_name,
+            self.iterations.len(),
+            self.passed_count(),
+            self.failed_count(),
+            self.error_count(),
+            self.pass_rate() * 100.0
+        )?;
+        let steps = self.steps();
+        let width = steps
+            .iter()
+            .map(|step| step.name.chars().count())
+            .fold("Step".len(), usize::max);
+        write!(
+            f,
+            "  {:<width$}  {:>6}  {:>6}  {:>9}  {:>9}  {:>9}  {:>9}",
+            "Step", "Passed", "Failed", "Mean", "StdDev", "Min", "Max"
+        )?;
+        let secs = |duration: Duration| format!("{:.3}s", duration.as_secs_f64());
+        for step in &steps {
+            write!(
+                f,
+                "\n  {:<width$}  {:>6}  {:>6}  {:>9}  {:>9}  {:>9}  {:>9}{}",
+                step.name,
+                step.passed,
+                step.failed,
+                secs(step.mean),
+                secs(step.std_dev),
+                secs(step.min),
+                secs(step.max),
+                if step.is_flaky() { "  flaky" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl TestHarness {
+    /// Executes a harness built by `build` `iterations` times, regardless of
+    /// failures, to find out whether a test is flaky before it becomes a
+    /// required check. Every iteration gets a harness of its own as steps
+    /// cannot be rerun, the previous one is dropped first, which stops its
+    /// services and tears down its fixtures. The report is logged once all
+    /// iterations ran
+    pub fn check_stability(
+        iterations: u32,
+        mut build: impl FnMut() -> TestHarness,
+    ) -> StabilityReport {
+        let mut report = StabilityReport {
+            test_name: String::new(),
+            iterations: Vec::new(),
+        };
+        for iteration in 1..=iterations {
+            let harness = build();
+            report.test_name.clone_from(&harness.test_name);
+            info!(
+                "Stability check of test {}, iteration {}/{}",
+                report.test_name, iteration, iterations
+            );
+            report.iterations.push(harness.execute());
+        }
+        info!("{}", report);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{ShellCommandStep, StepReport, TestStep};
+
+    fn run(steps: &[(&str, StepStatus, u64)]) -> Result<RunReport, HarnessError> {
+        let mut report = RunReport::new("Checkout");
+        let started = report.started;
+        report.steps = steps
+            .iter()
+            .map(|(name, status, millis)| StepReport {
+                name: name.to_string(),
+                description: String::new(),
+                status: status.clone(),
+                started: (!matches!(status, StepStatus::Skipped(_))).then_some(started),
+                duration: Duration::from_millis(*millis),
+            })
+            .collect();
+        Ok(report)
+    }
+
+    #[test]
+    fn test_step_statistics() {
+        let failed = StepStatus::Failed("timeout".to_string());
+        let skipped = StepStatus::Skipped("run aborted".to_string());
+        let report = StabilityReport {
+            test_name: "Checkout".to_string(),
+            iterations: vec![
+                run(&[
+                    ("Start", StepStatus::Passed, 100),
+                    ("Order", StepStatus::Passed, 200),
+                ]),
+                run(&[("Start", StepStatus::Passed, 300), ("Order", failed, 400)]),
+                run(&[("Start", StepStatus::Passed, 200), ("Order", skipped, 0)]),
+                Err(HarnessError::InvalidPlan(Vec::new())),
+            ],
+        };
+        assert_eq!(
+            (
+                report.passed_count(),
+                report.failed_count(),
+                report.error_count()
+            ),
+            (2, 1, 1)
+        );
+        assert!(!report.is_stable());
+
+        let steps = report.steps();
+        assert_eq!(steps.len(), 2);
+        assert_eq!((steps[0].passed, steps[0].failed), (3, 0));
+        assert_eq!(steps[0].mean, Duration::from_millis(200));
+        // Variance of 0.1s, 0.3s and 0.2s is 2/3 * 0.01s²
+        assert_eq!(steps[0].std_dev.as_micros(), 81649);
+        assert_eq!(
+            (steps[0].min, steps[0].max),
+            (Duration::from_millis(100), Duration::from_millis(300))
+        );
+        assert!(steps[1].is_flaky() && !steps[0].is_flaky());
+        assert_eq!(report.to_json()["steps"][1]["flaky"], true);
+        assert_eq!(
+            report.to_string(),
+            "Test Checkout over 4 iterations: 2 passed, 1 failed, 1 errors (50.0% passed)\n  Step   \
+             Passed  Failed       Mean     StdDev        Min        Max\n  Start       3       0     \
+             0.200s     0.082s     0.100s     0.300s\n  Order       1       1     0.300s     \
+             0.100s     0.200s     0.400s  flaky"
+        );
+    }
+
+    #[test]
+    fn test_check_stability_runs_every_iteration() {
+        let calls = AtomicUsize::new(0);
+        let report = TestHarness::check_stability(4, || {
+            let script = if calls.fetch_add(1, Ordering::SeqCst) % 2 == 0 {
+                "true"
+            } else {
+                "false"
+            };
+            let mut harness = TestHarness::new("StabilityTester", ".");
+            harness.failure_log_lines = 0;
+            harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+                "Check", script,
+            ))));
+            harness
+        });
+        assert_eq!(report.test_name, "StabilityTester");
+        assert_eq!((report.passed_count(), report.failed_count()), (2, 2));
+        assert_eq!(report.pass_rate(), 0.5);
+        let steps = report.steps();
+        assert_eq!((steps[0].passed, steps[0].failed), (2, 2));
+        assert!(steps[0].is_flaky());
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::{PollPolicy, ServiceAction, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that fails if a service took longer than `max` from being started
+/// by the harness to becoming healthy. Uses the recorded readiness if the
+/// service was already awaited, e.g. by [`crate::StartAllServices`],
+/// otherwise waits for it for the rest of the budget
+#[derive(Debug, Clone)]
+pub struct AssertStartupTime {
+    /// Name the step is reported under
+    pub name: String,
+    pub service_name: String,
+    pub max: Duration,
+}
+
+impl ServiceStepExecutor for AssertStartupTime {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let started_at = env.started_at(&self.service_name).ok_or_else(|| {
+            format!(
+                "Service '{}' was not started by the harness",
+                self.service_name
+            )
+        })?;
+        let ready_at = match env.ready_at(&self.service_name) {
+            Some(ready_at) => ready_at,
+            None => {
+                let policy = PollPolicy {
+                    interval: Duration::from_millis(10),
+                    timeout: self.max.saturating_sub(started_at.elapsed()),
+                    ..Default::default()
+                };
+                env.wait_until_healthy(&self.service_name, &policy)
+                    .map_err(|_| {
+                        format!(
+                            "Service '{}' not ready after {:?}, exceeding its startup budget of \
+                             {:?}",
+                            self.service_name,
+                            started_at.elapsed(),
+                            self.max
+                        )
+                    })?
+            }
+        };
+
+        let startup_time = ready_at - started_at;
+        if startup_time > self.max {
+            return Err(StepError::Assertion(format!(
+                "Service '{}' took {:?} to become ready, exceeding its startup budget of {:?}",
+                self.service_name, startup_time, self.max
+            )));
+        }
+        info!(
+            "Service '{}' became ready in {:?}, within its budget of {:?}",
+            self.service_name, startup_time, self.max
+        );
+        Ok(())
+    }
+
+    fn service_actions(&self) -> Vec<ServiceAction> {
+        vec![ServiceAction::Use(self.service_name.clone())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::DelayedReadyService;
+    use crate::TestHarness;
+
+    fn assert_startup_within(max: Duration) -> Result<(), StepError> {
+        let mut harness = TestHarness::new("StartupTimeTester", ".");
+        harness.add_service(Box::new(DelayedReadyService::new(
+            "Slow",
+            Duration::from_millis(200),
+        )));
+        let mut env = harness.step_env();
+        env.start_service(0).expect("Failed to start service");
+        AssertStartupTime {
+            name: "Check_Startup_Time".to_string(),
+            service_name: "Slow".to_string(),
+            max,
+        }
+        .execute(&mut env)
+    }
+
+    #[test]
+    fn test_startup_within_budget() {
+        assert_startup_within(Duration::from_secs(2)).expect("Startup is within budget");
+    }
+
+    #[test]
+    fn test_startup_over_budget() {
+        let error = assert_startup_within(Duration::from_millis(50))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("exceeding its startup budget"), "{}", error);
+    }
+}
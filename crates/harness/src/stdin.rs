@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+
+use tracing::warn;
+
+/// What a [`crate::SubProcessService`] reads from its stdin
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum StdinSource {
+    /// Reads hit end of file right away
+    #[default]
+    Null,
+    /// Shares the stdin of the test process
+    Inherit,
+    /// Reads these bytes, followed by end of file
+    Bytes(Vec<u8>),
+    /// Reads the contents of the file
+    File(PathBuf),
+}
+
+impl StdinSource {
+    pub(crate) fn stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            Self::Null => Stdio::null(),
+            Self::Inherit => Stdio::inherit(),
+            Self::Bytes(_) => Stdio::piped(),
+            Self::File(path) => File::open(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?
+                .into(),
+        })
+    }
+
+    /// Writes the bytes to the piped stdin of the child on a background
+    /// thread and closes it afterwards
+    pub(crate) fn feed(&self, service_name: &str, child: &mut Child) {
+        let (Self::Bytes(bytes), Some(mut stdin)) = (self, child.stdin.take()) else {
+            return;
+        };
+        let bytes = bytes.clone();
+        let service_name = service_name.to_string();
+        std::thread::spawn(move || {
+            if let Err(e) = stdin.write_all(&bytes) {
+                warn!("Failed to write stdin of '{}': {}", service_name, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,148 @@
+use std::io;
+use std::process::Child;
+#[cfg(unix)]
+use std::process::Command;
+use std::time::Duration;
+
+#[cfg(unix)]
+use tracing::warn;
+
+#[cfg(unix)]
+use crate::PollPolicy;
+
+/// How a [`crate::SubProcessService`] is stopped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StopMode {
+    /// Kill the process right away
+    #[default]
+    Kill,
+    /// Ask the process to exit with SIGTERM, so it can flush logs and
+    /// release ports, and kill it if it is still running after
+    /// `term_timeout`. On platforms without signals the process is killed
+    /// right away
+    Graceful { term_timeout: Duration },
+}
+
+/// Sends `signal` to the process, or to its process group if `group` is set
+#[cfg(unix)]
+fn send_signal(child: &Child, signal: &str, group: bool) -> io::Result<bool> {
+    let target = if group {
+        format!("-{}", child.id())
+    } else {
+        child.id().to_string()
+    };
+    let status = Command::new("kill")
+        .args(["-s", signal, "--", &target])
+        .status()?;
+    Ok(status.success())
+}
+
+/// Stops `child` according to `mode` without reaping it. If `group` is set,
+/// the child leads its own process group, which is signalled as a whole
+pub(crate) fn stop_child(child: &mut Child, mode: StopMode, group: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    if let StopMode::Graceful { term_timeout } = mode {
+        if send_signal(child, "TERM", group)? {
+            let exited = PollPolicy {
+                interval: Duration::from_millis(20),
+                timeout: term_timeout,
+                ..Default::default()
+            }
+            .poll(|| child.try_wait().ok().flatten().ok_or(()));
+            if exited.is_ok() {
+                return Ok(());
+            }
+            warn!(
+                "Process {} still running {:?} after SIGTERM, killing it",
+                child.id(),
+                term_timeout
+            );
+        }
+    }
+    #[cfg(unix)]
+    if group && send_signal(child, "KILL", group)? {
+        return Ok(());
+    }
+    #[cfg(not(unix))]
+    let _ = (mode, group);
+    child.kill()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    #[cfg(target_os = "linux")]
+    use crate::test_util::is_alive;
+    use crate::{Service, SubProcessService};
+
+    fn start_trapping(script: &str, term_timeout: Duration) -> SubProcessService {
+        let mut service =
+            SubProcessService::new("Trapping", "sh", vec!["-c".to_string(), script.to_string()]);
+        service.stop_mode = StopMode::Graceful { term_timeout };
+        service.start().expect("Failed to start service");
+        // The trap is only set once the script printed
+        PollPolicy::default()
+            .poll(|| (!service.output().is_empty()).then_some(()).ok_or(()))
+            .expect("Service did not get ready");
+        service
+    }
+
+    #[test]
+    fn test_graceful_stop_lets_service_clean_up() {
+        let root = tempfile::tempdir().unwrap();
+        let flushed = root.path().join("flushed");
+        let script = format!(
+            "trap 'echo done > {}; exit 0' TERM; echo ready; while true; do sleep 0.05; done",
+            flushed.display()
+        );
+        let mut service = start_trapping(&script, Duration::from_secs(5));
+
+        service.stop().expect("Failed to stop service");
+        assert_eq!(std::fs::read_to_string(&flushed).unwrap(), "done\n");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_stop_kills_process_group() {
+        let mut service = SubProcessService::new("Wrapper", "sh", vec![
+            "-c".to_string(),
+            "sleep 30 & echo $!; wait".to_string(),
+        ]);
+        service.process_group = true;
+        service.start().expect("Failed to start service");
+        let worker = PollPolicy::default()
+            .poll(|| {
+                let output = service.output();
+                output
+                    .first()
+                    .and_then(|line| line.line.parse::<u32>().ok())
+                    .ok_or(())
+            })
+            .expect("Wrapper did not print the pid of its worker");
+        assert!(is_alive(worker));
+
+        service.stop().expect("Failed to stop service");
+        PollPolicy {
+            interval: Duration::from_millis(10),
+            timeout: Duration::from_secs(2),
+            ..Default::default()
+        }
+        .poll(|| (!is_alive(worker)).then_some(()).ok_or(()))
+        .expect("Worker survived stopping the wrapper");
+    }
+
+    #[test]
+    fn test_graceful_stop_kills_after_timeout() {
+        let mut service = start_trapping(
+            "trap '' TERM; echo ready; while true; do sleep 0.05; done",
+            Duration::from_millis(200),
+        );
+
+        let started = Instant::now();
+        service.stop().expect("Failed to stop service");
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(!service.is_running());
+    }
+}
@@ -0,0 +1,525 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Display};
+use std::process::ExitCode;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::{HarnessError, RunReport, TestHarness};
+
+/// Whether the step of a run-once key is running or ran successfully
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunOnceState {
+    Running,
+    Done,
+}
+
+#[derive(Debug, Default)]
+struct RunOnceKeys {
+    states: Mutex<HashMap<String, RunOnceState>>,
+    changed: Condvar,
+}
+
+/// Tracks which run-once steps (see [`crate::StepOptions::run_once_key`])
+/// already ran. Clones share the same underlying state, so a single registry
+/// can be handed to every harness of a suite, also when they run at the same
+/// time
+#[derive(Debug, Clone, Default)]
+pub struct RunOnceRegistry {
+    keys: Arc<RunOnceKeys>,
+}
+
+impl RunOnceRegistry {
+    pub fn new() -> Self { Self::default() }
+
+    /// Whether a step with the given key ran successfully
+    pub fn has_run(&self, key: &str) -> bool { self.lock().get(key) == Some(&RunOnceState::Done) }
+
+    /// Claims the key for a step that is about to run, returning `false` if
+    /// its step already ran successfully. While the step of the key runs in
+    /// another harness, this waits until it finished, and takes over the
+    /// claim if it failed
+    pub(crate) fn claim(&self, key: &str) -> bool {
+        let mut states = self.lock();
+        loop {
+            match states.get(key) {
+                None => {
+                    states.insert(key.to_string(), RunOnceState::Running);
+                    return true;
+                }
+                Some(RunOnceState::Done) => return false,
+                Some(RunOnceState::Running) =>
+                    states = self
+                        .keys
+                        .changed
+                        .wait(states)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            }
+        }
+    }
+
+    /// Marks a claimed key as done after its step passed
+    pub(crate) fn complete(&self, key: &str) {
+        self.lock().insert(key.to_string(), RunOnceState::Done);
+        self.keys.changed.notify_all();
+    }
+
+    /// Releases a claimed key after its step failed, so it is retried by the
+    /// next harness
+    pub(crate) fn release(&self, key: &str) {
+        self.lock().remove(key);
+        self.keys.changed.notify_all();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, RunOnceState>> {
+        self.keys
+            .states
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl TestHarness {
+    /// Declares a resource the test cannot share, e.g. `port 5432` or `GPU`.
+    /// A [`TestSuite`] never runs two harnesses requiring the same resource
+    /// at the same time
+    pub fn require_exclusive(&mut self, resource: &str) {
+        self.exclusive_resources.push(resource.to_string());
+    }
+}
+
+/// Harnesses run together, e.g. all tests of a directory, with their
+/// reports aggregated into a [`SuiteReport`]. The harnesses share the
+/// [`RunOnceRegistry`] of the suite
+pub struct TestSuite {
+    pub name: String,
+    /// How many harnesses run at the same time, 1 runs them one after
+    /// another in the order they were added. Harnesses requiring the same
+    /// exclusive resource wait for each other, see
+    /// [`TestHarness::require_exclusive`]
+    pub jobs: usize,
+    pub run_once_registry: RunOnceRegistry,
+    harnesses: Vec<TestHarness>,
+}
+
+impl TestSuite {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            jobs: 1,
+            run_once_registry: RunOnceRegistry::new(),
+            harnesses: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, harness: TestHarness) { self.harnesses.push(harness); }
+
+    pub fn is_empty(&self) -> bool { self.harnesses.is_empty() }
+
+    /// The harnesses sharing the registry of the suite, in the order they
+    /// were added
+    pub(crate) fn into_harnesses(self) -> impl Iterator<Item = TestHarness> {
+        let registry = self.run_once_registry;
+        self.harnesses.into_iter().map(move |mut harness| {
+            harness.run_once_registry = registry.clone();
+            harness
+        })
+    }
+
+    /// Executes the harnesses on up to [`TestSuite::jobs`] threads, each
+    /// like [`TestHarness::execute`]. A thread picks the first harness in
+    /// order whose exclusive resources are free
+    pub fn run(self) -> SuiteReport {
+        let started = Instant::now();
+        let (name, jobs, count) = (self.name.clone(), self.jobs, self.harnesses.len());
+        let schedule = Schedule {
+            queue: Mutex::new(Queue {
+                pending: self.into_harnesses().enumerate().collect(),
+                held: HashSet::new(),
+            }),
+            released: Condvar::new(),
+        };
+        let results = Mutex::new((0..count).map(|_| None).collect::<Vec<_>>());
+        thread::scope(|scope| {
+            for _ in 0..jobs.clamp(1, count.max(1)) {
+                scope.spawn(|| loop {
+                    let Some((idx, harness)) = schedule.next() else {
+                        break;
+                    };
+                    let _lease = Lease {
+                        schedule: &schedule,
+                        resources: harness.exclusive_resources.clone(),
+                    };
+                    let test_name = harness.test_name.clone();
+                    let outcome = harness.execute();
+                    match &outcome {
+                        Ok(report) => info!(
+                            "Test {} of suite {} {}",
+                            test_name,
+                            name,
+                            if report.passed() { "passed" } else { "failed" }
+                        ),
+                        Err(e) => info!("Test {} of suite {} was aborted: {}", test_name, name, e),
+                    }
+                    results.lock().unwrap()[idx] = Some((test_name, outcome));
+                });
+            }
+        });
+        SuiteReport {
+            name,
+            results: results
+                .into_inner()
+                .unwrap()
+                .into_iter()
+                .flatten()
+                .collect(),
+            duration: started.elapsed(),
+        }
+    }
+}
+
+/// Harnesses of a [`TestSuite`] not started yet, with the exclusive
+/// resources held by the running ones
+struct Schedule {
+    queue: Mutex<Queue>,
+    released: Condvar,
+}
+
+struct Queue {
+    /// Harnesses with their position in the suite
+    pending: VecDeque<(usize, TestHarness)>,
+    held: HashSet<String>,
+}
+
+impl Schedule {
+    /// Takes the next harness whose resources are free, marking them held,
+    /// waiting for running harnesses to release theirs if there is none,
+    /// which cannot wait forever as the first one is free once nothing is
+    /// held. None once all harnesses were taken
+    fn next(&self) -> Option<(usize, TestHarness)> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            let Queue { pending, held } = &mut *queue;
+            if pending.is_empty() {
+                return None;
+            }
+            let free = pending.iter().position(|(_, harness)| {
+                harness
+                    .exclusive_resources
+                    .iter()
+                    .all(|resource| !held.contains(resource))
+            });
+            if let Some(next) = free.and_then(|idx| pending.remove(idx)) {
+                held.extend(next.1.exclusive_resources.iter().cloned());
+                return Some(next);
+            }
+            queue = self.released.wait(queue).unwrap();
+        }
+    }
+}
+
+/// Resources held by a running harness, released when dropped, also if the
+/// harness panicked
+struct Lease<'a> {
+    schedule: &'a Schedule,
+    resources: Vec<String>,
+}
+
+impl Drop for Lease<'_> {
+    fn drop(&mut self) {
+        let mut queue = self
+            .schedule
+            .queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for resource in &self.resources {
+            queue.held.remove(resource);
+        }
+        self.schedule.released.notify_all();
+    }
+}
+
+/// Outcome of running a [`TestSuite`]. Displays as a line per test with the
+/// causes of failures, followed by a summary
+#[derive(Debug)]
+pub struct SuiteReport {
+    pub name: String,
+    /// Name and outcome of every harness, in the order they were added
+    pub results: Vec<(String, Result<RunReport, HarnessError>)>,
+    pub duration: Duration,
+}
+
+impl SuiteReport {
+    /// Whether every harness ran and passed
+    pub fn passed(&self) -> bool { self.passed_count() == self.results.len() }
+
+    /// How many harnesses ran and passed
+    pub fn passed_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|(_, outcome)| outcome.as_ref().is_ok_and(RunReport::passed))
+            .count()
+    }
+
+    /// Reports of the harnesses that ran, leaving out aborted ones
+    pub fn reports(&self) -> Vec<RunReport> {
+        self.results
+            .iter()
+            .filter_map(|(_, outcome)| outcome.as_ref().ok().cloned())
+            .collect()
+    }
+
+    /// JUnit XML of all harnesses that ran, see
+    /// [`RunReport::combined_junit_xml`]
+    pub fn to_junit_xml(&self) -> String {
+        RunReport::combined_junit_xml(&self.name, &self.reports())
+    }
+
+    /// Exit status for a test binary: success only if every harness passed
+    pub fn exit_code(&self) -> ExitCode {
+        if self.passed() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+}
+
+impl Display for SuiteReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, outcome) in &self.results {
+            match outcome {
+                Ok(report) => {
+                    let status = if report.passed() { "PASS" } else { "FAIL" };
+                    writeln!(
+                        f,
+                        "{} {} ({:.3}s)",
+                        status,
+                        name,
+                        report.duration.as_secs_f64()
+                    )?;
+                    for failure in report.failures() {
+                        writeln!(f, "     {}", failure)?;
+                    }
+                }
+                Err(e) => writeln!(f, "ERROR {}: {}", name, e)?,
+            }
+        }
+        write!(
+            f,
+            "\n{} passed, {} failed of {} tests in {:.3}s",
+            self.passed_count(),
+            self.results.len() - self.passed_count(),
+            self.results.len(),
+            self.duration.as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        AsyncFnStep, ShellCommandStep, StepOptions, StepStatus, SubProcessServiceStarter, TestStep,
+    };
+
+    fn plan(registry: &RunOnceRegistry, seeded: &Arc<AtomicUsize>) -> TestHarness {
+        let mut harness = TestHarness::new("RunOnceTester", ".");
+        harness.run_once_registry = registry.clone();
+        let seeded = seeded.clone();
+        harness.add_step_with(
+            TestStep::AsyncFn(Box::new(AsyncFnStep {
+                name: "Seed_Data".to_string(),
+                description: "Expensive one-time setup".to_string(),
+                futurefn: Box::new(move |_| {
+                    let seeded = seeded.clone();
+                    Box::new(async move {
+                        seeded.fetch_add(1, Ordering::SeqCst);
+                        Ok(Value::Null)
+                    })
+                }),
+            })),
+            StepOptions {
+                run_once_key: Some("seed".to_string()),
+                ..Default::default()
+            },
+        );
+        harness
+    }
+
+    #[test]
+    fn test_run_once_step_executes_once_across_harnesses() {
+        let registry = RunOnceRegistry::new();
+        let seeded = Arc::new(AtomicUsize::new(0));
+
+        let first = plan(&registry, &seeded).execute().unwrap();
+        let second = plan(&registry, &seeded).execute().unwrap();
+
+        assert_eq!(seeded.load(Ordering::SeqCst), 1);
+        assert!(registry.has_run("seed"));
+        assert_eq!(first.steps[0].status, StepStatus::Passed);
+        assert!(matches!(second.steps[0].status, StepStatus::Skipped(_)));
+        assert!(second.passed());
+    }
+
+    fn shell(test_name: &str, script: &str) -> TestHarness {
+        let mut harness = TestHarness::new(test_name, ".");
+        harness.failure_log_lines = 0;
+        harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+            "Check", script,
+        ))));
+        harness
+    }
+
+    #[test]
+    fn test_suite_aggregates_reports() {
+        let mut suite = TestSuite::new("Suite");
+        suite.jobs = 2;
+        suite.add(shell("Passing", "true"));
+        suite.add(shell("Failing", "false"));
+        let mut aborted = TestHarness::new("Aborted", ".");
+        aborted.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Start_Missing".to_string(),
+            description: String::new(),
+            service_name: "Missing".to_string(),
+            wait_after: None,
+        })));
+        suite.add(aborted);
+
+        let report = suite.run();
+        let names = report
+            .results
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Passing", "Failing", "Aborted"]);
+        assert_eq!(report.passed_count(), 1);
+        assert!(!report.passed());
+        assert_eq!(report.exit_code(), ExitCode::FAILURE);
+
+        let summary = report.to_string();
+        assert!(summary.starts_with("PASS Passing ("), "{}", summary);
+        assert!(summary.contains("\nFAIL Failing ("), "{}", summary);
+        assert!(
+            summary.contains("\nERROR Aborted: Invalid test plan: Step 1 'Start_Missing'"),
+            "{}",
+            summary
+        );
+        assert!(
+            summary.contains("\n\n1 passed, 2 failed of 3 tests in "),
+            "{}",
+            summary
+        );
+        assert!(
+            report
+                .to_junit_xml()
+                .contains(r#"<testsuites name="Suite" tests="2" failures="1""#),
+            "{}",
+            report.to_junit_xml()
+        );
+    }
+
+    /// Harnesses in `root` sharing a run-once step running `seed`, followed
+    /// by a step checking that the seed step created `seeded`
+    fn seeded_suite(root: &std::path::Path, seed: &str) -> TestSuite {
+        let mut suite = TestSuite::new("Suite");
+        suite.jobs = 3;
+        for name in ["First", "Second", "Third"] {
+            let mut harness = TestHarness::new(name, root.to_str().unwrap());
+            harness.failure_log_lines = 0;
+            harness.add_step_with(
+                TestStep::Service(Box::new(ShellCommandStep::sh("Seed", seed))),
+                StepOptions {
+                    run_once_key: Some("seed".to_string()),
+                    ..Default::default()
+                },
+            );
+            harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+                "Check",
+                "test -f seeded",
+            ))));
+            suite.add(harness);
+        }
+        suite
+    }
+
+    fn seed_statuses(report: &SuiteReport) -> Vec<&StepStatus> {
+        report
+            .results
+            .iter()
+            .map(|(_, outcome)| &outcome.as_ref().unwrap().steps[0].status)
+            .collect()
+    }
+
+    #[test]
+    fn test_concurrent_harnesses_wait_for_run_once_step() {
+        let root = tempfile::tempdir().unwrap();
+        let report = seeded_suite(root.path(), "sleep 0.3 && echo x >> runs && touch seeded").run();
+        assert!(report.passed(), "{}", report);
+        let runs = std::fs::read_to_string(root.path().join("runs")).unwrap();
+        assert_eq!(runs.lines().count(), 1);
+        let statuses = seed_statuses(&report);
+        assert_eq!(
+            statuses
+                .iter()
+                .filter(|status| ***status == StepStatus::Passed)
+                .count(),
+            1,
+            "{:?}",
+            statuses
+        );
+    }
+
+    #[test]
+    fn test_waiting_harness_takes_over_failed_run_once_step() {
+        let root = tempfile::tempdir().unwrap();
+        // Only the first claimant fails, after the others started waiting
+        let seed = "if mkdir first; then sleep 0.3; exit 1; fi; touch seeded";
+        let report = seeded_suite(root.path(), seed).run();
+        assert_eq!(report.passed_count(), 2, "{}", report);
+        let mut statuses = seed_statuses(&report)
+            .into_iter()
+            .map(|status| match status {
+                StepStatus::Passed => "passed",
+                StepStatus::Failed(_) => "failed",
+                StepStatus::Skipped(_) => "skipped",
+                StepStatus::Warning(_) => "warning",
+            })
+            .collect::<Vec<_>>();
+        statuses.sort_unstable();
+        assert_eq!(statuses, ["failed", "passed", "skipped"]);
+    }
+
+    #[test]
+    fn test_harnesses_sharing_a_resource_run_one_at_a_time() {
+        let mut suite = TestSuite::new("Suite");
+        suite.jobs = 3;
+        for name in ["First", "Second"] {
+            let mut harness = shell(name, "sleep 0.3");
+            harness.require_exclusive("port 5432");
+            suite.add(harness);
+        }
+        suite.add(shell("Unrelated", "sleep 0.3"));
+
+        let report = suite.run();
+        assert!(report.passed(), "{}", report);
+        let [first, second, unrelated] = [0, 1, 2].map(|idx| {
+            let run = report.results[idx].1.as_ref().unwrap();
+            (run.started, run.started + run.duration)
+        });
+        assert!(
+            second.0 >= first.1,
+            "Harnesses sharing a resource overlapped"
+        );
+        assert!(
+            unrelated.0 < first.1,
+            "Unrelated harness waited for the others"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{HarnessError, RunReport, TestHarness};
+
+/// Selects steps by their [`crate::StepOptions::tags`], e.g. to run only the
+/// smoke tests of a harness or everything but the slow steps
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    /// Steps must carry at least one of these tags, any step matches if empty
+    pub include: Vec<String>,
+    /// Steps carrying any of these tags never match
+    pub exclude: Vec<String>,
+}
+
+impl TagFilter {
+    /// Matches steps carrying any of `tags`
+    pub fn include(tags: &[&str]) -> Self {
+        Self {
+            include: tags.iter().map(ToString::to_string).collect(),
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Matches steps carrying none of `tags`
+    pub fn exclude(tags: &[&str]) -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: tags.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|tag| tags.contains(tag)))
+            && !self.exclude.iter().any(|tag| tags.contains(tag))
+    }
+}
+
+impl TestHarness {
+    /// Executes only the steps matching `filter`, recording all other steps
+    /// as skipped. As with [`TestHarness::run_only`], the prerequisites and
+    /// dependencies of matching steps are executed as well, and setup and
+    /// teardown steps always run
+    pub fn execute_filtered(mut self, filter: &TagFilter) -> Result<RunReport, HarnessError> {
+        let step_names = self
+            .steps
+            .iter()
+            .filter(|planned| filter.matches(&planned.options.tags))
+            .map(|planned| planned.step.name().to_string())
+            .filter(|name| {
+                self.filter
+                    .as_ref()
+                    .is_none_or(|step_names| step_names.contains(name))
+            })
+            .collect();
+        self.filter = Some(step_names);
+        self.run(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{AsyncFnStep, StepOptions, StepStatus, TestStep};
+
+    fn tagged(harness: &mut TestHarness, name: &str, tags: &[&str], depends_on: &[&str]) {
+        harness.add_step_with(
+            TestStep::AsyncFn(Box::new(AsyncFnStep {
+                name: name.to_string(),
+                description: format!("Runs {}", name),
+                futurefn: Box::new(|_| Box::new(async { Ok(Value::Null) })),
+            })),
+            StepOptions {
+                tags: tags.iter().map(ToString::to_string).collect(),
+                depends_on: depends_on.iter().map(ToString::to_string).collect(),
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn test_execute_filtered_by_tags() {
+        let mut harness = TestHarness::new("TagTester", ".");
+        tagged(&mut harness, "Seed", &["slow"], &[]);
+        tagged(&mut harness, "Health", &["smoke"], &[]);
+        tagged(&mut harness, "Checkout", &["smoke", "network"], &[]);
+        tagged(&mut harness, "Report", &["smoke"], &["Seed"]);
+        tagged(&mut harness, "Load", &["slow"], &[]);
+
+        let filter = TagFilter {
+            include: vec!["smoke".to_string()],
+            exclude: vec!["network".to_string()],
+        };
+        assert!(!filter.matches(&[]));
+        assert!(TagFilter::exclude(&["slow"]).matches(&[]));
+        let report = harness
+            .execute_filtered(&filter)
+            .expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        let not_selected = StepStatus::Skipped("not selected".to_string());
+        let statuses = report
+            .steps
+            .iter()
+            .map(|step| (step.name.as_str(), &step.status))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, [
+            ("Seed", &StepStatus::Passed),
+            ("Health", &StepStatus::Passed),
+            ("Checkout", &not_selected),
+            ("Report", &StepStatus::Passed),
+            ("Load", &not_selected),
+        ]);
+        assert_eq!(report.auto_included, vec!["Seed".to_string()]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::Write;
+
+use crate::{RunReport, StepStatus};
+
+/// Escapes `#` and `\`, which would otherwise start a directive
+fn escape(text: &str) -> String { text.replace('\\', "\\\\").replace('#', "\\#") }
+
+impl RunReport {
+    /// Renders the report in the Test Anything Protocol, version 13, with a
+    /// test point per step. Failure messages are attached as YAML
+    /// diagnostics, skipped steps use the `SKIP` directive and warnings of
+    /// optional steps are printed as comments
+    pub fn to_tap(&self) -> String {
+        let mut tap = String::new();
+        let _ = writeln!(tap, "TAP version 13");
+        let _ = writeln!(tap, "1..{}", self.steps.len());
+        for (idx, step) in self.steps.iter().enumerate() {
+            let name = escape(&step.name);
+            match &step.status {
+                StepStatus::Passed => {
+                    let _ = writeln!(tap, "ok {} - {}", idx + 1, name);
+                }
+                StepStatus::Skipped(reason) => {
+                    let reason = escape(&reason.replace('\n', " "));
+                    let _ = writeln!(tap, "ok {} - {} # SKIP {}", idx + 1, name, reason);
+                }
+                StepStatus::Warning(message) => {
+                    let _ = writeln!(tap, "ok {} - {}", idx + 1, name);
+                    for line in message.lines() {
+                        let _ = writeln!(tap, "# {}", line);
+                    }
+                }
+                StepStatus::Failed(message) => {
+                    let _ = writeln!(tap, "not ok {} - {}", idx + 1, name);
+                    let _ = writeln!(tap, "  ---");
+                    let _ = writeln!(
+                        tap,
+                        "  message: {}",
+                        serde_json::to_string(message).unwrap_or_default()
+                    );
+                    let _ = writeln!(tap, "  duration_ms: {}", step.duration.as_millis());
+                    let _ = writeln!(tap, "  ...");
+                }
+            }
+        }
+        tap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{RunReport, StepReport, StepStatus};
+
+    #[test]
+    fn test_tap_output() {
+        let mut report = RunReport::new("Checkout");
+        report.steps = [
+            ("Start_Api", StepStatus::Passed),
+            (
+                "Create #1",
+                StepStatus::Failed("expected \"paid\"\ngot refunded".to_string()),
+            ),
+            ("Metrics", StepStatus::Warning("not available".to_string())),
+            ("Refund", StepStatus::Skipped("run aborted".to_string())),
+        ]
+        .into_iter()
+        .map(|(name, status)| StepReport {
+            name: name.to_string(),
+            description: String::new(),
+            status,
+            started: None,
+            duration: Duration::from_millis(40),
+        })
+        .collect();
+
+        assert_eq!(
+            report.to_tap(),
+            "TAP version 13\n1..4\nok 1 - Start_Api\nnot ok 2 - Create \\#1\n  ---\n  message: \
+             \"expected \\\"paid\\\"\\ngot refunded\"\n  duration_ms: 40\n  ...\nok 3 - Metrics\n# \
+             not available\nok 4 - Refund # SKIP run aborted\n"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::{PollPolicy, ServiceStepExecutor, StepEnv, StepError};
+
+/// A step that polls a TCP address until connecting to it succeeds, or with
+/// `expect_open` unset until it is refused, e.g. to wait for a port before
+/// the next step or to check a port is closed after stopping its service
+#[derive(Debug, Clone)]
+pub struct TcpProbeStep {
+    /// Name the step is reported under
+    pub name: String,
+    /// `host:port`, which may contain `{{port:name}}` placeholders, see
+    /// [`crate::PortAllocator`]
+    pub address: String,
+    pub expect_open: bool,
+    pub policy: PollPolicy,
+}
+
+impl TcpProbeStep {
+    /// Waits for `address` to accept connections
+    pub fn open(name: &str, address: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            address: address.to_string(),
+            expect_open: true,
+            policy: PollPolicy::default(),
+        }
+    }
+
+    /// Waits for `address` to refuse connections
+    pub fn closed(name: &str, address: &str) -> Self {
+        Self {
+            expect_open: false,
+            ..Self::open(name, address)
+        }
+    }
+}
+
+/// Whether a connection to `address` is accepted
+fn is_open(address: &str) -> Result<bool, String> {
+    let addrs = address
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", address, e))?;
+    Ok(addrs
+        .into_iter()
+        .any(|addr| TcpStream::connect_timeout(&addr, Duration::from_secs(1)).is_ok()))
+}
+
+impl ServiceStepExecutor for TcpProbeStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let address = env.substitute_ports(&self.address)?;
+        let state = if self.expect_open { "open" } else { "closed" };
+        self.policy
+            .poll(|| {
+                if is_open(&address)? == self.expect_open {
+                    Ok(())
+                } else {
+                    Err(format!("{} not {}", address, state))
+                }
+            })
+            .map_err(|what| StepError::Timeout {
+                what,
+                after: self.policy.timeout,
+            })?;
+        info!("{} is {}", address, state);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::TestHarness;
+
+    fn probe(step: TcpProbeStep, harness: &mut TestHarness) -> Result<(), StepError> {
+        TcpProbeStep {
+            policy: PollPolicy {
+                interval: Duration::from_millis(20),
+                timeout: Duration::from_millis(300),
+                ..Default::default()
+            },
+            ..step
+        }
+        .execute(&mut harness.step_env())
+    }
+
+    #[test]
+    fn test_probes_are_named() {
+        let first = TcpProbeStep::open("Wait_Api", "127.0.0.1:8080");
+        let second = TcpProbeStep::closed("Wait_Db_Stopped", "127.0.0.1:5432");
+        assert_eq!(
+            (first.name(), second.name()),
+            ("Wait_Api", "Wait_Db_Stopped")
+        );
+    }
+
+    #[test]
+    fn test_probe_open_and_closed_port() {
+        let mut harness = TestHarness::new("TcpProbeTester", ".");
+        let port = harness.ports.tcp("probe").unwrap();
+        let address = "127.0.0.1:{{port:probe}}";
+
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        probe(TcpProbeStep::open("Wait_Open", address), &mut harness).expect("Port is open");
+        let error = probe(TcpProbeStep::closed("Wait_Closed", address), &mut harness).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Timed out after 300ms: 127.0.0.1:{} not closed", port)
+        );
+
+        drop(listener);
+        probe(TcpProbeStep::closed("Wait_Closed", address), &mut harness).expect("Port is closed");
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, io, process};
+
+use serde_json::Value;
+use tracing::info;
+
+use crate::fixture::FixtureSlot;
+use crate::{Fixture, StepEnv, StepError, TestHarness};
+
+/// An isolated temporary directory for a test, e.g. as the data directory of
+/// a service. Once added with [`TestHarness::add_temp_dir`], its path is
+/// stored in the [`crate::Context`] under `name` and substituted for
+/// `{{tempdir:name}}` placeholders in service configuration and step URLs.
+/// The directory is deleted after the teardown steps ran, like the
+/// [`Fixture`] it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TempDirFixture {
+    pub name: String,
+    pub path: PathBuf,
+    /// Keep the directory for inspection if the run failed
+    pub keep_on_failure: bool,
+}
+
+impl TempDirFixture {
+    /// Creates a new empty directory in the temporary directory of the system
+    pub fn new(name: &str) -> io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let path = env::temp_dir().join(format!("harness-{}-{}-{}", name, process::id(), nanos));
+        fs::create_dir(&path)?;
+        Ok(Self {
+            name: name.to_string(),
+            path,
+            keep_on_failure: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+impl Fixture for TempDirFixture {
+    fn name(&self) -> &str { &self.name }
+
+    /// The directory was already created by [`TempDirFixture::new`]
+    fn setup(&mut self, _env: &mut StepEnv<'_>) -> Result<Value, StepError> {
+        Ok(Value::from(self.path.to_string_lossy().as_ref()))
+    }
+
+    /// Deletes the directory, unless it is kept because the run `failed`
+    fn teardown(&mut self, failed: bool) -> Result<(), StepError> {
+        if failed && self.keep_on_failure {
+            info!(
+                "Keeping temporary directory '{}' at {}",
+                self.name,
+                self.path.display()
+            );
+            return Ok(());
+        }
+        fs::remove_dir_all(&self.path).map_err(|e| {
+            StepError::io(
+                format!(
+                    "Failed to remove temporary directory {}",
+                    self.path.display()
+                ),
+                e,
+            )
+        })
+    }
+}
+
+impl TestHarness {
+    /// Makes the directory available to steps and services, and deletes it
+    /// once the run finished
+    pub fn add_temp_dir(&mut self, fixture: TempDirFixture) {
+        let path = fixture.path.to_string_lossy();
+        self.context
+            .insert(&fixture.name, Value::from(path.as_ref()));
+        self.ports.define("tempdir", &fixture.name, &path);
+        // Created already, so it is removed even if the harness never runs
+        self.fixtures.push(FixtureSlot {
+            fixture: Box::new(fixture),
+            set_up: true,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        FailurePolicy, ShellCommandStep, SubProcessService, SubProcessServiceStarter, TestStep,
+    };
+
+    #[test]
+    fn test_temp_dir_is_exposed_and_removed() {
+        let mut harness = TestHarness::new("TempDirTester", ".");
+        let fixture = TempDirFixture::new("scratch").unwrap();
+        let path = fixture.path.clone();
+        harness.add_temp_dir(fixture);
+        harness.add_service(Box::new(SubProcessService::new("Writer", "sh", vec![
+            "-c".to_string(),
+            "touch {{tempdir:scratch}}/ready; exec sleep 30".to_string(),
+        ])));
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: "Start_Writer".to_string(),
+            description: "Starts the writer".to_string(),
+            service_name: "Writer".to_string(),
+            wait_after: Some(std::time::Duration::from_millis(200)),
+        })));
+        harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+            "Check_Ready",
+            &format!("test -f {}/ready", path.display()),
+        ))));
+        let context = harness.context.clone();
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed(), "{:?}", report.steps);
+        assert_eq!(
+            context.get("scratch"),
+            Some(Value::from(path.to_str().unwrap()))
+        );
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_temp_dir_kept_on_failure() {
+        let mut harness = TestHarness::new("TempDirTester", ".");
+        let mut fixture = TempDirFixture::new("kept").unwrap();
+        fixture.keep_on_failure = true;
+        let path = fixture.path.clone();
+        harness.add_temp_dir(fixture);
+        harness.add_step(TestStep::Service(Box::new(ShellCommandStep::sh(
+            "Fail", "exit 1",
+        ))));
+        harness.failure_policy = FailurePolicy::Continue;
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(!report.passed());
+        assert!(path.exists());
+        fs::remove_dir_all(path).unwrap();
+    }
+}
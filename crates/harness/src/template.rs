@@ -0,0 +1,91 @@
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::{AsyncFnStep, Context, ServiceStepExecutor, TestStep};
+
+/// A reusable step definition producing a fresh [`TestStep`] for every plan
+/// it is added to, as steps themselves cannot be cloned. Every cloneable step
+/// executor is a template of itself, async steps are defined with
+/// [`AsyncFnTemplate`]
+pub trait StepTemplate {
+    fn instantiate(&self) -> TestStep;
+}
+
+impl<T> StepTemplate for T
+where
+    T: ServiceStepExecutor + Clone + 'static,
+{
+    fn instantiate(&self) -> TestStep { TestStep::Service(Box::new(self.clone())) }
+}
+
+/// Body of an [`AsyncFnTemplate`], shared by all steps instantiated from it
+pub type SharedAsyncFn =
+    Arc<dyn Fn(&Context) -> Box<dyn Future<Output = Result<Value, String>>> + Send + Sync>;
+
+/// Template of an [`AsyncFnStep`]
+#[derive(Clone)]
+pub struct AsyncFnTemplate {
+    pub name: String,
+    pub description: String,
+    pub futurefn: SharedAsyncFn,
+}
+
+impl Debug for AsyncFnTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFnTemplate")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+impl StepTemplate for AsyncFnTemplate {
+    fn instantiate(&self) -> TestStep {
+        let futurefn = self.futurefn.clone();
+        TestStep::AsyncFn(Box::new(AsyncFnStep {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            futurefn: Box::new(move |context| futurefn(context)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{AssertContextValue, TestHarness};
+
+    #[test]
+    fn test_template_instantiated_into_two_harnesses() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let fetch = AsyncFnTemplate {
+            name: "Fetch_Status".to_string(),
+            description: "Reports the status".to_string(),
+            futurefn: Arc::new(move |_| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                Box::new(async { Ok(serde_json::json!({ "status": "up" })) })
+            }),
+        };
+        let check = AssertContextValue {
+            name: "Check_Status".to_string(),
+            key: "Fetch_Status".to_string(),
+            pointer: "/status".to_string(),
+            expected: Value::from("up"),
+        };
+
+        for test_name in ["First", "Second"] {
+            let mut harness = TestHarness::new(test_name, ".");
+            harness.add_step(fetch.instantiate());
+            harness.add_step(check.instantiate());
+            let report = harness.execute().expect("Failed to execute test steps");
+            assert!(report.passed(), "{:?}", report.steps);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
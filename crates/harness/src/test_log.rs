@@ -0,0 +1,47 @@
+//! Logger for tests that need to assert on log output
+
+use std::sync::{Mutex, Once};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+static LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Records every log line and forwards it to `env_logger`
+struct CapturingLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool { true }
+
+    fn log(&self, record: &Record<'_>) {
+        LINES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(format!("{} {}", record.level(), record.args()));
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) { self.inner.flush(); }
+}
+
+/// Installs the capturing logger, can be called by every test
+pub(crate) fn init() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let inner = env_logger::Builder::from_default_env().build();
+        log::set_boxed_logger(Box::new(CapturingLogger { inner }))
+            .expect("Another logger is already installed");
+        log::set_max_level(LevelFilter::Trace);
+    });
+}
+
+/// Lines logged so far by all tests, formatted as `LEVEL message`
+pub(crate) fn captured() -> Vec<String> {
+    LINES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
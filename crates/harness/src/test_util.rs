@@ -0,0 +1,90 @@
+//! Mock services for testing the harness and integrations built on it
+
+use std::time::{Duration, Instant};
+
+use crate::{Service, ServiceError};
+
+/// A service that only reports running once `ready_after` has passed since
+/// it was started, modelling a slow-starting service
+#[derive(Debug)]
+pub struct DelayedReadyService {
+    pub name: String,
+    pub ready_after: Duration,
+    started_at: Option<Instant>,
+}
+
+impl DelayedReadyService {
+    pub fn new(name: &str, ready_after: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            ready_after,
+            started_at: None,
+        }
+    }
+}
+
+impl Service for DelayedReadyService {
+    fn name(&self) -> &str { &self.name }
+
+    fn start(&mut self) -> Result<(), ServiceError> {
+        if self.started_at.is_some() {
+            return Err(ServiceError::AlreadyRunning(self.name.clone()));
+        }
+        self.started_at = Some(Instant::now());
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.started_at
+            .is_some_and(|started_at| started_at.elapsed() >= self.ready_after)
+    }
+
+    fn stop(&mut self) -> Result<(), ServiceError> {
+        self.started_at = None;
+        Ok(())
+    }
+}
+
+/// Whether the process exists and did not exit, zombies count as exited
+#[cfg(target_os = "linux")]
+pub fn is_alive(pid: u32) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .is_ok_and(|stat| !stat.contains(") Z ") && !stat.contains(") X "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PollPolicy, StartAllServices, TestHarness, TestStep};
+
+    #[test]
+    fn test_dependent_waits_for_delayed_readiness() {
+        let mut harness = TestHarness::new("DelayedReadyTester", ".");
+        harness.add_service(Box::new(DelayedReadyService::new(
+            "db",
+            Duration::from_millis(300),
+        )));
+        harness.add_service(Box::new(DelayedReadyService::new("app", Duration::ZERO)));
+        harness.add_dependency("app", "db");
+        harness.add_step(TestStep::Service(Box::new(StartAllServices {
+            name: "Start_All".to_string(),
+            health_policy: PollPolicy {
+                interval: Duration::from_millis(10),
+                timeout: Duration::from_secs(5),
+                ..Default::default()
+            },
+        })));
+
+        let report = harness.execute().expect("Failed to execute test steps");
+        assert!(report.passed());
+        let startup_order = report.startup_order();
+        assert_eq!(startup_order[0].0, "db");
+        assert_eq!(startup_order[1].0, "app");
+        let waited = startup_order[1].1 - startup_order[0].1;
+        assert!(
+            waited >= Duration::from_millis(300),
+            "Dependent started {:?} after its dependency",
+            waited
+        );
+    }
+}
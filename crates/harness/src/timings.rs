@@ -0,0 +1,130 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use crate::RunReport;
+
+/// Wall-clock timings of a run, see [`RunReport::timings`]. Displays as a
+/// table, as logged at the end of every run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Executed steps with how long they took including retries
+    pub steps: Vec<(String, Duration)>,
+    /// Services awaited to become healthy with how long that took after they
+    /// were started
+    pub services: Vec<(String, Duration)>,
+    /// How long the run took including setup and teardown
+    pub total: Duration,
+}
+
+impl RunReport {
+    /// Timings of the executed steps and the startup of the services, in the
+    /// order they happened
+    pub fn timings(&self) -> Timings {
+        let steps = self
+            .steps
+            .iter()
+            .filter(|step| step.started.is_some())
+            .map(|step| (step.name.clone(), step.duration))
+            .collect();
+        let services = self
+            .ready_log
+            .iter()
+            .filter_map(|(service, ready_at)| {
+                let started_at = self
+                    .startup_log
+                    .iter()
+                    .filter(|(name, started_at)| name == service && started_at <= ready_at)
+                    .map(|(_, started_at)| *started_at)
+                    .max()?;
+                Some((service.clone(), *ready_at - started_at))
+            })
+            .collect();
+        Timings {
+            steps,
+            services,
+            total: self.duration,
+        }
+    }
+}
+
+impl Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .steps
+            .iter()
+            .chain(&self.services)
+            .map(|(name, _)| name.chars().count())
+            .fold("Service".len(), usize::max);
+        let mut row =
+            |name: &str, duration: &str| writeln!(f, "  {:<width$}  {:>9}", name, duration);
+        row("Step", "Duration")?;
+        for (name, duration) in &self.steps {
+            row(name, &format!("{:.3}s", duration.as_secs_f64()))?;
+        }
+        if !self.services.is_empty() {
+            row("Service", "Startup")?;
+            for (name, duration) in &self.services {
+                row(name, &format!("{:.3}s", duration.as_secs_f64()))?;
+            }
+        }
+        write!(
+            f,
+            "  {:<width$}  {:>8.3}s",
+            "Total",
+            self.total.as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StepReport, StepStatus};
+
+    #[test]
+    fn test_timings() {
+        let mut report = RunReport::new("Checkout");
+        let started = report.started;
+        report.duration = Duration::from_millis(2500);
+        report.startup_log = vec![
+            ("Api".to_string(), started),
+            ("Worker".to_string(), started + Duration::from_millis(100)),
+            ("Api".to_string(), started + Duration::from_millis(1000)),
+        ];
+        report.ready_log = vec![
+            ("Api".to_string(), started + Duration::from_millis(400)),
+            ("Api".to_string(), started + Duration::from_millis(1250)),
+        ];
+        report.steps = vec![
+            StepReport {
+                name: "Start_All".to_string(),
+                description: String::new(),
+                status: StepStatus::Passed,
+                started: Some(started),
+                duration: Duration::from_millis(400),
+            },
+            StepReport {
+                name: "Create_Order".to_string(),
+                description: String::new(),
+                status: StepStatus::Skipped("not selected".to_string()),
+                started: None,
+                duration: Duration::ZERO,
+            },
+        ];
+
+        let timings = report.timings();
+        assert_eq!(timings.steps, vec![(
+            "Start_All".to_string(),
+            Duration::from_millis(400)
+        )]);
+        assert_eq!(timings.services, vec![
+            ("Api".to_string(), Duration::from_millis(400)),
+            ("Api".to_string(), Duration::from_millis(250)),
+        ]);
+        assert_eq!(
+            timings.to_string(),
+            "  Step        Duration\n  Start_All     0.400s\n  Service      Startup\n  Api           \
+             0.400s\n  Api           0.250s\n  Total         2.500s"
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::BTreeSet;
+
+use crate::{HarnessError, ReadinessProbe, ServiceAction, TestHarness, TestStep};
+
+impl TestHarness {
+    /// Declares that at most one of `services` may run at a time, e.g.
+    /// because they bind the same port. Checked by
+    /// [`TestHarness::validate`]
+    pub fn add_exclusive_group(&mut self, services: &[&str]) {
+        self.exclusive_groups
+            .push(services.iter().map(ToString::to_string).collect());
+    }
+
+    /// Checks the plan for misconfigurations without running it, which is
+    /// also done by [`TestHarness::execute`] before any step. Step names have
+    /// to be unique, as dependencies, selections and cleanups refer to steps
+    /// by name, except for parallel groups which cannot be referred to.
+    /// Services declared exclusive are tracked through the starts and stops of
+    /// the steps (see [`crate::ServiceStepExecutor::service_actions`]) to
+    /// find steps after which they would run together
+    pub fn validate(&self) -> Result<(), HarnessError> {
+        let mut problems = Vec::new();
+        let mut names = BTreeSet::new();
+        for service in &self.services {
+            if !names.insert(service.name()) {
+                problems.push(format!("Duplicate service name '{}'", service.name()));
+            }
+        }
+        let mut step_names = BTreeSet::new();
+        let named_steps = self
+            .setup
+            .iter()
+            .chain(&self.steps)
+            .chain(&self.teardown)
+            .filter(|planned| !matches!(planned.step, TestStep::Parallel(_)));
+        for planned in named_steps {
+            if !step_names.insert(planned.step.name()) {
+                problems.push(format!("Duplicate step name '{}'", planned.step.name()));
+            }
+        }
+        for (idx, planned) in self.steps.iter().enumerate() {
+            let actions = planned
+                .step
+                .executors()
+                .into_iter()
+                .flat_map(|step_executor| {
+                    step_executor
+                        .service_actions()
+                        .into_iter()
+                        .map(move |action| (step_executor, action))
+                });
+            for (step_executor, action) in actions {
+                let (ServiceAction::Start(service)
+                | ServiceAction::Stop(service)
+                | ServiceAction::Use(service)) = action
+                else {
+                    continue;
+                };
+                if !names.contains(service.as_str()) {
+                    problems.push(format!(
+                        "Step {} '{}' refers to unknown service '{}'",
+                        idx + 1,
+                        step_executor.name(),
+                        service
+                    ));
+                }
+            }
+        }
+        for planned in &self.steps {
+            let TestStep::Parallel(steps) = &planned.step else {
+                continue;
+            };
+            for step_executor in steps.iter().flat_map(TestStep::executors) {
+                if !step_executor.service_actions().is_empty() {
+                    problems.push(format!(
+                        "Step '{}' acts on services, which steps in a parallel group cannot access",
+                        step_executor.name()
+                    ));
+                }
+            }
+        }
+        for group in &self.exclusive_groups {
+            for service in group {
+                if !self.services.iter().any(|s| s.name() == service) {
+                    problems.push(format!(
+                        "Exclusivity group {:?} contains unknown service '{}'",
+                        group, service
+                    ));
+                }
+            }
+        }
+        for (service_name, check) in &self.liveness_checks {
+            if matches!(check.probe, ReadinessProbe::LogPattern(_)) {
+                problems.push(format!(
+                    "Liveness check of service '{}' cannot use a log pattern probe",
+                    service_name
+                ));
+            }
+        }
+        for (step_name, cleanup) in &self.cleanups {
+            let known = self
+                .setup
+                .iter()
+                .chain(&self.steps)
+                .chain(&self.teardown)
+                .any(|planned| planned.step.name() == step_name);
+            if !known {
+                problems.push(format!(
+                    "Cleanup '{}' refers to unknown step '{}'",
+                    cleanup.name(),
+                    step_name
+                ));
+            }
+        }
+
+        let mut running = BTreeSet::new();
+        let mut reported = BTreeSet::new();
+        for (idx, planned) in self.steps.iter().enumerate() {
+            // Services started by any step of a parallel group or branch of a
+            // conditional step may run after it
+            let actions = planned
+                .step
+                .executors()
+                .into_iter()
+                .flat_map(|step_executor| step_executor.service_actions());
+            for action in actions {
+                match action {
+                    ServiceAction::Start(service) => {
+                        running.insert(service);
+                    }
+                    ServiceAction::StartAll =>
+                        running.extend(self.services.iter().map(|s| s.name().to_string())),
+                    ServiceAction::Stop(service) => {
+                        running.remove(&service);
+                    }
+                    ServiceAction::Use(_) => {}
+                }
+            }
+            for (group_idx, group) in self.exclusive_groups.iter().enumerate() {
+                let conflicting = group
+                    .iter()
+                    .filter(|service| running.contains(*service))
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                if conflicting.len() > 1 && reported.insert(group_idx) {
+                    problems.push(format!(
+                        "Exclusive services '{}' would run together after step {} '{}'",
+                        conflicting.join("', '"),
+                        idx + 1,
+                        planned.step.name()
+                    ));
+                }
+            }
+        }
+
+        if let Err(problem) = self.step_order() {
+            problems.push(problem);
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(HarnessError::InvalidPlan(problems))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        SubProcessService, SubProcessServiceStarter, SubProcessServiceStopper, WaitForExit,
+    };
+
+    fn harness_with_two_servers() -> TestHarness {
+        let mut harness = TestHarness::new("ExclusivityTester", ".");
+        for name in ["Server_V1", "Server_V2"] {
+            harness.add_service(Box::new(SubProcessService::new(name, "true", Vec::new())));
+        }
+        harness.add_exclusive_group(&["Server_V1", "Server_V2"]);
+        harness
+    }
+
+    fn starter(name: &str) -> TestStep {
+        TestStep::Service(Box::new(SubProcessServiceStarter {
+            name: name.to_string(),
+            description: format!("Starts {}", name),
+            service_name: name.to_string(),
+            wait_after: None,
+        }))
+    }
+
+    #[test]
+    fn test_validate_reports_exclusive_services_running_together() {
+        let mut harness = harness_with_two_servers();
+        harness.add_step(starter("Server_V1"));
+        harness.add_step(starter("Server_V2"));
+
+        let error = harness.validate().unwrap_err().to_string();
+        assert!(
+            error.contains("'Server_V1', 'Server_V2' would run together after step 2"),
+            "{}",
+            error
+        );
+        assert_eq!(harness.execute().unwrap_err().to_string(), error);
+    }
+
+    #[test]
+    fn test_validate_accepts_exclusive_services_run_in_turn() {
+        let mut harness = harness_with_two_servers();
+        harness.add_step(starter("Server_V1"));
+        harness.add_step(TestStep::Service(Box::new(SubProcessServiceStopper {
+            name: "Stop_Server_V1".to_string(),
+            description: "Stops Server_V1".to_string(),
+            service_name: "Server_V1".to_string(),
+            wait_after: None,
+        })));
+        harness.add_step(starter("Server_V2"));
+
+        harness.validate().expect("Services never run together");
+    }
+
+    #[test]
+    fn test_validate_rejects_service_actions_in_parallel_groups() {
+        let mut harness = harness_with_two_servers();
+        harness.add_step(TestStep::Parallel(vec![
+            starter("Server_V1"),
+            starter("Server_V2"),
+        ]));
+
+        let error = harness.validate().unwrap_err().to_string();
+        assert!(
+            error.contains(
+                "Step 'Server_V1' acts on services, which steps in a parallel group cannot access"
+            ),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_exclusive_services_started_in_parallel_group() {
+        let mut harness = harness_with_two_servers();
+        harness.add_step(TestStep::Parallel(vec![
+            starter("Server_V1"),
+            starter("Server_V2"),
+        ]));
+
+        let error = harness.validate().unwrap_err().to_string();
+        assert!(
+            error.contains("'Server_V1', 'Server_V2' would run together after step 1 'Parallel'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicate_step_names() {
+        let mut harness = TestHarness::new("DuplicateStepTester", ".");
+        for service_name in ["Migrate_Db", "Seed_Db"] {
+            harness.add_service(Box::new(SubProcessService::new(
+                service_name,
+                "true",
+                Vec::new(),
+            )));
+            harness.add_step(TestStep::Service(Box::new(WaitForExit {
+                name: "WaitForExit".to_string(),
+                service_name: service_name.to_string(),
+                expected_code: Some(0),
+                timeout: Duration::from_secs(5),
+                kill_on_timeout: false,
+            })));
+        }
+
+        let Err(HarnessError::InvalidPlan(problems)) = harness.validate() else {
+            panic!("Expected an invalid plan");
+        };
+        assert_eq!(problems, ["Duplicate step name 'WaitForExit'"]);
+    }
+}
@@ -0,0 +1,322 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use reqwest::Url;
+use serde_json::Value;
+use tracing::info;
+
+use crate::{ServiceStepExecutor, StepEnv, StepError};
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// A step connecting to a `ws://` URL, sending text messages and checking
+/// the messages received until `timeout`, e.g. to test push notifications.
+/// The URL and messages may contain `{{port:name}}` placeholders, see
+/// [`crate::PortAllocator`]
+#[derive(Debug, Clone)]
+pub struct WebSocketStep {
+    /// Name the step is reported under
+    pub name: String,
+    pub url: String,
+    /// Text messages sent once connected, in order
+    pub send: Vec<String>,
+    /// Patterns the first received messages must match in order
+    pub expect: Vec<Regex>,
+    pub timeout: Duration,
+    /// Stores the received messages in the [`crate::Context`] under this key
+    pub context_key: Option<String>,
+}
+
+impl WebSocketStep {
+    pub fn new(name: &str, url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            send: Vec::new(),
+            expect: Vec::new(),
+            timeout: Duration::from_secs(10),
+            context_key: None,
+        }
+    }
+}
+
+/// A client side WebSocket connection
+struct Connection {
+    stream: BufReader<TcpStream>,
+}
+
+impl Connection {
+    /// Connects to `url` and performs the opening handshake
+    fn open(url: &str) -> Result<Self, String> {
+        let parsed = Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+        if parsed.scheme() != "ws" {
+            return Err(format!("Only ws URLs are supported, not {}", url));
+        }
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| format!("No host in {}", url))?;
+        let port = parsed.port().unwrap_or(80);
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        };
+        let mut stream = TcpStream::connect((host, port))
+            .map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+        // The key only guards against caching proxies, it need not be random
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}:{}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, host, port
+        )
+        .map_err(|e| format!("Failed to send handshake to {}: {}", url, e))?;
+
+        let mut stream = BufReader::new(stream);
+        let mut status = String::new();
+        stream
+            .read_line(&mut status)
+            .map_err(|e| format!("Failed to read handshake of {}: {}", url, e))?;
+        if status.split_whitespace().nth(1) != Some("101") {
+            return Err(format!("{} refused the upgrade: {}", url, status.trim()));
+        }
+        let mut header = String::new();
+        while header != "\r\n" {
+            header.clear();
+            match stream.read_line(&mut header) {
+                Ok(0) => return Err(format!("{} closed during the handshake", url)),
+                Ok(_) => {}
+                Err(e) => return Err(format!("Failed to read handshake of {}: {}", url, e)),
+            }
+        }
+        Ok(Self { stream })
+    }
+
+    /// Sends a frame, masked as required for clients
+    fn send(&mut self, opcode: u8, payload: &[u8]) -> Result<(), String> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len @ 126..=0xffff => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        let mask = rand_mask();
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+        self.stream
+            .get_mut()
+            .write_all(&frame)
+            .map_err(|e| format!("Failed to send message: {}", e))
+    }
+
+    /// Reads the next message, answering pings. Returns `None` once the
+    /// server closed the connection
+    fn receive(&mut self) -> Result<Option<String>, String> {
+        let mut message = Vec::new();
+        loop {
+            let mut head = [0; 2];
+            self.stream
+                .read_exact(&mut head)
+                .map_err(|e| format!("Failed to read message: {}", e))?;
+            let fin = head[0] & 0x80 != 0;
+            let opcode = head[0] & 0x0f;
+            let len = match head[1] & 0x7f {
+                126 => {
+                    let mut len = [0; 2];
+                    self.read(&mut len)?;
+                    u64::from(u16::from_be_bytes(len))
+                }
+                127 => {
+                    let mut len = [0; 8];
+                    self.read(&mut len)?;
+                    u64::from_be_bytes(len)
+                }
+                len => u64::from(len),
+            };
+            let mask = if head[1] & 0x80 == 0 {
+                None
+            } else {
+                let mut mask = [0; 4];
+                self.read(&mut mask)?;
+                Some(mask)
+            };
+            let mut payload = vec![0; len as usize];
+            self.read(&mut payload)?;
+            if let Some(mask) = mask {
+                payload
+                    .iter_mut()
+                    .zip(mask.iter().cycle())
+                    .for_each(|(b, m)| *b ^= m);
+            }
+
+            match opcode {
+                OPCODE_CLOSE => return Ok(None),
+                OPCODE_PING => self.send(OPCODE_PONG, &payload)?,
+                OPCODE_PONG => {}
+                OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                    message.extend_from_slice(&payload);
+                    if fin {
+                        return Ok(Some(String::from_utf8_lossy(&message).into_owned()));
+                    }
+                }
+                _ => return Err(format!("Unsupported opcode {:#x}", opcode)),
+            }
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), String> {
+        self.stream
+            .read_exact(buf)
+            .map_err(|e| format!("Failed to read message: {}", e))
+    }
+}
+
+/// Masking key for a client frame, which only has to be unpredictable to
+/// intermediaries
+fn rand_mask() -> [u8; 4] {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    nanos.to_le_bytes()
+}
+
+impl ServiceStepExecutor for WebSocketStep {
+    fn name(&self) -> &str { &self.name }
+
+    fn execute(&self, env: &mut StepEnv<'_>) -> Result<(), StepError> {
+        let url = env.substitute_ports(&self.url)?;
+        let mut connection = Connection::open(&url)?;
+        for message in &self.send {
+            connection.send(OPCODE_TEXT, env.substitute_ports(message)?.as_bytes())?;
+        }
+
+        let started = Instant::now();
+        let mut received = Vec::new();
+        while received.len() < self.expect.len() {
+            let remaining = self.timeout.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+            let _ = connection
+                .stream
+                .get_ref()
+                .set_read_timeout(Some(remaining));
+            match connection.receive() {
+                Ok(Some(message)) => received.push(message),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        info!("Received {} message(s) from {}", received.len(), url);
+        let _ = connection.send(OPCODE_CLOSE, &[]);
+
+        for (idx, pattern) in self.expect.iter().enumerate() {
+            match received.get(idx) {
+                Some(message) if pattern.is_match(message) => {}
+                Some(message) => {
+                    return Err(StepError::Assertion(format!(
+                        "Message {} from {} is '{}', expected it to match '{}'",
+                        idx, url, message, pattern
+                    )));
+                }
+                None => {
+                    return Err(StepError::Timeout {
+                        what: format!(
+                            "{} of {} message(s) from {}",
+                            received.len(),
+                            self.expect.len(),
+                            url
+                        ),
+                        after: self.timeout,
+                    });
+                }
+            }
+        }
+        if let Some(key) = &self.context_key {
+            env.context
+                .insert(key, received.into_iter().map(Value::from).collect());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::TestHarness;
+
+    /// Accepts WebSocket connections on `port`, greeting each client and
+    /// echoing its text messages
+    fn serve_echo(port: u16) {
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while line != "\r\n" {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                }
+                let mut stream = stream;
+                let text = |message: &str| {
+                    let mut frame = vec![0x81, message.len() as u8];
+                    frame.extend_from_slice(message.as_bytes());
+                    frame
+                };
+                stream
+                    .write_all(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n")
+                    .unwrap();
+                stream.write_all(&text("welcome")).unwrap();
+                // Also checks pings are answered before the echo
+                stream.write_all(&[0x89, 0]).unwrap();
+                let mut connection = Connection { stream: reader };
+                while let Ok(Some(message)) = connection.receive() {
+                    stream
+                        .write_all(&text(&format!("echo: {}", message)))
+                        .unwrap();
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_send_and_expect_messages() {
+        let mut harness = TestHarness::new("WebSocketTester", ".");
+        serve_echo(harness.ports.tcp("Push").unwrap());
+        let mut step = WebSocketStep::new("Subscribe", "ws://127.0.0.1:{{port:Push}}/events");
+        step.send = vec!["subscribe orders".to_string()];
+        step.expect = vec![
+            Regex::new("^welcome$").unwrap(),
+            Regex::new("^echo: subscribe orders$").unwrap(),
+        ];
+        step.context_key = Some("pushed".to_string());
+        step.execute(&mut harness.step_env())
+            .expect("Messages should match");
+        assert_eq!(
+            harness.context.get("pushed"),
+            Some(serde_json::json!(["welcome", "echo: subscribe orders"]))
+        );
+
+        step.expect.push(Regex::new("^order created$").unwrap());
+        step.timeout = Duration::from_millis(200);
+        let error = step
+            .execute(&mut harness.step_env())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("2 of 3 message(s)"), "{}", error);
+    }
+}